@@ -0,0 +1,95 @@
+//! # Asgard
+//!
+//! All of the crates in one: `skuld`, `mimir`, `hermod` and `helheim`, each behind the
+//! feature of the same name (all enabled by default), with versions that work together.
+//! When `hermod` is enabled, so are the hermod integrations of the others, e.g. mimir's
+//! cache events and `Memoized`. skuld's `SkuldLogger` is behind the `logger` feature, and the `tracing`
+//! feature enables the `tracing` support of hermod, mimir and skuld.
+//!
+//! The crates are re-exported as is, and `asgard::prelude` has what is used most. The
+//! code generated by the `Warning` and `Event` derives refers to `helheim` and `hermod`
+//! by name, unless it is given their path through `asgard`:
+//!
+//! ```
+//! use asgard::prelude::*;
+//!
+//! #[derive(Warning)]
+//! #[warning(crate = asgard::helheim)]
+//! enum CacheWarning {
+//!    #[warning("entry {0} is stale")]
+//!    Stale(String),
+//! }
+//!
+//! #[derive(Event)]
+//! #[event(crate = asgard::hermod)]
+//! #[message(String)]
+//! struct Evicted;
+//!
+//! CacheWarning::Stale(String::from("theme")).emit();
+//! ```
+//!
+//! ## Example
+//! ```
+//! use asgard::prelude::*;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Setting {
+//!     name: String,
+//!     value: String,
+//! }
+//!
+//! impl Item for Setting {
+//!     type Key = String;
+//!     const TYPE_KEY: &'static str = "setting";
+//!
+//!     fn key(&self) -> String {
+//!         self.name.clone()
+//!     }
+//! }
+//!
+//! fn theme(cache: &Cache) -> Result<&str, String> {
+//!     match cache.get::<Setting>(String::from("theme")) {
+//!         Some(setting) => Ok(&setting.value),
+//!         None => bail!("no theme set"),
+//!     }
+//! }
+//!
+//! let mut cache = Cache::new();
+//! assert!(theme(&cache).is_err());
+//!
+//! cache.insert(Setting { name: String::from("theme"), value: String::from("dark") });
+//! assert_eq!(theme(&cache), Ok("dark"));
+//! ```
+
+#[cfg(feature = "helheim")]
+pub use helheim;
+
+#[cfg(feature = "hermod")]
+pub use hermod;
+
+#[cfg(feature = "mimir")]
+pub use mimir;
+
+#[cfg(feature = "skuld")]
+pub use skuld;
+
+/// # Prelude
+///
+/// The most used items of the enabled crates.
+pub mod prelude {
+    #[cfg(feature = "helheim")]
+    pub use helheim::{Error, Warning};
+
+    #[cfg(feature = "hermod")]
+    pub use hermod::{Event, EventEmitter, Sender};
+
+    #[cfg(feature = "mimir")]
+    pub use mimir::{Cache, Item, SharedCache};
+
+    #[cfg(feature = "skuld")]
+    pub use skuld::{bail, location, result};
+
+    #[cfg(feature = "logger")]
+    pub use skuld::log::SkuldLogger;
+}
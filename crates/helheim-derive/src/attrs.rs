@@ -0,0 +1,212 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Attribute, Error, Expr, ExprLit, ExprPath, Lit, LitStr, Meta, Path, Result, Token,
+};
+
+/// An argument of a `#[warning(...)]` attribute
+enum Arg {
+    /// The format string
+    Format(LitStr),
+    /// `key` or `key = value`
+    Meta(Box<Meta>),
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream) -> Result<Self> {
+        match input.peek(LitStr) {
+            true => input.parse().map(Arg::Format),
+            false => input.parse().map(|meta| Arg::Meta(Box::new(meta))),
+        }
+    }
+}
+
+/// The arguments of the `#[warning(...)]` (or `#[error(...)]`) attributes of a variant
+#[derive(Default)]
+pub(crate) struct Attrs {
+    pub(crate) format: Option<LitStr>,
+    /// A function formatting the fields instead of the format string
+    pub(crate) fmt: Option<Path>,
+    /// The variant of `log::Level` to emit at
+    pub(crate) level: Option<TokenStream>,
+    /// A format string for a suggestion, logged after the warning
+    pub(crate) help: Option<LitStr>,
+    /// The target to log with
+    pub(crate) target: Option<LitStr>,
+    /// A stable identifier of the warning, e.g. `W0012`
+    pub(crate) code: Option<LitStr>,
+    /// Where the warning is documented
+    pub(crate) url: Option<LitStr>,
+    /// Where `show_url` is, if `emit` logs the url after the warning
+    pub(crate) show_url: Option<Span>,
+    /// The class of the warning, for `set_policy`, e.g. `deprecation`
+    pub(crate) category: Option<LitStr>,
+    /// Where `transparent` is, if the warning forwards `Display` to its only field
+    pub(crate) transparent: Option<Span>,
+    /// Where `serialize` is, if the warning has a `to_report` method
+    pub(crate) serialize: Option<Span>,
+    /// Where `allow_unused` is, if fields can be left out of the format string
+    pub(crate) allow_unused: Option<Span>,
+    /// The path of `helheim`, if it is not a dependency, e.g. `asgard::helheim`
+    pub(crate) krate: Option<Path>,
+}
+
+impl Attrs {
+    pub(crate) fn parse(attrs: &[Attribute], name: &str) -> Result<Self> {
+        let mut parsed = Self::default();
+        let attrs = attrs.iter().filter(|attr| attr.path().is_ident(name));
+
+        for attr in attrs {
+            let args = attr.parse_args_with(Punctuated::<Arg, Token![,]>::parse_terminated)?;
+
+            for arg in args {
+                match arg {
+                    Arg::Format(lit) => {
+                        let span = lit.span();
+                        set(&mut parsed.format, lit, span, "format string")?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("fmt") => {
+                        set(&mut parsed.fmt, path(&meta)?, meta.span(), "fmt")?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("crate") => {
+                        set(&mut parsed.krate, path(&meta)?, meta.span(), "crate")?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("level") => {
+                        let lit = string(&meta)?;
+                        set(&mut parsed.level, level(&lit)?, meta.span(), "level")?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("help") => {
+                        set(&mut parsed.help, string(&meta)?, meta.span(), "help")?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("target") => {
+                        set(&mut parsed.target, string(&meta)?, meta.span(), "target")?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("code") => {
+                        set(&mut parsed.code, string(&meta)?, meta.span(), "code")?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("url") => {
+                        set(&mut parsed.url, string(&meta)?, meta.span(), "url")?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("show_url") => {
+                        meta.require_path_only()?;
+                        set(&mut parsed.show_url, meta.span(), meta.span(), "show_url")?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("category") => {
+                        set(
+                            &mut parsed.category,
+                            string(&meta)?,
+                            meta.span(),
+                            "category",
+                        )?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("transparent") => {
+                        meta.require_path_only()?;
+                        set(
+                            &mut parsed.transparent,
+                            meta.span(),
+                            meta.span(),
+                            "transparent",
+                        )?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("serialize") => {
+                        meta.require_path_only()?;
+                        set(&mut parsed.serialize, meta.span(), meta.span(), "serialize")?;
+                    }
+                    Arg::Meta(meta) if meta.path().is_ident("allow_unused") => {
+                        meta.require_path_only()?;
+                        set(
+                            &mut parsed.allow_unused,
+                            meta.span(),
+                            meta.span(),
+                            "allow_unused",
+                        )?;
+                    }
+                    Arg::Meta(meta) => {
+                        bail!(Error::new(
+                            meta.path().span(),
+                            format!("Unknown {name} argument")
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Fill in what is not set from the attributes of the enum
+    pub(crate) fn or(self, defaults: &Attrs) -> Self {
+        Self {
+            level: self.level.or_else(|| defaults.level.clone()),
+            target: self.target.or_else(|| defaults.target.clone()),
+            url: self.url.or_else(|| defaults.url.clone()),
+            show_url: self.show_url.or(defaults.show_url),
+            category: self.category.or_else(|| defaults.category.clone()),
+            allow_unused: self.allow_unused.or(defaults.allow_unused),
+            ..self
+        }
+    }
+}
+
+fn set<T>(slot: &mut Option<T>, value: T, span: Span, name: &str) -> Result<()> {
+    if slot.is_some() {
+        bail!(Error::new(span, format!("Expected only one {name}")));
+    }
+
+    *slot = Some(value);
+    Ok(())
+}
+
+/// The value of `key = "value"`
+fn string(meta: &Meta) -> Result<LitStr> {
+    let Meta::NameValue(meta) = meta else {
+        bail!(Error::new(
+            meta.span(),
+            "Expected a value (i.e. key = \"...\")"
+        ));
+    };
+
+    let Expr::Lit(ExprLit {
+        lit: Lit::Str(lit), ..
+    }) = &meta.value
+    else {
+        bail!(Error::new(meta.value.span(), "Expected a string"));
+    };
+
+    Ok(lit.clone())
+}
+
+/// The value of `key = path::to::item`
+fn path(meta: &Meta) -> Result<Path> {
+    let Meta::NameValue(meta) = meta else {
+        bail!(Error::new(
+            meta.span(),
+            "Expected a value (i.e. key = path::to::fn)"
+        ));
+    };
+
+    let Expr::Path(ExprPath { path, .. }) = &meta.value else {
+        bail!(Error::new(meta.value.span(), "Expected a path"));
+    };
+
+    Ok(path.clone())
+}
+
+fn level(lit: &LitStr) -> Result<TokenStream> {
+    let level = match lit.value().to_lowercase().as_str() {
+        "error" => quote! { Error },
+        "warn" | "warning" => quote! { Warn },
+        "info" => quote! { Info },
+        "debug" => quote! { Debug },
+        "trace" => quote! { Trace },
+        _ => bail!(Error::new(
+            lit.span(),
+            "Expected one of \"error\", \"warn\", \"info\", \"debug\" or \"trace\""
+        )),
+    };
+
+    Ok(level)
+}
@@ -0,0 +1,172 @@
+use crate::{
+    attrs::Attrs,
+    shape::{self, Shape},
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::collections::BTreeSet;
+use syn::{spanned::Spanned, DeriveInput, Error, Field, Result};
+
+/// The field of `shape` with the attribute `#[name]`, and its index
+fn marked<'a>(shape: &Shape<'a>, name: &str) -> Result<Option<(usize, &'a Field)>> {
+    let mut found = shape
+        .fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| field.attrs.iter().any(|attr| attr.path().is_ident(name)));
+
+    let marked = found.next();
+
+    if let Some((_, duplicate)) = found.next() {
+        bail!(Error::new(
+            duplicate.span(),
+            format!("Expected only one #[{name}] field")
+        ));
+    }
+
+    Ok(marked)
+}
+
+/// The index of the field the location is captured into: the `#[location]` field, or
+/// the field named `location`
+fn location(shape: &Shape) -> Result<Option<usize>> {
+    if let Some((n, _)) = marked(shape, "location")? {
+        return Ok(Some(n));
+    }
+
+    Ok(shape
+        .fields
+        .iter()
+        .position(|field| field.ident.as_ref().is_some_and(|n| n == "location")))
+}
+
+/// `From<field>`, capturing the location of the caller
+fn from(shape: &Shape, index: usize, field: &Field) -> Result<TokenStream> {
+    let location = location(shape)?;
+
+    let values = shape.fields.iter().enumerate().map(|(n, other)| {
+        let value = match n == index {
+            true => quote! { source },
+            false if Some(n) == location => quote! { ::std::panic::Location::caller() },
+            false => {
+                return Err(Error::new(
+                    other.span(),
+                    "Expected only the #[from] field and a location field",
+                ))
+            }
+        };
+
+        Ok(match &other.ident {
+            Some(ident) => quote! { #ident: #value },
+            None => value,
+        })
+    });
+
+    let values = values.collect::<Result<Vec<_>>>()?;
+    let path = &shape.path;
+    let ty = &field.ty;
+
+    let value = match field.ident {
+        Some(_) => quote! { #path { #(#values),* } },
+        None => quote! { #path ( #(#values),* ) },
+    };
+
+    Ok(quote! {
+        #[track_caller]
+        fn from(source: #ty) -> Self {
+            #value
+        }
+    })
+}
+
+pub(crate) fn error(input: DeriveInput) -> Result<TokenStream> {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut arms = vec![];
+    let mut sources = vec![];
+    let mut froms = vec![];
+
+    for shape in Shape::all(&input, "error")? {
+        let attrs = Attrs::parse(shape.attrs, "error")?;
+
+        let warning_only = [
+            attrs.level.as_ref().map(|n| n.span()),
+            attrs.help.as_ref().map(|n| n.span()),
+            attrs.target.as_ref().map(|n| n.span()),
+            attrs.code.as_ref().map(|n| n.span()),
+            attrs.url.as_ref().map(|n| n.span()),
+            attrs.show_url,
+            attrs.serialize,
+            attrs.allow_unused,
+            attrs.krate.as_ref().map(|n| n.span()),
+            shape.span_field().map(|(_, span)| span),
+        ];
+
+        if let Some(span) = warning_only.into_iter().flatten().next() {
+            bail!(Error::new(span, "Only supported on warnings"));
+        }
+
+        let display = shape.display(&attrs, "error", &mut BTreeSet::new())?;
+        let pattern = shape.pattern();
+        let bindings = shape.bindings();
+
+        arms.push(quote! {
+            #pattern => #display,
+        });
+
+        let from = marked(&shape, "from")?;
+
+        let source = match (attrs.transparent, marked(&shape, "source")?.or(from)) {
+            // the only field that is not skipped, see `Shape::display`
+            (Some(_), _) => {
+                let inner = &shape.shown()[0];
+                quote! { ::std::error::Error::source(#inner) }
+            }
+            (None, Some((n, _))) => {
+                let source = &bindings[n];
+                quote! { ::std::option::Option::Some(#source) }
+            }
+            (None, None) => quote! { ::std::option::Option::None },
+        };
+
+        sources.push(quote! {
+            #pattern => #source,
+        });
+
+        if let Some((n, field)) = from {
+            let from = self::from(&shape, n, field)?;
+            let ty = &field.ty;
+
+            froms.push(quote! {
+                impl #impl_generics ::std::convert::From<#ty> for #ident #ty_generics #where_clause {
+                    #from
+                }
+            });
+        }
+    }
+
+    let f = shape::formatter();
+
+    Ok(quote! {
+        impl #impl_generics ::std::fmt::Display for #ident #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn fmt(&self, #f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+
+        impl #impl_generics ::std::error::Error for #ident #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn source(&self) -> ::std::option::Option<&(dyn ::std::error::Error + 'static)> {
+                match self {
+                    #(#sources)*
+                }
+            }
+        }
+
+        #(#froms)*
+    })
+}
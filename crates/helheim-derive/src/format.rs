@@ -0,0 +1,162 @@
+use proc_macro2::Span;
+use std::collections::BTreeSet;
+use syn::{Error, LitStr, Result};
+
+/// The fields a format string can use
+pub(crate) struct Fields {
+    /// Named fields that are not skipped
+    pub(crate) named: Vec<String>,
+    /// Number of tuple fields that are not skipped
+    pub(crate) positional: usize,
+    /// Fields marked with `skip`
+    pub(crate) skipped: Vec<String>,
+}
+
+/// Rewrite the format string `lit` for the fields bound in the generated match arm:
+/// positional arguments (`{}`, `{0}`, `{:1$}`) become the names tuple fields are bound to
+/// (`{_0}`, `{_0}`, `{:_1$}`). Named arguments are captured from the bound fields as is,
+/// and escaped braces and format specs are kept.
+///
+/// Arguments that are not in `fields` are an error, the names of the ones that are get
+/// added to `used`.
+pub(crate) fn rewrite(
+    lit: &LitStr,
+    fields: &Fields,
+    used: &mut BTreeSet<String>,
+) -> Result<LitStr> {
+    let format = lit.value();
+    let mut out = String::with_capacity(format.len());
+    let mut chars = format.char_indices().peekable();
+    // the index of the next `{}`
+    let mut next = 0;
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '{' if chars.peek().map(|n| n.1) == Some('{') => {
+                chars.next();
+                out.push_str("{{");
+            }
+            '}' if chars.peek().map(|n| n.1) == Some('}') => {
+                chars.next();
+                out.push_str("}}");
+            }
+            '{' => {
+                let mut placeholder = String::new();
+
+                let end = loop {
+                    match chars.next() {
+                        Some((end, '}')) => break end + 1,
+                        Some((_, c)) => placeholder.push(c),
+                        None => bail!(Error::new(
+                            span(lit, start..format.len()),
+                            "unmatched `{` in format string"
+                        )),
+                    }
+                };
+
+                let mut arg = |arg: &str| {
+                    let arg = argument(arg, &mut next, fields)
+                        .map_err(|message| Error::new(span(lit, start..end), message))?;
+
+                    used.insert(arg.clone());
+                    Ok::<_, Error>(arg)
+                };
+
+                let (name, spec) = match placeholder.split_once(':') {
+                    Some((name, spec)) => (name, Some(spec)),
+                    None => (placeholder.as_str(), None),
+                };
+
+                out.push('{');
+                out.push_str(&arg(name.trim())?);
+
+                if let Some(spec) = spec {
+                    if spec.contains(".*") {
+                        bail!(Error::new(
+                            span(lit, start..end),
+                            "`.*` precision is not supported, use e.g. `.1$`"
+                        ));
+                    }
+
+                    out.push(':');
+                    out.push_str(&count_arguments(spec, &mut arg)?);
+                }
+
+                out.push('}');
+            }
+            '}' => bail!(Error::new(
+                span(lit, start..start + 1),
+                "unmatched `}` in format string"
+            )),
+            c => out.push(c),
+        }
+    }
+
+    Ok(LitStr::new(&out, lit.span()))
+}
+
+/// The name the field `arg` refers to is bound to: tuple fields are bound as `_0`,
+/// `_1`, ..., and `next` counts the implicit positions of `{}`
+fn argument(arg: &str, next: &mut usize, fields: &Fields) -> std::result::Result<String, String> {
+    let position = match arg {
+        "" => {
+            *next += 1;
+            Some(*next - 1)
+        }
+        _ if arg.bytes().all(|n| n.is_ascii_digit()) => arg.parse().ok(),
+        _ => None,
+    };
+
+    match position {
+        Some(position) if position < fields.positional => Ok(format!("_{position}")),
+        Some(position) => Err(match fields.positional {
+            0 => String::from("there are no tuple fields to format"),
+            1 => format!("there is 1 tuple field, but {{{position}}} is the field at {position}"),
+            n => {
+                format!("there are {n} tuple fields, but {{{position}}} is the field at {position}")
+            }
+        }),
+        None if fields.named.iter().any(|n| n == arg) => Ok(arg.to_string()),
+        None if fields.skipped.iter().any(|n| n == arg) => {
+            Err(format!("`{arg}` is skipped, it can't be formatted"))
+        }
+        None if fields.positional > 0 => Err(format!(
+            "there is no field `{arg}`, tuple fields are formatted by position, e.g. {{0}}"
+        )),
+        None => Err(format!("there is no field `{arg}`")),
+    }
+}
+
+/// Rewrite the width and precision arguments (`1$`, `name$`) of a format spec
+fn count_arguments(spec: &str, arg: &mut impl FnMut(&str) -> Result<String>) -> Result<String> {
+    let mut out = String::with_capacity(spec.len());
+    let mut word = String::new();
+
+    for c in spec.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+
+        match c {
+            '$' if !word.is_empty() => out.push_str(&arg(&std::mem::take(&mut word))?),
+            _ => out.push_str(&std::mem::take(&mut word)),
+        }
+
+        out.push(c);
+    }
+
+    Ok(out + &word)
+}
+
+/// The span of `range` of the value of `lit`, if it can be pointed at
+fn span(lit: &LitStr, range: std::ops::Range<usize>) -> Span {
+    let token = lit.token();
+
+    // the value only lines up with the token without escapes or raw string hashes
+    match token.to_string() == format!("\"{}\"", lit.value()) {
+        true => token.subspan(range.start + 1..range.end + 1),
+        false => None,
+    }
+    .unwrap_or_else(|| lit.span())
+}
@@ -0,0 +1,484 @@
+//! # Helheim Derive
+//!
+//! `#[derive(Warning)]` and `#[derive(Error)]` for `helheim`, which re-exports them.
+//!
+//! ## Example
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum MyWarning {
+//!    #[warning("Something went wrong")]
+//!    Something,
+//! }
+//!
+//! MyWarning::Something.emit();
+//! ```
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+#[macro_use]
+extern crate skuld;
+extern crate syn;
+
+mod attrs;
+mod error;
+mod format;
+mod shape;
+
+use attrs::Attrs;
+use proc_macro::TokenStream as StdTokenStream;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use shape::Shape;
+use std::collections::BTreeSet;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Ident, Result};
+
+fn helheim(input: DeriveInput) -> Result<TokenStream> {
+    let ident = &input.ident;
+    // not shadowed by the fields bound in `emit`
+    let message = Ident::new("message", Span::mixed_site());
+    let fields = Ident::new("fields", Span::mixed_site());
+    let log_level = Ident::new("level", Span::mixed_site());
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // the attributes of an enum apply to all of its variants
+    let defaults = match &input.data {
+        Data::Enum(_) => Attrs::parse(&input.attrs, "warning")?,
+        _ => Attrs::default(),
+    };
+
+    if let Some(format) = &defaults.format {
+        bail!(Error::new(
+            format.span(),
+            "Expected the format string on the variants"
+        ));
+    }
+
+    if let Some(fmt) = &defaults.fmt {
+        bail!(Error::new(fmt.span(), "Expected fmt on the variants"));
+    }
+
+    if let Some(code) = &defaults.code {
+        bail!(Error::new(code.span(), "Expected the code on the variants"));
+    }
+
+    if let Some(transparent) = defaults.transparent {
+        bail!(Error::new(
+            transparent,
+            "Expected transparent on the variants"
+        ));
+    }
+
+    // `serialize` and `crate` are on the type, which is the only variant of a struct
+    let Attrs {
+        serialize, krate, ..
+    } = Attrs::parse(&input.attrs, "warning")?;
+
+    let krate = match krate {
+        Some(krate) => quote! { #krate },
+        None => quote! { ::helheim },
+    };
+
+    let mut arms = vec![];
+    let mut levels = vec![];
+    let mut targets = vec![];
+    let mut helps = vec![];
+    let mut names = vec![];
+    let mut codes = vec![];
+    let mut urls = vec![];
+    let mut shown_urls = vec![];
+    let mut categories = vec![];
+    let mut records = vec![];
+    let mut reports = vec![];
+    let mut spans = vec![];
+
+    for shape in Shape::all(&input, "warning")? {
+        let attrs = Attrs::parse(shape.attrs, "warning")?.or(&defaults);
+
+        if let (Data::Enum(_), Some(serialize)) = (&input.data, attrs.serialize) {
+            bail!(Error::new(serialize, "Expected serialize on the enum"));
+        }
+
+        if let (Data::Enum(_), Some(krate)) = (&input.data, &attrs.krate) {
+            bail!(Error::new(krate.span(), "Expected crate on the enum"));
+        }
+
+        let mut used = BTreeSet::new();
+        let display = shape.display(&attrs, "warning", &mut used)?;
+
+        let help = match &attrs.help {
+            Some(help) => {
+                let help_lit = shape.format(help, &mut used)?;
+                quote! { ::std::option::Option::Some(format!(#help_lit)) }
+            }
+            None => quote! { ::std::option::Option::None },
+        };
+
+        if attrs.allow_unused.is_none() {
+            shape.check_unused(&used, "warning")?;
+        }
+
+        let level = attrs.level.unwrap_or(quote! { Warn });
+        let target = match attrs.target {
+            Some(target) => quote! { #target },
+            None => quote! { ::std::module_path!() },
+        };
+
+        let code = match &attrs.code {
+            Some(code) => quote! { ::std::option::Option::Some(#code) },
+            None => quote! { ::std::option::Option::None },
+        };
+
+        let url = match &attrs.url {
+            Some(url) => quote! { ::std::option::Option::Some(#url) },
+            None => quote! { ::std::option::Option::None },
+        };
+
+        let show_url = attrs.show_url.is_some();
+
+        let category = match &attrs.category {
+            Some(category) => quote! { ::std::option::Option::Some(#category) },
+            None => quote! { ::std::option::Option::None },
+        };
+
+        let path = &shape.path;
+        let name = &shape.name;
+        let pattern = shape.pattern();
+
+        arms.push(quote! {
+            #pattern => #display,
+        });
+
+        levels.push(quote! {
+            #path { .. } => #krate::__log::Level::#level,
+        });
+
+        targets.push(quote! {
+            #path { .. } => #target,
+        });
+
+        helps.push(quote! {
+            #pattern => #help,
+        });
+
+        names.push(quote! {
+            #path { .. } => #name,
+        });
+
+        codes.push(quote! {
+            #path { .. } => #code,
+        });
+
+        urls.push(quote! {
+            #path { .. } => #url,
+        });
+
+        shown_urls.push(quote! {
+            #path { .. } => #show_url,
+        });
+
+        categories.push(quote! {
+            #path { .. } => #category,
+        });
+
+        let span = match shape.span_field() {
+            Some((field, _)) => quote! {
+                ::std::option::Option::Some((
+                    ::std::convert::AsRef::<str>::as_ref(&#field.0),
+                    ::std::clone::Clone::clone(&#field.1),
+                ))
+            },
+            None => quote! { ::std::option::Option::None },
+        };
+
+        spans.push(quote! {
+            #pattern => #span,
+        });
+
+        let keys = shape.keys();
+        let bindings = shape.shown();
+
+        records.push(quote! {
+            #pattern => #krate::__log::log!(
+                target: self.target(),
+                #log_level,
+                warning = #name,
+                code = self.code(),
+                url = self.url(),
+                category = self.category()
+                #(, #keys:% = (&&&#krate::__KvField(#bindings)).__helheim_kv())*;
+                "{}",
+                #message
+            ),
+        });
+
+        reports.push(quote! {
+            #pattern => #krate::WarningReport {
+                name: #name,
+                code: self.code(),
+                url: self.url(),
+                category: self.category(),
+                level: self.level().as_str(),
+                message: ::std::string::ToString::to_string(self),
+                help: self.help(),
+                fields: {
+                    let mut #fields = #krate::__serde_json::Map::new();
+                    #(
+                        #fields.insert(
+                            ::std::string::String::from(#keys),
+                            #krate::__serde_json::to_value(#bindings).unwrap_or_default(),
+                        );
+                    )*
+                    #fields
+                },
+            },
+        });
+    }
+
+    let to_report = serialize.map(|_| {
+        quote! {
+            /// The warning as data, with its fields serialized
+            #[allow(unused_variables)]
+            pub fn to_report(&self) -> #krate::WarningReport {
+                match self {
+                    #(#reports)*
+                }
+            }
+        }
+    });
+
+    let log = match cfg!(feature = "kv") {
+        true => quote! {
+            {
+                #[allow(unused_imports)]
+                use #krate::{__KvDebug as _, __KvDisplay as _, __KvOther as _};
+
+                match self {
+                    #(#records)*
+                }
+            }
+        },
+        false => quote! {
+            #krate::__log::log!(target: self.target(), #log_level, "{}", #message)
+        },
+    };
+
+    let f = shape::formatter();
+
+    Ok(quote! {
+        impl #impl_generics ::std::fmt::Display for #ident #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn fmt(&self, #f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// The level `emit` logs at
+            pub fn level(&self) -> #krate::__log::Level {
+                match self {
+                    #(#levels)*
+                }
+            }
+
+            /// The target `emit` logs with, the module of the warning by default
+            pub fn target(&self) -> &'static str {
+                match self {
+                    #(#targets)*
+                }
+            }
+
+            /// The suggestion logged after the warning, set with `help`
+            #[allow(unused_variables)]
+            pub fn help(&self) -> ::std::option::Option<::std::string::String> {
+                match self {
+                    #(#helps)*
+                }
+            }
+
+            /// What the warning is called, `Enum::Variant` or `Struct`
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#names)*
+                }
+            }
+
+            /// The identifier of the warning, set with `code`
+            pub fn code(&self) -> ::std::option::Option<&'static str> {
+                match self {
+                    #(#codes)*
+                }
+            }
+
+            /// Where the warning is documented, set with `url`
+            pub fn url(&self) -> ::std::option::Option<&'static str> {
+                match self {
+                    #(#urls)*
+                }
+            }
+
+            /// The class of the warning, set with `category`, see `helheim::set_policy`
+            pub fn category(&self) -> ::std::option::Option<&'static str> {
+                match self {
+                    #(#categories)*
+                }
+            }
+
+            /// The source and the range in it the warning is about, set with
+            /// `#[warning(span)]` on a `(source, range)` field
+            #[allow(unused_variables)]
+            pub fn source_span(
+                &self,
+            ) -> ::std::option::Option<(&str, ::std::ops::Range<usize>)> {
+                match self {
+                    #(#spans)*
+                }
+            }
+
+            pub fn emit(&self) {
+                self.__helheim_emit(false);
+            }
+
+            /// Like `emit`, but sends the warning to `sink` instead of `log`
+            pub fn emit_to(&self, sink: &(impl #krate::WarningSink + ?Sized)) {
+                let denied = match self.__helheim_policy() {
+                    #krate::Policy::Silence => return,
+                    policy => policy == #krate::Policy::Deny,
+                };
+
+                #krate::__record(self.name(), self.code());
+                sink.emit(#krate::EmittedWarning {
+                    name: self.name(),
+                    code: self.code(),
+                    url: self.url(),
+                    category: self.category(),
+                    level: match denied {
+                        true => #krate::__log::Level::Error,
+                        false => self.level(),
+                    },
+                    target: self.target(),
+                    message: ::std::string::ToString::to_string(self),
+                    help: self.help(),
+                });
+
+                if denied {
+                    #krate::__escalate(self.name());
+                }
+            }
+
+            /// Like `emit`, but with the source of `source_span` shown below the warning,
+            /// with the range underlined
+            pub fn emit_pretty(&self) {
+                self.__helheim_emit(true);
+            }
+
+            fn __helheim_policy(&self) -> #krate::Policy {
+                #krate::__policy(self.name(), self.code(), self.target(), self.category())
+            }
+
+            /// What `emit` logs: the warning, its snippet if `pretty`, its help, and its
+            /// url if it has `show_url`
+            #[allow(unused_variables)]
+            fn __helheim_message(&self, pretty: bool) -> ::std::string::String {
+                let mut #message = ::std::string::ToString::to_string(self);
+
+                if let (true, ::std::option::Option::Some((source, span))) =
+                    (pretty, self.source_span())
+                {
+                    #message = #krate::__snippet(&#message, source, span);
+                }
+
+                if let ::std::option::Option::Some(help) = self.help() {
+                    #message.push_str(&::std::format!("\n  help: {}", help));
+                }
+
+                let show_url = match self {
+                    #(#shown_urls)*
+                };
+
+                if let (true, ::std::option::Option::Some(url)) = (show_url, self.url()) {
+                    #message.push_str(&::std::format!("\n  see: {}", url));
+                }
+
+                #message
+            }
+
+            #[allow(unused_variables)]
+            fn __helheim_emit(&self, pretty: bool) {
+                let denied = match self.__helheim_policy() {
+                    #krate::Policy::Silence => return,
+                    policy => policy == #krate::Policy::Deny,
+                };
+
+                let #message = self.__helheim_message(pretty);
+
+                let #log_level = match denied {
+                    true => #krate::__log::Level::Error,
+                    false => self.level(),
+                };
+
+                #krate::__record(self.name(), self.code());
+                #log;
+                #krate::__fallback(#log_level, &#message);
+
+                if denied {
+                    #krate::__escalate(self.name());
+                }
+            }
+
+            /// Like `emit`, but each message is only logged once per process, e.g. for
+            /// warnings in a loop
+            pub fn emit_once(&self) {
+                static EMITTED: ::std::sync::OnceLock<
+                    ::std::sync::Mutex<::std::collections::HashSet<::std::string::String>>,
+                > = ::std::sync::OnceLock::new();
+
+                let emitted = EMITTED.get_or_init(::std::default::Default::default);
+
+                if emitted.lock().unwrap().insert(self.to_string()) {
+                    self.emit();
+                }
+            }
+
+            /// Like `emit_once`, but only the first warning of each variant is logged,
+            /// whatever its fields
+            pub fn emit_once_per_variant(&self) {
+                static EMITTED: ::std::sync::OnceLock<
+                    ::std::sync::Mutex<::std::collections::HashSet<&'static str>>,
+                > = ::std::sync::OnceLock::new();
+
+                let emitted = EMITTED.get_or_init(::std::default::Default::default);
+
+                if emitted.lock().unwrap().insert(self.name()) {
+                    self.emit();
+                }
+            }
+
+            pub fn into_emit(self) {
+                self.emit();
+            }
+
+            #to_report
+        }
+    })
+}
+
+#[proc_macro_derive(Warning, attributes(warning))]
+pub fn warning(input: StdTokenStream) -> StdTokenStream {
+    helheim(parse_macro_input!(input as DeriveInput))
+        .map(StdTokenStream::from)
+        .map_err(Error::into_compile_error)
+        .unwrap_or_else(StdTokenStream::from)
+}
+
+#[proc_macro_derive(Error, attributes(error, from, source, location))]
+pub fn error(input: StdTokenStream) -> StdTokenStream {
+    error::error(parse_macro_input!(input as DeriveInput))
+        .map(StdTokenStream::from)
+        .map_err(Error::into_compile_error)
+        .unwrap_or_else(StdTokenStream::from)
+}
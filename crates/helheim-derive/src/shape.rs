@@ -0,0 +1,269 @@
+use crate::{attrs::Attrs, format};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use std::collections::BTreeSet;
+use syn::{spanned::Spanned, Attribute, Data, DeriveInput, Error, Fields, Ident, LitStr, Result};
+
+/// A variant of an enum, or a struct: what a warning or error can be
+pub(crate) struct Shape<'a> {
+    /// The path to match it with
+    pub(crate) path: TokenStream,
+    /// What it is called, `Enum::Variant` or `Struct`
+    pub(crate) name: String,
+    pub(crate) attrs: &'a [Attribute],
+    pub(crate) fields: &'a Fields,
+    pub(crate) span: Span,
+    /// Which fields are marked with `skip`
+    skipped: Vec<bool>,
+    /// The index of the field marked with `span`
+    span_field: Option<(usize, Span)>,
+}
+
+impl<'a> Shape<'a> {
+    /// All shapes of `input`, with the `#[name(...)]` attributes of the fields parsed
+    pub(crate) fn all(input: &'a DeriveInput, name: &str) -> Result<Vec<Self>> {
+        let ident = &input.ident;
+
+        let shapes = match &input.data {
+            Data::Enum(data) => data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let variant_ident = &variant.ident;
+
+                    Shape::new(
+                        quote! { #ident::#variant_ident },
+                        format!("{ident}::{variant_ident}"),
+                        &variant.attrs,
+                        &variant.fields,
+                        variant.span(),
+                        name,
+                    )
+                })
+                .collect::<Result<_>>()?,
+            Data::Struct(data) => vec![Shape::new(
+                quote! { #ident },
+                ident.to_string(),
+                &input.attrs,
+                &data.fields,
+                input.span(),
+                name,
+            )?],
+            Data::Union(_) => bail!(Error::new(input.span(), "Expected enum or struct")),
+        };
+
+        Ok(shapes)
+    }
+
+    fn new(
+        path: TokenStream,
+        shape_name: String,
+        attrs: &'a [Attribute],
+        fields: &'a Fields,
+        span: Span,
+        name: &str,
+    ) -> Result<Self> {
+        let mut skipped = vec![false; fields.len()];
+        let mut span_field = None;
+
+        for (i, field) in fields.iter().enumerate() {
+            for attr in field.attrs.iter().filter(|n| n.path().is_ident(name)) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("skip") {
+                        skipped[i] = true;
+                    } else if meta.path.is_ident("span") {
+                        if span_field.is_some() {
+                            bail!(meta.error("Expected only one span"));
+                        }
+
+                        span_field = Some((i, meta.path.span()));
+                    } else {
+                        bail!(meta.error(format!(
+                            "Unknown {name} field argument, expected skip or span"
+                        )));
+                    }
+
+                    Ok(())
+                })?;
+            }
+        }
+
+        Ok(Shape {
+            path,
+            name: shape_name,
+            attrs,
+            fields,
+            span,
+            skipped,
+            span_field,
+        })
+    }
+
+    /// What the fields are bound to: named fields by name, tuple fields as `_0`, `_1`, ...
+    /// counting only the fields that are not skipped
+    pub(crate) fn bindings(&self) -> Vec<Ident> {
+        let mut position = 0;
+
+        self.fields
+            .iter()
+            .zip(&self.skipped)
+            .enumerate()
+            .map(|(i, (field, skipped))| match (&field.ident, skipped) {
+                (Some(ident), _) => ident.clone(),
+                // can't be used in the format string
+                (None, true) => Ident::new(&format!("_{i}"), Span::mixed_site()),
+                (None, false) => {
+                    position += 1;
+                    Ident::new(&format!("_{}", position - 1), Span::call_site())
+                }
+            })
+            .collect()
+    }
+
+    /// The bindings of the fields that are not skipped, see `bindings`
+    pub(crate) fn shown(&self) -> Vec<Ident> {
+        self.bindings()
+            .into_iter()
+            .zip(&self.skipped)
+            .filter(|(_, skipped)| !**skipped)
+            .map(|(binding, _)| binding)
+            .collect()
+    }
+
+    /// The names of the fields that are not skipped, tuple fields by position
+    pub(crate) fn keys(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .zip(&self.skipped)
+            .filter(|(_, skipped)| !**skipped)
+            .enumerate()
+            .map(|(position, (field, _))| match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => position.to_string(),
+            })
+            .collect()
+    }
+
+    /// What the field marked with `span` is bound to, and where `span` is
+    pub(crate) fn span_field(&self) -> Option<(Ident, Span)> {
+        self.span_field
+            .map(|(i, span)| (self.bindings().swap_remove(i), span))
+    }
+
+    /// The pattern binding every field, see `bindings`
+    pub(crate) fn pattern(&self) -> TokenStream {
+        let path = &self.path;
+        let bindings = self.bindings();
+
+        match self.fields {
+            Fields::Named(_) => quote! { #path { #(#bindings),* } },
+            Fields::Unnamed(_) => quote! { #path ( #(#bindings),* ) },
+            Fields::Unit => quote! { #path },
+        }
+    }
+
+    /// Rewrite the format string `lit` for the bound fields, see `format::rewrite`
+    pub(crate) fn format(&self, lit: &LitStr, used: &mut BTreeSet<String>) -> Result<LitStr> {
+        let mut fields = format::Fields {
+            named: vec![],
+            positional: 0,
+            skipped: vec![],
+        };
+
+        for (field, skipped) in self.fields.iter().zip(&self.skipped) {
+            match (&field.ident, skipped) {
+                (Some(ident), true) => fields.skipped.push(ident.to_string()),
+                (Some(ident), false) => fields.named.push(ident.to_string()),
+                (None, true) => {}
+                (None, false) => fields.positional += 1,
+            }
+        }
+
+        format::rewrite(lit, &fields, used)
+    }
+
+    /// Error on the first field that is not in `used`, unless it is skipped, or it is the
+    /// `span` field
+    pub(crate) fn check_unused(&self, used: &BTreeSet<String>, name: &str) -> Result<()> {
+        let span_field = self.span_field.map(|(i, _)| i);
+        let fields = self.fields.iter().zip(self.bindings()).enumerate();
+
+        for (i, (field, binding)) in fields {
+            if self.skipped[i] || span_field == Some(i) || used.contains(&binding.to_string()) {
+                continue;
+            }
+
+            bail!(Error::new(
+                field.span(),
+                format!(
+                    "Field not in the format string: use it, mark it with #[{name}(skip)], \
+                     or add #[{name}(allow_unused)]"
+                )
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The expression writing the shape to the `formatter`, with its fields bound. The
+    /// fields the format string uses are added to `used`.
+    pub(crate) fn display(
+        &self,
+        attrs: &Attrs,
+        name: &str,
+        used: &mut BTreeSet<String>,
+    ) -> Result<TokenStream> {
+        let f = formatter();
+
+        if let Some(fmt) = &attrs.fmt {
+            if attrs.format.is_some() || attrs.transparent.is_some() {
+                bail!(Error::new(
+                    fmt.span(),
+                    "Expected only one of a format string, fmt or transparent"
+                ));
+            }
+
+            let shown = self.shown();
+            used.extend(shown.iter().map(Ident::to_string));
+
+            return Ok(quote! { #f.write_str(&#fmt(#(#shown),*)) });
+        }
+
+        let display = match (&attrs.format, attrs.transparent) {
+            (Some(format), None) => {
+                let format_lit = self.format(format, used)?;
+                quote! { write!(#f, #format_lit) }
+            }
+            (None, Some(transparent)) => {
+                let [inner] = self.shown().try_into().map_err(|_| {
+                    Error::new(
+                        transparent,
+                        format!("Expected exactly one field in a transparent {name}"),
+                    )
+                })?;
+
+                used.insert(inner.to_string());
+                quote! { ::std::fmt::Display::fmt(#inner, #f) }
+            }
+            (Some(format), Some(_)) => bail!(Error::new(
+                format.span(),
+                format!("Transparent {name}s have no format string")
+            )),
+            (None, None) => bail!(Error::new(
+                self.span,
+                format!(
+                    "Expected #[{name}(\"...\")], #[{name}(fmt = ...)] or \
+                     #[{name}(transparent)] attribute"
+                )
+            )),
+        };
+
+        Ok(display)
+    }
+}
+
+/// The formatter of the `Display` impl, which the fields, and the functions of `fmt`,
+/// don't clash with
+pub(crate) fn formatter() -> Ident {
+    Ident::new("f", Span::mixed_site())
+}
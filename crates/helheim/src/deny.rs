@@ -0,0 +1,64 @@
+use std::{
+    env,
+    sync::{OnceLock, RwLock},
+};
+
+/// The variable with the warnings to deny, comma-separated
+const DENY_VAR: &str = "HELHEIM_DENY";
+
+/// The denied warnings, see `deny`
+static DENIED: OnceLock<RwLock<Vec<String>>> = OnceLock::new();
+
+fn denied() -> &'static RwLock<Vec<String>> {
+    DENIED.get_or_init(|| {
+        let denied = env::var(DENY_VAR).unwrap_or_default();
+
+        RwLock::new(
+            denied
+                .split(',')
+                .map(str::trim)
+                .filter(|n| !n.is_empty())
+                .map(String::from)
+                .collect(),
+        )
+    })
+}
+
+/// Turn the warnings `selector` matches into errors: `emit` logs them at the `error`
+/// level and then panics in debug builds, or exits the process with code 1. The
+/// selector is one of
+///  - a `code`, e.g. `W0012`
+///  - a name, e.g. `ConfigWarning::Unused`, or the name of a whole enum, `ConfigWarning`
+///  - a target, or a module the target is in, e.g. `parse` for `parse::toml`
+///  - `*`, for every warning
+///
+/// The selectors in the comma-separated `HELHEIM_DENY` variable are denied too, e.g.
+/// `HELHEIM_DENY=W0012,parse`.
+pub fn deny(selector: impl Into<String>) {
+    denied().write().unwrap().push(selector.into());
+}
+
+/// Whether a warning is denied, see `deny`
+pub(crate) fn is_denied(name: &str, code: Option<&str>, target: &str) -> bool {
+    let enum_name = name.split("::").next().unwrap_or(name);
+
+    denied().read().unwrap().iter().any(|selector| {
+        selector == "*"
+            || Some(selector.as_str()) == code
+            || selector == name
+            || selector == enum_name
+            || target
+                .strip_prefix(selector.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+    })
+}
+
+/// Called by `emit` after logging a denied warning
+#[doc(hidden)]
+#[track_caller]
+pub fn __escalate(name: &str) {
+    match cfg!(debug_assertions) {
+        true => panic!("denied warning {name} was emitted"),
+        false => std::process::exit(1),
+    }
+}
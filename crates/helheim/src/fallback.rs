@@ -0,0 +1,36 @@
+use log::{Level, LevelFilter};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static SILENT: AtomicBool = AtomicBool::new(false);
+
+/// # Fallback
+///
+/// What `emit` does when there is no logger, see `set_fallback`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fallback {
+    /// Write the warning to stderr
+    #[default]
+    Stderr,
+    /// Drop the warning, like `log` does
+    Silent,
+}
+
+/// Set what `emit` does when there is no logger. Without a logger, `log`'s max level is
+/// `Off`, so warnings are also written to stderr when it is set to `Off` on purpose,
+/// unless the fallback is `Silent`.
+pub fn set_fallback(fallback: Fallback) {
+    SILENT.store(fallback == Fallback::Silent, Ordering::Relaxed);
+}
+
+/// Called by `emit` after logging
+#[doc(hidden)]
+pub fn __fallback(level: Level, message: &str) {
+    if log::max_level() != LevelFilter::Off || SILENT.load(Ordering::Relaxed) {
+        return;
+    }
+
+    match level {
+        Level::Warn => eprintln!("warning: {message}"),
+        level => eprintln!("{}: {message}", level.as_str().to_lowercase()),
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt::{Debug, Display};
+
+/// A field of a warning logged as a key-value by `emit` (`kv` feature). It is formatted
+/// with `Debug` if it implements it, else with `Display`, else it is logged as `<?>`,
+/// so logging the fields adds no bounds to generic warnings.
+///
+/// The right formatting is picked by autoref: `(&&&__KvField(field)).__helheim_kv()`
+/// finds `__KvDebug` first, then `__KvDisplay`, then `__KvOther`.
+#[doc(hidden)]
+pub struct __KvField<'a, T: ?Sized>(pub &'a T);
+
+#[doc(hidden)]
+pub trait __KvDebug {
+    fn __helheim_kv(&self) -> String;
+}
+
+impl<T: Debug + ?Sized> __KvDebug for &&__KvField<'_, T> {
+    fn __helheim_kv(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+#[doc(hidden)]
+pub trait __KvDisplay {
+    fn __helheim_kv(&self) -> String;
+}
+
+impl<T: Display + ?Sized> __KvDisplay for &__KvField<'_, T> {
+    fn __helheim_kv(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+#[doc(hidden)]
+pub trait __KvOther {
+    fn __helheim_kv(&self) -> String;
+}
+
+impl<T: ?Sized> __KvOther for __KvField<'_, T> {
+    fn __helheim_kv(&self) -> String {
+        String::from("<?>")
+    }
+}
+
+// the extra borrows are what picks the formatting
+#[cfg(test)]
+#[allow(clippy::needless_borrow)]
+mod tests {
+    use super::*;
+    use std::fmt;
+
+    struct Shown;
+
+    impl Display for Shown {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "shown")
+        }
+    }
+
+    struct Opaque;
+
+    fn generic<T: Display>(value: &T) -> String {
+        (&&&__KvField(value)).__helheim_kv()
+    }
+
+    #[test]
+    fn picks_the_formatting() {
+        assert_eq!((&&&__KvField(&"x")).__helheim_kv(), "\"x\"");
+        assert_eq!((&&&__KvField(&Shown)).__helheim_kv(), "shown");
+        assert_eq!((&&&__KvField(&Opaque)).__helheim_kv(), "<?>");
+
+        // only the bounds of the generic are known
+        assert_eq!(generic(&"x"), "x");
+    }
+}
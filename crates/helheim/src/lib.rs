@@ -16,135 +16,459 @@
 //!
 //! warning.emit(); // log::warn!("Something went wrong");
 //! ```
+//!
+//! Fields are interpolated like with `thiserror`: by name for struct variants, and by
+//! position for tuple variants.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum ConfigWarning {
+//!    #[warning("bad value {value} in {file}")]
+//!    BadValue { value: String, file: String },
+//!    #[warning("{1} is deprecated, use {0} instead")]
+//!    Deprecated(&'static str, &'static str),
+//! }
+//!
+//! let warning = ConfigWarning::BadValue {
+//!     value: String::from("-1"),
+//!     file: String::from("config.toml"),
+//! };
+//! assert_eq!(warning.to_string(), "bad value -1 in config.toml");
+//!
+//! let warning = ConfigWarning::Deprecated("--output", "-o");
+//! assert_eq!(warning.to_string(), "-o is deprecated, use --output instead");
+//! ```
+//!
+//! When a format string isn't enough, e.g. for plurals, `fmt` sets a function that gets
+//! references to the fields, and returns the message.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! fn skipped(count: &usize, file: &str) -> String {
+//!     match count {
+//!         1 => format!("skipped 1 line of {file}"),
+//!         n => format!("skipped {n} lines of {file}"),
+//!     }
+//! }
+//!
+//! #[derive(Warning)]
+//! enum ReadWarning {
+//!    #[warning(fmt = skipped)]
+//!    Skipped(usize, String),
+//! }
+//!
+//! assert_eq!(ReadWarning::Skipped(1, String::from("a.csv")).to_string(), "skipped 1 line of a.csv");
+//! assert_eq!(ReadWarning::Skipped(3, String::from("a.csv")).to_string(), "skipped 3 lines of a.csv");
+//! ```
+//!
+//! Fields marked with `#[warning(skip)]` are left out: they don't count for the
+//! positions of tuple fields, and aren't passed to `fmt`, passed on as key-values, or
+//! serialized.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! struct Payload(Vec<u8>);
+//!
+//! #[derive(Warning)]
+//! enum UploadWarning {
+//!    #[warning("{0} was retried {1} times")]
+//!    Retried(String, #[warning(skip)] Payload, u32),
+//! }
+//!
+//! let warning = UploadWarning::Retried(String::from("a.bin"), Payload(vec![0; 1024]), 3);
+//! assert_eq!(warning.to_string(), "a.bin was retried 3 times");
+//! ```
+//!
+//! The format strings are checked at compile time: formatting a field that doesn't exist
+//! is an error, and so is leaving a field out of both the message and the `help`, unless
+//! the variant (or enum) has `allow_unused`.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum CacheWarning {
+//!    #[warning("cache entry {0} is stale", allow_unused)]
+//!    Stale(String, std::time::Instant),
+//! }
+//! ```
+//!
+//! Format specs and escaped braces work like in `format!`.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum LayoutWarning {
+//!    #[warning("unknown key {key:?} in {{layout}}")]
+//!    UnknownKey { key: String },
+//!    #[warning("[{0:>4}] {1:.2}% slower")]
+//!    Slow(u32, f64),
+//! }
+//!
+//! let warning = LayoutWarning::UnknownKey { key: String::from("colour") };
+//! assert_eq!(warning.to_string(), "unknown key \"colour\" in {layout}");
+//!
+//! assert_eq!(LayoutWarning::Slow(7, 12.345).to_string(), "[   7] 12.35% slower");
+//! ```
+//!
+//! Structs with a single shape can derive `Warning` too, with the attribute on the type.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! #[warning("{path} is not formatted")]
+//! struct Unformatted {
+//!     path: String,
+//! }
+//!
+//! let warning = Unformatted { path: String::from("src/main.rs") };
+//! assert_eq!(warning.to_string(), "src/main.rs is not formatted");
+//! ```
+//!
+//! Generic warnings work too, the bounds the format string needs are up to you.
+//!
+//! ```
+//! use helheim::Warning;
+//! use std::fmt::Display;
+//!
+//! #[derive(Warning)]
+//! enum ParseWarning<T: Display> {
+//!    #[warning("ignoring {0}")]
+//!    Ignored(T),
+//! }
+//!
+//! assert_eq!(ParseWarning::Ignored(3).to_string(), "ignoring 3");
+//! ```
+//!
+//! A suggestion can be added with `help`, a format string like the message. `emit` logs
+//! it on an indented line after the warning.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum BuildWarning {
+//!    #[warning("{0} already exists", help = "try passing --force to overwrite {0}")]
+//!    Exists(String),
+//! }
+//!
+//! let warning = BuildWarning::Exists(String::from("out/"));
+//! assert_eq!(warning.help().unwrap(), "try passing --force to overwrite out/");
+//!
+//! warning.emit(); // log::warn!("out/ already exists\n  help: try passing --force to overwrite out/");
+//! ```
+//!
+//! A link to the documentation of a warning can be set with `url`, on a variant or an
+//! enum. It is part of the reports and the `kv` fields, and with `show_url`, `emit`
+//! also logs it after the warning and its help.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum LintWarning {
+//!    #[warning("{0} is unused", url = "https://docs.example.com/W0012", show_url)]
+//!    Unused(String),
+//!    #[warning("{0} is deprecated", url = "https://docs.example.com/W0013")]
+//!    Deprecated(String),
+//! }
+//!
+//! let warning = LintWarning::Unused(String::from("x"));
+//! assert_eq!(warning.url(), Some("https://docs.example.com/W0012"));
+//!
+//! warning.emit(); // log::warn!("x is unused\n  see: https://docs.example.com/W0012");
+//! LintWarning::Deprecated(String::from("y")).emit(); // log::warn!("y is deprecated");
+//! ```
+//!
+//! For warnings about some text, e.g. a config file, a `(source, range)` field can be
+//! marked with `#[warning(span)]`. `emit_pretty` then logs the lines of the source the
+//! range covers below the warning, with the range underlined.
+//!
+//! ```
+//! use helheim::Warning;
+//! use std::ops::Range;
+//!
+//! #[derive(Warning)]
+//! enum ConfigWarning {
+//!    #[warning("{key} must be positive")]
+//!    Negative {
+//!        key: String,
+//!        #[warning(span)]
+//!        span: (String, Range<usize>),
+//!    },
+//! }
+//!
+//! let source = String::from("name = \"app\"\nport = -1\n");
+//! let warning = ConfigWarning::Negative {
+//!     key: String::from("port"),
+//!     span: (source, 20..22),
+//! };
+//!
+//! assert_eq!(warning.source_span().unwrap().1, 20..22);
+//!
+//! // log::warn!("port must be positive\n --> 2:8\n  |\n2 | port = -1\n  |        ^^");
+//! warning.emit_pretty();
+//! ```
+//!
+//! Warnings emitted before a logger is set up are written to stderr instead, unless
+//! `set_fallback(Fallback::Silent)` is called.
+//!
+//! In loops, `emit_once` logs each message only once per process, and
+//! `emit_once_per_variant` only the first warning of each variant.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum ImportWarning {
+//!    #[warning("skipping row {0}, it is empty")]
+//!    Empty(usize),
+//! }
+//!
+//! for row in [3, 3, 8] {
+//!     // logs row 3 and row 8
+//!     ImportWarning::Empty(row).emit_once();
+//!
+//!     // only logs row 3
+//!     ImportWarning::Empty(row).emit_once_per_variant();
+//! }
+//! ```
+//!
+//! Wrappers around other warnings or errors can forward `Display` to their only field
+//! with `transparent`.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum AppWarning {
+//!    #[warning(transparent)]
+//!    Io(std::io::Error),
+//! }
+//!
+//! let warning = AppWarning::Io(std::io::Error::other("disk is slow"));
+//! assert_eq!(warning.to_string(), "disk is slow");
+//! ```
+//!
+//! `emit` logs at the `warn` level, unless another one is set with `level`, and with the
+//! module of the warning as the target, unless another one is set with `target`. Both
+//! can be set on an enum, for all of its variants.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! #[warning(target = "sync")]
+//! enum SyncWarning {
+//!    #[warning("using cached copy of {0}")]
+//!    #[warning(level = "info")]
+//!    Cached(String),
+//!    #[warning("could not reach {0}", level = "error")]
+//!    Unreachable(String),
+//! }
+//!
+//! assert_eq!(SyncWarning::Cached(String::from("a")).level(), log::Level::Info);
+//! assert_eq!(SyncWarning::Unreachable(String::from("a")).level(), log::Level::Error);
+//! assert_eq!(SyncWarning::Cached(String::from("a")).target(), "sync");
+//! ```
+//!
+//! With the `kv` feature, `emit` passes the name of the warning, its `code`, its `url`
+//! and its fields as `log` key-values, for structured loggers. Fields are formatted
+//! with `Debug`, or with `Display` if they don't implement it, so this adds no bounds to
+//! generic warnings. This enables the `kv` feature of `log`.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum LintWarning {
+//!    #[warning("{name} is unused", code = "W0012")]
+//!    Unused { name: String },
+//! }
+//!
+//! let warning = LintWarning::Unused { name: String::from("x") };
+//! assert_eq!(warning.name(), "LintWarning::Unused");
+//! assert_eq!(warning.code(), Some("W0012"));
+//!
+//! // log::warn!(warning = "LintWarning::Unused", code = "W0012", name:? = "x"; "x is unused");
+//! warning.emit();
+//! ```
+//!
+//! With the `serde` feature, `#[warning(serialize)]` on the type adds `to_report`, which
+//! returns the warning as a `WarningReport`, e.g. to send it to a reporting endpoint.
+//! The fields have to implement `Serialize`.
+//!
+//! `emit_to` sends a warning to a `WarningSink` instead of `log`, e.g. a test collector
+//! or, with the `hermod` feature, a hermod emitter, as a `WarningEvent`.
+//!
+//! The generated code refers to `helheim` by name. When it is re-exported instead, e.g.
+//! by `asgard`, the path is given with `#[warning(crate = asgard::helheim)]` on the
+//! type. `log` does not have to be a dependency either way.
+//!
+//! For strict runs, e.g. in CI, warnings can be denied with `deny` or the
+//! `HELHEIM_DENY` variable. `emit` then logs them as errors, and panics in debug builds,
+//! or exits the process.
+//!
+//! ```should_panic
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum LintWarning {
+//!    #[warning("{0} is unused", code = "W0012")]
+//!    Unused(String),
+//! }
+//!
+//! // like HELHEIM_DENY=W0012
+//! helheim::deny("W0012");
+//!
+//! LintWarning::Unused(String::from("x")).emit();
+//! ```
+//!
+//! Warnings can be put in a `category`, on a variant or an enum. `set_policy` then
+//! silences or denies a whole category at once, e.g. so users can hide deprecations,
+//! or escalate them before upgrading.
+//!
+//! ```
+//! use helheim::{Policy, Warning};
+//!
+//! #[derive(Warning)]
+//! #[warning(category = "deprecation")]
+//! enum ApiWarning {
+//!    #[warning("{0} is deprecated")]
+//!    Deprecated(String),
+//! }
+//!
+//! helheim::set_policy("deprecation", Policy::Silence);
+//!
+//! let warning = ApiWarning::Deprecated(String::from("/v1/users"));
+//! assert_eq!(warning.category(), Some("deprecation"));
+//!
+//! warning.emit(); // logs nothing
+//! assert!(helheim::report().is_empty());
+//! ```
+//!
+//! ## Reports
+//!
+//! Every warning `emit` logs is counted. `report` returns the counts, e.g. to print a
+//! summary at the end of a run, or to fail when there were warnings.
+//!
+//! ```
+//! use helheim::Warning;
+//!
+//! #[derive(Warning)]
+//! enum LintWarning {
+//!    #[warning("{0} is unused", code = "W0012")]
+//!    Unused(String),
+//! }
+//!
+//! LintWarning::Unused(String::from("x")).emit();
+//! LintWarning::Unused(String::from("y")).emit();
+//!
+//! let report = helheim::report();
+//! assert_eq!(report.total(), 2);
+//! assert_eq!(report.to_string(), "2 warnings emitted\n  2 x LintWarning::Unused [W0012]");
+//! ```
+//!
+//! ## Errors
+//!
+//! `#[derive(Error)]` implements `Display` the same way, with `#[error(...)]`, and
+//! `std::error::Error`. The `#[from]` field is the source of the error, and gets a
+//! `From` impl, which captures the location of the caller into the `#[location]` field
+//! (or the field named `location`), if there is one.
+//!
+//! ```
+//! use helheim::Error;
+//! use std::{io, panic::Location};
+//!
+//! #[derive(Error, Debug)]
+//! enum LoadError {
+//!    #[error("At {location}: IO error: {error}")]
+//!    Io {
+//!        #[from]
+//!        error: io::Error,
+//!        location: &'static Location<'static>,
+//!    },
+//!    #[error("{0} is empty")]
+//!    Empty(String),
+//! }
+//!
+//! fn load() -> Result<String, LoadError> {
+//!     Err(io::Error::other("disk unplugged"))?
+//! }
+//!
+//! let error = load().unwrap_err();
+//! assert!(error.to_string().contains("IO error: disk unplugged"));
+//! assert!(std::error::Error::source(&error).is_some());
+//! ```
 
-extern crate proc_macro;
-extern crate proc_macro2;
-extern crate quote;
-#[macro_use]
-extern crate skuld;
-extern crate syn;
-
-use proc_macro::TokenStream as StdTokenStream;
-use proc_macro2::{Span, TokenStream};
-use quote::quote;
-use syn::{
-    parse_macro_input, punctuated::Punctuated, spanned::Spanned, Data, DeriveInput, Error, Ident,
-    LitStr, Meta, Result, Token,
-};
-
-type WArgs = Punctuated<LitStr, Token![,]>;
-
-fn helheim(input: DeriveInput) -> Result<TokenStream> {
-    let DeriveInput {
-        ident,
-        data: Data::Enum(data),
-        ..
-    } = input
-    else {
-        bail!(Error::new(input.span(), "Expected enum"));
-    };
-
-    let variants = &data.variants;
-    let mut arms = vec![];
-
-    for variant in variants {
-        let attrs = &variant.attrs;
-        let wattr = attrs.iter().find(|attr| attr.path().is_ident("warning"));
-
-        let Some(wattr) = wattr else {
-            bail!(Error::new(
-                variant.span(),
-                "Expected #[warning(...)] attribute"
-            ))
-        };
+mod deny;
+mod fallback;
+mod kv;
+mod policy;
+mod report;
 
-        let Meta::List(args) = &wattr.meta else {
-            bail!(Error::new(
-                wattr.span(),
-                "Expected a list (i.e. #[warning(...)]) attribute"
-            ))
-        };
+#[cfg(feature = "serde")]
+mod serialize;
 
-        let Ok(args) = args.parse_args_with(WArgs::parse_terminated) else {
-            bail!(Error::new(args.span(), "Failed to parse these arguments"));
-        };
+mod sink;
+mod snippet;
 
-        let Some(display) = args.first() else {
-            bail!(Error::new(
-                args.span(),
-                "Expected only one argument in this attribute, found none"
-            ))
-        };
+pub use deny::*;
+pub use fallback::*;
+pub use helheim_derive::{Error, Warning};
+pub use kv::*;
+pub use policy::*;
+pub use report::*;
 
-        if args.len() != 1 {
-            bail!(Error::new(
-                args.span(),
-                "Expected only one argument in this attribute"
-            ))
-        }
+#[cfg(feature = "serde")]
+pub use serialize::*;
 
-        let format_lit = display
-            .value()
-            .split("{")
-            .enumerate()
-            .map(|(n, fmt)| {
-                if n == 0 {
-                    fmt.to_string()
-                } else {
-                    format!("{{f{fmt}")
-                }
-            })
-            .fold(String::new(), |acc, fmt| acc + &fmt);
+pub use sink::*;
+pub use snippet::*;
 
-        let format_lit = LitStr::new(&format_lit, Span::call_site());
+/// Used by `#[derive(Warning)]`, so `log` does not have to be a dependency
+#[doc(hidden)]
+pub use log as __log;
 
-        let fields = variant.fields.iter().enumerate().map(|(i, field)| {
-            if let Some(ident) = &field.ident {
-                let prefixed = Ident::new(&format!("f{}", i), Span::call_site());
-                quote! { #ident: #prefixed }
-            } else {
-                let prefixed = Ident::new(&format!("f{}", i), Span::call_site());
-                quote! { #prefixed }
-            }
-        });
+/// Used by `to_report`
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub use serde_json as __serde_json;
 
-        let arm = match variant.fields.iter().next() {
-            Some(field) if field.ident.is_some() => quote! { { #(#fields),* } },
-            Some(_) => quote! { ( #(#fields),* ) },
-            None => quote! {},
-        };
+#[cfg(test)]
+mod tests {
+    use crate::Warning;
 
-        arms.push(quote! {
-            #ident::#variant #arm => format!(#format_lit),
-        });
+    #[derive(Warning)]
+    #[warning(crate = crate, url = "https://docs.example.com/lints")]
+    enum LintWarning {
+        #[warning("{0} is unused", help = "remove it")]
+        Unused(String),
+        #[warning("{0} is deprecated", show_url)]
+        Deprecated(String),
     }
 
-    Ok(quote! {
-        impl ::std::fmt::Display for #ident {
-            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
-                let s = match self {
-                    #(#arms)*
-                };
-                write!(f, "{}", s)
-            }
-        }
+    #[test]
+    fn urls_are_only_logged_with_show_url() {
+        let unused = LintWarning::Unused(String::from("x"));
+        assert_eq!(
+            unused.__helheim_message(false),
+            "x is unused\n  help: remove it"
+        );
 
-        impl #ident {
-            pub fn emit(&self) {
-                ::log::warn!("{}", self);
-            }
+        let deprecated = LintWarning::Deprecated(String::from("y"));
+        assert_eq!(
+            deprecated.__helheim_message(false),
+            "y is deprecated\n  see: https://docs.example.com/lints"
+        );
 
-            pub fn into_emit(self) {
-                self.emit();
-            }
-        }
-    })
-}
-
-#[proc_macro_derive(Warning, attributes(warning))]
-pub fn warning(input: StdTokenStream) -> StdTokenStream {
-    helheim(parse_macro_input!(input as DeriveInput))
-        .map(StdTokenStream::from)
-        .map_err(Error::into_compile_error)
-        .unwrap_or_else(StdTokenStream::from)
+        // either way, the url is known
+        assert_eq!(unused.url(), deprecated.url());
+    }
 }
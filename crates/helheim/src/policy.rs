@@ -0,0 +1,46 @@
+use crate::deny::is_denied;
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+/// The policies set with `set_policy`, by category
+static POLICIES: OnceLock<RwLock<HashMap<String, Policy>>> = OnceLock::new();
+
+fn policies() -> &'static RwLock<HashMap<String, Policy>> {
+    POLICIES.get_or_init(Default::default)
+}
+
+/// # Policy
+///
+/// What `emit` does with the warnings of a category, see `set_policy`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Policy {
+    /// Drop the warnings: they are not logged, sent or counted
+    Silence,
+    /// Log the warnings at their level, unless they are denied with `deny`
+    #[default]
+    Warn,
+    /// Deny the warnings, like `deny`
+    Deny,
+}
+
+/// Set what `emit` does with the warnings in `category`, set with `category` on a
+/// variant or an enum. Warnings without a category, or in a category without a policy,
+/// are logged (`Policy::Warn`).
+pub fn set_policy(category: impl Into<String>, policy: Policy) {
+    policies().write().unwrap().insert(category.into(), policy);
+}
+
+/// What to do with a warning, from the policy of its category and `deny`
+#[doc(hidden)]
+pub fn __policy(name: &str, code: Option<&str>, target: &str, category: Option<&str>) -> Policy {
+    let policy = category
+        .and_then(|category| policies().read().unwrap().get(category).copied())
+        .unwrap_or_default();
+
+    match policy {
+        Policy::Warn if is_denied(name, code, target) => Policy::Deny,
+        policy => policy,
+    }
+}
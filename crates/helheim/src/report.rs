@@ -0,0 +1,77 @@
+use std::{collections::BTreeMap, fmt, sync::Mutex};
+
+/// The warnings emitted so far, by name
+static REGISTRY: Mutex<BTreeMap<&'static str, Count>> = Mutex::new(BTreeMap::new());
+
+/// # Count
+///
+/// How often one warning was emitted, see `Report`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Count {
+    /// `Enum::Variant` or `Struct`
+    pub name: &'static str,
+    /// Set with `code`
+    pub code: Option<&'static str>,
+    pub count: usize,
+}
+
+/// # Report
+///
+/// The warnings emitted in this process, see `report`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Sorted by name
+    pub counts: Vec<Count>,
+}
+
+impl Report {
+    /// Number of warnings emitted
+    pub fn total(&self) -> usize {
+        self.counts.iter().map(|n| n.count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.total() {
+            1 => write!(f, "1 warning emitted")?,
+            total => write!(f, "{total} warnings emitted")?,
+        }
+
+        for count in &self.counts {
+            write!(f, "\n  {} x {}", count.count, count.name)?;
+
+            if let Some(code) = count.code {
+                write!(f, " [{code}]")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The warnings emitted so far, with how often each was emitted
+pub fn report() -> Report {
+    Report {
+        counts: REGISTRY.lock().unwrap().values().copied().collect(),
+    }
+}
+
+/// Called by `emit`
+#[doc(hidden)]
+pub fn __record(name: &'static str, code: Option<&'static str>) {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .entry(name)
+        .or_insert(Count {
+            name,
+            code,
+            count: 0,
+        })
+        .count += 1;
+}
@@ -0,0 +1,35 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// # WarningReport
+///
+/// A warning as data, returned by `to_report` with `#[warning(serialize)]`.
+///
+/// ```
+/// use helheim::Warning;
+///
+/// #[derive(Warning)]
+/// #[warning(serialize)]
+/// enum LintWarning {
+///    #[warning("{name} is unused", code = "W0012")]
+///    Unused { name: String },
+/// }
+///
+/// let report = LintWarning::Unused { name: String::from("x") }.to_report();
+/// assert_eq!(report.code, Some("W0012"));
+/// assert_eq!(report.fields["name"], "x");
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WarningReport {
+    /// `Enum::Variant` or `Struct`
+    pub name: &'static str,
+    pub code: Option<&'static str>,
+    pub url: Option<&'static str>,
+    pub category: Option<&'static str>,
+    /// e.g. `"WARN"`
+    pub level: &'static str,
+    pub message: String,
+    pub help: Option<String>,
+    /// The serialized fields, tuple fields by position
+    pub fields: Map<String, Value>,
+}
@@ -0,0 +1,218 @@
+use log::Level;
+use std::sync::Mutex;
+
+#[cfg(feature = "hermod")]
+use std::{
+    sync::{mpsc, Arc, OnceLock},
+    thread,
+};
+
+/// # EmittedWarning
+///
+/// A warning sent to a `WarningSink` by `emit_to`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmittedWarning {
+    /// `Enum::Variant` or `Struct`
+    pub name: &'static str,
+    pub code: Option<&'static str>,
+    pub url: Option<&'static str>,
+    pub category: Option<&'static str>,
+    pub level: Level,
+    pub target: &'static str,
+    pub message: String,
+    pub help: Option<String>,
+}
+
+/// # WarningSink
+///
+/// Where `emit_to` sends warnings to, instead of `log`. Implemented for closures, for
+/// `Mutex<Vec<EmittedWarning>>` to collect warnings in tests, and, with the `hermod`
+/// feature, for hermod's emitters (an `EventEmitter` in an `Arc`).
+///
+/// ```
+/// use helheim::{EmittedWarning, Warning};
+/// use std::sync::Mutex;
+///
+/// #[derive(Warning)]
+/// enum LintWarning {
+///    #[warning("{0} is unused", code = "W0012")]
+///    Unused(String),
+/// }
+///
+/// let collected = Mutex::new(Vec::<EmittedWarning>::new());
+/// LintWarning::Unused(String::from("x")).emit_to(&collected);
+///
+/// let collected = collected.into_inner().unwrap();
+/// assert_eq!(collected[0].message, "x is unused");
+/// assert_eq!(collected[0].code, Some("W0012"));
+/// ```
+pub trait WarningSink {
+    fn emit(&self, warning: EmittedWarning);
+}
+
+impl<F> WarningSink for F
+where
+    F: Fn(EmittedWarning),
+{
+    fn emit(&self, warning: EmittedWarning) {
+        self(warning)
+    }
+}
+
+impl WarningSink for Mutex<Vec<EmittedWarning>> {
+    fn emit(&self, warning: EmittedWarning) {
+        self.lock().unwrap().push(warning);
+    }
+}
+
+/// The event `WarningSink` emits on hermod's emitters
+#[cfg(feature = "hermod")]
+pub struct WarningEvent;
+
+#[cfg(feature = "hermod")]
+impl hermod::Event for WarningEvent {
+    type Message = EmittedWarning;
+}
+
+/// Queues the warning, without waiting for the listeners of `WarningEvent`. They are
+/// called in the order warnings were emitted, on a background thread shared by every
+/// emitter, so emitting never blocks (or deadlocks) an executor.
+#[cfg(feature = "hermod")]
+impl WarningSink for Arc<hermod::EventEmitter> {
+    fn emit(&self, warning: EmittedWarning) {
+        // the thread only stops if the process is exiting
+        let _ = forwarder()
+            .lock()
+            .unwrap()
+            .send((Arc::clone(self), warning));
+    }
+}
+
+#[cfg(feature = "hermod")]
+type Forwarded = (Arc<hermod::EventEmitter>, EmittedWarning);
+
+/// The thread forwarding warnings to `EventEmitter`s
+#[cfg(feature = "hermod")]
+fn forwarder() -> &'static Mutex<mpsc::Sender<Forwarded>> {
+    static FORWARDER: OnceLock<Mutex<mpsc::Sender<Forwarded>>> = OnceLock::new();
+
+    FORWARDER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Forwarded>();
+
+        thread::Builder::new()
+            .name(String::from("helheim-warnings"))
+            .spawn(move || {
+                for (emitter, warning) in receiver {
+                    futures::executor::block_on(hermod::EventEmitter::emit::<WarningEvent>(
+                        &emitter, warning,
+                    ));
+                }
+            })
+            .expect("failed to spawn the warning thread");
+
+        Mutex::new(sender)
+    })
+}
+
+#[cfg(feature = "hermod")]
+impl WarningSink for hermod::SyncEventEmitter {
+    fn emit(&self, warning: EmittedWarning) {
+        self.emit::<WarningEvent>(&warning);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Warning;
+
+    #[cfg(feature = "hermod")]
+    use std::time::Duration;
+
+    #[cfg(feature = "hermod")]
+    fn warning(message: &str) -> EmittedWarning {
+        EmittedWarning {
+            name: "Test",
+            code: None,
+            url: None,
+            category: None,
+            level: Level::Warn,
+            target: "test",
+            message: String::from(message),
+            help: None,
+        }
+    }
+
+    #[derive(Warning)]
+    #[warning(crate = crate)]
+    enum LintWarning {
+        #[warning("{0} is unused", level = "info")]
+        Unused(String),
+    }
+
+    #[test]
+    fn derives_can_name_the_crate() {
+        let collected = Mutex::new(vec![]);
+        LintWarning::Unused(String::from("x")).emit_to(&collected);
+
+        let collected = collected.into_inner().unwrap();
+        assert_eq!(collected[0].message, "x is unused");
+        assert_eq!(collected[0].level, Level::Info);
+    }
+
+    #[test]
+    #[cfg(feature = "hermod")]
+    fn emitters_receive_warnings_in_order() {
+        let emitter = Arc::new(hermod::EventEmitter::new());
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+
+        emitter.on::<WarningEvent>(move |warning| {
+            sender
+                .lock()
+                .unwrap()
+                .send(warning.message.clone())
+                .unwrap();
+            Box::pin(async { Ok(()) })
+        });
+
+        for message in ["first", "second"] {
+            WarningSink::emit(&emitter, warning(message));
+        }
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), "first");
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), "second");
+    }
+
+    #[test]
+    #[cfg(feature = "hermod")]
+    fn listeners_can_emit_warnings() {
+        let emitter = Arc::new(hermod::EventEmitter::new());
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let inner = Arc::downgrade(&emitter);
+
+        // emitting from a listener used to wait on itself
+        emitter.on::<WarningEvent>(move |warning| {
+            if warning.message == "outer" {
+                if let Some(emitter) = inner.upgrade() {
+                    WarningSink::emit(&emitter, super::tests::warning("inner"));
+                }
+            }
+
+            sender
+                .lock()
+                .unwrap()
+                .send(warning.message.clone())
+                .unwrap();
+            Box::pin(async { Ok(()) })
+        });
+
+        WarningSink::emit(&emitter, warning("outer"));
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), "outer");
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), "inner");
+    }
+}
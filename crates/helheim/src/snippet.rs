@@ -0,0 +1,67 @@
+use std::ops::Range;
+
+/// `message`, followed by the lines of `source` that `span` covers, with the span
+/// underlined:
+///
+/// ```text
+/// port must be positive
+///  --> 3:8
+///   |
+/// 3 | port = -1
+///   |        ^^
+/// ```
+#[doc(hidden)]
+pub fn __snippet(message: &str, source: &str, span: Range<usize>) -> String {
+    let start = floor(source, span.start);
+    let end = floor(source, span.end).max(start);
+
+    let first = source[..start].matches('\n').count() + 1;
+    let last = first + source[start..end].matches('\n').count();
+    let width = last.to_string().len();
+    let gutter = " ".repeat(width);
+
+    let line_start = source[..start].rfind('\n').map_or(0, |n| n + 1);
+    let column = source[line_start..start].chars().count() + 1;
+
+    let mut snippet = format!("{message}\n{gutter}--> {first}:{column}\n{gutter} |");
+    let mut offset = line_start;
+
+    for (number, raw) in source[line_start..].split('\n').enumerate() {
+        let number = first + number;
+
+        if number > last {
+            break;
+        }
+
+        let line = raw.strip_suffix('\r').unwrap_or(raw);
+        let from = start.saturating_sub(offset).min(line.len());
+        let to = end.saturating_sub(offset).min(line.len());
+
+        let indent = line[..from].chars().count();
+        // an empty span still gets one marker
+        let marks = line[from..to]
+            .chars()
+            .count()
+            .max(usize::from(number == first));
+
+        snippet += &format!("\n{number:>width$} | {line}");
+        if marks > 0 {
+            snippet += &format!("\n{gutter} | {}{}", " ".repeat(indent), "^".repeat(marks));
+        }
+
+        offset += raw.len() + 1;
+    }
+
+    snippet
+}
+
+/// The closest char boundary at or before `index`
+fn floor(source: &str, index: usize) -> usize {
+    let mut index = index.min(source.len());
+
+    while !source.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    index
+}
@@ -0,0 +1,169 @@
+//! # Hermod Derive
+//!
+//! `#[derive(Event)]` for `hermod`, enabled with its `derive` feature. The message
+//! type is given with `#[message(...)]`.
+//!
+//! With `#[emitter(...)]`, an `emit_<event>` method is also added to the given type,
+//! which has to be defined in the same crate and dereference to an `EventEmitter`.
+//!
+//! The generated code refers to `hermod` by name. When it is re-exported instead, e.g.
+//! by `asgard`, the path is given with `#[event(crate = asgard::hermod)]`.
+//!
+//! ## Example
+//! ```
+//! use hermod::{Event, EventEmitter};
+//! use std::ops::Deref;
+//!
+//! pub struct AppEvents(EventEmitter);
+//!
+//! impl Deref for AppEvents {
+//!     type Target = EventEmitter;
+//!
+//!     fn deref(&self) -> &EventEmitter {
+//!         &self.0
+//!     }
+//! }
+//!
+//! #[derive(Event)]
+//! #[message(String)]
+//! #[emitter(AppEvents)]
+//! pub struct SomethingHappened;
+//!
+//! let events = AppEvents(EventEmitter::new());
+//!
+//! events.on::<SomethingHappened>(|msg| Box::pin(async move {
+//!     assert_eq!(*msg, "hello");
+//!     Ok(())
+//! }));
+//!
+//! async_std::task::block_on(events.emit_something_happened(String::from("hello")));
+//! ```
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+#[macro_use]
+extern crate skuld;
+extern crate syn;
+
+use proc_macro::TokenStream as StdTokenStream;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, DeriveInput, Error, Expr, ExprPath, Ident,
+    Meta, Path, Result, Type,
+};
+
+/// The argument of the `#[name(...)]` attribute, if there is one
+fn argument(attrs: &[Attribute], name: &str) -> Result<Option<Type>> {
+    let mut found = attrs.iter().filter(|attr| attr.path().is_ident(name));
+
+    let Some(attr) = found.next() else {
+        return Ok(None);
+    };
+
+    if let Some(duplicate) = found.next() {
+        bail!(Error::new(
+            duplicate.span(),
+            format!("Expected only one #[{name}(...)] attribute")
+        ));
+    }
+
+    attr.parse_args().map(Some)
+}
+
+/// The path of `hermod`, from `#[event(crate = path::to::hermod)]`
+fn krate(attrs: &[Attribute]) -> Result<Option<Path>> {
+    let mut krate = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("event")) {
+        let meta = attr.parse_args::<Meta>()?;
+
+        let Meta::NameValue(meta) = meta else {
+            bail!(Error::new(meta.span(), "Expected crate = path::to::hermod"));
+        };
+
+        if !meta.path.is_ident("crate") {
+            bail!(Error::new(meta.path.span(), "Unknown event argument"));
+        }
+
+        let Expr::Path(ExprPath { path, .. }) = meta.value else {
+            bail!(Error::new(meta.value.span(), "Expected a path"));
+        };
+
+        if krate.replace(path).is_some() {
+            bail!(Error::new(attr.span(), "Expected only one crate"));
+        }
+    }
+
+    Ok(krate)
+}
+
+/// `SomethingHappened` to `something_happened`
+fn snake_case(ident: &Ident) -> String {
+    let mut snake = String::new();
+
+    for (n, c) in ident.to_string().chars().enumerate() {
+        if c.is_uppercase() && n != 0 {
+            snake.push('_');
+        }
+
+        snake.extend(c.to_lowercase());
+    }
+
+    snake
+}
+
+fn hermod(input: DeriveInput) -> Result<TokenStream> {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let Some(message) = argument(&input.attrs, "message")? else {
+        bail!(Error::new(
+            input.span(),
+            "Expected #[message(...)] attribute"
+        ));
+    };
+
+    let krate = match krate(&input.attrs)? {
+        Some(krate) => quote! { #krate },
+        None => quote! { ::hermod },
+    };
+
+    let mut tokens = quote! {
+        impl #impl_generics #krate::Event for #ident #ty_generics #where_clause {
+            type Message = #message;
+        }
+    };
+
+    if let Some(emitter) = argument(&input.attrs, "emitter")? {
+        if !input.generics.params.is_empty() {
+            bail!(Error::new(
+                input.generics.span(),
+                "#[emitter(...)] is not supported on generic events"
+            ));
+        }
+
+        let method = Ident::new(&format!("emit_{}", snake_case(ident)), Span::call_site());
+        let doc = format!("Emit `{ident}`, see `EventEmitter::emit`");
+
+        tokens.extend(quote! {
+            impl #emitter {
+                #[doc = #doc]
+                pub async fn #method(&self, message: #message) {
+                    self.emit::<#ident>(message).await
+                }
+            }
+        });
+    }
+
+    Ok(tokens)
+}
+
+#[proc_macro_derive(Event, attributes(message, emitter, event))]
+pub fn event(input: StdTokenStream) -> StdTokenStream {
+    hermod(parse_macro_input!(input as DeriveInput))
+        .map(StdTokenStream::from)
+        .map_err(Error::into_compile_error)
+        .unwrap_or_else(StdTokenStream::from)
+}
@@ -0,0 +1,49 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use hermod::{DispatchMode, Event, EventEmitter};
+
+const LISTENERS: usize = 100;
+
+struct Tick;
+
+impl Event for Tick {
+    type Message = u64;
+}
+
+fn emitter(mode: DispatchMode) -> EventEmitter {
+    let emitter = EventEmitter::new();
+    emitter.set_dispatch_mode::<Tick>(mode);
+
+    for _ in 0..LISTENERS {
+        emitter.on::<Tick>(|n| {
+            black_box(*n);
+            Box::pin(async { Ok(()) })
+        });
+    }
+
+    emitter
+}
+
+fn benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("emit");
+
+    for (name, mode) in [
+        ("100 listeners, concurrent", DispatchMode::Concurrent),
+        (
+            "100 listeners, sequential",
+            DispatchMode::Sequential {
+                stop_on_error: false,
+            },
+        ),
+    ] {
+        let emitter = emitter(mode);
+
+        group.bench_function(name, |b| {
+            b.iter(|| async_std::task::block_on(emitter.emit::<Tick>(black_box(1))))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(emit, benches);
+criterion_main!(emit);
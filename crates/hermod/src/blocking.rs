@@ -0,0 +1,268 @@
+use crate::{
+    events::{log_error, Registered},
+    ErrorHandler, Event, EventInfo, ListenerError, ListenerPanic, Subscription, SubscriptionId,
+};
+use std::{
+    any::{type_name, TypeId},
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+type SyncListener<Ev> =
+    Box<dyn Fn(&<Ev as Event>::Message) -> Result<(), ListenerError> + Send + Sync>;
+
+/// # SyncEventEmitter
+///
+/// A blocking `EventEmitter`: listeners are plain closures, and `emit` calls them
+/// inline before returning. Useful in non-async code, or where awaiting is not
+/// possible, e.g. in `Drop` impls.
+///
+/// Listeners are called by priority and then registration order. Errors and panics
+/// are handled like in the `EventEmitter`.
+///
+/// ```
+/// use hermod::{Event, SyncEventEmitter};
+/// use std::sync::{
+///     atomic::{AtomicUsize, Ordering},
+///     Arc,
+/// };
+///
+/// pub struct Closed;
+///
+/// impl Event for Closed {
+///     type Message = u32;
+/// }
+///
+/// let emitter = Arc::new(SyncEventEmitter::new());
+/// let open = Arc::new(AtomicUsize::new(1));
+///
+/// let counter = Arc::clone(&open);
+/// emitter.on::<Closed>(move |_| {
+///     counter.fetch_sub(1, Ordering::SeqCst);
+///     Ok(())
+/// });
+///
+/// struct Connection(u32, Arc<SyncEventEmitter>);
+///
+/// impl Drop for Connection {
+///     fn drop(&mut self) {
+///         self.1.emit::<Closed>(&self.0);
+///     }
+/// }
+///
+/// drop(Connection(1, Arc::clone(&emitter)));
+/// assert_eq!(open.load(Ordering::SeqCst), 0);
+/// ```
+pub struct SyncEventEmitter {
+    listeners: RwLock<HashMap<TypeId, Vec<Registered>>>,
+    next_id: AtomicU64,
+    on_error: RwLock<ErrorHandler>,
+}
+
+impl SyncEventEmitter {
+    pub fn new() -> Self {
+        Self {
+            listeners: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            on_error: RwLock::new(log_error),
+        }
+    }
+
+    /// Set the function called with errors returned by listeners. By default, they
+    /// are logged with `log::error!`.
+    pub fn set_error_handler(&self, handler: ErrorHandler) {
+        *self.on_error.write().unwrap() = handler;
+    }
+
+    pub fn on<Ev: Event>(
+        &self,
+        listener: impl Fn(&Ev::Message) -> Result<(), ListenerError> + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        self.on_with_priority::<Ev>(0, listener)
+    }
+
+    /// Register a listener with a priority, see `EventEmitter::on_with_priority`
+    pub fn on_with_priority<Ev: Event>(
+        &self,
+        priority: i32,
+        listener: impl Fn(&Ev::Message) -> Result<(), ListenerError> + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        let alive = Arc::new(AtomicBool::new(true));
+        self.register::<Ev>(Box::new(listener), alive, priority)
+    }
+
+    /// Like `on`, but the listener is removed when the returned guard is dropped.
+    pub fn subscribe<Ev: Event>(
+        &self,
+        listener: impl Fn(&Ev::Message) -> Result<(), ListenerError> + Send + Sync + 'static,
+    ) -> Subscription {
+        let alive = Arc::new(AtomicBool::new(true));
+        let id = self.register::<Ev>(Box::new(listener), Arc::clone(&alive), 0);
+
+        Subscription { id, alive }
+    }
+
+    /// Register a listener that is removed after its first invocation.
+    pub fn once<Ev: Event>(
+        &self,
+        listener: impl FnOnce(&Ev::Message) -> Result<(), ListenerError> + Send + 'static,
+    ) -> SubscriptionId {
+        let alive = Arc::new(AtomicBool::new(true));
+        let listener = Mutex::new(Some(listener));
+        let flag = Arc::clone(&alive);
+
+        let listener = move |msg: &Ev::Message| {
+            // a listener emitting the event again sees itself as dead already
+            let listener = match flag.swap(false, Ordering::AcqRel) {
+                true => listener.lock().unwrap().take(),
+                false => None,
+            };
+
+            match listener {
+                Some(listener) => listener(msg),
+                None => Ok(()),
+            }
+        };
+
+        self.register::<Ev>(Box::new(listener), alive, 0)
+    }
+
+    /// Remove a listener. Returns whether it was still registered.
+    pub fn off(&self, id: SubscriptionId) -> bool {
+        let mut found = false;
+
+        for list in self.listeners.write().unwrap().values_mut() {
+            list.retain(|n| {
+                let matches = n.id == id && n.is_alive();
+                found |= matches;
+                n.id != id && n.is_alive()
+            });
+        }
+
+        found
+    }
+
+    /// Number of listeners currently registered for `Ev`
+    pub fn listener_count<Ev: Event>(&self) -> usize {
+        self.listeners
+            .read()
+            .unwrap()
+            .get(&TypeId::of::<Ev>())
+            .map_or(0, |n| n.iter().filter(|n| n.is_alive()).count())
+    }
+
+    /// Call every listener of `Ev`, one after another, before returning. Errors are
+    /// passed to the error handler.
+    pub fn emit<Ev: Event>(&self, arg: &Ev::Message) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("emit", event = type_name::<Ev>()).entered();
+
+        for (id, listener) in self.listeners_of::<Ev>() {
+            if let Err(e) = call::<Ev>(&listener, arg) {
+                let on_error = *self.on_error.read().unwrap();
+                let event = type_name::<Ev>();
+
+                on_error(&*e, EventInfo { event, id });
+            }
+        }
+    }
+
+    /// Like `emit`, but stops at and returns the first error.
+    pub fn try_emit<Ev: Event>(&self, arg: &Ev::Message) -> Result<(), ListenerError> {
+        for (_, listener) in self.listeners_of::<Ev>() {
+            call::<Ev>(&listener, arg)?;
+        }
+
+        Ok(())
+    }
+
+    fn register<Ev: Event>(
+        &self,
+        listener: SyncListener<Ev>,
+        alive: Arc<AtomicBool>,
+        priority: i32,
+    ) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.fetch_add(1, Ordering::Relaxed));
+
+        let mut listeners = self.listeners.write().unwrap();
+        let list = listeners.entry(TypeId::of::<Ev>()).or_default();
+
+        list.retain(Registered::is_alive);
+
+        let index = list.partition_point(|n| n.priority >= priority);
+
+        list.insert(
+            index,
+            Registered {
+                id,
+                priority,
+                alive,
+                listener: Arc::new(listener),
+            },
+        );
+
+        id
+    }
+
+    /// The alive listeners of `Ev`, in order. The lock is released before they are
+    /// called, so listeners can emit and (un)register listeners themselves.
+    fn listeners_of<Ev: Event>(&self) -> Vec<(SubscriptionId, Arc<SyncListener<Ev>>)> {
+        self.listeners
+            .read()
+            .unwrap()
+            .get(&TypeId::of::<Ev>())
+            .into_iter()
+            .flatten()
+            .filter(|n| n.is_alive())
+            .filter_map(|n| Some((n.id, Arc::clone(&n.listener).downcast().ok()?)))
+            .collect()
+    }
+}
+
+impl Default for SyncEventEmitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Call `listener`, turning panics into errors
+fn call<Ev: Event>(listener: &SyncListener<Ev>, arg: &Ev::Message) -> Result<(), ListenerError> {
+    panic::catch_unwind(AssertUnwindSafe(|| listener(arg)))
+        .unwrap_or_else(|e| Err(ListenerPanic::new(e).into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Ping;
+
+    impl Event for Ping {
+        type Message = u32;
+    }
+
+    #[test]
+    fn default_emitters_start_without_listeners() {
+        let events = SyncEventEmitter::default();
+        let sum = Arc::new(AtomicU64::new(0));
+
+        assert_eq!(events.listener_count::<Ping>(), 0);
+        assert!(events.try_emit::<Ping>(&1).is_ok());
+
+        let total = Arc::clone(&sum);
+        events.once::<Ping>(move |n| {
+            total.fetch_add(u64::from(*n), Ordering::SeqCst);
+            Ok(())
+        });
+
+        events.emit::<Ping>(&2);
+        events.emit::<Ping>(&3);
+
+        assert_eq!(sum.load(Ordering::SeqCst), 2);
+        assert_eq!(events.listener_count::<Ping>(), 0);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{middleware::FlowFuture, Subscription, SubscriptionId};
+use futures::future;
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt,
+    ops::ControlFlow,
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+};
+
+/// # Overflow
+///
+/// What a `BroadcastReceiver` does with a message when its buffer is full, see
+/// `EventEmitter::receiver`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop the oldest buffered message. The receiver gets a `RecvError::Lagged`.
+    #[default]
+    DropOldest,
+    /// Drop the new message. The receiver gets a `RecvError::Lagged`.
+    DropNewest,
+    /// Make `emit` wait until the receiver has room
+    Wait,
+}
+
+/// # RecvError
+///
+/// Returned by `BroadcastReceiver::recv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// This many messages were dropped since the last `recv`. The next `recv`
+    /// continues with the messages that were kept.
+    Lagged(u64),
+    /// The emitter was dropped, and every message was received
+    Closed,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Lagged(n) => write!(f, "receiver lagged behind by {n} messages"),
+            RecvError::Closed => f.write_str("emitter is closed"),
+        }
+    }
+}
+
+impl Error for RecvError {}
+
+struct State<M> {
+    buffer: VecDeque<Arc<M>>,
+    // messages dropped since the last `recv`
+    lagged: u64,
+    // the receiver or the listener is gone
+    closed: bool,
+    receiver: Option<Waker>,
+    // the emits waiting for room
+    senders: Vec<Waker>,
+}
+
+/// The buffer shared by the listener and the receiver
+pub(crate) struct Channel<M> {
+    capacity: usize,
+    overflow: Overflow,
+    state: Mutex<State<M>>,
+}
+
+impl<M: Send + Sync + 'static> Channel<M> {
+    pub(crate) fn new(capacity: usize, overflow: Overflow) -> Arc<Self> {
+        Arc::new(Self {
+            capacity: capacity.max(1),
+            overflow,
+            state: Mutex::new(State {
+                buffer: VecDeque::new(),
+                lagged: 0,
+                closed: false,
+                receiver: None,
+                senders: vec![],
+            }),
+        })
+    }
+
+    /// Buffer `msg`, applying the overflow policy
+    pub(crate) fn send(self: &Arc<Self>, msg: Arc<M>) -> FlowFuture {
+        let channel = Arc::clone(self);
+        let mut msg = Some(msg);
+
+        Box::pin(future::poll_fn(move |cx| {
+            let mut state = channel.state.lock().unwrap();
+
+            if state.closed {
+                return Poll::Ready(Ok(ControlFlow::Continue(())));
+            }
+
+            if state.buffer.len() >= channel.capacity {
+                match channel.overflow {
+                    Overflow::DropOldest => {
+                        state.buffer.pop_front();
+                        state.lagged += 1;
+                    }
+                    Overflow::DropNewest => {
+                        state.lagged += 1;
+                        return Poll::Ready(Ok(ControlFlow::Continue(())));
+                    }
+                    Overflow::Wait => {
+                        state.senders.push(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                }
+            }
+
+            state.buffer.extend(msg.take());
+
+            if let Some(receiver) = state.receiver.take() {
+                receiver.wake();
+            }
+
+            Poll::Ready(Ok(ControlFlow::Continue(())))
+        }))
+    }
+
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+
+        state.receiver.take().into_iter().for_each(Waker::wake);
+        state.senders.drain(..).for_each(Waker::wake);
+    }
+}
+
+/// Closes the channel once the listener holding it is dropped with the emitter
+pub(crate) struct Closer<M: Send + Sync + 'static>(pub(crate) Arc<Channel<M>>);
+
+impl<M: Send + Sync + 'static> Drop for Closer<M> {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+/// # BroadcastReceiver
+///
+/// The messages of an event, returned by `EventEmitter::receiver`. Unlike an
+/// `EventStream`, the buffer is bounded: when the receiver falls behind, the
+/// `Overflow` policy applies. The listener behind the receiver is removed when it
+/// is dropped.
+pub struct BroadcastReceiver<M: Send + Sync + 'static> {
+    pub(crate) channel: Arc<Channel<M>>,
+    pub(crate) subscription: Subscription,
+}
+
+impl<M: Send + Sync + 'static> BroadcastReceiver<M> {
+    pub fn id(&self) -> SubscriptionId {
+        self.subscription.id
+    }
+
+    /// The next message, waiting for one if the buffer is empty.
+    pub async fn recv(&mut self) -> Result<Arc<M>, RecvError> {
+        future::poll_fn(|cx| {
+            let mut state = self.channel.state.lock().unwrap();
+
+            if state.lagged > 0 {
+                return Poll::Ready(Err(RecvError::Lagged(std::mem::take(&mut state.lagged))));
+            }
+
+            if let Some(msg) = state.buffer.pop_front() {
+                state.senders.drain(..).for_each(Waker::wake);
+                return Poll::Ready(Ok(msg));
+            }
+
+            if state.closed {
+                return Poll::Ready(Err(RecvError::Closed));
+            }
+
+            state.receiver = Some(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Number of buffered messages
+    pub fn len(&self) -> usize {
+        self.channel.state.lock().unwrap().buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<M: Send + Sync + 'static> Drop for BroadcastReceiver<M> {
+    fn drop(&mut self) {
+        // emits waiting for room stop waiting
+        self.channel.close();
+    }
+}
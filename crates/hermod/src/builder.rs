@@ -0,0 +1,147 @@
+use crate::{MaybeSend, MaybeSendFuture, MaybeSync, Sender, Spawn};
+use std::{any::type_name, future::Future, marker::PhantomData};
+
+/// # SenderBuilder
+///
+/// Configures a `Sender` before spawning its task, see `Sender::builder`. Unlike the
+/// constructors of `Sender`, `build` takes a closure returning any future, so handlers
+/// can capture the state of the application and be written with `async move`.
+///
+/// ```
+/// use hermod::Sender;
+/// use std::sync::{
+///     atomic::{AtomicU64, Ordering},
+///     Arc,
+/// };
+///
+/// let total = Arc::new(AtomicU64::new(0));
+///
+/// let queue = Sender::<u64, u64>::builder()
+///     .with_name("totals")
+///     .with_workers(4)
+///     .with_capacity(100)
+///     .build({
+///         let total = Arc::clone(&total);
+///
+///         move |amount| {
+///             let total = Arc::clone(&total);
+///             async move { total.fetch_add(amount, Ordering::SeqCst) + amount }
+///         }
+///     });
+///
+/// async_std::task::block_on(async {
+///     for amount in 1..=10u64 {
+///         queue.try_emit(amount).unwrap();
+///     }
+///
+///     queue.drain().await;
+/// });
+///
+/// assert_eq!(total.load(Ordering::SeqCst), 55);
+/// assert_eq!(queue.name(), "totals");
+/// ```
+pub struct SenderBuilder<T, R, D = ()> {
+    spawner: Box<dyn Spawn>,
+    name: &'static str,
+    workers: usize,
+    capacity: Option<usize>,
+    data: Box<dyn FnMut() -> D>,
+    _types: PhantomData<fn(T) -> R>,
+}
+
+impl<T, R> Sender<T, R>
+where
+    T: MaybeSend + MaybeSync + 'static,
+    R: MaybeSend + MaybeSync + 'static,
+{
+    /// Configure a queue spawned on the bundled runtime, see `new`
+    #[cfg(any(
+        feature = "async-std",
+        feature = "tokio",
+        all(feature = "wasm", target_arch = "wasm32")
+    ))]
+    pub fn builder() -> SenderBuilder<T, R> {
+        Self::builder_on(crate::runtime::spawn)
+    }
+
+    /// Like `builder`, but the task is spawned with `spawner`.
+    pub fn builder_on(spawner: impl Spawn + 'static) -> SenderBuilder<T, R> {
+        SenderBuilder {
+            spawner: Box::new(spawner),
+            name: type_name::<T>(),
+            workers: 1,
+            capacity: None,
+            data: Box::new(|| ()),
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<T, R, D> SenderBuilder<T, R, D>
+where
+    T: MaybeSend + MaybeSync + 'static,
+    R: MaybeSend + MaybeSync + 'static,
+    D: MaybeSend + MaybeSync + 'static,
+{
+    /// The name of the queue in the metrics, instead of the type name of its events
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Handle up to `workers` (at least 1) events concurrently, see `Sender::with_workers`
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Queue at most `capacity` (at least 1) events, see `Sender::bounded`
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Give each worker its own data, created with `data`, for `build_with`
+    pub fn with_data<E>(self, data: impl FnMut() -> E + 'static) -> SenderBuilder<T, R, E> {
+        SenderBuilder {
+            spawner: self.spawner,
+            name: self.name,
+            workers: self.workers,
+            capacity: self.capacity,
+            data: Box::new(data),
+            _types: PhantomData,
+        }
+    }
+
+    /// Spawn the queue, handling its events with `listener`, which receives the data of
+    /// the worker like the handlers of `Sender::new`
+    pub fn build_with(
+        mut self,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+    ) -> Sender<T, R> {
+        let data = (0..self.workers).map(|_| (self.data)()).collect();
+        let spawner = move |future: MaybeSendFuture<'static, ()>| self.spawner.spawn(future);
+
+        Sender::build(spawner, self.name, self.capacity, listener, data)
+    }
+}
+
+impl<T, R> SenderBuilder<T, R>
+where
+    T: MaybeSend + MaybeSync + 'static,
+    R: MaybeSend + MaybeSync + 'static,
+{
+    /// Spawn the queue, handling its events with `listener`
+    pub fn build<F>(
+        self,
+        listener: impl Fn(T) -> F + MaybeSend + MaybeSync + 'static,
+    ) -> Sender<T, R>
+    where
+        F: Future<Output = R> + MaybeSend + 'static,
+    {
+        self.build_with(move |event, _| Box::pin(listener(event)))
+    }
+}
@@ -0,0 +1,102 @@
+use futures::Future;
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    // the tasks waiting in `Cancelled`
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// # CancellationToken
+///
+/// Passed to the handlers registered with `EventEmitter::on_with_cancellation` and
+/// `Sender::with_cancellation`, and cancelled by their `shutdown`, so long-running
+/// handlers can stop early instead of being dropped mid-await. Clones share their
+/// state.
+///
+/// ```
+/// use futures::future::{self, Either};
+/// use hermod::CancellationToken;
+/// use std::time::Duration;
+///
+/// let token = CancellationToken::new();
+/// token.cancel();
+///
+/// async_std::task::block_on(async {
+///     let work = Box::pin(async_std::task::sleep(Duration::from_secs(60)));
+///
+///     match future::select(work, token.cancelled()).await {
+///         Either::Left(_) => unreachable!(),
+///         Either::Right(_) => assert!(token.is_cancelled()),
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the token and its clones. Cancelling twice has no effect.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Release);
+
+        for waker in self.inner.wakers.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// A future resolving once the token is cancelled
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled {
+            token: self.clone(),
+        }
+    }
+}
+
+/// # Cancelled
+///
+/// Returned by `CancellationToken::cancelled`.
+#[derive(Debug)]
+pub struct Cancelled {
+    token: CancellationToken,
+}
+
+impl Future for Cancelled {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        let mut wakers = self.token.inner.wakers.lock().unwrap();
+
+        // `cancel` may have run before the lock was taken
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        if !wakers.iter().any(|n| n.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    error::Error,
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// the furthest a matching day is searched for, enough for any Feb 29th on a weekday
+const SEARCH_DAYS: u64 = 366 * 28;
+
+/// # Cron
+///
+/// A cron expression with the usual 5 fields: minute, hour, day of the month,
+/// month and day of the week (0 or 7 is Sunday). Fields are `*`, numbers, ranges
+/// (`1-5`) and lists (`1,15`), optionally with a step (`*/15`, `0-30/10`).
+/// Times are in UTC.
+///
+/// Like most cron implementations, when both the day of the month and the day of
+/// the week are restricted, a day matching either of them matches.
+///
+/// ```
+/// use hermod::Cron;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// // every weekday at 9:30
+/// let cron = "30 9 * * 1-5".parse::<Cron>().unwrap();
+///
+/// // Saturday, 1 January 2000, 00:00
+/// let saturday = UNIX_EPOCH + Duration::from_secs(946_684_800);
+/// let monday = saturday + Duration::from_secs(2 * 86_400 + 9 * 3600 + 30 * 60);
+///
+/// assert_eq!(cron.next_after(saturday), Some(monday));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // whether the day of the month and of the week are `*`
+    any_day: bool,
+    any_weekday: bool,
+}
+
+/// # CronError
+///
+/// Returned when parsing an invalid `Cron` expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CronError {
+    /// The expression does not have 5 fields
+    FieldCount(usize),
+    /// A field could not be parsed, or is out of range
+    InvalidField(String),
+}
+
+impl fmt::Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CronError::FieldCount(n) => write!(f, "expected 5 cron fields, got {n}"),
+            CronError::InvalidField(field) => write!(f, "invalid cron field `{field}`"),
+        }
+    }
+}
+
+impl Error for CronError {}
+
+impl FromStr for Cron {
+    type Err = CronError;
+
+    fn from_str(expression: &str) -> Result<Self, CronError> {
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(CronError::FieldCount(fields.len()));
+        };
+
+        // Sunday can be written as both 0 and 7
+        let mut weekday_bits = parse_field(weekdays, 0, 7)?;
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits |= 1;
+        }
+
+        Ok(Cron {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekday_bits,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+}
+
+impl Cron {
+    /// The first matching minute strictly after `time`, or `None` if the expression
+    /// never matches (e.g. February 30th).
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let seconds = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let start = seconds / 60 + 1;
+
+        let first_day = start / 1440;
+
+        for day in first_day..first_day + SEARCH_DAYS {
+            if !self.matches_day(day) {
+                continue;
+            }
+
+            let from = match day == first_day {
+                true => start % 1440,
+                false => 0,
+            };
+
+            if let Some(minute) = (from..1440).find(|&n| self.matches_minute(n)) {
+                let minutes = day * 1440 + minute;
+                return Some(UNIX_EPOCH + Duration::from_secs(minutes * 60));
+            }
+        }
+
+        None
+    }
+
+    fn matches_minute(&self, minute_of_day: u64) -> bool {
+        bit(self.hours, minute_of_day / 60) && bit(self.minutes, minute_of_day % 60)
+    }
+
+    fn matches_day(&self, days_since_epoch: u64) -> bool {
+        let (month, day) = month_day(days_since_epoch);
+
+        // 1 January 1970 was a Thursday
+        let weekday = (days_since_epoch + 4) % 7;
+
+        let day_matches = bit(self.days, day);
+        let weekday_matches = bit(self.weekdays, weekday);
+
+        let matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day_matches || weekday_matches,
+            _ => day_matches && weekday_matches,
+        };
+
+        matches && bit(self.months, month)
+    }
+}
+
+fn bit(bits: u64, n: u64) -> bool {
+    bits & (1 << n) != 0
+}
+
+/// Parse a field into a bitset of the values it matches
+fn parse_field(field: &str, min: u64, max: u64) -> Result<u64, CronError> {
+    let invalid = || CronError::InvalidField(field.to_string());
+
+    let mut bits = 0;
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u64>().map_err(|_| invalid())?),
+            None => (item, 1),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (
+                start.parse::<u64>().map_err(|_| invalid())?,
+                end.parse::<u64>().map_err(|_| invalid())?,
+            ),
+            // a single value with a step runs until the end, like `5/15`
+            None if item.contains('/') => (range.parse::<u64>().map_err(|_| invalid())?, max),
+            None => {
+                let value = range.parse::<u64>().map_err(|_| invalid())?;
+                (value, value)
+            }
+        };
+
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+/// The month (1-12) and day of the month (1-31) of a day since the epoch
+fn month_day(days_since_epoch: u64) -> (u64, u64) {
+    // Howard Hinnant's `civil_from_days`, with eras starting on 1 March 0000
+    let z = days_since_epoch + 719_468;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = match shifted_month < 10 {
+        true => shifted_month + 3,
+        false => shifted_month - 9,
+    };
+
+    (month, day)
+}
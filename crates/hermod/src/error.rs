@@ -0,0 +1,82 @@
+use std::{error, fmt};
+
+/// # Error
+///
+/// Returned by the `emit` methods of a `Sender`. Gives the event back when it was
+/// not queued.
+///
+/// ```
+/// use hermod::{Error, Sender};
+/// use std::{sync::Arc, time::Duration};
+///
+/// let queue = Arc::new(Sender::<u32, u32>::new(|n, _| Box::pin(async move {
+///     if n == 0 {
+///         panic!("cannot handle 0");
+///     }
+///
+///     n
+/// }), ()));
+///
+/// async_std::task::block_on(async {
+///     let timeout = Duration::from_secs(1);
+///     let failed = Arc::clone(&queue).emit_timeout(0u32, timeout).await;
+///     assert!(matches!(failed, Err(Error::HandlerFailed)));
+///
+///     queue.close().await;
+///     let closed = Arc::clone(&queue).emit(1u32).await.unwrap_err();
+///     assert_eq!(closed.into_inner(), Some(1));
+/// });
+/// ```
+pub enum Error<T> {
+    /// The queue is closed, or its task has stopped
+    Closed(T),
+    /// The queue is bounded and full
+    Full(T),
+    /// The handler did not respond in time. The event was queued.
+    Timeout,
+    /// The handler panicked, or stopped without responding. The event was queued.
+    HandlerFailed,
+}
+
+impl<T> Error<T> {
+    /// The event, if it was not queued
+    pub fn into_inner(self) -> Option<T> {
+        match self {
+            Error::Closed(event) | Error::Full(event) => Some(event),
+            Error::Timeout | Error::HandlerFailed => None,
+        }
+    }
+
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> Error<U> {
+        match self {
+            Error::Closed(event) => Error::Closed(f(event)),
+            Error::Full(event) => Error::Full(f(event)),
+            Error::Timeout => Error::Timeout,
+            Error::HandlerFailed => Error::HandlerFailed,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Error<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Closed(_) => f.write_str("Closed(..)"),
+            Error::Full(_) => f.write_str("Full(..)"),
+            Error::Timeout => f.write_str("Timeout"),
+            Error::HandlerFailed => f.write_str("HandlerFailed"),
+        }
+    }
+}
+
+impl<T> fmt::Display for Error<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Closed(_) => f.write_str("queue is closed"),
+            Error::Full(_) => f.write_str("queue is full"),
+            Error::Timeout => f.write_str("timed out waiting for a response"),
+            Error::HandlerFailed => f.write_str("handler failed without responding"),
+        }
+    }
+}
+
+impl<T> error::Error for Error<T> {}
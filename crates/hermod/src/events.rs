@@ -1,16 +1,281 @@
-use async_std::sync::Arc;
-use futures::future::{self, BoxFuture};
+use crate::{
+    broadcast::{Channel, Closer},
+    group::{Buffered, SharedGroup},
+    idle::InFlight,
+    metrics::EventStats,
+    middleware::{FlowFuture, Middleware},
+    runtime::Instant,
+    topic::{self, Topics},
+    BroadcastReceiver, CancellationToken, EventMetrics, EventStream, Next, Overflow, PausePolicy,
+    Published, Subscription, SubscriptionId,
+};
+use futures::{
+    channel::{mpsc, oneshot},
+    future::{self, BoxFuture},
+    Future, FutureExt,
+};
 use log::error;
+
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+use crate::{
+    limit::Limiter, schedule::Timer, LimitPolicy, RateLimit, Recurrence, RetryPolicy, Scheduled,
+};
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+use std::time::Duration;
 use std::{
-    any::{Any, TypeId},
+    any::{type_name, Any, TypeId},
     collections::HashMap,
     error::Error,
-    marker::PhantomData,
+    fmt,
+    ops::ControlFlow,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
 };
 
-type Listener<Ev, Err> = fn(Arc<<Ev as Event>::Message>) -> ResultFuture<Err>;
-type ResultFuture<Err> = BoxFuture<'static, Result<(), Err>>;
-type EventList = Vec<Box<dyn Any + Send + Sync>>;
+type Listener<Ev> = Box<dyn Fn(Arc<<Ev as Event>::Message>) -> FlowFuture + Send + Sync>;
+type ResultFuture = BoxFuture<'static, Result<(), ListenerError>>;
+type ParentLink<Ev> = Box<
+    dyn Fn(&EventEmitter, &Arc<<Ev as Event>::Message>) -> Vec<(SubscriptionId, FlowFuture)>
+        + Send
+        + Sync,
+>;
+
+/// The error returned by listeners. Any error can be returned with `?`.
+pub type ListenerError = Box<dyn Error + Send + Sync>;
+
+/// Called with every error returned by a listener, see `EventEmitter::set_error_handler`
+pub type ErrorHandler = fn(&dyn Error, EventInfo);
+
+/// # EventInfo
+///
+/// Describes a listener, passed to the error handler (for the listener that failed)
+/// and to middleware.
+#[derive(Clone, Copy, Debug)]
+pub struct EventInfo {
+    /// Type name of the event
+    pub event: &'static str,
+    /// The listener
+    pub id: SubscriptionId,
+}
+
+/// # Dispatch
+///
+/// Whether `EventEmitter::emit_cancellable` called every listener.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dispatch {
+    /// Every listener was called
+    Completed,
+    /// A listener stopped the propagation, the listeners after it were not called
+    Cancelled,
+}
+
+/// # DispatchMode
+///
+/// How `EventEmitter::emit`, `emit_collect` and `try_emit` call the listeners of an
+/// event, see `EventEmitter::set_dispatch_mode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DispatchMode {
+    /// Start every listener, then wait for all of them
+    #[default]
+    Concurrent,
+    /// Call the listeners one after another, each once the previous one is done, so
+    /// they never interleave. With `stop_on_error`, `emit` stops at the first error.
+    Sequential { stop_on_error: bool },
+}
+
+/// # AnyEvent
+///
+/// An emitted event, as passed to the listeners registered with `EventEmitter::on_any`.
+pub struct AnyEvent {
+    /// `TypeId` of the event (not of its message)
+    pub type_id: TypeId,
+    /// Type name of the event
+    pub name: &'static str,
+    /// The message, an `Ev::Message`
+    pub message: Arc<dyn Any + Send + Sync>,
+}
+
+impl AnyEvent {
+    fn new<Ev: Event>(message: &Arc<Ev::Message>) -> Self {
+        Self {
+            type_id: TypeId::of::<Ev>(),
+            name: type_name::<Ev>(),
+            message: Arc::clone(message) as _,
+        }
+    }
+
+    /// Whether this is an `Ev`
+    pub fn is<Ev: Event>(&self) -> bool {
+        self.type_id == TypeId::of::<Ev>()
+    }
+
+    /// The message, if this is an `Ev`
+    pub fn downcast<Ev: Event>(&self) -> Option<Arc<Ev::Message>> {
+        match self.is::<Ev>() {
+            true => Arc::clone(&self.message).downcast().ok(),
+            false => None,
+        }
+    }
+}
+
+/// # DeadLetter
+///
+/// An event a listener failed to handle, passed to the dead-letter sink (see
+/// `EventEmitter::set_dead_letter_sink`).
+pub struct DeadLetter {
+    /// The event, `downcast` it to requeue it
+    pub event: AnyEvent,
+    /// The listener that failed
+    pub listener: SubscriptionId,
+    pub error: ListenerError,
+}
+
+type DeadLetterSink = Arc<dyn Fn(DeadLetter) + Send + Sync>;
+type UnhandledHandler = Arc<dyn Fn(AnyEvent) + Send + Sync>;
+
+/// Wildcard listeners are registered as listeners of this event
+struct Wildcard;
+
+impl Event for Wildcard {
+    type Message = AnyEvent;
+}
+
+/// Adapt a listener that cannot stop propagation
+fn continuing<Ev: Event>(
+    listener: impl Fn(Arc<Ev::Message>) -> ResultFuture + Send + Sync + 'static,
+) -> Listener<Ev> {
+    Box::new(move |msg| {
+        let future = listener(msg);
+        Box::pin(async move { future.await.map(|()| ControlFlow::Continue(())) })
+    })
+}
+
+/// Adapt a topic listener, so it is only called for the topics matching `pattern`
+fn topic_listener(
+    pattern: String,
+    listener: impl Fn(Arc<Published>) -> ResultFuture + Send + Sync + 'static,
+) -> Listener<Topics> {
+    continuing::<Topics>(move |msg| match topic::matches(&pattern, &msg.topic) {
+        true => listener(msg),
+        false => Box::pin(async { Ok(()) }),
+    })
+}
+
+/// # ListenerPanic
+///
+/// The error reported when a listener panics. The panic does not reach the emitter.
+#[derive(Debug)]
+pub struct ListenerPanic {
+    message: String,
+}
+
+impl ListenerPanic {
+    pub(crate) fn new(payload: Box<dyn Any + Send>) -> Self {
+        Self {
+            message: crate::runtime::panic_message(payload),
+        }
+    }
+
+    /// The panic's message, if it had one
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ListenerPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "listener panicked: {}", self.message)
+    }
+}
+
+impl Error for ListenerPanic {}
+
+/// Call `listener`, turning panics (while calling it or polling its future) into errors
+fn guarded<Ev: Event>(listener: &Listener<Ev>, arg: Arc<Ev::Message>) -> FlowFuture {
+    match panic::catch_unwind(AssertUnwindSafe(|| listener(arg))) {
+        Ok(future) => Box::pin(
+            AssertUnwindSafe(future)
+                .catch_unwind()
+                .map(|n| n.unwrap_or_else(|e| Err(ListenerPanic::new(e).into()))),
+        ),
+        Err(e) => Box::pin(future::ready(Err(ListenerPanic::new(e).into()))),
+    }
+}
+
+pub(crate) fn log_error(e: &dyn Error, info: EventInfo) {
+    error!("Error in {} listener: {e}", info.event);
+}
+
+/// A listener, along with what is needed to remove it
+pub(crate) struct Registered<L: ?Sized = dyn Any + Send + Sync> {
+    pub(crate) id: SubscriptionId,
+    pub(crate) priority: i32,
+    // cleared when the listener's `Subscription` guard is dropped
+    pub(crate) alive: Arc<AtomicBool>,
+    pub(crate) listener: Arc<L>,
+}
+
+impl<L: ?Sized> Registered<L> {
+    pub(crate) fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::Acquire)
+    }
+}
+
+/// The listeners of `Ev`, sorted by priority. Typed, so that emitting only downcasts
+/// the list, not each listener.
+struct TypedList<Ev: Event> {
+    list: Vec<Registered<Listener<Ev>>>,
+}
+
+/// A `TypedList` of any event
+trait EventList: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// The type name of the event
+    fn name(&self) -> &'static str;
+    /// Number of alive listeners
+    fn alive(&self) -> usize;
+    /// Remove the listener `id`, returning whether it was alive
+    fn off(&mut self, id: SubscriptionId) -> bool;
+}
+
+impl<Ev: Event> EventList for TypedList<Ev> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn name(&self) -> &'static str {
+        type_name::<Ev>()
+    }
+
+    fn alive(&self) -> usize {
+        self.list.iter().filter(|n| n.is_alive()).count()
+    }
+
+    fn off(&mut self, id: SubscriptionId) -> bool {
+        let mut found = false;
+
+        self.list.retain(|n| {
+            if n.id == id {
+                found |= n.is_alive();
+
+                // so calls deferred by the listener know it is gone
+                n.alive.store(false, Ordering::Release);
+            }
+
+            n.id != id && n.is_alive()
+        });
+
+        found
+    }
+}
 
 /// # The `Event` Trait
 ///
@@ -25,11 +290,31 @@ pub trait Event: Send + Sync + 'static {
 /// The `EventEmitter` is used to emit events and to listen
 /// to them. You can listen to an event from anywhere, and emit it from anywhere.
 ///
-/// Only one Error type can be used, for all listeners. Different error types on a
-/// per-listener basis cannot be done.
+/// Listeners can fail with any error, errors are logged unless a handler is set
+/// with `set_error_handler`.
 ///
-/// ```no_run
-/// use mimir::{Event, EventEmitter};
+/// Listeners are closures, so they can capture application state.
+///
+/// Everything takes `&self`, so the emitter can be shared with an `Arc` or put in a
+/// `static`:
+///
+/// ```
+/// use hermod::EventEmitter;
+/// use std::sync::OnceLock;
+///
+/// static EVENTS: OnceLock<EventEmitter> = OnceLock::new();
+///
+/// pub fn events() -> &'static EventEmitter {
+///     EVENTS.get_or_init(EventEmitter::new)
+/// }
+/// ```
+///
+/// ```
+/// use hermod::{Event, EventEmitter};
+/// use std::sync::{
+///     atomic::{AtomicUsize, Ordering},
+///     Arc,
+/// };
 ///
 /// pub struct SomethingHappened;
 ///
@@ -37,48 +322,2027 @@ pub trait Event: Send + Sync + 'static {
 ///     type Message = String;
 /// }
 ///
-/// let mut emitter = EventEmitter::new();
+/// let emitter = EventEmitter::new();
+/// let count = Arc::new(AtomicUsize::new(0));
 ///
-/// emitter.on::<SomethingHappened>(|msg| {
-///     assert_eq!(msg, "Hi there!");
+/// let counter = Arc::clone(&count);
+/// emitter.on::<SomethingHappened>(move |msg| {
+///     let counter = Arc::clone(&counter);
+///
+///     Box::pin(async move {
+///         assert_eq!(*msg, "Hi there!");
+///         counter.fetch_add(1, Ordering::SeqCst);
+///         Ok(())
+///     })
 /// });
 ///
-/// emitter.emit::<SomethingHappened>(String::from("Hi there!")).await;
+/// async_std::task::block_on(emitter.emit::<SomethingHappened>(String::from("Hi there!")));
+/// assert_eq!(count.load(Ordering::SeqCst), 1);
 /// ```
-pub struct EventEmitter<Err: Error + 'static> {
-    _phantom: PhantomData<Err>,
-    listeners: HashMap<TypeId, EventList>,
+pub struct EventEmitter {
+    // a `TypedList<Ev>` per event
+    listeners: RwLock<HashMap<TypeId, Box<dyn EventList>>>,
+    next_id: AtomicU64,
+    on_error: RwLock<ErrorHandler>,
+    dead_letters: RwLock<Option<DeadLetterSink>>,
+    unhandled: RwLock<Option<UnhandledHandler>>,
+    groups: RwLock<HashMap<String, SharedGroup>>,
+    layers: RwLock<Vec<Arc<Middleware>>>,
+    stats: RwLock<HashMap<TypeId, Arc<EventStats>>>,
+    default_mode: RwLock<DispatchMode>,
+    modes: RwLock<HashMap<TypeId, DispatchMode>>,
+    // a `ParentLink<Ev>` per parent of each event
+    parents: RwLock<HashMap<TypeId, Vec<Arc<dyn Any + Send + Sync>>>>,
+    // passed to the listeners registered with `on_with_cancellation`
+    token: CancellationToken,
+    in_flight: InFlight,
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    retries: RwLock<HashMap<TypeId, RetryPolicy>>,
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    limits: RwLock<HashMap<TypeId, Arc<Limiter>>>,
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    timer: Timer,
+}
+
+impl fmt::Debug for EventEmitter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventEmitter")
+            .field("events", &self.registered_events())
+            .field("default_mode", &*self.default_mode.read().unwrap())
+            .finish_non_exhaustive()
+    }
 }
 
-impl<Err: Error + 'static> EventEmitter<Err> {
+impl EventEmitter {
     pub fn new() -> Self {
         Self {
-            _phantom: PhantomData,
-            listeners: HashMap::new(),
+            listeners: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            on_error: RwLock::new(log_error),
+            dead_letters: RwLock::new(None),
+            unhandled: RwLock::new(None),
+            groups: RwLock::new(HashMap::new()),
+            layers: RwLock::new(vec![]),
+            stats: RwLock::new(HashMap::new()),
+            default_mode: RwLock::new(DispatchMode::Concurrent),
+            modes: RwLock::new(HashMap::new()),
+            parents: RwLock::new(HashMap::new()),
+            token: CancellationToken::new(),
+            in_flight: InFlight::default(),
+            #[cfg(any(feature = "async-std", feature = "tokio"))]
+            retries: RwLock::new(HashMap::new()),
+            #[cfg(any(feature = "async-std", feature = "tokio"))]
+            limits: RwLock::new(HashMap::new()),
+            #[cfg(any(feature = "async-std", feature = "tokio"))]
+            timer: Timer::default(),
         }
     }
 
-    pub fn on<Ev: Event>(&mut self, listener: Listener<Ev, Err>) {
+    /// Set the function called with errors returned by listeners. By default, they
+    /// are logged with `log::error!`.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    /// use std::fs;
+    ///
+    /// pub struct Reload;
+    ///
+    /// impl Event for Reload {
+    ///     type Message = ();
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    ///
+    /// emitter.set_error_handler(|e, info| eprintln!("{} failed: {e}", info.event));
+    /// emitter.on::<Reload>(|_| {
+    ///     Box::pin(async {
+    ///         fs::read("config.toml")?;
+    ///         Ok(())
+    ///     })
+    /// });
+    ///
+    /// async_std::task::block_on(emitter.emit::<Reload>(()));
+    /// ```
+    pub fn set_error_handler(&self, handler: ErrorHandler) {
+        *self.on_error.write().unwrap() = handler;
+    }
+
+    /// Pass every event a listener fails to handle, along with the error, to `sink`, e.g.
+    /// to persist or requeue it. This is in addition to the error handler, and only
+    /// applies to `emit` and `emit_cancellable`: the other ways to emit return the
+    /// errors to the caller, who still has the event.
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use hermod::{Event, EventEmitter};
+    ///
+    /// pub struct Upload;
+    ///
+    /// impl Event for Upload {
+    ///     type Message = String;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let (sender, mut failed) = mpsc::unbounded();
+    ///
+    /// emitter.set_dead_letter_sink(move |letter| {
+    ///     let _ = sender.unbounded_send(letter);
+    /// });
+    ///
+    /// emitter.on::<Upload>(|_| Box::pin(async { Err("offline".into()) }));
+    /// async_std::task::block_on(emitter.emit::<Upload>(String::from("report.pdf")));
+    ///
+    /// let letter = failed.try_next().unwrap().unwrap();
+    ///
+    /// assert_eq!(*letter.event.downcast::<Upload>().unwrap(), "report.pdf");
+    /// assert_eq!(letter.error.to_string(), "offline");
+    /// ```
+    pub fn set_dead_letter_sink(&self, sink: impl Fn(DeadLetter) + Send + Sync + 'static) {
+        *self.dead_letters.write().unwrap() = Some(Arc::new(sink));
+    }
+
+    /// Call `handler` with every event emitted while it has no listeners, to catch
+    /// wiring mistakes. Wildcard listeners do not count, the listeners of its parents
+    /// (see `set_parent`) do.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// pub struct Shutdown;
+    ///
+    /// impl Event for Shutdown {
+    ///     type Message = u32;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let unhandled = Arc::new(Mutex::new(vec![]));
+    ///
+    /// let events = Arc::clone(&unhandled);
+    /// emitter.set_unhandled_handler(move |event| {
+    ///     events.lock().unwrap().push((event.name, *event.downcast::<Shutdown>().unwrap()));
+    /// });
+    ///
+    /// async_std::task::block_on(emitter.emit::<Shutdown>(1));
+    /// assert_eq!(*unhandled.lock().unwrap(), [(std::any::type_name::<Shutdown>(), 1)]);
+    /// ```
+    pub fn set_unhandled_handler(&self, handler: impl Fn(AnyEvent) + Send + Sync + 'static) {
+        *self.unhandled.write().unwrap() = Some(Arc::new(handler));
+    }
+
+    /// Add a middleware, wrapping every call of every listener. The first middleware added
+    /// is the outermost one. A middleware can skip the listener by not running `next`.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    /// use std::time::Instant;
+    ///
+    /// pub struct Request;
+    ///
+    /// impl Event for Request {
+    ///     type Message = ();
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    ///
+    /// emitter.layer(|next, info| {
+    ///     Box::pin(async move {
+    ///         let start = Instant::now();
+    ///         let result = next.run().await;
+    ///
+    ///         println!("{} took {:?}", info.event, start.elapsed());
+    ///         result
+    ///     })
+    /// });
+    ///
+    /// emitter.on::<Request>(|_| Box::pin(async { Ok(()) }));
+    /// async_std::task::block_on(emitter.emit::<Request>(()));
+    /// ```
+    pub fn layer(
+        &self,
+        middleware: impl Fn(Next, EventInfo) -> FlowFuture + Send + Sync + 'static,
+    ) {
+        self.layers.write().unwrap().push(Arc::new(middleware));
+    }
+
+    /// Resolve once no emit is in flight, e.g. for tests to wait for events to be
+    /// handled instead of sleeping. An emit is in flight from its first poll, so emits
+    /// spawned onto other tasks may not have started yet. Emits scheduled for later are
+    /// not in flight until they are due.
+    ///
+    /// ```
+    /// use futures::future;
+    /// use hermod::{Event, EventEmitter};
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// pub struct Job;
+    ///
+    /// impl Event for Job {
+    ///     type Message = ();
+    /// }
+    ///
+    /// static DONE: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// let events = EventEmitter::new();
+    ///
+    /// events.on::<Job>(|_| Box::pin(async {
+    ///     async_std::task::yield_now().await;
+    ///     DONE.fetch_add(1, Ordering::SeqCst);
+    ///     Ok(())
+    /// }));
+    ///
+    /// async_std::task::block_on(async {
+    ///     let emits = future::join_all((0..3).map(|_| events.emit::<Job>(())));
+    ///
+    ///     // the emits are polled first, then `idle` waits for them
+    ///     future::join(emits, async {
+    ///         events.idle().await;
+    ///         assert_eq!(DONE.load(Ordering::SeqCst), 3);
+    ///     })
+    ///     .await;
+    /// });
+    /// ```
+    pub async fn idle(&self) {
+        self.in_flight.idle().await
+    }
+
+    /// Counters and latencies of every event emitted so far
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    ///
+    /// pub struct Click;
+    ///
+    /// impl Event for Click {
+    ///     type Message = ();
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    ///
+    /// emitter.on::<Click>(|_| Box::pin(async { Ok(()) }));
+    /// emitter.on::<Click>(|_| Box::pin(async { Err("not now".into()) }));
+    ///
+    /// async_std::task::block_on(emitter.emit::<Click>(()));
+    ///
+    /// let metrics = &emitter.metrics()[0];
+    ///
+    /// assert_eq!((metrics.emitted, metrics.failures), (1, 1));
+    /// assert_eq!(metrics.latency.count, 2);
+    /// ```
+    pub fn metrics(&self) -> Vec<EventMetrics> {
+        let stats = self.stats.read().unwrap();
+        stats.values().map(|n| n.metrics()).collect()
+    }
+
+    /// Retry the listeners of `Ev` that fail, according to `policy`. Errors are only
+    /// reported (or returned) once the last attempt failed. Wildcard listeners are never
+    /// retried.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter, RetryPolicy};
+    /// use std::sync::atomic::{AtomicU32, Ordering};
+    ///
+    /// pub struct Backup;
+    ///
+    /// impl Event for Backup {
+    ///     type Message = ();
+    /// }
+    ///
+    /// static ATTEMPTS: AtomicU32 = AtomicU32::new(0);
+    ///
+    /// let emitter = EventEmitter::new();
+    /// emitter.set_retry_policy::<Backup>(RetryPolicy::new(3));
+    ///
+    /// emitter.on::<Backup>(|_| {
+    ///     Box::pin(async {
+    ///         match ATTEMPTS.fetch_add(1, Ordering::SeqCst) {
+    ///             0 | 1 => Err("server busy".into()),
+    ///             _ => Ok(()),
+    ///         }
+    ///     })
+    /// });
+    ///
+    /// assert!(async_std::task::block_on(emitter.try_emit::<Backup>(())).is_ok());
+    /// assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 3);
+    /// ```
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn set_retry_policy<Ev: Event>(&self, policy: RetryPolicy) {
+        self.retries
+            .write()
+            .unwrap()
+            .insert(TypeId::of::<Ev>(), policy);
+    }
+
+    /// Limit how often `Ev` is emitted with `emit`, so a misbehaving producer cannot
+    /// overwhelm the listeners. The emits over the limit are dropped, coalesced or
+    /// delayed, see `LimitPolicy`. `emit_cancellable`, `emit_collect` and `try_emit`
+    /// are not limited, as their callers wait for the results of the listeners.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter, RateLimit};
+    /// use std::{
+    ///     sync::{
+    ///         atomic::{AtomicUsize, Ordering},
+    ///         Arc,
+    ///     },
+    ///     time::Duration,
+    /// };
+    ///
+    /// pub struct Log;
+    ///
+    /// impl Event for Log {
+    ///     type Message = String;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let written = Arc::new(AtomicUsize::new(0));
+    ///
+    /// emitter.set_rate_limit::<Log>(RateLimit::new(5, Duration::from_secs(60)));
+    ///
+    /// let counter = Arc::clone(&written);
+    /// emitter.on::<Log>(move |_| {
+    ///     counter.fetch_add(1, Ordering::SeqCst);
+    ///     Box::pin(async { Ok(()) })
+    /// });
+    ///
+    /// async_std::task::block_on(async {
+    ///     for n in 0..100 {
+    ///         emitter.emit::<Log>(format!("line {n}")).await;
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(written.load(Ordering::SeqCst), 5);
+    /// ```
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn set_rate_limit<Ev: Event>(&self, limit: RateLimit) {
+        self.limits
+            .write()
+            .unwrap()
+            .insert(TypeId::of::<Ev>(), Arc::new(Limiter::new(limit)));
+    }
+
+    /// Register a listener for `Ev`. It stays registered until it is removed with `off`.
+    pub fn on<Ev: Event>(
+        &self,
+        listener: impl Fn(Arc<Ev::Message>) -> ResultFuture + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        self.register::<Ev>(continuing::<Ev>(listener), 0).0
+    }
+
+    /// Like `on`, with a priority. Listeners are called by priority (highest first),
+    /// then in the order they were registered. `on` uses a priority of `0`.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// pub struct Startup;
+    ///
+    /// impl Event for Startup {
+    ///     type Message = ();
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let order = Arc::new(Mutex::new(vec![]));
+    ///
+    /// for (name, priority) in [("late", -1), ("normal", 0), ("early", 10)] {
+    ///     let order = Arc::clone(&order);
+    ///
+    ///     emitter.on_with_priority::<Startup>(priority, move |_| {
+    ///         order.lock().unwrap().push(name);
+    ///         Box::pin(async { Ok(()) })
+    ///     });
+    /// }
+    ///
+    /// async_std::task::block_on(emitter.emit::<Startup>(()));
+    /// assert_eq!(*order.lock().unwrap(), ["early", "normal", "late"]);
+    /// ```
+    pub fn on_with_priority<Ev: Event>(
+        &self,
+        priority: i32,
+        listener: impl Fn(Arc<Ev::Message>) -> ResultFuture + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        self.register::<Ev>(continuing::<Ev>(listener), priority).0
+    }
+
+    /// Register a listener that can stop the propagation of `Ev` to the listeners after
+    /// it (by priority, see `on_with_priority`), by returning `ControlFlow::Break`.
+    ///
+    /// Stopping only has an effect with `emit_cancellable`, which calls the listeners
+    /// one after another. The other ways to emit call every listener regardless.
+    ///
+    /// ```
+    /// use hermod::{Dispatch, Event, EventEmitter};
+    /// use std::ops::ControlFlow;
+    ///
+    /// pub struct KeyPress;
+    ///
+    /// impl Event for KeyPress {
+    ///     type Message = char;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    ///
+    /// // the focused text box consumes everything but escape
+    /// emitter.on_cancellable::<KeyPress>(10, |key| {
+    ///     Box::pin(async move {
+    ///         match *key {
+    ///             '\x1b' => Ok(ControlFlow::Continue(())),
+    ///             _ => Ok(ControlFlow::Break(())),
+    ///         }
+    ///     })
+    /// });
+    ///
+    /// emitter.on::<KeyPress>(|_| Box::pin(async { Ok(()) }));
+    ///
+    /// async_std::task::block_on(async {
+    ///     assert_eq!(emitter.emit_cancellable::<KeyPress>('a').await, Dispatch::Cancelled);
+    ///     assert_eq!(emitter.emit_cancellable::<KeyPress>('\x1b').await, Dispatch::Completed);
+    /// });
+    /// ```
+    pub fn on_cancellable<Ev: Event>(
+        &self,
+        priority: i32,
+        listener: impl Fn(Arc<Ev::Message>) -> FlowFuture + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        self.register::<Ev>(Box::new(listener), priority).0
+    }
+
+    /// Like `on`, but the listener is only called for the messages matching `filter`.
+    /// The filter runs inline in `emit`, before any listener future is created.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// pub struct StatusCode;
+    ///
+    /// impl Event for StatusCode {
+    ///     type Message = u16;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let errors = Arc::new(Mutex::new(vec![]));
+    ///
+    /// let seen = Arc::clone(&errors);
+    /// emitter.on_filtered::<StatusCode>(|code| *code >= 500, move |code| {
+    ///     seen.lock().unwrap().push(*code);
+    ///     Box::pin(async { Ok(()) })
+    /// });
+    ///
+    /// async_std::task::block_on(async {
+    ///     for code in [200, 503, 404, 500] {
+    ///         emitter.emit::<StatusCode>(code).await;
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(*errors.lock().unwrap(), [503, 500]);
+    /// ```
+    pub fn on_filtered<Ev: Event>(
+        &self,
+        filter: impl Fn(&Ev::Message) -> bool + Send + Sync + 'static,
+        listener: impl Fn(Arc<Ev::Message>) -> ResultFuture + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        let listener = continuing::<Ev>(listener);
+
+        let filtered = move |msg: Arc<Ev::Message>| match filter(&msg) {
+            true => listener(msg),
+            false => Box::pin(async { Ok(ControlFlow::Continue(())) }) as FlowFuture,
+        };
+
+        self.register::<Ev>(Box::new(filtered), 0).0
+    }
+
+    /// Like `on`, but the listener is part of the named `group`, which can be paused and
+    /// resumed as a whole, see `pause`.
+    pub fn on_in_group<Ev: Event>(
+        &self,
+        group: &str,
+        listener: impl Fn(Arc<Ev::Message>) -> ResultFuture + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        let id = self.reserve_id();
+        let group = self.group(group);
+        let listener = Arc::new(continuing::<Ev>(listener));
+
+        let grouped = move |msg: Arc<Ev::Message>| {
+            let mut state = group.lock().unwrap();
+
+            match state.paused {
+                None => {
+                    drop(state);
+                    return listener(msg);
+                }
+                Some(PausePolicy::Drop) => {}
+                Some(PausePolicy::Buffer) => {
+                    let listener = Arc::clone(&listener);
+                    let call: Buffered =
+                        Box::new(move |emitter| emitter.replay::<Ev>(id, listener, msg));
+
+                    state.buffer.push(call);
+                }
+            }
+
+            Box::pin(async { Ok(ControlFlow::Continue(())) }) as FlowFuture
+        };
+
+        let alive = Arc::new(AtomicBool::new(true));
+        self.register_as::<Ev>(id, Box::new(grouped), alive, 0);
+
+        id
+    }
+
+    /// Pause the listeners of `group` (see `on_in_group`), e.g. while they are being
+    /// reconfigured. Their events are buffered until the group is resumed.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// pub struct Redraw;
+    ///
+    /// impl Event for Redraw {
+    ///     type Message = u32;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let frames = Arc::new(Mutex::new(vec![]));
+    ///
+    /// let drawn = Arc::clone(&frames);
+    /// emitter.on_in_group::<Redraw>("ui", move |frame| {
+    ///     drawn.lock().unwrap().push(*frame);
+    ///     Box::pin(async { Ok(()) })
+    /// });
+    ///
+    /// async_std::task::block_on(async {
+    ///     emitter.pause("ui");
+    ///     emitter.emit::<Redraw>(1).await;
+    ///     emitter.emit::<Redraw>(2).await;
+    ///     assert!(frames.lock().unwrap().is_empty());
+    ///
+    ///     emitter.resume("ui").await;
+    ///     emitter.emit::<Redraw>(3).await;
+    /// });
+    ///
+    /// assert_eq!(*frames.lock().unwrap(), [1, 2, 3]);
+    /// ```
+    pub fn pause(&self, group: &str) {
+        self.pause_with(group, PausePolicy::Buffer);
+    }
+
+    /// Like `pause`, with a policy for the events received while paused.
+    pub fn pause_with(&self, group: &str, policy: PausePolicy) {
+        self.group(group).lock().unwrap().paused = Some(policy);
+    }
+
+    /// Resume `group`, calling its listeners with the buffered events first, in order.
+    /// Events emitted meanwhile are buffered after them.
+    pub async fn resume(&self, group: &str) {
+        let Some(group) = self.groups.read().unwrap().get(group).cloned() else {
+            return;
+        };
+
+        loop {
+            let buffered = {
+                let mut state = group.lock().unwrap();
+
+                if state.buffer.is_empty() {
+                    state.paused = None;
+                    return;
+                }
+
+                std::mem::take(&mut state.buffer)
+            };
+
+            for call in buffered {
+                call(self).await;
+            }
+        }
+    }
+
+    pub fn is_paused(&self, group: &str) -> bool {
+        self.groups
+            .read()
+            .unwrap()
+            .get(group)
+            .is_some_and(|n| n.lock().unwrap().paused.is_some())
+    }
+
+    fn group(&self, name: &str) -> SharedGroup {
+        if let Some(group) = self.groups.read().unwrap().get(name) {
+            return Arc::clone(group);
+        }
+
+        let mut groups = self.groups.write().unwrap();
+        Arc::clone(groups.entry(name.to_string()).or_default())
+    }
+
+    /// Call a listener whose group was paused, like `dispatch` does
+    fn replay<Ev: Event>(
+        &self,
+        id: SubscriptionId,
+        listener: Arc<Listener<Ev>>,
+        arg: Arc<Ev::Message>,
+    ) -> BoxFuture<'_, ()> {
+        let stats = self.stats_of::<Ev>();
+        let call = self.layered::<Ev>(id, self.call::<Ev>(listener, &arg, &stats));
+
+        Box::pin(async move {
+            if let Err(e) = call.await {
+                self.report::<Ev>(&arg, e, id);
+            }
+        })
+    }
+
+    /// Like `on`, but the listener also receives a `CancellationToken`, cancelled by
+    /// `shutdown`, so long-running listeners can stop early.
+    ///
+    /// ```
+    /// use futures::future::{self, Either};
+    /// use hermod::{Event, EventEmitter};
+    /// use std::time::Duration;
+    ///
+    /// pub struct Synchronize;
+    ///
+    /// impl Event for Synchronize {
+    ///     type Message = ();
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    ///
+    /// emitter.on_with_cancellation::<Synchronize>(|_, token| {
+    ///     Box::pin(async move {
+    ///         let work = Box::pin(async_std::task::sleep(Duration::from_secs(60)));
+    ///
+    ///         match future::select(work, token.cancelled()).await {
+    ///             Either::Left(_) => Ok(()),
+    ///             Either::Right(_) => Err("sync interrupted".into()),
+    ///         }
+    ///     })
+    /// });
+    ///
+    /// async_std::task::block_on(async {
+    ///     let sync = emitter.try_emit::<Synchronize>(());
+    ///     emitter.shutdown();
+    ///
+    ///     assert!(sync.await.is_err());
+    /// });
+    /// ```
+    pub fn on_with_cancellation<Ev: Event>(
+        &self,
+        listener: impl Fn(Arc<Ev::Message>, CancellationToken) -> ResultFuture + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        let token = self.token.clone();
+        self.on::<Ev>(move |msg| listener(msg, token.clone()))
+    }
+
+    /// Cancel the token passed to the listeners registered with `on_with_cancellation`,
+    /// e.g. when the application shuts down. Events emitted afterwards are still
+    /// dispatched, with the cancelled token.
+    pub fn shutdown(&self) {
+        self.token.cancel();
+    }
+
+    /// Make `Parent` receive every `Child`, converted with `into`, so a family of events
+    /// can be listened to at once. The parent's listeners are called after the child's,
+    /// in the same dispatch, and their errors are reported as errors of `Child`.
+    /// Parents can have parents themselves, but the relationships must not form a cycle.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// pub enum NetworkEvent {
+    ///     Connected(u32),
+    ///     Disconnected(u32),
+    /// }
+    ///
+    /// pub struct AnyNetworkEvent;
+    ///
+    /// impl Event for AnyNetworkEvent {
+    ///     type Message = NetworkEvent;
+    /// }
+    ///
+    /// pub struct Connected;
+    ///
+    /// impl Event for Connected {
+    ///     type Message = u32;
+    /// }
+    ///
+    /// pub struct Disconnected;
+    ///
+    /// impl Event for Disconnected {
+    ///     type Message = u32;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let seen = Arc::new(Mutex::new(vec![]));
+    ///
+    /// emitter.set_parent::<Connected, AnyNetworkEvent>(|peer| NetworkEvent::Connected(**peer));
+    /// emitter.set_parent::<Disconnected, AnyNetworkEvent>(|peer| NetworkEvent::Disconnected(**peer));
+    ///
+    /// let events = Arc::clone(&seen);
+    /// emitter.on::<AnyNetworkEvent>(move |event| {
+    ///     events.lock().unwrap().push(event);
+    ///     Box::pin(async { Ok(()) })
+    /// });
+    ///
+    /// async_std::task::block_on(async {
+    ///     emitter.emit::<Connected>(1).await;
+    ///     emitter.emit::<Disconnected>(1).await;
+    /// });
+    ///
+    /// let seen = seen.lock().unwrap();
+    /// assert_eq!(*seen[0], NetworkEvent::Connected(1));
+    /// assert_eq!(*seen[1], NetworkEvent::Disconnected(1));
+    /// ```
+    pub fn set_parent<Child: Event, Parent: Event>(
+        &self,
+        into: impl Fn(&Arc<Child::Message>) -> Parent::Message + Send + Sync + 'static,
+    ) {
+        let link: ParentLink<Child> =
+            Box::new(move |emitter, arg| emitter.family::<Parent>(&Arc::new(into(arg))));
+
+        self.parents
+            .write()
+            .unwrap()
+            .entry(TypeId::of::<Child>())
+            .or_default()
+            .push(Arc::new(link));
+    }
+
+    /// Register a listener for every event, e.g. for logging. Wildcard listeners are
+    /// called before the listeners of the emitted event, and cannot stop its propagation.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    ///
+    /// pub struct Login;
+    ///
+    /// impl Event for Login {
+    ///     type Message = String;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    ///
+    /// emitter.on_any(|event| {
+    ///     Box::pin(async move {
+    ///         if let Some(user) = event.downcast::<Login>() {
+    ///             assert_eq!(*user, "alice");
+    ///         }
+    ///
+    ///         println!("{} emitted", event.name);
+    ///         Ok(())
+    ///     })
+    /// });
+    ///
+    /// async_std::task::block_on(emitter.emit::<Login>(String::from("alice")));
+    /// ```
+    pub fn on_any(
+        &self,
+        listener: impl Fn(Arc<AnyEvent>) -> ResultFuture + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        self.register::<Wildcard>(continuing::<Wildcard>(listener), 0)
+            .0
+    }
+
+    /// Register a listener for the topics matching `pattern`, for events that are not
+    /// known at compile time (e.g. from plugins). Topics are dot-separated, and in the
+    /// pattern `*` matches a single segment and `**` any number of them.
+    ///
+    /// Topic listeners are called by priority and registration order like the others,
+    /// and share their error handling.
+    ///
+    /// ```
+    /// use hermod::EventEmitter;
+    ///
+    /// let emitter = EventEmitter::new();
+    ///
+    /// emitter.on_topic("orders.*", |msg| {
+    ///     Box::pin(async move {
+    ///         let id = msg.downcast::<u64>().ok_or("expected an order id")?;
+    ///         println!("{}: order {id}", msg.topic);
+    ///         Ok(())
+    ///     })
+    /// });
+    ///
+    /// // every topic
+    /// emitter.on_topic("**", |msg| Box::pin(async move {
+    ///     println!("{} published", msg.topic);
+    ///     Ok(())
+    /// }));
+    ///
+    /// async_std::task::block_on(async {
+    ///     emitter.publish("orders.created", 42u64).await;
+    ///     emitter.publish("stock.low", "widgets").await;
+    /// });
+    /// ```
+    pub fn on_topic(
+        &self,
+        pattern: impl Into<String>,
+        listener: impl Fn(Arc<Published>) -> ResultFuture + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        self.register::<Topics>(topic_listener(pattern.into(), listener), 0)
+            .0
+    }
+
+    /// Like `on_topic`, but the listener is removed when the returned guard is dropped.
+    pub fn subscribe_topic(
+        &self,
+        pattern: impl Into<String>,
+        listener: impl Fn(Arc<Published>) -> ResultFuture + Send + Sync + 'static,
+    ) -> Subscription {
+        let (id, alive) = self.register::<Topics>(topic_listener(pattern.into(), listener), 0);
+        Subscription { id, alive }
+    }
+
+    /// Like `on`, but the listener is removed when the returned guard is dropped.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    ///
+    /// pub struct Tick;
+    ///
+    /// impl Event for Tick {
+    ///     type Message = ();
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    ///
+    /// let subscription = emitter.subscribe::<Tick>(|_| Box::pin(async { Ok(()) }));
+    /// assert_eq!(emitter.listener_count::<Tick>(), 1);
+    ///
+    /// drop(subscription);
+    /// assert_eq!(emitter.listener_count::<Tick>(), 0);
+    /// ```
+    pub fn subscribe<Ev: Event>(
+        &self,
+        listener: impl Fn(Arc<Ev::Message>) -> ResultFuture + Send + Sync + 'static,
+    ) -> Subscription {
+        let (id, alive) = self.register::<Ev>(continuing::<Ev>(listener), 0);
+        Subscription { id, alive }
+    }
+
+    /// Register a listener that is removed after its first invocation.
+    pub fn once<Ev: Event>(
+        &self,
+        listener: impl FnOnce(Arc<Ev::Message>) -> ResultFuture + Send + 'static,
+    ) -> SubscriptionId {
+        let alive = Arc::new(AtomicBool::new(true));
+        let listener = Mutex::new(Some(listener));
+        let flag = Arc::clone(&alive);
+
+        let listener = continuing::<Ev>(move |msg| {
+            // concurrent emits may both see the listener as alive, only one wins the swap
+            let listener = match flag.swap(false, Ordering::AcqRel) {
+                true => listener.lock().unwrap().take(),
+                false => None,
+            };
+
+            match listener {
+                Some(listener) => listener(msg),
+                None => Box::pin(async { Ok(()) }),
+            }
+        });
+
+        self.register_with::<Ev>(listener, alive, 0)
+    }
+
+    /// Wait for the next `Ev`. Resolves with `None` if the emitter is dropped first.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    ///
+    /// pub struct Ready;
+    ///
+    /// impl Event for Ready {
+    ///     type Message = u32;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let ready = emitter.wait_for::<Ready>();
+    ///
+    /// async_std::task::block_on(async {
+    ///     emitter.emit::<Ready>(7).await;
+    ///     assert_eq!(ready.await.as_deref(), Some(&7));
+    /// });
+    /// ```
+    pub fn wait_for<Ev: Event>(
+        &self,
+    ) -> impl Future<Output = Option<Arc<Ev::Message>>> + Send + 'static {
+        let (sender, receiver) = oneshot::channel();
+
+        self.once::<Ev>(move |msg| {
+            // the receiver may have been dropped, nobody is waiting anymore then
+            let _ = sender.send(msg);
+            Box::pin(async { Ok(()) })
+        });
+
+        async move { receiver.await.ok() }
+    }
+
+    /// The messages of every following `Ev`, as a stream. Messages are buffered until
+    /// the stream is polled, and the listener is removed when it is dropped.
+    ///
+    /// ```
+    /// use futures::StreamExt;
+    /// use hermod::{Event, EventEmitter};
+    ///
+    /// pub struct Temperature;
+    ///
+    /// impl Event for Temperature {
+    ///     type Message = i32;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let too_hot = emitter.stream::<Temperature>().filter(|n| std::future::ready(**n > 30));
+    ///
+    /// async_std::task::block_on(async {
+    ///     for temperature in [20, 35, 25, 40] {
+    ///         emitter.emit::<Temperature>(temperature).await;
+    ///     }
+    ///
+    ///     let alerts = too_hot.take(2).map(|n| *n).collect::<Vec<_>>().await;
+    ///     assert_eq!(alerts, [35, 40]);
+    /// });
+    /// ```
+    pub fn stream<Ev: Event>(&self) -> EventStream<Ev::Message> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        let subscription = self.subscribe::<Ev>(move |msg| {
+            // the stream is being dropped, its guard removes the listener
+            let _ = sender.unbounded_send(msg);
+            Box::pin(async { Ok(()) })
+        });
+
+        EventStream {
+            receiver,
+            subscription,
+        }
+    }
+
+    /// The messages of every following `Ev`, buffered for a receiver that polls them at
+    /// its own pace, e.g. an actor owning its loop. At most `capacity` messages are
+    /// buffered, then `overflow` applies.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter, Overflow, RecvError};
+    ///
+    /// pub struct Price;
+    ///
+    /// impl Event for Price {
+    ///     type Message = u32;
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let mut prices = emitter.receiver::<Price>(2, Overflow::DropOldest);
+    ///
+    /// async_std::task::block_on(async {
+    ///     for price in [100, 101, 102] {
+    ///         emitter.emit::<Price>(price).await;
+    ///     }
+    ///
+    ///     // the oldest price was dropped to make room
+    ///     assert_eq!(prices.recv().await, Err(RecvError::Lagged(1)));
+    ///     assert_eq!(*prices.recv().await.unwrap(), 101);
+    ///     assert_eq!(*prices.recv().await.unwrap(), 102);
+    /// });
+    ///
+    /// drop(emitter);
+    /// assert_eq!(async_std::task::block_on(prices.recv()), Err(RecvError::Closed));
+    /// ```
+    pub fn receiver<Ev: Event>(
+        &self,
+        capacity: usize,
+        overflow: Overflow,
+    ) -> BroadcastReceiver<Ev::Message> {
+        let channel = Channel::new(capacity, overflow);
+        let closer = Closer(Arc::clone(&channel));
+
+        // dropping the listener, e.g. with the emitter, closes the receiver
+        let listener = move |msg| closer.0.send(msg);
+        let (id, alive) = self.register::<Ev>(Box::new(listener), 0);
+
+        BroadcastReceiver {
+            channel,
+            subscription: Subscription { id, alive },
+        }
+    }
+
+    /// Register a listener called once `Ev` was not emitted for `delay`, with the last
+    /// message, e.g. to react to a burst of file changes only once. The listener runs
+    /// on the emitter's timer task, so `emit` does not wait for it, and is not called
+    /// once removed with `off`.
+    ///
+    /// Middleware, retries and metrics apply when the event is emitted, not to the
+    /// delayed call.
+    ///
+    /// ```
+    /// use futures::{channel::mpsc, StreamExt};
+    /// use hermod::{Event, EventEmitter};
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// pub struct FileChanged;
+    ///
+    /// impl Event for FileChanged {
+    ///     type Message = &'static str;
+    /// }
+    ///
+    /// let events = Arc::new(EventEmitter::new());
+    /// let (reload, mut reloads) = mpsc::unbounded();
+    ///
+    /// Arc::clone(&events).on_debounced::<FileChanged>(Duration::from_millis(200), move |path| {
+    ///     let _ = reload.unbounded_send(*path);
+    ///     Box::pin(async { Ok(()) })
+    /// });
+    ///
+    /// async_std::task::block_on(async {
+    ///     for path in ["a.rs", "b.rs", "c.rs"] {
+    ///         events.emit::<FileChanged>(path).await;
+    ///     }
+    ///
+    ///     // only the last change is reloaded
+    ///     assert_eq!(reloads.next().await, Some("c.rs"));
+    /// });
+    /// ```
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn on_debounced<Ev: Event>(
+        self: Arc<Self>,
+        delay: Duration,
+        listener: impl Fn(Arc<Ev::Message>) -> ResultFuture + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        let id = self.reserve_id();
+        let listener = Arc::new(continuing::<Ev>(listener));
+        let alive = Arc::new(AtomicBool::new(true));
+        let pending = Mutex::new(None::<Scheduled>);
+
+        // the emitter holds the listener, which only holds it weakly
+        let events = Arc::downgrade(&self);
+        let registered = Arc::clone(&alive);
+
+        let debounced = move |msg| {
+            let Some(events) = events.upgrade() else {
+                return Box::pin(async { Ok(ControlFlow::Continue(())) }) as FlowFuture;
+            };
+
+            let mut pending = pending.lock().unwrap();
+
+            // every message restarts the delay
+            if let Some(previous) = pending.take() {
+                previous.cancel();
+            }
+
+            let delivery = events.deferred::<Ev>(id, Arc::clone(&listener), &registered, msg);
+            *pending = Some(events.timer.schedule(Instant::now() + delay, delivery));
+
+            Box::pin(async { Ok(ControlFlow::Continue(())) }) as FlowFuture
+        };
+
+        self.register_as::<Ev>(id, Box::new(debounced), alive, 0);
+
+        id
+    }
+
+    /// Register a listener called at most once per `interval`. The first message is
+    /// handled right away, and the last one received during the interval at its end.
+    /// Like `on_debounced`, the delayed calls run on the emitter's timer task.
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn on_throttled<Ev: Event>(
+        self: Arc<Self>,
+        interval: Duration,
+        listener: impl Fn(Arc<Ev::Message>) -> ResultFuture + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        let id = self.reserve_id();
+        let listener = Arc::new(continuing::<Ev>(listener));
+        let alive = Arc::new(AtomicBool::new(true));
+
+        // when the listener was last called, and the message waiting for the next call
+        let state = Arc::new(Mutex::new((None::<Instant>, None::<Arc<Ev::Message>>)));
+
+        let events = Arc::downgrade(&self);
+        let registered = Arc::clone(&alive);
+
+        let throttled = move |msg| {
+            let Some(events) = events.upgrade() else {
+                return Box::pin(async { Ok(ControlFlow::Continue(())) }) as FlowFuture;
+            };
+
+            let now = Instant::now();
+            let mut throttle = state.lock().unwrap();
+
+            let next = match throttle.0 {
+                Some(last) if now < last + interval => last + interval,
+                _ => {
+                    throttle.0 = Some(now);
+                    return listener(msg);
+                }
+            };
+
+            // the first message of the interval schedules the call, later ones replace it
+            if throttle.1.replace(msg).is_none() {
+                let state = Arc::clone(&state);
+                let listener = Arc::clone(&listener);
+                let registered = Arc::clone(&registered);
+                let deferring = Arc::clone(&events);
+
+                let delivery = Box::pin(async move {
+                    let msg = {
+                        let mut throttle = state.lock().unwrap();
+                        throttle.0 = Some(Instant::now());
+                        throttle.1.take()
+                    };
+
+                    if let Some(msg) = msg {
+                        deferring
+                            .deferred::<Ev>(id, listener, &registered, msg)
+                            .await;
+                    }
+                });
+
+                events.timer.schedule(next, delivery);
+            }
+
+            Box::pin(async { Ok(ControlFlow::Continue(())) }) as FlowFuture
+        };
+
+        self.register_as::<Ev>(id, Box::new(throttled), alive, 0);
+
+        id
+    }
+
+    /// Remove a listener. Returns whether it was still registered.
+    pub fn off(&self, id: SubscriptionId) -> bool {
+        let mut found = false;
+
+        for list in self.listeners.write().unwrap().values_mut() {
+            found |= list.off(id);
+        }
+
+        found
+    }
+
+    /// Number of listeners currently registered for `Ev`
+    pub fn listener_count<Ev: Event>(&self) -> usize {
         self.listeners
+            .read()
+            .unwrap()
+            .get(&TypeId::of::<Ev>())
+            .map_or(0, |n| n.alive())
+    }
+
+    /// Same as `listener_count`
+    pub fn listeners_for<Ev: Event>(&self) -> usize {
+        self.listener_count::<Ev>()
+    }
+
+    /// The type names of the events with listeners, and how many they have, sorted by
+    /// name. Wildcard and topic listeners are listed under internal event types.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    ///
+    /// pub struct Opened;
+    ///
+    /// impl Event for Opened {
+    ///     type Message = ();
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    ///
+    /// emitter.on::<Opened>(|_| Box::pin(async { Ok(()) }));
+    /// emitter.on::<Opened>(|_| Box::pin(async { Ok(()) }));
+    ///
+    /// assert_eq!(emitter.listeners_for::<Opened>(), 2);
+    /// assert_eq!(emitter.registered_events(), [(std::any::type_name::<Opened>(), 2)]);
+    /// ```
+    pub fn registered_events(&self) -> Vec<(&'static str, usize)> {
+        let mut events = self
+            .listeners
+            .read()
+            .unwrap()
+            .values()
+            .map(|list| (list.name(), list.alive()))
+            .filter(|(_, count)| *count > 0)
+            .collect::<Vec<_>>();
+
+        events.sort();
+        events
+    }
+
+    fn register<Ev: Event>(
+        &self,
+        listener: Listener<Ev>,
+        priority: i32,
+    ) -> (SubscriptionId, Arc<AtomicBool>) {
+        let alive = Arc::new(AtomicBool::new(true));
+        let id = self.register_with::<Ev>(listener, Arc::clone(&alive), priority);
+
+        (id, alive)
+    }
+
+    fn register_with<Ev: Event>(
+        &self,
+        listener: Listener<Ev>,
+        alive: Arc<AtomicBool>,
+        priority: i32,
+    ) -> SubscriptionId {
+        let id = self.reserve_id();
+        self.register_as::<Ev>(id, listener, alive, priority);
+
+        id
+    }
+
+    /// A new id, for listeners that need to know theirs before they are registered
+    fn reserve_id(&self) -> SubscriptionId {
+        SubscriptionId(self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn register_as<Ev: Event>(
+        &self,
+        id: SubscriptionId,
+        listener: Listener<Ev>,
+        alive: Arc<AtomicBool>,
+        priority: i32,
+    ) {
+        let mut listeners = self.listeners.write().unwrap();
+        let list = listeners
             .entry(TypeId::of::<Ev>())
-            .or_default()
-            .push(Box::new(listener));
+            .or_insert_with(|| Box::new(TypedList::<Ev> { list: vec![] }))
+            .as_any_mut()
+            .downcast_mut::<TypedList<Ev>>()
+            .map(|n| &mut n.list)
+            .expect("listeners are keyed by the type id of their event");
+
+        // listeners whose guard was dropped are only removed here and in `off`
+        list.retain(Registered::is_alive);
+
+        // keep the list sorted by priority, after everything registered before with the same one
+        let index = list.partition_point(|n| n.priority >= priority);
+
+        list.insert(
+            index,
+            Registered {
+                id,
+                priority,
+                alive,
+                listener: Arc::new(listener),
+            },
+        );
+    }
+
+    /// Set how the listeners of every event are called, unless set for the event with
+    /// `set_dispatch_mode`. Listeners are called concurrently by default.
+    pub fn set_default_dispatch_mode(&self, mode: DispatchMode) {
+        *self.default_mode.write().unwrap() = mode;
     }
 
+    /// Set how the listeners of `Ev` are called, e.g. sequentially for listeners that
+    /// mutate shared state and must not interleave.
+    ///
+    /// ```
+    /// use hermod::{DispatchMode, Event, EventEmitter};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// pub struct Migrate;
+    ///
+    /// impl Event for Migrate {
+    ///     type Message = ();
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    /// let steps = Arc::new(Mutex::new(vec![]));
+    ///
+    /// emitter.set_dispatch_mode::<Migrate>(DispatchMode::Sequential { stop_on_error: true });
+    ///
+    /// for step in ["schema", "data", "indexes"] {
+    ///     let steps = Arc::clone(&steps);
+    ///
+    ///     emitter.on::<Migrate>(move |_| {
+    ///         let steps = Arc::clone(&steps);
+    ///
+    ///         Box::pin(async move {
+    ///             // the previous step has finished when the next one starts
+    ///             async_std::task::yield_now().await;
+    ///             steps.lock().unwrap().push(step);
+    ///
+    ///             match step {
+    ///                 "data" => Err("constraint violated".into()),
+    ///                 _ => Ok(()),
+    ///             }
+    ///         })
+    ///     });
+    /// }
+    ///
+    /// async_std::task::block_on(emitter.emit::<Migrate>(()));
+    /// assert_eq!(*steps.lock().unwrap(), ["schema", "data"]);
+    /// ```
+    pub fn set_dispatch_mode<Ev: Event>(&self, mode: DispatchMode) {
+        self.modes.write().unwrap().insert(TypeId::of::<Ev>(), mode);
+    }
+
+    /// Call every listener of `Ev`, by priority and then registration order (see
+    /// `on_with_priority`), and wait for all of them. The listeners' futures run
+    /// concurrently, so only the order in which they are started is guaranteed, unless
+    /// the event is dispatched sequentially (see `set_dispatch_mode`).
     pub async fn emit<Ev: Event>(&self, arg: Ev::Message) {
+        let _busy = self.in_flight.enter();
+        let emit = self.emit_now::<Ev>(Arc::new(arg));
+
+        #[cfg(feature = "tracing")]
+        let emit = tracing::Instrument::instrument(
+            emit,
+            tracing::debug_span!("emit", event = type_name::<Ev>()),
+        );
+
+        emit.await
+    }
+
+    async fn emit_now<Ev: Event>(&self, arg: Arc<Ev::Message>) {
+        #[cfg(any(feature = "async-std", feature = "tokio"))]
+        let Some(arg) = self.limited::<Ev>(arg).await
+        else {
+            return;
+        };
+
+        if let DispatchMode::Sequential { stop_on_error } = self.mode_of::<Ev>() {
+            let _ = self
+                .each_listener::<Ev>(&arg, |id, result| match result {
+                    Ok(_) => ControlFlow::Continue(()),
+                    Err(e) => {
+                        self.report::<Ev>(&arg, e, id);
+
+                        match stop_on_error {
+                            true => ControlFlow::Break(()),
+                            false => ControlFlow::Continue(()),
+                        }
+                    }
+                })
+                .await;
+
+            return;
+        }
+
+        let (ids, futures): (Vec<_>, Vec<_>) = self.dispatch::<Ev>(&arg).unzip();
+        let results = future::join_all(futures).await;
+
+        for (id, result) in ids.into_iter().zip(results) {
+            if let Err(e) = result {
+                self.report::<Ev>(&arg, e, id);
+            }
+        }
+    }
+
+    /// Call the listeners of `Ev` one after another, waiting for each before calling
+    /// the next, until one of them stops the propagation (see `on_cancellable`).
+    /// Errors are passed to the error handler and do not stop the propagation.
+    pub async fn emit_cancellable<Ev: Event>(&self, arg: Ev::Message) -> Dispatch {
+        let _busy = self.in_flight.enter();
         let arg = Arc::new(arg);
 
-        if let Some(event_list) = self.listeners.get(&TypeId::of::<Ev>()) {
-            let futures = event_list
-                .iter()
-                .filter_map(|n| n.downcast_ref::<Listener<Ev, Err>>())
-                .map(|n| async { n(Arc::clone(&arg)).await });
+        let flow = self
+            .each_listener::<Ev>(&arg, |id, result| match result {
+                Ok(flow) => flow,
+                Err(e) => {
+                    self.report::<Ev>(&arg, e, id);
+                    ControlFlow::Continue(())
+                }
+            })
+            .await;
+
+        match flow {
+            ControlFlow::Continue(()) => Dispatch::Completed,
+            ControlFlow::Break(()) => Dispatch::Cancelled,
+        }
+    }
+
+    /// Like `emit`, but returns the result of every listener (in the order they were
+    /// called) instead of passing errors to the error handler.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    ///
+    /// pub struct Save;
+    ///
+    /// impl Event for Save {
+    ///     type Message = ();
+    /// }
+    ///
+    /// let emitter = EventEmitter::new();
+    ///
+    /// emitter.on::<Save>(|_| Box::pin(async { Ok(()) }));
+    /// emitter.on::<Save>(|_| Box::pin(async { Err("disk full".into()) }));
+    ///
+    /// let results = async_std::task::block_on(emitter.emit_collect::<Save>(()));
+    ///
+    /// assert!(results[0].is_ok());
+    /// assert_eq!(results[1].as_ref().unwrap_err().to_string(), "disk full");
+    /// ```
+    pub async fn emit_collect<Ev: Event>(
+        &self,
+        arg: Ev::Message,
+    ) -> Vec<Result<(), ListenerError>> {
+        let _busy = self.in_flight.enter();
+
+        if let DispatchMode::Sequential { .. } = self.mode_of::<Ev>() {
+            let mut results = vec![];
+
+            let _ = self
+                .each_listener::<Ev>(&Arc::new(arg), |_, result| {
+                    results.push(result.map(|_| ()));
+                    ControlFlow::Continue(())
+                })
+                .await;
+
+            return results;
+        }
+
+        let results = future::join_all(self.dispatch::<Ev>(&Arc::new(arg)).map(|(_, n)| n)).await;
+        results.into_iter().map(|n| n.map(|_| ())).collect()
+    }
+
+    /// Like `emit`, but returns the first error. The remaining listeners' futures are
+    /// dropped as soon as one fails.
+    pub async fn try_emit<Ev: Event>(&self, arg: Ev::Message) -> Result<(), ListenerError> {
+        let _busy = self.in_flight.enter();
+
+        if let DispatchMode::Sequential { .. } = self.mode_of::<Ev>() {
+            let mut error = None;
+
+            let _ = self
+                .each_listener::<Ev>(&Arc::new(arg), |_, result| match result {
+                    Ok(_) => ControlFlow::Continue(()),
+                    Err(e) => {
+                        error = Some(e);
+                        ControlFlow::Break(())
+                    }
+                })
+                .await;
+
+            return error.map_or(Ok(()), Err);
+        }
+
+        future::try_join_all(self.dispatch::<Ev>(&Arc::new(arg)).map(|(_, n)| n)).await?;
+        Ok(())
+    }
+
+    /// Call the topic listeners whose pattern matches `topic` (see `on_topic`), and wait
+    /// for all of them.
+    pub async fn publish(&self, topic: impl Into<String>, payload: impl Any + Send + Sync) {
+        let published = Published {
+            topic: topic.into(),
+            payload: Arc::new(payload),
+        };
+
+        self.emit::<Topics>(published).await
+    }
+
+    /// `emit` the event once `delay` has passed, on a background task shared by every
+    /// scheduled event of the emitter. The delivery can be cancelled with the returned
+    /// handle.
+    ///
+    /// ```
+    /// use hermod::{Event, EventEmitter};
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// pub struct Reminder;
+    ///
+    /// impl Event for Reminder {
+    ///     type Message = &'static str;
+    /// }
+    ///
+    /// let events = Arc::new(EventEmitter::new());
+    ///
+    /// let reminder = Arc::clone(&events)
+    ///     .emit_after::<Reminder>("stand up", Duration::from_secs(3600));
+    /// assert!(reminder.is_pending());
+    ///
+    /// reminder.cancel();
+    /// assert!(!reminder.is_pending());
+    /// ```
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn emit_after<Ev: Event>(self: Arc<Self>, arg: Ev::Message, delay: Duration) -> Scheduled {
+        self.emit_at::<Ev>(arg, Instant::now() + delay)
+    }
+
+    /// Like `emit_after`, but the event is emitted at `at`
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn emit_at<Ev: Event>(self: Arc<Self>, arg: Ev::Message, at: Instant) -> Scheduled {
+        let events = Arc::clone(&self);
+
+        self.timer
+            .schedule(at, Box::pin(async move { events.emit::<Ev>(arg).await }))
+    }
+
+    /// `emit` a clone of `arg` on every `recurrence`, until the returned handle is
+    /// cancelled or the emitter is dropped. Like `emit_after`, this runs on the
+    /// emitter's timer task, but the schedule does not keep the emitter alive.
+    ///
+    /// ```
+    /// use hermod::{every, Cron, Event, EventEmitter};
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// pub struct Tick;
+    ///
+    /// impl Event for Tick {
+    ///     type Message = ();
+    /// }
+    ///
+    /// pub struct Report;
+    ///
+    /// impl Event for Report {
+    ///     type Message = &'static str;
+    /// }
+    ///
+    /// let events = Arc::new(EventEmitter::new());
+    ///
+    /// let ticks = Arc::clone(&events).schedule::<Tick>(every(Duration::from_secs(30)), ());
+    ///
+    /// // every Monday at 9:00 UTC
+    /// let weekly = "0 9 * * 1".parse::<Cron>().unwrap();
+    /// Arc::clone(&events).schedule::<Report>(weekly, "weekly");
+    ///
+    /// ticks.cancel();
+    /// ```
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn schedule<Ev: Event>(
+        self: Arc<Self>,
+        recurrence: impl Into<Recurrence>,
+        arg: Ev::Message,
+    ) -> Scheduled
+    where
+        Ev::Message: Clone,
+    {
+        let events = Arc::downgrade(&self);
+
+        self.timer.repeat(
+            recurrence.into(),
+            Box::new(move || {
+                let events = events.upgrade()?;
+                let arg = arg.clone();
+
+                Some(Box::pin(async move { events.emit::<Ev>(arg).await }))
+            }),
+        )
+    }
+
+    /// Call the alive listeners of `Ev`, in order
+    fn dispatch<Ev: Event>(
+        &self,
+        arg: &Arc<Ev::Message>,
+    ) -> impl Iterator<Item = (SubscriptionId, FlowFuture)> {
+        let family = self.family::<Ev>(arg);
+
+        if family.is_empty() {
+            self.unhandled::<Ev>(arg);
+        }
+
+        let futures = self
+            .wildcards_of::<Ev>(arg)
+            .into_iter()
+            .chain(family)
+            .collect::<Vec<_>>();
+
+        self.stats_of::<Ev>().emitted(futures.len());
+        futures.into_iter()
+    }
+
+    /// The calls to the listeners of `Ev`, then to those of its parents (see
+    /// `set_parent`)
+    fn family<Ev: Event>(&self, arg: &Arc<Ev::Message>) -> Vec<(SubscriptionId, FlowFuture)> {
+        let stats = self.stats_of::<Ev>();
+
+        self.listeners_of::<Ev>()
+            .into_iter()
+            .map(|(id, n)| (id, self.layered::<Ev>(id, self.call::<Ev>(n, arg, &stats))))
+            .chain(self.parents_of::<Ev>(arg))
+            .collect()
+    }
+
+    /// The calls to the listeners of the parents of `Ev`
+    fn parents_of<Ev: Event>(&self, arg: &Arc<Ev::Message>) -> Vec<(SubscriptionId, FlowFuture)> {
+        let links = self
+            .parents
+            .read()
+            .unwrap()
+            .get(&TypeId::of::<Ev>())
+            .cloned()
+            .unwrap_or_default();
+
+        links
+            .into_iter()
+            .filter_map(|n| n.downcast::<ParentLink<Ev>>().ok())
+            .flat_map(|link| link(self, arg))
+            .collect()
+    }
+
+    /// Call the alive listeners of `Ev` one after another, passing each result to `each`
+    /// until it breaks. Returns whether it did.
+    async fn each_listener<Ev: Event>(
+        &self,
+        arg: &Arc<Ev::Message>,
+        mut each: impl FnMut(SubscriptionId, Result<ControlFlow<()>, ListenerError>) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let stats = self.stats_of::<Ev>();
+        let wildcards = self.wildcards_of::<Ev>(arg);
+        let listeners = self.listeners_of::<Ev>();
+
+        stats.emitted(wildcards.len() + listeners.len());
+
+        if listeners.is_empty()
+            && !self
+                .parents
+                .read()
+                .unwrap()
+                .contains_key(&TypeId::of::<Ev>())
+        {
+            self.unhandled::<Ev>(arg);
+        }
+
+        // wildcard listeners cannot stop the propagation
+        for (id, listener) in wildcards {
+            let result = listener.await.map(|_| ControlFlow::Continue(()));
+            each(id, result)?;
+        }
+
+        for (id, listener) in listeners {
+            let result = self
+                .layered::<Ev>(id, self.call::<Ev>(listener, arg, &stats))
+                .await;
+
+            each(id, result)?;
+        }
+
+        for (id, listener) in self.parents_of::<Ev>(arg) {
+            each(id, listener.await)?;
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Apply the rate limit of `Ev`, returning the message to emit, if any
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    async fn limited<Ev: Event>(&self, arg: Arc<Ev::Message>) -> Option<Arc<Ev::Message>> {
+        let Some(limiter) = self
+            .limits
+            .read()
+            .unwrap()
+            .get(&TypeId::of::<Ev>())
+            .cloned()
+        else {
+            return Some(arg);
+        };
+
+        match limiter.policy() {
+            LimitPolicy::Drop => limiter.take().ok().map(|_| arg),
+            LimitPolicy::Wait => {
+                limiter.acquire().await;
+                Some(arg)
+            }
+            LimitPolicy::Coalesce => {
+                {
+                    let mut pending = limiter.pending.lock().unwrap();
+
+                    // another emit is waiting, and emits this message instead of its own
+                    if pending.is_some() {
+                        *pending = Some(arg);
+                        return None;
+                    }
 
-            for result in future::join_all(futures).await {
-                if let Err(e) = result {
-                    error!("Error in callback: {e}");
+                    if limiter.take().is_ok() {
+                        return Some(arg);
+                    }
+
+                    *pending = Some(arg);
                 }
+
+                // the latest message once there is a token
+                limiter.acquire().await;
+                let latest = limiter.pending.lock().unwrap().take()?;
+
+                latest.downcast().ok()
+            }
+        }
+    }
+
+    fn mode_of<Ev: Event>(&self) -> DispatchMode {
+        match self.modes.read().unwrap().get(&TypeId::of::<Ev>()) {
+            Some(&mode) => mode,
+            None => *self.default_mode.read().unwrap(),
+        }
+    }
+
+    /// Call the listener `id` outside of `emit`, unless it was removed by then. The
+    /// emit already went through the middleware and metrics, so the call does not.
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    fn deferred<Ev: Event>(
+        self: &Arc<Self>,
+        id: SubscriptionId,
+        listener: Arc<Listener<Ev>>,
+        alive: &Arc<AtomicBool>,
+        arg: Arc<Ev::Message>,
+    ) -> BoxFuture<'static, ()> {
+        let events = Arc::clone(self);
+        let alive = Arc::clone(alive);
+
+        Box::pin(async move {
+            if !alive.load(Ordering::Acquire) {
+                return;
             }
+
+            let _busy = events.in_flight.enter();
+
+            if let Err(e) = guarded::<Ev>(&listener, Arc::clone(&arg)).await {
+                events.report::<Ev>(&arg, e, id);
+            }
+        })
+    }
+
+    /// Call the wildcard listeners with `arg`
+    fn wildcards_of<Ev: Event>(&self, arg: &Arc<Ev::Message>) -> Vec<(SubscriptionId, FlowFuture)> {
+        let listeners = self.listeners_of::<Wildcard>();
+
+        if listeners.is_empty() {
+            return vec![];
+        }
+
+        let event = Arc::new(AnyEvent::new::<Ev>(arg));
+
+        listeners
+            .into_iter()
+            .map(|(id, n)| {
+                let future = guarded::<Wildcard>(&n, Arc::clone(&event));
+                (id, self.layered::<Ev>(id, future))
+            })
+            .collect()
+    }
+
+    /// The alive listeners of `Ev`, in order. The lock is only held while cloning them,
+    /// so listeners can (un)register listeners themselves.
+    fn listeners_of<Ev: Event>(&self) -> Vec<(SubscriptionId, Arc<Listener<Ev>>)> {
+        self.listeners
+            .read()
+            .unwrap()
+            .get(&TypeId::of::<Ev>())
+            .and_then(|n| n.as_any().downcast_ref::<TypedList<Ev>>())
+            .into_iter()
+            .flat_map(|n| &n.list)
+            .filter(|n| n.is_alive())
+            .map(|n| (n.id, Arc::clone(&n.listener)))
+            .collect()
+    }
+
+    /// Wrap the call of the listener `id` of `Ev` in the middleware
+    fn layered<Ev: Event>(&self, id: SubscriptionId, future: FlowFuture) -> FlowFuture {
+        let layers = self.layers.read().unwrap();
+        let info = EventInfo {
+            event: type_name::<Ev>(),
+            id,
+        };
+
+        layers
+            .iter()
+            .rev()
+            .fold(future, |future, layer| layer(Next { future }, info))
+    }
+
+    /// Call `listener`, recording its duration and result in `stats`
+    fn call<Ev: Event>(
+        &self,
+        listener: Arc<Listener<Ev>>,
+        arg: &Arc<Ev::Message>,
+        stats: &Arc<EventStats>,
+    ) -> FlowFuture {
+        let stats = Arc::clone(stats);
+        let start = Instant::now();
+
+        Box::pin(self.retried::<Ev>(listener, arg).map(move |result| {
+            stats.handled(start.elapsed(), result.is_ok());
+            result
+        }))
+    }
+
+    /// Call `listener`, retrying it according to the retry policy of `Ev`
+    fn retried<Ev: Event>(
+        &self,
+        listener: Arc<Listener<Ev>>,
+        arg: &Arc<Ev::Message>,
+    ) -> FlowFuture {
+        let first = guarded::<Ev>(&listener, Arc::clone(arg));
+
+        #[cfg(any(feature = "async-std", feature = "tokio"))]
+        if let Some(&policy) = self.retries.read().unwrap().get(&TypeId::of::<Ev>()) {
+            let arg = Arc::clone(arg);
+
+            return Box::pin(async move {
+                let mut result = first.await;
+
+                for attempt in 1..policy.attempts() {
+                    if result.is_ok() {
+                        break;
+                    }
+
+                    crate::runtime::sleep(policy.delay(attempt)).await;
+                    result = guarded::<Ev>(&listener, Arc::clone(&arg)).await;
+                }
+
+                result
+            });
+        }
+
+        first
+    }
+
+    fn stats_of<Ev: Event>(&self) -> Arc<EventStats> {
+        let type_id = TypeId::of::<Ev>();
+
+        if let Some(stats) = self.stats.read().unwrap().get(&type_id) {
+            return Arc::clone(stats);
+        }
+
+        let mut stats = self.stats.write().unwrap();
+        let stats = stats
+            .entry(type_id)
+            .or_insert_with(|| Arc::new(EventStats::new(type_name::<Ev>())));
+
+        Arc::clone(stats)
+    }
+
+    /// Pass an event without listeners to the unhandled handler
+    fn unhandled<Ev: Event>(&self, arg: &Arc<Ev::Message>) {
+        let handler = self.unhandled.read().unwrap().clone();
+
+        if let Some(handler) = handler {
+            handler(AnyEvent::new::<Ev>(arg));
         }
     }
+
+    /// Pass the error of a listener to the error handler and the dead-letter sink
+    fn report<Ev: Event>(&self, arg: &Arc<Ev::Message>, error: ListenerError, id: SubscriptionId) {
+        let event = type_name::<Ev>();
+        let on_error = *self.on_error.read().unwrap();
+
+        on_error(&*error, EventInfo { event, id });
+
+        let sink = self.dead_letters.read().unwrap().clone();
+
+        if let Some(sink) = sink {
+            sink(DeadLetter {
+                event: AnyEvent::new::<Ev>(arg),
+                listener: id,
+                error,
+            });
+        }
+    }
+}
+
+impl Default for EventEmitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "async-std", not(feature = "tokio")))]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    struct Ping;
+
+    impl Event for Ping {
+        type Message = u32;
+    }
+
+    /// An emitter forwarding every `Ping` to the returned receiver
+    fn pinged() -> (Arc<EventEmitter>, mpsc::UnboundedReceiver<u32>) {
+        let events = Arc::new(EventEmitter::new());
+        let (sender, receiver) = mpsc::unbounded();
+
+        events.on::<Ping>(move |n| {
+            let _ = sender.unbounded_send(*n);
+            Box::pin(async { Ok(()) })
+        });
+
+        (events, receiver)
+    }
+
+    #[test]
+    fn default_emitters_start_without_listeners() {
+        let events = EventEmitter::default();
+        assert_eq!(events.listener_count::<Ping>(), 0);
+
+        let (sender, mut pings) = mpsc::unbounded();
+        events.on::<Ping>(move |n| {
+            let _ = sender.unbounded_send(*n);
+            Box::pin(async { Ok(()) })
+        });
+
+        async_std::task::block_on(async {
+            events.emit::<Ping>(3).await;
+            assert_eq!(pings.next().await, Some(3));
+        });
+    }
+
+    #[test]
+    fn idle_waits_for_emits_in_flight() {
+        let events = EventEmitter::new();
+        let (gate, opened) = mpsc::unbounded::<()>();
+        let opened = Arc::new(futures::lock::Mutex::new(opened));
+
+        events.on::<Ping>(move |_| {
+            let opened = Arc::clone(&opened);
+            Box::pin(async move {
+                opened.lock().await.next().await;
+                Ok(())
+            })
+        });
+
+        async_std::task::block_on(async {
+            let mut emit = Box::pin(events.emit::<Ping>(0));
+            let mut idle = Box::pin(events.idle());
+
+            assert!(futures::poll!(&mut emit).is_pending());
+            assert!(futures::poll!(&mut idle).is_pending());
+
+            gate.unbounded_send(()).unwrap();
+            emit.await;
+            idle.await;
+        });
+    }
+
+    #[test]
+    fn delayed_events_are_emitted_unless_cancelled() {
+        let (events, mut pings) = pinged();
+
+        let later = Arc::clone(&events).emit_after::<Ping>(2, Duration::from_secs(3600));
+        Arc::clone(&events).emit_after::<Ping>(1, Duration::from_millis(1));
+
+        assert!(later.cancel());
+
+        async_std::task::block_on(async {
+            assert_eq!(pings.next().await, Some(1));
+        });
+    }
+
+    #[test]
+    fn removed_listeners_are_not_called_later() {
+        let events = Arc::new(EventEmitter::new());
+        let (sender, mut calls) = mpsc::unbounded();
+        let id = events.reserve_id();
+        let alive = Arc::new(AtomicBool::new(true));
+
+        let listener = Arc::new(continuing::<Ping>(move |n| {
+            let _ = sender.unbounded_send(*n);
+            Box::pin(async { Ok(()) })
+        }));
+
+        events.register_as::<Ping>(
+            id,
+            continuing::<Ping>(|_| Box::pin(async { Ok(()) })),
+            Arc::clone(&alive),
+            0,
+        );
+        let later = events.deferred::<Ping>(id, listener, &alive, Arc::new(1));
+
+        assert!(events.off(id));
+        async_std::task::block_on(later);
+
+        assert!(calls.try_recv().is_err());
+    }
+
+    #[test]
+    fn debounced_calls_skip_the_middleware() {
+        let events = Arc::new(EventEmitter::new());
+        let (sender, mut calls) = mpsc::unbounded();
+        let layered = Arc::new(AtomicU64::new(0));
+
+        let counter = Arc::clone(&layered);
+        events.layer(move |next, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Box::pin(next.run())
+        });
+
+        Arc::clone(&events).on_debounced::<Ping>(Duration::from_millis(1), move |n| {
+            let _ = sender.unbounded_send(*n);
+            Box::pin(async { Ok(()) })
+        });
+
+        async_std::task::block_on(async {
+            events.emit::<Ping>(1).await;
+            assert_eq!(calls.next().await, Some(1));
+        });
+
+        assert_eq!(layered.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn schedules_do_not_keep_the_emitter_alive() {
+        let (events, _pings) = pinged();
+        let weak = Arc::downgrade(&events);
+
+        let _schedule =
+            Arc::clone(&events).schedule::<Ping>(crate::every(Duration::from_secs(3600)), 1);
+        drop(events);
+
+        assert!(weak.upgrade().is_none());
+    }
 }
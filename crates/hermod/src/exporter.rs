@@ -0,0 +1,150 @@
+use prometheus::{
+    exponential_buckets, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::sync::OnceLock;
+
+#[cfg(feature = "queue")]
+use prometheus::IntGaugeVec;
+
+/// The hermod metrics, registered in their own `Registry`
+pub(crate) struct Collectors {
+    registry: Registry,
+    #[cfg(feature = "events")]
+    pub(crate) emitted: IntCounterVec,
+    #[cfg(feature = "events")]
+    pub(crate) failures: IntCounterVec,
+    #[cfg(feature = "events")]
+    pub(crate) listener_duration: HistogramVec,
+    #[cfg(feature = "queue")]
+    pub(crate) depth: IntGaugeVec,
+    #[cfg(feature = "queue")]
+    pub(crate) in_flight: IntGaugeVec,
+    #[cfg(feature = "queue")]
+    pub(crate) handled: IntCounterVec,
+    #[cfg(feature = "queue")]
+    pub(crate) panics: IntCounterVec,
+    #[cfg(feature = "queue")]
+    pub(crate) handler_duration: HistogramVec,
+}
+
+static COLLECTORS: OnceLock<Collectors> = OnceLock::new();
+
+fn counter(registry: &Registry, name: &str, help: &str, label: &str) -> IntCounterVec {
+    let counter = IntCounterVec::new(Opts::new(name, help), &[label]).unwrap();
+    registry.register(Box::new(counter.clone())).unwrap();
+    counter
+}
+
+#[cfg(feature = "queue")]
+fn gauge(registry: &Registry, name: &str, help: &str, label: &str) -> IntGaugeVec {
+    let gauge = IntGaugeVec::new(Opts::new(name, help), &[label]).unwrap();
+    registry.register(Box::new(gauge.clone())).unwrap();
+    gauge
+}
+
+fn histogram(registry: &Registry, name: &str, help: &str, label: &str) -> HistogramVec {
+    // 10µs to ~84s
+    let buckets = exponential_buckets(0.00001, 4.0, 12).unwrap();
+    let opts = HistogramOpts::new(name, help).buckets(buckets);
+
+    let histogram = HistogramVec::new(opts, &[label]).unwrap();
+    registry.register(Box::new(histogram.clone())).unwrap();
+    histogram
+}
+
+pub(crate) fn collectors() -> &'static Collectors {
+    COLLECTORS.get_or_init(|| {
+        let registry = Registry::new();
+
+        Collectors {
+            #[cfg(feature = "events")]
+            emitted: counter(
+                &registry,
+                "hermod_events_emitted_total",
+                "How often each event was emitted",
+                "event",
+            ),
+            #[cfg(feature = "events")]
+            failures: counter(
+                &registry,
+                "hermod_listener_failures_total",
+                "Listener calls that failed after retries, including panics",
+                "event",
+            ),
+            #[cfg(feature = "events")]
+            listener_duration: histogram(
+                &registry,
+                "hermod_listener_duration_seconds",
+                "Duration of the listener calls",
+                "event",
+            ),
+            #[cfg(feature = "queue")]
+            depth: gauge(
+                &registry,
+                "hermod_queue_depth",
+                "Events queued or being handled",
+                "queue",
+            ),
+            #[cfg(feature = "queue")]
+            in_flight: gauge(
+                &registry,
+                "hermod_queue_in_flight",
+                "Events being handled",
+                "queue",
+            ),
+            #[cfg(feature = "queue")]
+            handled: counter(
+                &registry,
+                "hermod_queue_handled_total",
+                "Events handled",
+                "queue",
+            ),
+            #[cfg(feature = "queue")]
+            panics: counter(
+                &registry,
+                "hermod_queue_panics_total",
+                "Handlers that panicked",
+                "queue",
+            ),
+            #[cfg(feature = "queue")]
+            handler_duration: histogram(
+                &registry,
+                "hermod_queue_handler_duration_seconds",
+                "Duration of the handlers",
+                "queue",
+            ),
+            registry,
+        }
+    })
+}
+
+/// The `Registry` of the hermod metrics (`prometheus` feature), e.g. to gather them
+/// with the metrics of the application. Event metrics are labelled by the type name
+/// of the event, queue metrics by the name of the queue.
+pub fn prometheus_registry() -> &'static Registry {
+    &collectors().registry
+}
+
+/// The hermod metrics in the Prometheus text format (`prometheus` feature), to serve
+/// on a `/metrics` endpoint.
+///
+/// ```
+/// use hermod::Sender;
+///
+/// let queue = Sender::<u32, u32>::builder()
+///     .with_name("doubler")
+///     .build(|n| async move { n * 2 });
+///
+/// async_std::task::block_on(async {
+///     queue.try_emit(21u32).unwrap();
+///     queue.drain().await;
+/// });
+///
+/// let metrics = hermod::encode_prometheus().unwrap();
+///
+/// assert!(metrics.contains("hermod_queue_handled_total{queue=\"doubler\"} 1"));
+/// assert!(metrics.contains("hermod_queue_depth{queue=\"doubler\"} 0"));
+/// ```
+pub fn encode_prometheus() -> Result<String, prometheus::Error> {
+    TextEncoder::new().encode_to_string(&prometheus_registry().gather())
+}
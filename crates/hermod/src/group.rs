@@ -0,0 +1,28 @@
+use crate::EventEmitter;
+use futures::future::BoxFuture;
+use std::sync::{Arc, Mutex};
+
+/// A listener call put off until its group is resumed
+pub(crate) type Buffered = Box<dyn for<'a> FnOnce(&'a EventEmitter) -> BoxFuture<'a, ()> + Send>;
+
+/// # PausePolicy
+///
+/// What happens to the events of a paused listener group, see
+/// `EventEmitter::pause_with`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PausePolicy {
+    /// Keep the events, and call the listeners with them when the group is resumed
+    #[default]
+    Buffer,
+    /// Drop the events
+    Drop,
+}
+
+/// The state of a listener group
+#[derive(Default)]
+pub(crate) struct Group {
+    pub(crate) paused: Option<PausePolicy>,
+    pub(crate) buffer: Vec<Buffered>,
+}
+
+pub(crate) type SharedGroup = Arc<Mutex<Group>>;
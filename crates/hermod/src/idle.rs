@@ -0,0 +1,59 @@
+use futures::future;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    task::{Poll, Waker},
+};
+
+/// Counts the emits in flight, see `EventEmitter::idle`
+#[derive(Default)]
+pub(crate) struct InFlight {
+    count: AtomicUsize,
+    // the tasks waiting in `idle`
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl InFlight {
+    /// Count an emit until the returned guard is dropped
+    pub(crate) fn enter(&self) -> Busy<'_> {
+        self.count.fetch_add(1, Ordering::AcqRel);
+        Busy(self)
+    }
+
+    /// Resolve once nothing is in flight
+    pub(crate) async fn idle(&self) {
+        future::poll_fn(|cx| {
+            if self.count.load(Ordering::Acquire) == 0 {
+                return Poll::Ready(());
+            }
+
+            let mut wakers = self.wakers.lock().unwrap();
+
+            // the last emit may have finished before the lock was taken
+            if self.count.load(Ordering::Acquire) == 0 {
+                return Poll::Ready(());
+            }
+
+            wakers.push(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+pub(crate) struct Busy<'a>(&'a InFlight);
+
+impl Drop for Busy<'_> {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0
+                .wakers
+                .lock()
+                .unwrap()
+                .drain(..)
+                .for_each(Waker::wake);
+        }
+    }
+}
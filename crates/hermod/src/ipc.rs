@@ -0,0 +1,699 @@
+use crate::{runtime, Backoff, Event, EventEmitter, RetryPolicy, SubscriptionId};
+use futures::{
+    channel::{mpsc, oneshot},
+    future::{self, BoxFuture, Either, FutureExt},
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    AsyncReadExt, Stream, StreamExt,
+};
+use log::{error, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    any::type_name,
+    collections::{HashMap, HashSet},
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock, Weak,
+    },
+    time::Duration,
+};
+
+#[cfg(feature = "async-std")]
+use async_std::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(feature = "async-std")]
+use futures::stream;
+#[cfg(feature = "async-std")]
+use std::net::SocketAddr;
+
+#[cfg(all(unix, feature = "async-std"))]
+use async_std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(all(unix, feature = "async-std"))]
+use std::path::PathBuf;
+
+// bumped whenever frames change, peers with another version are refused
+const PROTOCOL_VERSION: u32 = 1;
+
+/// The default limits, see `IpcBridge::with_max_frame_size` and `with_outbox_size`
+const MAX_FRAME_SIZE: usize = 1 << 20;
+const OUTBOX_SIZE: usize = 1024;
+
+type Import = Box<dyn Fn(Value) -> serde_json::Result<BoxFuture<'static, ()>> + Send + Sync>;
+
+/// What is sent over a connection, one JSON object per line
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Frame {
+    /// The handshake, sent first by both sides, with the events they import
+    Hello {
+        version: u32,
+        events: Vec<String>,
+    },
+    /// Sent when the events a side imports change
+    Subscribe {
+        events: Vec<String>,
+    },
+    Event {
+        name: String,
+        message: Value,
+    },
+}
+
+/// A connected process
+struct Peer {
+    // the events it wants to receive
+    events: HashSet<String>,
+    outbox: mpsc::Sender<String>,
+}
+
+impl Peer {
+    /// Queue `frame` for the peer, disconnecting it if it does not keep up
+    fn send(&mut self, frame: String) {
+        if let Err(e) = self.outbox.try_send(frame) {
+            if e.is_full() {
+                warn!("Disconnecting IPC peer, it does not keep up with the events");
+                self.outbox.close_channel();
+            }
+        }
+    }
+}
+
+/// How large frames and outboxes may get, see `IpcBridge::with_max_frame_size`
+#[derive(Clone, Copy)]
+struct Limits {
+    max_frame_size: usize,
+    outbox_size: usize,
+}
+
+struct Shared {
+    emitter: Arc<EventEmitter>,
+    exports: Mutex<HashMap<&'static str, SubscriptionId>>,
+    imports: RwLock<HashMap<&'static str, Import>>,
+    peers: Mutex<HashMap<u64, Peer>>,
+    next_peer: AtomicU64,
+    // resolves once the bridge is dropped, to stop its connections
+    closed: future::Shared<oneshot::Receiver<()>>,
+}
+
+/// # IpcBridge
+///
+/// Connects the `EventEmitter`s of several processes, over TCP or Unix domain
+/// sockets. Exported events emitted in one process are emitted again in every
+/// connected process that imports them, so the event code does not change when a
+/// program is split into several processes.
+///
+/// Messages are sent as JSON, and events are identified by their type name, so
+/// every process should be built from the same event definitions. An event is
+/// either exported or imported by a process, not both, so it cannot bounce between
+/// processes forever.
+///
+/// Connections made with `connect_tcp` and `connect_unix` are retried when they
+/// fail or drop, see `with_reconnect`. Every connection is closed once the bridge is
+/// dropped.
+///
+/// The TCP and Unix domain socket transports need the `async-std` feature, other
+/// transports (e.g. the sockets of Tokio) can be used with `listen_with` and
+/// `connect_with`.
+///
+/// ```no_run
+/// use hermod::{Event, EventEmitter, IpcBridge};
+/// use std::sync::Arc;
+///
+/// pub struct OrderCreated;
+///
+/// impl Event for OrderCreated {
+///     type Message = u64;
+/// }
+///
+/// let events = Arc::new(EventEmitter::new());
+///
+/// async_std::task::block_on(async {
+///     // in the daemon
+///     let bridge = IpcBridge::new(Arc::clone(&events));
+///     bridge.export::<OrderCreated>();
+///     bridge.listen_tcp("127.0.0.1:7400").await.unwrap();
+///
+///     // in the worker process
+///     let bridge = IpcBridge::new(Arc::clone(&events));
+///     bridge.import::<OrderCreated>();
+///     bridge.connect_tcp("127.0.0.1:7400");
+/// });
+/// ```
+pub struct IpcBridge {
+    shared: Arc<Shared>,
+    reconnect: RetryPolicy,
+    limits: Limits,
+    // dropped with the bridge, which resolves `Shared::closed`
+    _close: oneshot::Sender<()>,
+}
+
+impl IpcBridge {
+    pub fn new(emitter: Arc<EventEmitter>) -> Self {
+        let reconnect = RetryPolicy::new(u32::MAX)
+            .with_backoff(Backoff::Exponential {
+                initial: Duration::from_millis(100),
+                max: Duration::from_secs(10),
+            })
+            .with_jitter();
+
+        let (close, closed) = oneshot::channel();
+
+        Self {
+            shared: Arc::new(Shared {
+                emitter,
+                exports: Mutex::new(HashMap::new()),
+                imports: RwLock::new(HashMap::new()),
+                peers: Mutex::new(HashMap::new()),
+                next_peer: AtomicU64::new(0),
+                closed: closed.shared(),
+            }),
+            reconnect,
+            limits: Limits {
+                max_frame_size: MAX_FRAME_SIZE,
+                outbox_size: OUTBOX_SIZE,
+            },
+            _close: close,
+        }
+    }
+
+    /// How outgoing connections are retried: `attempts` is how many times in a row
+    /// connecting may fail before giving up. By default, they are retried forever,
+    /// waiting up to 10 seconds.
+    pub fn with_reconnect(mut self, policy: RetryPolicy) -> Self {
+        self.reconnect = policy;
+        self
+    }
+
+    /// Close the connections receiving a frame larger than `bytes`, 1 MiB by default
+    pub fn with_max_frame_size(mut self, bytes: usize) -> Self {
+        self.limits.max_frame_size = bytes;
+        self
+    }
+
+    /// Queue at most `frames` (at least 1) frames for each connection, 1024 by
+    /// default. A peer that does not read its frames fast enough is disconnected,
+    /// instead of buffering them forever.
+    pub fn with_outbox_size(mut self, frames: usize) -> Self {
+        self.limits.outbox_size = frames.max(1);
+        self
+    }
+
+    /// Send every `Ev` emitted in this process to the connected processes importing
+    /// it. Returns `false` if `Ev` is imported.
+    pub fn export<Ev: Event>(&self) -> bool
+    where
+        Ev::Message: Serialize,
+    {
+        let name = type_name::<Ev>();
+
+        if self.shared.imports.read().unwrap().contains_key(name) {
+            return false;
+        }
+
+        let mut exports = self.shared.exports.lock().unwrap();
+
+        if exports.contains_key(name) {
+            return true;
+        }
+
+        let shared = Arc::downgrade(&self.shared);
+
+        let id = self.shared.emitter.on::<Ev>(move |msg| {
+            let shared = Weak::clone(&shared);
+
+            Box::pin(async move {
+                if let Some(shared) = shared.upgrade() {
+                    let message = serde_json::to_value(&*msg)?;
+                    shared.broadcast(name, message)?;
+                }
+
+                Ok(())
+            })
+        });
+
+        exports.insert(name, id);
+        true
+    }
+
+    /// Emit every `Ev` received from a connected process in this process. Returns
+    /// `false` if `Ev` is exported.
+    pub fn import<Ev: Event>(&self) -> bool
+    where
+        Ev::Message: DeserializeOwned,
+    {
+        let name = type_name::<Ev>();
+
+        if self.shared.exports.lock().unwrap().contains_key(name) {
+            return false;
+        }
+
+        let emitter = Arc::clone(&self.shared.emitter);
+
+        let import: Import = Box::new(move |message| {
+            let message = serde_json::from_value::<Ev::Message>(message)?;
+            let emitter = Arc::clone(&emitter);
+
+            Ok(Box::pin(async move { emitter.emit::<Ev>(message).await }))
+        });
+
+        self.shared.imports.write().unwrap().insert(name, import);
+
+        // peers only send the events we import
+        let events = self.shared.import_names();
+        let frame = serde_json::to_string(&Frame::Subscribe { events }).unwrap();
+
+        for peer in self.shared.peers.lock().unwrap().values_mut() {
+            peer.send(frame.clone());
+        }
+
+        true
+    }
+
+    /// Number of processes currently connected
+    pub fn peer_count(&self) -> usize {
+        self.shared.peers.lock().unwrap().len()
+    }
+
+    /// Accept connections on `addr`. Returns the address listened on, e.g. to find
+    /// the port when binding to port 0.
+    #[cfg(feature = "async-std")]
+    pub async fn listen_tcp(&self, addr: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+
+        self.listen_with(stream::unfold(listener, |listener| async move {
+            let stream = listener.accept().await.map(|(stream, _)| stream);
+            Some((stream, listener))
+        }));
+
+        Ok(addr)
+    }
+
+    /// Accept connections on the Unix domain socket at `path`
+    #[cfg(all(unix, feature = "async-std"))]
+    pub async fn listen_unix(&self, path: impl Into<PathBuf>) -> io::Result<()> {
+        let listener = UnixListener::bind(path.into()).await?;
+
+        self.listen_with(stream::unfold(listener, |listener| async move {
+            let stream = listener.accept().await.map(|(stream, _)| stream);
+            Some((stream, listener))
+        }));
+
+        Ok(())
+    }
+
+    /// Accept the connections of `incoming`, for other transports. Stops once the
+    /// bridge is dropped.
+    pub fn listen_with<S>(&self, incoming: impl Stream<Item = io::Result<S>> + Send + 'static)
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let shared = Arc::downgrade(&self.shared);
+        let closed = self.shared.closed.clone();
+        let limits = self.limits;
+
+        let accepting = async move {
+            let mut incoming = Box::pin(incoming);
+
+            while let Some(stream) = incoming.next().await {
+                let Some(shared) = shared.upgrade() else {
+                    break;
+                };
+
+                match stream {
+                    Ok(stream) => runtime::spawn(Box::pin(async move {
+                        if let Err(e) = serve(shared, stream, limits).await {
+                            warn!("IPC connection failed: {e}");
+                        }
+                    })),
+                    Err(e) => warn!("Failed to accept IPC connection: {e}"),
+                }
+            }
+        };
+
+        runtime::spawn(Box::pin(async move {
+            future::select(Box::pin(accepting), closed).await;
+        }));
+    }
+
+    /// Connect to the bridge listening on `addr`, reconnecting when the connection
+    /// fails or drops.
+    #[cfg(feature = "async-std")]
+    pub fn connect_tcp(&self, addr: impl Into<String>) {
+        let addr = addr.into();
+
+        self.connect_with(move || {
+            let addr = addr.clone();
+            Box::pin(async move { TcpStream::connect(addr).await })
+        });
+    }
+
+    /// Like `connect_tcp`, with the Unix domain socket at `path`
+    #[cfg(all(unix, feature = "async-std"))]
+    pub fn connect_unix(&self, path: impl Into<PathBuf>) {
+        let path = path.into();
+
+        self.connect_with(move || {
+            let path = path.clone();
+            Box::pin(async move { UnixStream::connect(path).await })
+        });
+    }
+
+    /// Connect with `connect`, for other transports. Reconnects until the bridge is
+    /// dropped, or connecting failed too often (see `with_reconnect`).
+    pub fn connect_with<S>(
+        &self,
+        connect: impl Fn() -> BoxFuture<'static, io::Result<S>> + Send + 'static,
+    ) where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let weak = Arc::downgrade(&self.shared);
+        let policy = self.reconnect;
+        let limits = self.limits;
+
+        runtime::spawn(Box::pin(async move {
+            let mut failures = 0;
+
+            while let Some(shared) = weak.upgrade() {
+                match connect().await {
+                    Ok(stream) => {
+                        failures = 0;
+
+                        if let Err(e) = serve(shared, stream, limits).await {
+                            warn!("IPC connection failed: {e}");
+                        }
+                    }
+                    Err(e) => {
+                        // so the bridge can be dropped while waiting to reconnect
+                        drop(shared);
+
+                        failures += 1;
+                        warn!("Failed to connect to IPC peer: {e}");
+                    }
+                }
+
+                if failures >= policy.attempts() {
+                    error!("Giving up connecting to IPC peer after {failures} attempts");
+                    break;
+                }
+
+                runtime::sleep(policy.delay(failures.max(1))).await;
+            }
+        }));
+    }
+}
+
+impl Drop for IpcBridge {
+    fn drop(&mut self) {
+        for (_, id) in self.shared.exports.lock().unwrap().drain() {
+            self.shared.emitter.off(id);
+        }
+    }
+}
+
+impl Shared {
+    fn import_names(&self) -> Vec<String> {
+        let imports = self.imports.read().unwrap();
+        imports.keys().map(|n| n.to_string()).collect()
+    }
+
+    /// Send an exported event to the peers importing it
+    fn broadcast(&self, name: &str, message: Value) -> serde_json::Result<()> {
+        let mut peers = self.peers.lock().unwrap();
+        let mut peers = peers
+            .values_mut()
+            .filter(|n| n.events.contains(name))
+            .peekable();
+
+        if peers.peek().is_none() {
+            return Ok(());
+        }
+
+        let name = name.to_string();
+        let frame = serde_json::to_string(&Frame::Event { name, message })?;
+
+        for peer in peers {
+            peer.send(frame.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Handle a frame received from `peer`
+    async fn receive(&self, peer: u64, frame: Frame) {
+        match frame {
+            Frame::Event { name, message } => {
+                let delivery = match self.imports.read().unwrap().get(name.as_str()) {
+                    Some(import) => import(message),
+                    None => return,
+                };
+
+                match delivery {
+                    Ok(delivery) => delivery.await,
+                    Err(e) => error!("Failed to decode IPC event {name}: {e}"),
+                }
+            }
+            Frame::Subscribe { events } => {
+                if let Some(peer) = self.peers.lock().unwrap().get_mut(&peer) {
+                    peer.events = events.into_iter().collect();
+                }
+            }
+            Frame::Hello { .. } => warn!("Ignoring repeated IPC handshake"),
+        }
+    }
+}
+
+/// Run a connection until either side closes it, or the bridge is dropped
+async fn serve<S>(shared: Arc<Shared>, stream: S, limits: Limits) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let closed = shared.closed.clone();
+
+    match future::select(Box::pin(connection(&shared, stream, limits)), closed).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Ok(()),
+    }
+}
+
+async fn connection<S>(shared: &Shared, stream: S, limits: Limits) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let (reader, mut writer) = stream.split();
+    let mut reader = BufReader::new(reader);
+    let max = limits.max_frame_size;
+
+    let events = shared.import_names();
+    let hello = Frame::Hello {
+        version: PROTOCOL_VERSION,
+        events,
+    };
+
+    write_line(&mut writer, &serde_json::to_string(&hello)?).await?;
+
+    let events = match read_line(&mut reader, max).await? {
+        Some(line) => match serde_json::from_str(&line)? {
+            Frame::Hello { version, events } if version == PROTOCOL_VERSION => events,
+            Frame::Hello { version, .. } => {
+                let message = format!("peer uses IPC protocol version {version}");
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+            _ => {
+                let message = "peer did not start with a handshake";
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        },
+        None => return Ok(()),
+    };
+
+    // the sender itself holds one more slot
+    let (outbox, mut outgoing) = mpsc::channel::<String>(limits.outbox_size - 1);
+    let id = shared.next_peer.fetch_add(1, Ordering::Relaxed);
+
+    shared.peers.lock().unwrap().insert(
+        id,
+        Peer {
+            events: events.into_iter().collect(),
+            outbox,
+        },
+    );
+
+    let writing = Box::pin(async move {
+        while let Some(line) = outgoing.next().await {
+            write_line(&mut writer, &line).await?;
+        }
+
+        Ok::<_, io::Error>(())
+    });
+
+    let reading = Box::pin(async {
+        while let Some(line) = read_line(&mut reader, max).await? {
+            match serde_json::from_str::<Frame>(&line) {
+                Ok(frame) => shared.receive(id, frame).await,
+                Err(e) => error!("Failed to decode IPC frame: {e}"),
+            }
+        }
+
+        Ok(())
+    });
+
+    let result = match future::select(writing, reading).await {
+        Either::Left((result, _)) | Either::Right((result, _)) => result,
+    };
+
+    shared.peers.lock().unwrap().remove(&id);
+    result
+}
+
+/// Read a line without its newline, failing if it is longer than `max` bytes. `None`
+/// once the stream ends.
+async fn read_line(
+    reader: &mut (impl AsyncBufRead + Unpin),
+    max: usize,
+) -> io::Result<Option<String>> {
+    let mut line = vec![];
+    let read = (&mut *reader)
+        .take(max as u64 + 1)
+        .read_until(b'\n', &mut line)
+        .await?;
+
+    if read == 0 {
+        return Ok(None);
+    }
+
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    } else if line.len() > max {
+        let message = format!("IPC frame larger than {max} bytes");
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+async fn write_line(writer: &mut (impl AsyncWrite + Unpin), line: &str) -> io::Result<()> {
+    writer.write_all(line.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await
+}
+
+#[cfg(all(test, feature = "async-std", not(feature = "tokio")))]
+mod tests {
+    use super::*;
+    use async_std::task;
+
+    struct Ping;
+
+    impl Event for Ping {
+        type Message = u32;
+    }
+
+    /// Wait for `done`, checking it between short naps
+    async fn until(done: impl Fn() -> bool) {
+        while !done() {
+            task::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[test]
+    fn events_cross_the_bridge() {
+        let exporter = IpcBridge::new(Arc::new(EventEmitter::new()));
+        exporter.export::<Ping>();
+
+        let events = Arc::new(EventEmitter::new());
+        let (sender, mut pings) = mpsc::unbounded();
+
+        events.on::<Ping>(move |n| {
+            let _ = sender.unbounded_send(*n);
+            Box::pin(async { Ok(()) })
+        });
+
+        let importer = IpcBridge::new(events);
+        importer.import::<Ping>();
+
+        task::block_on(async {
+            let addr = exporter.listen_tcp("127.0.0.1:0").await.unwrap();
+            importer.connect_tcp(addr.to_string());
+
+            // the exporter knows what the importer imports once it counts it
+            until(|| exporter.peer_count() == 1).await;
+
+            exporter.shared.emitter.emit::<Ping>(7).await;
+            assert_eq!(pings.next().await, Some(7));
+        });
+    }
+
+    #[test]
+    fn slow_peers_are_disconnected() {
+        let (outbox, mut outgoing) = mpsc::channel(0);
+        let mut peer = Peer {
+            events: HashSet::new(),
+            outbox,
+        };
+
+        peer.send(String::from("first"));
+        peer.send(String::from("second"));
+
+        // what was queued is still written, then the connection ends
+        task::block_on(async {
+            assert_eq!(outgoing.next().await.as_deref(), Some("first"));
+            assert_eq!(outgoing.next().await, None);
+        });
+    }
+
+    #[test]
+    fn large_frames_close_the_connection() {
+        let bridge = IpcBridge::new(Arc::new(EventEmitter::new())).with_max_frame_size(64);
+
+        task::block_on(async {
+            let addr = bridge.listen_tcp("127.0.0.1:0").await.unwrap();
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+
+            let hello = Frame::Hello {
+                version: PROTOCOL_VERSION,
+                events: vec![],
+            };
+
+            write_line(&mut stream, &serde_json::to_string(&hello).unwrap())
+                .await
+                .unwrap();
+            stream.write_all(&[b'x'; 100]).await.unwrap();
+
+            // the bridge's hello, then the end of the stream
+            let mut received = String::new();
+            stream.read_to_string(&mut received).await.unwrap();
+
+            assert_eq!(received.lines().count(), 1);
+        });
+    }
+
+    #[test]
+    fn dropping_the_bridge_closes_its_connections() {
+        let bridge = IpcBridge::new(Arc::new(EventEmitter::new()));
+
+        task::block_on(async {
+            let addr = bridge.listen_tcp("127.0.0.1:0").await.unwrap();
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+
+            let hello = Frame::Hello {
+                version: PROTOCOL_VERSION,
+                events: vec![],
+            };
+
+            write_line(&mut stream, &serde_json::to_string(&hello).unwrap())
+                .await
+                .unwrap();
+
+            until(|| bridge.peer_count() == 1).await;
+            drop(bridge);
+
+            let mut received = String::new();
+            stream.read_to_string(&mut received).await.unwrap();
+
+            assert_eq!(received.lines().count(), 1);
+        });
+    }
+}
@@ -1,5 +1,3 @@
-#![feature(stmt_expr_attributes)]
-
 //! # hermod
 //!
 //! Intra-process communication utility crate.
@@ -12,17 +10,109 @@
 //!
 //! ### Features
 //!
-//!  - **Emit from anywhere**: You can emit events from anywhere using
-//!    an immutable reference - using an `Arc`, for example.
+//!  - **Emit from anywhere**: You can emit events and register listeners
+//!    from anywhere using an immutable reference - using an `Arc` or a
+//!    `static`, for example.
 //!
 //!  - **Async callbacks**: Hermod was made to be used asynchronously,
 //!    so the callbacks you register are async.
 //!
-//! ### Drawbacks
+//!  - **Derive**: `#[derive(Event)]` (`derive` feature) implements `Event`
+//!    from a `#[message(...)]` attribute, and can add typed `emit_*` methods
+//!    to your emitter type with `#[emitter(...)]`.
+//!
+//!  - **Unsubscribing**: `on` returns a `SubscriptionId` for `off`, and
+//!    `subscribe` returns a guard that removes the listener when dropped.
+//!
+//!  - **One-shot listeners**: `once` registers a listener that runs only
+//!    once, and `wait_for` returns a future resolving with the next message.
+//!
+//!  - **Streams**: `stream` returns the messages of an event as a `Stream`,
+//!    to use combinators instead of callbacks.
+//!
+//!  - **Broadcast receivers**: `receiver` buffers the messages of an event
+//!    for a consumer polling them at its own pace, dropping the oldest or
+//!    newest messages or making `emit` wait when it falls behind.
+//!
+//!  - **Filters**: `on_filtered` only calls a listener for the messages
+//!    matching a predicate.
+//!
+//!  - **Listener groups**: Listeners registered with `on_in_group` can be
+//!    paused and resumed together, buffering or dropping their events.
+//!
+//!  - **Priorities**: `on_with_priority` registers a listener with a
+//!    priority. Listeners are called by priority, then registration order.
+//!
+//!  - **Error handling**: Listeners can fail with any error, and panics are
+//!    caught and reported as a `ListenerPanic`. Errors are logged, or passed
+//!    to the handler set with `set_error_handler`. Failed events can be
+//!    collected with `set_dead_letter_sink`.
+//!
+//!  - **Unhandled events**: `set_unhandled_handler` is called with the events
+//!    emitted while they have no listeners.
+//!
+//!  - **Retries**: `set_retry_policy` retries failing listeners of an event,
+//!    with a fixed or exponential backoff.
+//!
+//!  - **Rate limits**: `set_rate_limit` limits how often an event is
+//!    emitted, dropping, coalescing or delaying the emits over the limit.
+//!
+//!  - **Middleware**: `layer` wraps every listener call, e.g. for logging,
+//!    timing or access checks.
+//!
+//!  - **Sequential dispatch**: `set_dispatch_mode` calls the listeners of an
+//!    event one after another instead of concurrently, optionally stopping at
+//!    the first error.
+//!
+//!  - **Cancellation**: Listeners registered with `on_cancellable` can stop
+//!    the propagation to lower priority listeners in `emit_cancellable`.
 //!
-//!  - Registering listeners requires a mutable reference. You
-//!    must put the emitter in a lock (`Arc<Mutex<...>>`) or
-//!    register them all in one place.
+//!  - **Shutdown**: `shutdown` cancels the `CancellationToken` passed to
+//!    listeners registered with `on_with_cancellation`.
+//!
+//!  - **Delayed events**: `emit_after` and `emit_at` emit an event later,
+//!    returning a `Scheduled` handle to cancel it. Every delayed event of an
+//!    emitter shares a single timer task.
+//!
+//!  - **Debounce and throttle**: `on_debounced` and `on_throttled` collapse
+//!    bursts of events into fewer listener calls.
+//!
+//!  - **Recurring events**: `schedule` emits an event on an interval
+//!    (`every`) or a `Cron` expression, until it is cancelled.
+//!
+//!  - **Topics**: For events that are not known at compile time, `publish`
+//!    sends a payload on a string topic, and `on_topic` listens to the topics
+//!    matching a pattern like `orders.*`.
+//!
+//!  - **Event hierarchies**: `set_parent` makes a parent event receive its
+//!    children, so `on::<AnyNetworkEvent>` can listen to `Connected` and
+//!    `Disconnected` at once.
+//!
+//!  - **Wildcard listeners**: `on_any` registers a listener for every
+//!    event, e.g. for logging or metrics.
+//!
+//!  - **Cross-process events**: `IpcBridge` (`ipc` feature, with a bundled
+//!    runtime) sends events to other processes over TCP or Unix domain
+//!    sockets, reconnecting when the connection drops.
+//!
+//!  - **Blocking emitter**: `SyncEventEmitter` calls plain closures inline,
+//!    for non-async code or `Drop` impls.
+//!
+//!  - **Waiting for idle**: `idle` resolves once no emit is in flight, so
+//!    tests can wait for events to settle instead of sleeping.
+//!
+//!  - **Introspection**: `listeners_for` and `registered_events` return the
+//!    listeners currently registered, and the `Debug` impl prints them.
+//!
+//!  - **Metrics**: `metrics` returns how many events were emitted and failed,
+//!    and the latency percentiles of their listeners.
+//!
+//!  - **Logging**: With the `logging` feature, every emit and listener call
+//!    is logged under the `hermod` target: emits and durations at `trace`,
+//!    failures at `debug`.
+//!
+//!  - **Tracing**: With the `tracing` feature, every emit runs in a `debug`
+//!    span named `emit`, with the type of the event as its `event` field.
 //!
 //! ## Queue
 //! <sub> Requires `queue` feature </sub>
@@ -30,6 +120,18 @@
 //! The queue can be used from anywhere to send messages to a
 //! single-threaded callback.
 //!
+//! The callback runs in a background task, spawned on async-std
+//! (`async-std` feature, default) or Tokio (`tokio` feature). If both are
+//! enabled, e.g. because another dependency uses the default features, Tokio
+//! is used, so the tasks need a Tokio runtime. Other executors can be used
+//! with `Sender::new_on` and the `Spawn` trait.
+//!
+//! In browsers, the `wasm` feature (with the default features disabled) spawns
+//! the task with `wasm_bindgen_futures::spawn_local` on wasm32, and relaxes the
+//! `Send` and `Sync` bounds of the messages, responses and data to `MaybeSend`
+//! and `MaybeSync`. Delays, timeouts and schedules need one of the bundled
+//! runtimes, so they are not available there.
+//!
 //! ### Features
 //!
 //!  - **Send from anywhere**: You can send messages from anywhere
@@ -39,19 +141,233 @@
 //!  - **Persistant data**: You can persist some data between calls.
 //!    Because the queue is single-threaded, we can just use a mutable
 //!    reference with no overhead.
+//!
+//!  - **Builder**: `Sender::builder` configures the name, workers and
+//!    capacity of a queue, and takes handlers capturing the state of the
+//!    application, written with `async move`.
+//!
+//!  - **Workers**: `Sender::with_workers` handles several messages
+//!    concurrently, each worker with its own data.
+//!
+//!  - **Partitions**: `Sender::partitioned` handles messages with the same
+//!    key in order, and messages with different keys concurrently.
+//!
+//!  - **Batching**: `Sender::batched` handles messages in batches, by size
+//!    or latency, e.g. for bulk database inserts.
+//!
+//!  - **Panic isolation**: A panicking handler only loses its message, the
+//!    queue keeps running. Panics are passed to `Sender::set_panic_handler`.
+//!
+//!  - **Retries and dead letters**: `Sender::set_retry_policy` retries the
+//!    messages whose handler panicked, or returned an error in a
+//!    `Sender::fallible` queue, and `Sender::set_dead_letter_sink` collects
+//!    the ones that still failed.
+//!
+//!  - **Streaming responses**: Handlers of a `Sender::streaming` queue can
+//!    send several responses through a `ResponseSink`.
+//!
+//!  - **Backpressure**: `Sender::bounded` limits how many messages can be
+//!    queued. `emit` then waits for room, and `try_emit` fails instead.
+//!
+//!  - **Timeouts**: `emit_timeout` waits for the response for a limited
+//!    time, so a hanging handler cannot block the caller forever.
+//!
+//!  - **Errors**: The `emit` methods fail with an `Error`, telling a closed
+//!    or full queue, a timeout and a failed handler apart. The event is given
+//!    back when it was not queued.
+//!
+//!  - **Delayed messages**: `emit_after` and `emit_at` queue a message later,
+//!    and can be cancelled with the returned `Scheduled` handle. `schedule`
+//!    queues a message on an interval or a `Cron` expression.
+//!
+//!  - **Durability**: `Sender::durable` (`durable` feature) logs queued
+//!    messages to disk until they are handled, so they survive a restart.
+//!
+//!  - **Routing**: `Router` sends each message to the first of several
+//!    queues whose predicate matches it, or to a fallback.
+//!
+//!  - **Requests**: `Rpc` sends a `Request` to its single responder and
+//!    returns the response, typed by the request.
+//!
+//!  - **Waiting for idle**: `Sender::idle` resolves once nothing is queued
+//!    or being handled.
+//!
+//!  - **Supervision**: `Supervisor` restarts the tasks of its queues when
+//!    they stop, with a `RestartPolicy`, and reports it with `WorkerEvent`s.
+//!
+//!  - **Shutdown**: `close` stops accepting messages and `drain` waits for
+//!    the queued ones. Dropping the `Sender` stops the task once it is empty.
+//!    `shutdown` also cancels the `CancellationToken` passed to the handlers
+//!    of a `Sender::with_cancellation` queue.
+//!
+//!  - **Metrics**: `metrics` returns the queue depth, how many messages were
+//!    handled or panicked, and the handler latency percentiles. With the
+//!    `metrics` feature, all counters are also reported to the `metrics`
+//!    crate facade. With the `prometheus` feature, they are registered in a
+//!    Prometheus registry, and `encode_prometheus` returns them in the text
+//!    format for scraping, with the number of handlers in flight.
+//!
+//!  - **Tracing**: With the `tracing` feature, every handler call runs in a
+//!    `debug` span, `handle` or `handle_batch`, with the type of the message
+//!    as its `event` field.
 
-extern crate async_std;
 extern crate futures;
 extern crate log;
 
+#[cfg(feature = "events")]
+mod blocking;
+
+#[cfg(feature = "events")]
+mod broadcast;
+
+#[cfg(feature = "queue")]
+mod builder;
+
+#[cfg(any(feature = "events", feature = "queue"))]
+mod cancel;
+
+#[cfg(all(
+    any(feature = "events", feature = "queue"),
+    any(feature = "async-std", feature = "tokio")
+))]
+mod cron;
+
+#[cfg(feature = "queue")]
+mod error;
+
+#[cfg(all(feature = "prometheus", any(feature = "events", feature = "queue")))]
+mod exporter;
+
 #[cfg(feature = "events")]
 mod events;
 
+#[cfg(feature = "events")]
+mod group;
+
+#[cfg(feature = "events")]
+mod idle;
+
+#[cfg(all(feature = "ipc", any(feature = "async-std", feature = "tokio")))]
+mod ipc;
+
+#[cfg(all(feature = "events", any(feature = "async-std", feature = "tokio")))]
+mod limit;
+
+#[cfg(any(feature = "events", feature = "queue"))]
+mod metrics;
+
+#[cfg(feature = "events")]
+mod middleware;
+
 #[cfg(feature = "queue")]
 mod queue;
 
+#[cfg(all(
+    any(feature = "events", feature = "queue"),
+    any(feature = "async-std", feature = "tokio")
+))]
+mod retry;
+
+#[cfg(feature = "queue")]
+mod router;
+
+#[cfg(feature = "queue")]
+mod rpc;
+
+mod runtime;
+
+#[cfg(all(
+    any(feature = "events", feature = "queue"),
+    any(feature = "async-std", feature = "tokio")
+))]
+mod schedule;
+
+#[cfg(feature = "events")]
+mod subscription;
+
+#[cfg(all(feature = "queue", any(feature = "async-std", feature = "tokio")))]
+mod supervisor;
+
+#[cfg(feature = "events")]
+mod topic;
+
+#[cfg(feature = "durable")]
+mod wal;
+
+#[cfg(feature = "events")]
+pub use blocking::*;
+
+#[cfg(feature = "events")]
+pub use broadcast::*;
+
+#[cfg(feature = "queue")]
+pub use builder::*;
+
+#[cfg(any(feature = "events", feature = "queue"))]
+pub use cancel::*;
+
+#[cfg(all(
+    any(feature = "events", feature = "queue"),
+    any(feature = "async-std", feature = "tokio")
+))]
+pub use cron::*;
+
+#[cfg(feature = "queue")]
+pub use error::*;
+
 #[cfg(feature = "events")]
 pub use events::*;
 
+#[cfg(all(feature = "prometheus", any(feature = "events", feature = "queue")))]
+pub use exporter::*;
+
+#[cfg(feature = "derive")]
+pub use hermod_derive::Event;
+
+#[cfg(feature = "events")]
+pub use group::PausePolicy;
+
+#[cfg(all(feature = "ipc", any(feature = "async-std", feature = "tokio")))]
+pub use ipc::*;
+
+#[cfg(all(feature = "events", any(feature = "async-std", feature = "tokio")))]
+pub use limit::*;
+
+#[cfg(any(feature = "events", feature = "queue"))]
+pub use metrics::*;
+
+#[cfg(feature = "events")]
+pub use middleware::*;
+
 #[cfg(feature = "queue")]
 pub use queue::*;
+
+#[cfg(all(
+    any(feature = "events", feature = "queue"),
+    any(feature = "async-std", feature = "tokio")
+))]
+pub use retry::*;
+
+#[cfg(feature = "queue")]
+pub use router::*;
+
+#[cfg(feature = "queue")]
+pub use rpc::*;
+
+#[cfg(feature = "queue")]
+pub use runtime::*;
+
+#[cfg(all(
+    any(feature = "events", feature = "queue"),
+    any(feature = "async-std", feature = "tokio")
+))]
+pub use schedule::*;
+
+#[cfg(feature = "events")]
+pub use subscription::*;
+
+#[cfg(all(feature = "queue", any(feature = "async-std", feature = "tokio")))]
+pub use supervisor::*;
+
+#[cfg(feature = "events")]
+pub use topic::*;
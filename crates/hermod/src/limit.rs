@@ -0,0 +1,126 @@
+use std::{
+    any::Any,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// # LimitPolicy
+///
+/// What happens to the emits over a `RateLimit`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LimitPolicy {
+    /// The event is not emitted
+    #[default]
+    Drop,
+    /// Only the last event is emitted, once the limit allows it. The emits it replaced
+    /// return right away.
+    Coalesce,
+    /// `emit` waits until the limit allows it
+    Wait,
+}
+
+/// # RateLimit
+///
+/// How often an event can be emitted, see `EventEmitter::set_rate_limit`. A token
+/// bucket: `burst` events can be emitted at once, then `count` per `per`.
+///
+/// ```
+/// use hermod::{LimitPolicy, RateLimit};
+/// use std::time::Duration;
+///
+/// // 10 per second, in bursts of up to 20, keeping only the latest event over that
+/// let limit = RateLimit::new(10, Duration::from_secs(1))
+///     .with_burst(20)
+///     .with_policy(LimitPolicy::Coalesce);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    // tokens per second
+    rate: f64,
+    burst: u32,
+    policy: LimitPolicy,
+}
+
+impl RateLimit {
+    /// Allow `count` (at least 1) events per `per`, in bursts of up to `count`, dropping
+    /// the others
+    pub fn new(count: u32, per: Duration) -> Self {
+        let count = count.max(1);
+
+        Self {
+            rate: count as f64 / per.as_secs_f64().max(f64::MIN_POSITIVE),
+            burst: count,
+            policy: LimitPolicy::Drop,
+        }
+    }
+
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    pub fn with_policy(mut self, policy: LimitPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn policy(&self) -> LimitPolicy {
+        self.policy
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// The state of a `RateLimit`
+pub(crate) struct Limiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+    // the message a coalescing emit is waiting to emit
+    pub(crate) pending: Mutex<Option<Arc<dyn Any + Send + Sync>>>,
+}
+
+impl Limiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            bucket: Mutex::new(Bucket {
+                tokens: limit.burst as f64,
+                refilled: Instant::now(),
+            }),
+            pending: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn policy(&self) -> LimitPolicy {
+        self.limit.policy
+    }
+
+    /// Take a token, or return how long until the next one
+    pub(crate) fn take(&self) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+
+        let refill = now.duration_since(bucket.refilled).as_secs_f64() * self.limit.rate;
+        bucket.tokens = (bucket.tokens + refill).min(self.limit.burst as f64);
+        bucket.refilled = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(Duration::from_secs_f64(
+            (1.0 - bucket.tokens) / self.limit.rate,
+        ))
+    }
+
+    /// Wait for a token and take it
+    pub(crate) async fn acquire(&self) {
+        while let Err(wait) = self.take() {
+            crate::runtime::sleep(wait).await;
+        }
+    }
+}
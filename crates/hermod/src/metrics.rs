@@ -0,0 +1,313 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+#[cfg(feature = "queue")]
+use crate::PanicHandler;
+#[cfg(feature = "queue")]
+use std::{any::Any, sync::RwLock};
+
+const BUCKETS: usize = 64;
+
+/// # Latency
+///
+/// Latency percentiles. These are upper bounds, accurate to a factor of 2.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Latency {
+    /// Number of measurements
+    pub count: u64,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+}
+
+/// Histogram with power-of-two buckets, in microseconds
+struct Histogram {
+    buckets: [AtomicU64; BUCKETS],
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    fn record(&self, duration: Duration) {
+        let micros = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+
+        // bucket `n` holds `2^(n - 1)..2^n`, bucket 0 holds 0
+        let bucket = (u64::BITS - micros.leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn latency(&self) -> Latency {
+        let buckets = self
+            .buckets
+            .iter()
+            .map(|n| n.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+
+        let count = buckets.iter().sum::<u64>();
+
+        let percentile = |p: f64| {
+            let target = ((count as f64 * p).ceil() as u64).max(1);
+            let mut seen = 0;
+
+            for (bucket, n) in buckets.iter().enumerate() {
+                seen += n;
+
+                if seen >= target {
+                    return Duration::from_micros((1u64 << bucket) - 1);
+                }
+            }
+
+            Duration::ZERO
+        };
+
+        Latency {
+            count,
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+        }
+    }
+}
+
+/// # EventMetrics
+///
+/// Metrics of one event type, see `EventEmitter::metrics`.
+#[cfg(feature = "events")]
+#[derive(Clone, Debug)]
+pub struct EventMetrics {
+    /// Type name of the event
+    pub event: &'static str,
+    /// How often the event was emitted
+    pub emitted: u64,
+    /// How many listener calls failed (after retries), including panics
+    pub failures: u64,
+    /// Duration of the listener calls
+    pub latency: Latency,
+}
+
+#[cfg(feature = "events")]
+pub(crate) struct EventStats {
+    event: &'static str,
+    emitted: AtomicU64,
+    failures: AtomicU64,
+    latency: Histogram,
+}
+
+#[cfg(feature = "events")]
+impl EventStats {
+    pub(crate) fn new(event: &'static str) -> Self {
+        Self {
+            event,
+            emitted: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            latency: Histogram::new(),
+        }
+    }
+
+    pub(crate) fn emitted(&self, listeners: usize) {
+        self.emitted.fetch_add(1, Ordering::Relaxed);
+
+        #[cfg(feature = "logging")]
+        log::trace!(target: "hermod", "Emitting {} to {listeners} listeners", self.event);
+        #[cfg(not(feature = "logging"))]
+        let _ = listeners;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("hermod_events_emitted_total", "event" => self.event).increment(1);
+
+        #[cfg(feature = "prometheus")]
+        crate::exporter::collectors()
+            .emitted
+            .with_label_values(&[self.event])
+            .inc();
+    }
+
+    pub(crate) fn handled(&self, duration: Duration, ok: bool) {
+        self.latency.record(duration);
+
+        if !ok {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "logging")]
+        match ok {
+            true => log::trace!(target: "hermod", "Listener of {} took {duration:?}", self.event),
+            false => {
+                log::debug!(target: "hermod", "Listener of {} failed after {duration:?}", self.event)
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            metrics::histogram!("hermod_listener_duration_seconds", "event" => self.event)
+                .record(duration);
+
+            if !ok {
+                metrics::counter!("hermod_listener_failures_total", "event" => self.event)
+                    .increment(1);
+            }
+        }
+
+        #[cfg(feature = "prometheus")]
+        {
+            let collectors = crate::exporter::collectors();
+
+            collectors
+                .listener_duration
+                .with_label_values(&[self.event])
+                .observe(duration.as_secs_f64());
+
+            if !ok {
+                collectors.failures.with_label_values(&[self.event]).inc();
+            }
+        }
+    }
+
+    pub(crate) fn metrics(&self) -> EventMetrics {
+        EventMetrics {
+            event: self.event,
+            emitted: self.emitted.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            latency: self.latency.latency(),
+        }
+    }
+}
+
+/// # QueueMetrics
+///
+/// Metrics of a queue, see `Sender::metrics`.
+#[cfg(feature = "queue")]
+#[derive(Clone, Copy, Debug)]
+pub struct QueueMetrics {
+    /// Events queued or being handled
+    pub depth: u64,
+    /// Events handled so far
+    pub handled: u64,
+    /// Handlers that panicked
+    pub panics: u64,
+    /// Duration of the handlers
+    pub latency: Latency,
+}
+
+/// Shared by a queue and its task. Besides the metrics, it holds the panic handler,
+/// which can be changed once the task runs.
+#[cfg(feature = "queue")]
+pub(crate) struct QueueStats {
+    queue: &'static str,
+    queued: AtomicU64,
+    handled: AtomicU64,
+    panics: AtomicU64,
+    latency: Histogram,
+    on_panic: RwLock<PanicHandler>,
+}
+
+#[cfg(feature = "queue")]
+impl QueueStats {
+    pub(crate) fn new(queue: &'static str) -> Self {
+        Self {
+            queue,
+            queued: AtomicU64::new(0),
+            handled: AtomicU64::new(0),
+            panics: AtomicU64::new(0),
+            latency: Histogram::new(),
+            on_panic: RwLock::new(crate::queue::log_panic),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        self.queue
+    }
+
+    pub(crate) fn queued(&self) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("hermod_queue_depth", "queue" => self.queue).increment(1);
+
+        #[cfg(feature = "prometheus")]
+        crate::exporter::collectors()
+            .depth
+            .with_label_values(&[self.queue])
+            .inc();
+    }
+
+    /// `count` events are passed to a handler
+    pub(crate) fn started(&self, count: usize) {
+        #[cfg(feature = "prometheus")]
+        crate::exporter::collectors()
+            .in_flight
+            .with_label_values(&[self.queue])
+            .add(count as i64);
+
+        #[cfg(not(feature = "prometheus"))]
+        let _ = count;
+    }
+
+    pub(crate) fn set_panic_handler(&self, handler: PanicHandler) {
+        *self.on_panic.write().unwrap() = handler;
+    }
+
+    /// Pass the panic of a handler to the panic handler. Returns the panic message.
+    pub(crate) fn panicked(&self, payload: Box<dyn Any + Send>) -> String {
+        let on_panic = *self.on_panic.read().unwrap();
+        let message = crate::runtime::panic_message(payload);
+
+        on_panic(self.queue, &message);
+        message
+    }
+
+    pub(crate) fn handled(&self, duration: Duration, panicked: bool) {
+        self.latency.record(duration);
+        self.handled.fetch_add(1, Ordering::Relaxed);
+
+        if panicked {
+            self.panics.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            metrics::gauge!("hermod_queue_depth", "queue" => self.queue).decrement(1);
+            metrics::histogram!("hermod_queue_handler_duration_seconds", "queue" => self.queue)
+                .record(duration);
+
+            if panicked {
+                metrics::counter!("hermod_queue_panics_total", "queue" => self.queue).increment(1);
+            }
+        }
+
+        #[cfg(feature = "prometheus")]
+        {
+            let collectors = crate::exporter::collectors();
+            let labels = &[self.queue];
+
+            collectors.depth.with_label_values(labels).dec();
+            collectors.in_flight.with_label_values(labels).dec();
+            collectors.handled.with_label_values(labels).inc();
+            collectors
+                .handler_duration
+                .with_label_values(labels)
+                .observe(duration.as_secs_f64());
+
+            if panicked {
+                collectors.panics.with_label_values(labels).inc();
+            }
+        }
+    }
+
+    pub(crate) fn metrics(&self) -> QueueMetrics {
+        let handled = self.handled.load(Ordering::Relaxed);
+
+        QueueMetrics {
+            depth: self.queued.load(Ordering::Relaxed).saturating_sub(handled),
+            handled,
+            panics: self.panics.load(Ordering::Relaxed),
+            latency: self.latency.latency(),
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use crate::{EventInfo, ListenerError};
+use futures::future::BoxFuture;
+use std::ops::ControlFlow;
+
+pub(crate) type FlowFuture = BoxFuture<'static, Result<ControlFlow<()>, ListenerError>>;
+pub(crate) type Middleware = dyn Fn(Next, EventInfo) -> FlowFuture + Send + Sync;
+
+/// # Next
+///
+/// The rest of a dispatch, passed to middleware (see `EventEmitter::layer`): the inner
+/// middleware and eventually the listener itself.
+pub struct Next {
+    pub(crate) future: FlowFuture,
+}
+
+impl Next {
+    /// Continue the dispatch. `ControlFlow::Break` means a cancellable listener stopped
+    /// the propagation, see `EventEmitter::on_cancellable`.
+    pub async fn run(self) -> Result<ControlFlow<()>, ListenerError> {
+        self.future.await
+    }
+}
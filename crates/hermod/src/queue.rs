@@ -1,14 +1,171 @@
-use async_std::{stream::StreamExt, sync::Arc};
+use crate::{
+    metrics::QueueStats, runtime::Instant, CancellationToken, Error, MaybeSend, MaybeSendFuture,
+    MaybeSync, QueueMetrics, Spawn,
+};
 use futures::{
-    channel::mpsc::{self, SendError, UnboundedReceiver as MRecv, UnboundedSender as MSend},
-    future::BoxFuture,
-    SinkExt, StreamExt,
+    channel::{
+        mpsc::{self, TrySendError, UnboundedReceiver as MRecv, UnboundedSender as MSend},
+        oneshot,
+    },
+    future::{self, Either, FutureExt, Shared},
+    stream::FuturesUnordered,
+    SinkExt, Stream, StreamExt,
+};
+use std::{
+    any::type_name,
+    collections::hash_map::DefaultHasher,
+    error::Error as StdError,
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, OnceLock, RwLock,
+    },
 };
 
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+use crate::{schedule::Timer, Recurrence, RetryPolicy, Scheduled};
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+use std::time::Duration;
+
+#[cfg(feature = "durable")]
+use crate::wal::{Durable, Journal, Wal};
+#[cfg(feature = "durable")]
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "durable")]
+use std::{io, path::Path};
+
+/// Called once an event was handled, with whether its handler panicked, e.g. to
+/// acknowledge or retry it in a durable queue's log
+pub(crate) type OnHandled = Box<dyn FnOnce(bool) + Send>;
+
+/// What the queue's task receives
+pub(crate) enum Message<T, R> {
+    Event(T, MSend<R>, Option<OnHandled>),
+    // acknowledged once every message queued before it was handled
+    Drain(oneshot::Sender<()>),
+}
+
+impl<T, R> Message<T, R> {
+    /// The event of a message that could not be queued. The caller gets it back, so a
+    /// durable queue forgets it.
+    fn into_event(self) -> T {
+        match self {
+            Message::Event(event, _, on_handled) => {
+                if let Some(on_handled) = on_handled {
+                    on_handled(false);
+                }
+
+                event
+            }
+            Message::Drain(_) => unreachable!("drains are only sent with send"),
+        }
+    }
+}
+
+/// The sending half of the queue's channel
+enum Channel<M> {
+    Unbounded(MSend<M>),
+    // every send goes through its own clone of `sender`, which futures guarantees a slot,
+    // so callers never wait for each other. `len` counts the queued messages instead.
+    Bounded {
+        sender: mpsc::Sender<M>,
+        len: Arc<AtomicUsize>,
+        capacity: usize,
+    },
+}
+
+impl<M> Channel<M> {
+    /// A bounded channel queueing at most `capacity` messages before senders wait. The
+    /// receiver must be wrapped with `counted`.
+    fn bounded(capacity: usize) -> (Self, mpsc::Receiver<M>, Arc<AtomicUsize>) {
+        let capacity = capacity.max(1);
+        let (sender, receiver) = mpsc::channel(capacity);
+        let len = Arc::new(AtomicUsize::new(0));
+
+        let channel = Channel::Bounded {
+            sender,
+            len: Arc::clone(&len),
+            capacity,
+        };
+
+        (channel, receiver, len)
+    }
+
+    /// Send `msg`, waiting for room in a bounded queue. Gives it back if the queue is
+    /// closed.
+    async fn send(&self, msg: M) -> Result<(), M> {
+        match self {
+            Channel::Unbounded(n) => n.unbounded_send(msg).map_err(TrySendError::into_inner),
+            Channel::Bounded { sender, len, .. } => {
+                let mut sender = sender.clone();
+                len.fetch_add(1, Ordering::SeqCst);
+
+                if let Err(e) = sender.try_send(msg) {
+                    len.fetch_sub(1, Ordering::SeqCst);
+                    return Err(e.into_inner());
+                }
+
+                // the message is queued, but its sender stays parked until there is room
+                let _ = future::poll_fn(|cx| sender.poll_ready(cx)).await;
+                Ok(())
+            }
+        }
+    }
+
+    async fn close(&self) {
+        match self {
+            Channel::Unbounded(n) => n.close_channel(),
+            Channel::Bounded { sender, .. } => sender.clone().close_channel(),
+        }
+    }
+
+    fn try_send(&self, msg: M) -> Result<(), Error<M>> {
+        let result = match self {
+            Channel::Unbounded(n) => n.unbounded_send(msg),
+            Channel::Bounded {
+                sender,
+                len,
+                capacity,
+            } => {
+                let room = |n: usize| (n < *capacity).then_some(n + 1);
+
+                if len
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, room)
+                    .is_err()
+                {
+                    return Err(Error::Full(msg));
+                }
+
+                let result = sender.clone().try_send(msg);
+
+                if result.is_err() {
+                    len.fetch_sub(1, Ordering::SeqCst);
+                }
+
+                result
+            }
+        };
+
+        result.map_err(|e| match e.is_full() {
+            true => Error::Full(e.into_inner()),
+            false => Error::Closed(e.into_inner()),
+        })
+    }
+}
+
+/// Counts the messages taken from a bounded channel, see `Channel::bounded`
+fn counted<M>(receiver: mpsc::Receiver<M>, len: Arc<AtomicUsize>) -> impl Stream<Item = M> + Unpin {
+    receiver.inspect(move |_| {
+        len.fetch_sub(1, Ordering::SeqCst);
+    })
+}
+
 /// # Sender
 ///
 /// A queue that can be used from anywhere. Wrapper for
-/// `futures::channel::mpsc::UnboundedSender` and `UnboundedReceiver`. Calling `.emit()` returns
+/// `futures::channel::mpsc`, unbounded unless created with `bounded`. Calling `.emit()` returns
 /// an `UnboundedReciever` when `Ok`. It will recieve one event, and then close (unless sending
 /// fails).
 ///
@@ -40,49 +197,1892 @@ use futures::{
 /// ```
 pub struct Sender<T, R>
 where
-    T: Send + Sync + 'static,
-    R: Send + Sync + 'static,
+    T: MaybeSend + MaybeSync + 'static,
+    R: MaybeSend + MaybeSync + 'static,
+{
+    sender: Channel<Message<T, R>>,
+    // resolves once the task has stopped
+    finished: Shared<oneshot::Receiver<()>>,
+    stats: Arc<QueueStats>,
+    // passed to the handlers of a `with_cancellation` queue
+    token: CancellationToken,
+    recovery: Arc<Recovery<T>>,
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    timer: Timer,
+    // where a durable queue persists its events
+    #[cfg(feature = "durable")]
+    journal: Option<Arc<dyn Journal<T>>>,
+}
+
+/// # ResponseSink
+///
+/// Passed to the handlers of a `Sender::streaming` queue, to send any number of
+/// responses. The caller's stream ends once the sink is finished or dropped.
+pub struct ResponseSink<R> {
+    sender: MSend<R>,
+}
+
+impl<R> ResponseSink<R> {
+    /// Send a (partial) response. Returns `false` if the caller stopped listening, in
+    /// which case the handler can stop early.
+    pub fn send(&self, response: R) -> bool {
+        self.sender.unbounded_send(response).is_ok()
+    }
+
+    /// Whether the caller stopped listening
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    /// End the caller's stream. Equivalent to dropping the sink.
+    pub fn finish(self) {
+        self.sender.close_channel();
+    }
+}
+
+/// Called with the name of a queue and the message of a panic of its handler, see
+/// `Sender::set_panic_handler`
+pub type PanicHandler = fn(&str, &str);
+
+pub(crate) fn log_panic(queue: &str, message: &str) {
+    log::error!("Handler of {queue} panicked: {message}");
+}
+
+/// The error returned by the handlers of a `Sender::fallible` queue. Any error can be
+/// returned with `?`.
+pub type HandlerError = Box<dyn StdError + Send + Sync>;
+
+/// # Failure
+///
+/// Why a handler failed to handle an event, passed to the dead-letter sink (see
+/// `Sender::set_dead_letter_sink`).
+#[derive(Debug)]
+pub enum Failure {
+    /// The handler panicked, with the panic message
+    Panicked(String),
+    /// The handler of a `Sender::fallible` queue returned an error
+    Error(HandlerError),
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Panicked(message) => f.write_str(message),
+            Failure::Error(e) => e.fmt(f),
+        }
+    }
+}
+
+type DeadLetterSink<T> = Arc<dyn Fn(T, &Failure) + Send + Sync>;
+
+/// How a queue recovers from failing handlers, see `Sender::set_retry_policy` and
+/// `Sender::set_dead_letter_sink`. Shared by a queue and its task.
+pub(crate) struct Recovery<T> {
+    // set along with the policy or the sink, which require `T: Clone`
+    clone: OnceLock<fn(&T) -> T>,
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    retries: RwLock<Option<RetryPolicy>>,
+    dead_letters: RwLock<Option<DeadLetterSink<T>>>,
+}
+
+impl<T> Recovery<T> {
+    fn new() -> Self {
+        Self {
+            clone: OnceLock::new(),
+            #[cfg(any(feature = "async-std", feature = "tokio"))]
+            retries: RwLock::new(None),
+            dead_letters: RwLock::new(None),
+        }
+    }
+
+    /// Handle `event`, retrying it and passing it to the dead-letter sink while the
+    /// handler fails. Returns whether the last attempt panicked.
+    async fn handle<R, D>(
+        &self,
+        handler: Handler<T, R, D>,
+        event: T,
+        data: &mut D,
+        sender: MSend<R>,
+        token: CancellationToken,
+        stats: &QueueStats,
+    ) -> bool {
+        // nothing to recover, so the event is not cloned
+        let Some(clone) = self.clone.get() else {
+            let result = attempt(handler, event, data, sender, token, stats).await;
+            return matches!(result, Err(Failure::Panicked(_)));
+        };
+
+        // only retries need `mut`
+        #[cfg_attr(not(any(feature = "async-std", feature = "tokio")), allow(unused_mut))]
+        let mut result = attempt(
+            handler.clone(),
+            clone(&event),
+            data,
+            sender.clone(),
+            token.clone(),
+            stats,
+        )
+        .await;
+
+        #[cfg(any(feature = "async-std", feature = "tokio"))]
+        let policy = *self.retries.read().unwrap();
+
+        #[cfg(any(feature = "async-std", feature = "tokio"))]
+        if let Some(policy) = policy {
+            for tries in 1..policy.attempts() {
+                if result.is_ok() {
+                    break;
+                }
+
+                crate::runtime::sleep(policy.delay(tries)).await;
+                result = attempt(
+                    handler.clone(),
+                    clone(&event),
+                    data,
+                    sender.clone(),
+                    token.clone(),
+                    stats,
+                )
+                .await;
+            }
+        }
+
+        let Err(failure) = result else {
+            return false;
+        };
+
+        let sink = self.dead_letters.read().unwrap().clone();
+
+        if let Some(sink) = sink {
+            sink(event, &failure);
+        }
+
+        matches!(failure, Failure::Panicked(_))
+    }
+}
+
+/// Handle `event` once, catching a panic of the handler. Panics are passed to the
+/// panic handler, errors are logged.
+async fn attempt<T, R, D>(
+    handler: Handler<T, R, D>,
+    event: T,
+    data: &mut D,
+    sender: MSend<R>,
+    token: CancellationToken,
+    stats: &QueueStats,
+) -> Result<(), Failure> {
+    let handled = handler.handle(event, data, sender, token);
+
+    #[cfg(feature = "tracing")]
+    let handled = tracing::Instrument::instrument(
+        handled,
+        tracing::debug_span!("handle", event = type_name::<T>()),
+    );
+
+    match AssertUnwindSafe(handled).catch_unwind().await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => {
+            log::error!("Handler of {} failed: {e}", stats.name());
+            Err(Failure::Error(e))
+        }
+        Err(payload) => Err(Failure::Panicked(stats.panicked(payload))),
+    }
+}
+
+/// The handler of a `Sender::new` queue
+pub(crate) trait SingleFn<T, R, D>:
+    for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R> + MaybeSend + MaybeSync
+{
+}
+
+impl<F, T, R, D> SingleFn<T, R, D> for F where
+    F: for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R> + MaybeSend + MaybeSync
+{
+}
+
+/// The handler of a `Sender::fallible` queue
+trait FallibleFn<T, R, D>:
+    for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, Result<R, HandlerError>> + MaybeSend + MaybeSync
+{
+}
+
+impl<F, T, R, D> FallibleFn<T, R, D> for F where
+    F: for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, Result<R, HandlerError>>
+        + MaybeSend
+        + MaybeSync
 {
-    sender: MSend<(T, MSend<R>)>,
+}
+
+/// The handler of a `Sender::streaming` queue
+trait StreamingFn<T, R, D>:
+    for<'a> Fn(T, &'a mut D, ResponseSink<R>) -> MaybeSendFuture<'a, ()> + MaybeSend + MaybeSync
+{
+}
+
+impl<F, T, R, D> StreamingFn<T, R, D> for F where
+    F: for<'a> Fn(T, &'a mut D, ResponseSink<R>) -> MaybeSendFuture<'a, ()> + MaybeSend + MaybeSync
+{
+}
+
+/// The handler of a `Sender::with_cancellation` queue
+trait CancellableFn<T, R, D>:
+    for<'a> Fn(T, &'a mut D, CancellationToken) -> MaybeSendFuture<'a, R> + MaybeSend + MaybeSync
+{
+}
+
+impl<F, T, R, D> CancellableFn<T, R, D> for F where
+    F: for<'a> Fn(T, &'a mut D, CancellationToken) -> MaybeSendFuture<'a, R>
+        + MaybeSend
+        + MaybeSync
+{
+}
+
+/// How the queue's task turns an event into responses
+enum Handler<T, R, D> {
+    Single(Arc<dyn SingleFn<T, R, D>>),
+    Fallible(Arc<dyn FallibleFn<T, R, D>>),
+    Streaming(Arc<dyn StreamingFn<T, R, D>>),
+    Cancellable(Arc<dyn CancellableFn<T, R, D>>),
+}
+
+impl<T, R, D> Clone for Handler<T, R, D> {
+    fn clone(&self) -> Self {
+        match self {
+            Handler::Single(n) => Handler::Single(Arc::clone(n)),
+            Handler::Fallible(n) => Handler::Fallible(Arc::clone(n)),
+            Handler::Streaming(n) => Handler::Streaming(Arc::clone(n)),
+            Handler::Cancellable(n) => Handler::Cancellable(Arc::clone(n)),
+        }
+    }
+}
+
+impl<T, R, D> Handler<T, R, D> {
+    /// Handle `event`, sending the response unless the handler returned an error
+    async fn handle(
+        self,
+        event: T,
+        data: &mut D,
+        mut sender: MSend<R>,
+        token: CancellationToken,
+    ) -> Result<(), HandlerError> {
+        let res = match self {
+            Handler::Single(listener) => listener(event, data).await,
+            Handler::Fallible(listener) => listener(event, data).await?,
+            Handler::Streaming(listener) => {
+                listener(event, data, ResponseSink { sender }).await;
+                return Ok(());
+            }
+            Handler::Cancellable(listener) => listener(event, data, token).await,
+        };
+
+        // a disconnected response channel means nobody waits for the response
+        if let Err(e) = sender.send(res).await {
+            if !e.is_disconnected() {
+                eprintln!("Error sending response: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Handle the messages from `receiver`, running up to one handler per element of `idle`
+async fn run<T, R, D>(
+    mut receiver: impl Stream<Item = Message<T, R>> + Unpin,
+    handler: Handler<T, R, D>,
+    mut idle: Vec<D>,
+    stats: Arc<QueueStats>,
+    token: CancellationToken,
+    recovery: Arc<Recovery<T>>,
+) where
+    T: MaybeSend + MaybeSync + 'static,
+    R: MaybeSend + 'static,
+    D: MaybeSend + 'static,
+{
+    let mut running = FuturesUnordered::<MaybeSendFuture<'static, D>>::new();
+
+    loop {
+        let message = if running.is_empty() {
+            receiver.next().await
+        } else if idle.is_empty() {
+            idle.extend(running.next().await);
+            continue;
+        } else {
+            match future::select(receiver.next(), running.next()).await {
+                Either::Left((message, _)) => message,
+                Either::Right((data, _)) => {
+                    idle.extend(data);
+                    continue;
+                }
+            }
+        };
+
+        match message {
+            Some(Message::Event(event, sender, on_handled)) => {
+                let mut data = idle.pop().unwrap();
+                let stats = Arc::clone(&stats);
+                let token = token.clone();
+                let handler = handler.clone();
+                let recovery = Arc::clone(&recovery);
+
+                running.push(Box::pin(async move {
+                    let start = Instant::now();
+                    stats.started(1);
+
+                    // a panicking handler only loses its event, the queue keeps going. The
+                    // worker keeps its data, see `Sender::set_panic_handler`.
+                    let panicked = recovery
+                        .handle(handler, event, &mut data, sender, token, &stats)
+                        .await;
+
+                    // before counting the event, so a retry is queued by then
+                    if let Some(on_handled) = on_handled {
+                        on_handled(panicked);
+                    }
+
+                    stats.handled(start.elapsed(), panicked);
+                    data
+                }));
+            }
+            Some(Message::Drain(ack)) => {
+                idle.extend(running.by_ref().collect::<Vec<_>>().await);
+                let _ = ack.send(());
+            }
+            None => break,
+        }
+    }
+
+    // the queue is closed, finish the events that are still being handled
+    while running.next().await.is_some() {}
+}
+
+/// Route the messages from `receiver` to one worker per element of `data`, by the hash
+/// of their key. Each worker handles its events in order.
+async fn run_partitioned<T, R, D>(
+    mut receiver: impl Stream<Item = Message<T, R>> + Unpin,
+    handler: Handler<T, R, D>,
+    hash: Box<dyn Fn(&T) -> u64 + Send>,
+    data: Vec<D>,
+    stats: Arc<QueueStats>,
+    token: CancellationToken,
+    recovery: Arc<Recovery<T>>,
+) where
+    T: MaybeSend + MaybeSync + 'static,
+    R: MaybeSend + 'static,
+    D: MaybeSend + 'static,
+{
+    let (partitions, workers): (Vec<_>, Vec<_>) = data
+        .into_iter()
+        .map(|data| {
+            let (sender, receiver) = mpsc::unbounded();
+            let worker = run(
+                receiver,
+                handler.clone(),
+                vec![data],
+                Arc::clone(&stats),
+                token.clone(),
+                Arc::clone(&recovery),
+            );
+
+            (sender, worker)
+        })
+        .unzip();
+
+    let route = async move {
+        while let Some(message) = receiver.next().await {
+            match message {
+                Message::Event(event, sender, on_handled) => {
+                    let partition = &partitions[hash(&event) as usize % partitions.len()];
+                    let _ = partition.unbounded_send(Message::Event(event, sender, on_handled));
+                }
+                Message::Drain(ack) => {
+                    let acked = partitions.iter().map(|partition| {
+                        let (ack, acked) = oneshot::channel();
+                        let _ = partition.unbounded_send(Message::Drain(ack));
+                        acked
+                    });
+
+                    future::join_all(acked.collect::<Vec<_>>()).await;
+                    let _ = ack.send(());
+                }
+            }
+        }
+
+        // dropping the partitions stops the workers once they are empty
+    };
+
+    future::join(route, future::join_all(workers)).await;
+}
+
+/// A batch handler's settings, see `Sender::batched`
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+struct Batching<T, R, D> {
+    listener: Box<dyn BatchFn<T, R, D>>,
+    max_size: usize,
+    max_latency: Duration,
+}
+
+/// The handler of a `Sender::batched` queue
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+trait BatchFn<T, R, D>:
+    for<'a> Fn(Vec<T>, &'a mut D) -> MaybeSendFuture<'a, R> + MaybeSend + MaybeSync
+{
+}
+
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+impl<F, T, R, D> BatchFn<T, R, D> for F where
+    F: for<'a> Fn(Vec<T>, &'a mut D) -> MaybeSendFuture<'a, R> + MaybeSend + MaybeSync
+{
+}
+
+/// Handle the events from `receiver` in batches, with a single worker
+#[cfg(any(feature = "async-std", feature = "tokio"))]
+async fn run_batched<T, R, D>(
+    mut receiver: impl Stream<Item = Message<T, R>> + Unpin,
+    batching: Batching<T, R, D>,
+    mut data: D,
+    stats: Arc<QueueStats>,
+) where
+    T: MaybeSend + 'static,
+    R: Clone + MaybeSend + 'static,
+    D: MaybeSend + 'static,
+{
+    let mut open = true;
+
+    while open {
+        let mut batch = vec![];
+        let mut drained = vec![];
+
+        // the first event starts the batch, and its latency
+        match receiver.next().await {
+            Some(Message::Event(event, sender, on_handled)) => {
+                batch.push((event, sender, on_handled))
+            }
+            Some(Message::Drain(ack)) => {
+                let _ = ack.send(());
+                continue;
+            }
+            None => break,
+        }
+
+        let deadline = Instant::now() + batching.max_latency;
+
+        while batch.len() < batching.max_size {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let sleep = Box::pin(crate::runtime::sleep(timeout));
+
+            match future::select(receiver.next(), sleep).await {
+                Either::Left((Some(Message::Event(event, sender, on_handled)), _)) => {
+                    batch.push((event, sender, on_handled))
+                }
+                // draining flushes the batch
+                Either::Left((Some(Message::Drain(ack)), _)) => {
+                    drained.push(ack);
+                    break;
+                }
+                Either::Left((None, _)) => {
+                    open = false;
+                    break;
+                }
+                Either::Right(_) => break,
+            }
+        }
+
+        let start = Instant::now();
+        stats.started(batch.len());
+
+        let (events, rest): (Vec<_>, Vec<_>) = batch
+            .into_iter()
+            .map(|(n, sender, on_handled)| (n, (sender, on_handled)))
+            .unzip();
+
+        let handled = async { (batching.listener)(events, &mut data).await };
+
+        #[cfg(feature = "tracing")]
+        let handled = tracing::Instrument::instrument(
+            handled,
+            tracing::debug_span!("handle_batch", event = type_name::<T>()),
+        );
+
+        let handled = AssertUnwindSafe(handled);
+
+        match handled.catch_unwind().await {
+            Ok(response) => {
+                // every event of the batch gets its result
+                for (sender, on_handled) in rest {
+                    let _ = sender.unbounded_send(response.clone());
+
+                    if let Some(on_handled) = on_handled {
+                        on_handled(false);
+                    }
+
+                    stats.handled(start.elapsed(), false);
+                }
+            }
+            Err(e) => {
+                stats.panicked(e);
+
+                // the events are all handled, by a single panicking call
+                for (i, (_, on_handled)) in rest.into_iter().enumerate() {
+                    if let Some(on_handled) = on_handled {
+                        on_handled(true);
+                    }
+
+                    stats.handled(start.elapsed(), i == 0);
+                }
+            }
+        }
+
+        for ack in drained {
+            let _ = ack.send(());
+        }
+    }
 }
 
 impl<T, R> Sender<T, R>
 where
-    T: Send + Sync + 'static,
-    R: Send + Sync + 'static,
+    T: MaybeSend + MaybeSync + 'static,
+    R: MaybeSend + MaybeSync + 'static,
 {
-    pub fn new<D: Send + Sync + 'static>(
-        listener: for<'a> fn(T, &'a mut D) -> BoxFuture<'a, R>,
+    /// Spawn the task running `listener`, see the crate docs for the runtimes. With
+    /// Tokio, this has to be called from within a runtime.
+    #[cfg(any(
+        feature = "async-std",
+        feature = "tokio",
+        all(feature = "wasm", target_arch = "wasm32")
+    ))]
+    pub fn new<D: MaybeSend + MaybeSync + 'static>(
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> Self {
+        Self::new_on(crate::runtime::spawn, listener, data)
+    }
+
+    /// Like `new`, but the task is spawned with `spawner` instead of the bundled runtime.
+    pub fn new_on<D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
         data: D,
     ) -> Self {
-        let (sender, mut receiver) = mpsc::unbounded::<(T, MSend<R>)>();
+        Self::build(spawner, type_name::<T>(), None, listener, vec![data])
+    }
 
-        async_std::task::spawn(async move {
-            let mut data = data;
+    /// Like `new`, but the handler returns a `Result`. Errors are retried like panics
+    /// (see `set_retry_policy`) and passed to the dead-letter sink, the caller only
+    /// receives a response once the handler succeeds.
+    ///
+    /// ```
+    /// use futures::StreamExt;
+    /// use hermod::{RetryPolicy, Sender};
+    /// use std::sync::Arc;
+    ///
+    /// let queue = Arc::new(Sender::<String, u32>::fallible(|text, _| Box::pin(async move {
+    ///     Ok(text.parse::<u32>()?)
+    /// }), ()));
+    ///
+    /// queue.set_retry_policy(RetryPolicy::new(3));
+    ///
+    /// async_std::task::block_on(async {
+    ///     let mut parsed = Arc::clone(&queue).emit("42").await.unwrap();
+    ///     assert_eq!(parsed.next().await, Some(42));
+    ///
+    ///     // tried 3 times, without a response
+    ///     let mut failed = queue.emit("forty-two").await.unwrap();
+    ///     assert_eq!(failed.next().await, None);
+    /// });
+    /// ```
+    #[cfg(any(
+        feature = "async-std",
+        feature = "tokio",
+        all(feature = "wasm", target_arch = "wasm32")
+    ))]
+    pub fn fallible<D: MaybeSend + MaybeSync + 'static>(
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, Result<R, HandlerError>>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> Self {
+        Self::fallible_on(crate::runtime::spawn, listener, data)
+    }
 
-            while let Some((event, mut sender)) = receiver.next().await {
-                let res = listener(event, &mut data).await;
+    /// Like `fallible`, but the task is spawned with `spawner`.
+    pub fn fallible_on<D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, Result<R, HandlerError>>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        let sender = Channel::Unbounded(sender);
+        let handler = Handler::Fallible(Arc::new(listener));
 
-                if let Err(e) = sender.send(res).await {
-                    eprintln!("Error sending response: {:?}", e);
-                }
+        Self::spawn(spawner, sender, receiver, handler, vec![data])
+    }
+
+    /// Like `new`, but the handler can send any number of responses through a
+    /// `ResponseSink`, e.g. to report progress. The caller receives them as a stream,
+    /// which ends when the sink is finished or dropped.
+    ///
+    /// ```
+    /// use futures::StreamExt;
+    /// use hermod::Sender;
+    /// use std::sync::Arc;
+    ///
+    /// let queue = Arc::new(Sender::<u32, u32>::streaming(|count, _, sink| Box::pin(async move {
+    ///     for n in 1..=count {
+    ///         sink.send(n);
+    ///     }
+    ///
+    ///     sink.finish();
+    /// }), ()));
+    ///
+    /// async_std::task::block_on(async {
+    ///     let responses = queue.emit(3u32).await.unwrap();
+    ///     assert_eq!(responses.collect::<Vec<_>>().await, [1, 2, 3]);
+    /// });
+    /// ```
+    #[cfg(any(
+        feature = "async-std",
+        feature = "tokio",
+        all(feature = "wasm", target_arch = "wasm32")
+    ))]
+    pub fn streaming<D: MaybeSend + MaybeSync + 'static>(
+        listener: impl for<'a> Fn(T, &'a mut D, ResponseSink<R>) -> MaybeSendFuture<'a, ()>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> Self {
+        Self::streaming_on(crate::runtime::spawn, listener, data)
+    }
+
+    /// Like `streaming`, but the task is spawned with `spawner`.
+    pub fn streaming_on<D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        listener: impl for<'a> Fn(T, &'a mut D, ResponseSink<R>) -> MaybeSendFuture<'a, ()>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        let sender = Channel::Unbounded(sender);
+        let handler = Handler::Streaming(Arc::new(listener));
+
+        Self::spawn(spawner, sender, receiver, handler, vec![data])
+    }
+
+    /// Like `new`, but the handler also receives a `CancellationToken`, cancelled by
+    /// `shutdown`. Long-running handlers can watch it to stop early, instead of making
+    /// `shutdown` wait for them.
+    ///
+    /// ```
+    /// use futures::future::{self, Either};
+    /// use hermod::Sender;
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// let queue = Arc::new(Sender::<(), bool>::with_cancellation(|_, _, token| Box::pin(async move {
+    ///     let work = Box::pin(async_std::task::sleep(Duration::from_secs(60)));
+    ///
+    ///     // whether the work was finished
+    ///     matches!(future::select(work, token.cancelled()).await, Either::Left(_))
+    /// }), ()));
+    ///
+    /// async_std::task::block_on(async {
+    ///     let mut response = Arc::clone(&queue).emit(()).await.unwrap();
+    ///
+    ///     queue.shutdown().await;
+    ///     assert_eq!(response.try_next().unwrap(), Some(false));
+    /// });
+    /// ```
+    #[cfg(any(
+        feature = "async-std",
+        feature = "tokio",
+        all(feature = "wasm", target_arch = "wasm32")
+    ))]
+    pub fn with_cancellation<D: MaybeSend + MaybeSync + 'static>(
+        listener: impl for<'a> Fn(T, &'a mut D, CancellationToken) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> Self {
+        Self::with_cancellation_on(crate::runtime::spawn, listener, data)
+    }
+
+    /// Like `with_cancellation`, but the task is spawned with `spawner`.
+    pub fn with_cancellation_on<D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        listener: impl for<'a> Fn(T, &'a mut D, CancellationToken) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        let sender = Channel::Unbounded(sender);
+        let handler = Handler::Cancellable(Arc::new(listener));
+
+        Self::spawn(spawner, sender, receiver, handler, vec![data])
+    }
+
+    /// Like `new`, but up to `workers` (at least 1) events are handled concurrently. Each
+    /// worker has its own data, created with `data`. Responses are sent in the order the
+    /// handlers finish, but each event still gets its own response.
+    ///
+    /// ```
+    /// use async_std::stream::StreamExt;
+    /// use hermod::Sender;
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// let queue = Arc::new(Sender::<u64, u64>::with_workers(4, |ms, _| Box::pin(async move {
+    ///     async_std::task::sleep(Duration::from_millis(ms)).await;
+    ///     ms
+    /// }), || ()));
+    ///
+    /// async_std::task::block_on(async {
+    ///     let mut slow = Arc::clone(&queue).emit(100u64).await.unwrap();
+    ///     let mut fast = Arc::clone(&queue).emit(1u64).await.unwrap();
+    ///
+    ///     // the fast event does not wait for the slow one
+    ///     assert_eq!(fast.next().await, Some(1));
+    ///     assert_eq!(slow.next().await, Some(100));
+    /// });
+    /// ```
+    #[cfg(any(
+        feature = "async-std",
+        feature = "tokio",
+        all(feature = "wasm", target_arch = "wasm32")
+    ))]
+    pub fn with_workers<D: MaybeSend + MaybeSync + 'static>(
+        workers: usize,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: impl FnMut() -> D,
+    ) -> Self {
+        Self::with_workers_on(crate::runtime::spawn, workers, listener, data)
+    }
+
+    /// Like `with_workers`, but the task is spawned with `spawner`.
+    pub fn with_workers_on<D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        workers: usize,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        mut data: impl FnMut() -> D,
+    ) -> Self {
+        let data = (0..workers.max(1)).map(|_| data()).collect();
+
+        Self::build(spawner, type_name::<T>(), None, listener, data)
+    }
+
+    /// Like `with_workers`, but events with the same key are handled in order, by the
+    /// same worker. Events with different keys are spread over the `partitions` (at
+    /// least 1) workers by their hash, and handled concurrently.
+    ///
+    /// ```
+    /// use hermod::Sender;
+    /// use std::sync::Mutex;
+    ///
+    /// static HANDLED: Mutex<Vec<(&str, u32)>> = Mutex::new(vec![]);
+    ///
+    /// // (account, sequence number)
+    /// let queue = Sender::<(&'static str, u32), ()>::partitioned(
+    ///     4,
+    ///     |(account, _)| *account,
+    ///     |event, _| Box::pin(async move {
+    ///         HANDLED.lock().unwrap().push(event);
+    ///     }),
+    ///     || (),
+    /// );
+    ///
+    /// async_std::task::block_on(async {
+    ///     for seq in 0..10 {
+    ///         queue.try_emit(("alice", seq)).unwrap();
+    ///         queue.try_emit(("bob", seq)).unwrap();
+    ///     }
+    ///
+    ///     queue.drain().await;
+    /// });
+    ///
+    /// let handled = HANDLED.lock().unwrap();
+    ///
+    /// for account in ["alice", "bob"] {
+    ///     let seqs = handled.iter().filter(|n| n.0 == account).map(|n| n.1);
+    ///     assert!(seqs.eq(0..10));
+    /// }
+    /// ```
+    #[cfg(any(
+        feature = "async-std",
+        feature = "tokio",
+        all(feature = "wasm", target_arch = "wasm32")
+    ))]
+    pub fn partitioned<K: Hash, D: MaybeSend + MaybeSync + 'static>(
+        partitions: usize,
+        key: impl Fn(&T) -> K + Send + 'static,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: impl FnMut() -> D,
+    ) -> Self {
+        Self::partitioned_on(crate::runtime::spawn, partitions, key, listener, data)
+    }
+
+    /// Like `partitioned`, but the task is spawned with `spawner`.
+    pub fn partitioned_on<K: Hash, D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        partitions: usize,
+        key: impl Fn(&T) -> K + Send + 'static,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        mut data: impl FnMut() -> D,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        let handler = Handler::Single(Arc::new(listener));
+        let data = (0..partitions.max(1)).map(|_| data()).collect();
+
+        let hash = Box::new(move |event: &T| {
+            let mut hasher = DefaultHasher::new();
+            key(event).hash(&mut hasher);
+            hasher.finish()
+        });
+
+        let sender = Channel::Unbounded(sender);
+
+        Self::spawn_task(
+            spawner,
+            type_name::<T>(),
+            sender,
+            move |stats, token, recovery| {
+                Box::pin(run_partitioned(
+                    receiver, handler, hash, data, stats, token, recovery,
+                ))
+            },
+        )
+    }
+
+    /// Like `new`, but at most `capacity` (at least 1) events are queued. When the queue
+    /// is full, `emit` waits for room, and `try_emit` fails.
+    ///
+    /// ```
+    /// use hermod::{Error, Sender};
+    /// use std::sync::Arc;
+    ///
+    /// let queue = Arc::new(Sender::<u32, ()>::bounded(1, |_, _| Box::pin(async {
+    ///     std::future::pending::<()>().await
+    /// }), ()));
+    ///
+    /// // the task is busy with the first event and the second fills the queue
+    /// let _ = queue.try_emit(1u32);
+    /// let _ = queue.try_emit(2u32);
+    ///
+    /// assert!(matches!(queue.try_emit(3u32), Err(Error::Full(3))));
+    /// ```
+    #[cfg(any(
+        feature = "async-std",
+        feature = "tokio",
+        all(feature = "wasm", target_arch = "wasm32")
+    ))]
+    pub fn bounded<D: MaybeSend + MaybeSync + 'static>(
+        capacity: usize,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> Self {
+        Self::bounded_on(crate::runtime::spawn, capacity, listener, data)
+    }
+
+    /// Like `bounded`, but the task is spawned with `spawner`.
+    pub fn bounded_on<D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        capacity: usize,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> Self {
+        Self::build(
+            spawner,
+            type_name::<T>(),
+            Some(capacity),
+            listener,
+            vec![data],
+        )
+    }
+
+    /// Like `new`, but every event is appended to a write-ahead log at `path` before it
+    /// is queued, and acknowledged once handled. Events that were not handled because
+    /// of a crash are queued again the next time the queue is opened, so they are
+    /// handled at least once. An event whose handler panics is queued again right away.
+    ///
+    /// Events are stored as JSON. An event that cannot be logged is still queued, but
+    /// only in memory. Events that cannot be read back, e.g. after their type changed,
+    /// or whose handler panicked 3 times, are moved to `<path>.dead` and logged.
+    ///
+    /// ```
+    /// use hermod::Sender;
+    ///
+    /// let path = std::env::temp_dir().join("hermod-durable-doctest.log");
+    /// # let _ = std::fs::remove_file(&path);
+    ///
+    /// // a previous run queued an event, but crashed before handling it
+    /// let queue = Sender::<String, ()>::durable(&path, |_, _| Box::pin(async {
+    ///     std::future::pending::<()>().await;
+    /// }), ()).unwrap();
+    ///
+    /// queue.try_emit("send invoice #12").unwrap();
+    /// drop(queue);
+    ///
+    /// let queue = Sender::<String, ()>::durable(&path, |event, _| Box::pin(async move {
+    ///     assert_eq!(event, "send invoice #12");
+    /// }), ()).unwrap();
+    ///
+    /// async_std::task::block_on(queue.drain());
+    /// assert_eq!(queue.metrics().handled, 1);
+    /// ```
+    #[cfg(all(feature = "durable", any(feature = "async-std", feature = "tokio")))]
+    pub fn durable<D: MaybeSend + MaybeSync + 'static>(
+        path: impl AsRef<Path>,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> io::Result<Self>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        Self::durable_on(crate::runtime::spawn, path, listener, data)
+    }
+
+    /// Like `durable`, but the task is spawned with `spawner`.
+    #[cfg(feature = "durable")]
+    pub fn durable_on<D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        path: impl AsRef<Path>,
+        listener: impl for<'a> Fn(T, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> io::Result<Self>
+    where
+        T: Serialize + DeserializeOwned + Send,
+        R: Send,
+    {
+        let (wal, pending) = Wal::open(path.as_ref())?;
+        let (sender, receiver) = mpsc::unbounded();
+
+        let mut queue = Self::spawn(
+            spawner,
+            Channel::Unbounded(sender.clone()),
+            receiver,
+            Handler::Single(Arc::new(listener)),
+            vec![data],
+        );
+
+        let durable = Durable::new(wal, sender, Arc::clone(&queue.stats));
+
+        // queue what a previous run left before anything new
+        for (seq, event) in pending {
+            durable.requeue(seq, event, 1);
+        }
+
+        queue.journal = Some(durable);
+        Ok(queue)
+    }
+
+    /// Like `new`, but events are handled in batches of up to `max_size` events (at
+    /// least 1). A batch is handled once it is full, or `max_latency` after its first
+    /// event was received. Every event of a batch receives a clone of the response.
+    ///
+    /// ```
+    /// use futures::StreamExt;
+    /// use hermod::Sender;
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// // insert rows in bulk, responding with the size of the insert
+    /// let queue = Arc::new(Sender::<String, usize>::batched(
+    ///     100,
+    ///     Duration::from_millis(10),
+    ///     |rows, inserted| Box::pin(async move {
+    ///         *inserted += rows.len();
+    ///         rows.len()
+    ///     }),
+    ///     0usize,
+    /// ));
+    ///
+    /// async_std::task::block_on(async {
+    ///     let mut first = Arc::clone(&queue).emit("alice").await.unwrap();
+    ///     let mut second = Arc::clone(&queue).emit("bob").await.unwrap();
+    ///
+    ///     assert_eq!(first.next().await, Some(2));
+    ///     assert_eq!(second.next().await, Some(2));
+    /// });
+    /// ```
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn batched<D: MaybeSend + MaybeSync + 'static>(
+        max_size: usize,
+        max_latency: Duration,
+        listener: impl for<'a> Fn(Vec<T>, &'a mut D) -> MaybeSendFuture<'a, R>
+            + MaybeSend
+            + MaybeSync
+            + 'static,
+        data: D,
+    ) -> Self
+    where
+        R: Clone,
+    {
+        let (sender, receiver) = mpsc::unbounded();
+
+        let batching = Batching {
+            listener: Box::new(listener),
+            max_size: max_size.max(1),
+            max_latency,
+        };
+
+        Self::spawn_task(
+            crate::runtime::spawn,
+            type_name::<T>(),
+            Channel::Unbounded(sender),
+            move |stats, _, _| Box::pin(run_batched(receiver, batching, data, stats)),
+        )
+    }
+
+    /// Spawn the task, with one worker per element of `data`
+    fn spawn<D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        sender: Channel<Message<T, R>>,
+        receiver: impl Stream<Item = Message<T, R>> + MaybeSend + Unpin + 'static,
+        handler: Handler<T, R, D>,
+        data: Vec<D>,
+    ) -> Self {
+        Self::spawn_named(spawner, type_name::<T>(), sender, receiver, handler, data)
+    }
+
+    /// Like `spawn`, with `name` instead of the type name of the events in the metrics
+    fn spawn_named<D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        name: &'static str,
+        sender: Channel<Message<T, R>>,
+        receiver: impl Stream<Item = Message<T, R>> + MaybeSend + Unpin + 'static,
+        handler: Handler<T, R, D>,
+        data: Vec<D>,
+    ) -> Self {
+        Self::spawn_task(spawner, name, sender, move |stats, token, recovery| {
+            Box::pin(run(receiver, handler, data, stats, token, recovery))
+        })
+    }
+
+    /// Spawn a queue handling its events with `listener`, with one worker per element of
+    /// `data`, and at most `capacity` queued events if any, see `SenderBuilder`
+    pub(crate) fn build<D: MaybeSend + MaybeSync + 'static>(
+        spawner: impl Spawn,
+        name: &'static str,
+        capacity: Option<usize>,
+        listener: impl SingleFn<T, R, D> + 'static,
+        data: Vec<D>,
+    ) -> Self {
+        let handler = Handler::Single(Arc::new(listener));
+
+        match capacity {
+            Some(capacity) => {
+                let (sender, receiver, len) = Channel::bounded(capacity);
+                let receiver = counted(receiver, len);
+
+                Self::spawn_named(spawner, name, sender, receiver, handler, data)
+            }
+            None => {
+                let (sender, receiver) = mpsc::unbounded();
+                let sender = Channel::Unbounded(sender);
+
+                Self::spawn_named(spawner, name, sender, receiver, handler, data)
+            }
+        }
+    }
+
+    /// Spawn the task returned by `task`, which receives from the other end of `sender`
+    fn spawn_task(
+        spawner: impl Spawn,
+        name: &'static str,
+        sender: Channel<Message<T, R>>,
+        task: impl FnOnce(
+            Arc<QueueStats>,
+            CancellationToken,
+            Arc<Recovery<T>>,
+        ) -> MaybeSendFuture<'static, ()>,
+    ) -> Self {
+        let (finish, finished) = oneshot::channel();
+        let stats = Arc::new(QueueStats::new(name));
+        let token = CancellationToken::new();
+        let recovery = Arc::new(Recovery::new());
+        let task = task(Arc::clone(&stats), token.clone(), Arc::clone(&recovery));
+
+        spawner.spawn(Box::pin(async move {
+            let _finish = finish;
+            task.await;
+        }));
+
+        Sender {
+            sender,
+            finished: finished.shared(),
+            stats,
+            token,
+            recovery,
+            #[cfg(any(feature = "async-std", feature = "tokio"))]
+            timer: Timer::default(),
+            #[cfg(feature = "durable")]
+            journal: None,
+        }
+    }
+
+    /// Wrap `event` for the task, appending it to the log of a durable queue
+    fn message(&self, event: T, sender: MSend<R>) -> Message<T, R> {
+        #[cfg(feature = "durable")]
+        if let Some(journal) = &self.journal {
+            match Arc::clone(journal).append(&event) {
+                Ok(on_handled) => return Message::Event(event, sender, Some(on_handled)),
+                Err(e) => log::error!("Failed to persist queued event, it is only in memory: {e}"),
+            }
+        }
+
+        Message::Event(event, sender, None)
+    }
+
+    /// Set the function called when a handler panics, with the name of the queue and
+    /// the panic message. By default, panics are logged with `log::error!`.
+    ///
+    /// A panic only loses the event being handled, the queue keeps going. The worker
+    /// keeps its data, as the panic left it: handlers mutating their data should not
+    /// rely on it being consistent after a panic.
+    ///
+    /// ```
+    /// use hermod::Sender;
+    ///
+    /// let queue = Sender::<u32, ()>::new(|n, _| Box::pin(async move {
+    ///     assert_ne!(n, 0, "division by zero");
+    /// }), ());
+    ///
+    /// queue.set_panic_handler(|queue, message| eprintln!("{queue}: {message}"));
+    ///
+    /// async_std::task::block_on(async {
+    ///     queue.try_emit(0u32).unwrap();
+    ///     queue.drain().await;
+    /// });
+    ///
+    /// assert_eq!(queue.metrics().panics, 1);
+    /// ```
+    pub fn set_panic_handler(&self, handler: PanicHandler) {
+        self.stats.set_panic_handler(handler);
+    }
+
+    /// Retry events whose handler panicked, or returned an error in a `fallible` queue,
+    /// according to `policy`, before they are given up on and passed to the dead-letter
+    /// sink. Every attempt gets a clone of the event, and every failed attempt is passed
+    /// to the panic handler, or logged if it is an error. Batched queues are not retried.
+    ///
+    /// The responses a streaming handler sent before panicking are not taken back: the
+    /// caller receives the responses of every attempt, so a handler that panics after
+    /// sending some sends them again on each retry.
+    ///
+    /// ```
+    /// use hermod::{RetryPolicy, Sender};
+    /// use std::sync::atomic::{AtomicU32, Ordering};
+    ///
+    /// static ATTEMPTS: AtomicU32 = AtomicU32::new(0);
+    ///
+    /// let queue = Sender::<u32, ()>::new(|_, _| Box::pin(async {
+    ///     assert_ne!(ATTEMPTS.fetch_add(1, Ordering::SeqCst), 0, "server busy");
+    /// }), ());
+    ///
+    /// queue.set_panic_handler(|_, _| {});
+    /// queue.set_retry_policy(RetryPolicy::new(3));
+    ///
+    /// async_std::task::block_on(async {
+    ///     queue.try_emit(0u32).unwrap();
+    ///     queue.drain().await;
+    /// });
+    ///
+    /// assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 2);
+    /// ```
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn set_retry_policy(&self, policy: RetryPolicy)
+    where
+        T: Clone,
+    {
+        let _ = self.recovery.clone.set(T::clone);
+        *self.recovery.retries.write().unwrap() = Some(policy);
+    }
+
+    /// Pass every event whose handler panicked, or returned an error in a `fallible`
+    /// queue, on its last attempt if retried, to `sink` along with the `Failure`, e.g. to
+    /// persist or requeue it. Batched queues do not pass their events to the sink.
+    ///
+    /// ```
+    /// use futures::channel::mpsc;
+    /// use hermod::{Failure, Sender};
+    ///
+    /// let queue = Sender::<String, ()>::fallible(|_, _| Box::pin(async {
+    ///     Err("offline".into())
+    /// }), ());
+    ///
+    /// let (sender, mut failed) = mpsc::unbounded();
+    ///
+    /// queue.set_dead_letter_sink(move |event, failure| {
+    ///     let error = matches!(failure, Failure::Error(_));
+    ///     let _ = sender.unbounded_send((event, failure.to_string(), error));
+    /// });
+    ///
+    /// async_std::task::block_on(async {
+    ///     queue.try_emit("report.pdf").unwrap();
+    ///     queue.drain().await;
+    /// });
+    ///
+    /// let (event, message, error) = failed.try_next().unwrap().unwrap();
+    /// assert_eq!((event.as_str(), message.as_str(), error), ("report.pdf", "offline", true));
+    /// ```
+    pub fn set_dead_letter_sink(&self, sink: impl Fn(T, &Failure) + Send + Sync + 'static)
+    where
+        T: Clone,
+    {
+        let _ = self.recovery.clone.set(T::clone);
+        *self.recovery.dead_letters.write().unwrap() = Some(Arc::new(sink));
+    }
+
+    /// Depth, counters and handler latencies of the queue
+    ///
+    /// ```
+    /// use hermod::Sender;
+    ///
+    /// let queue = Sender::<u32, ()>::new(|_, _| Box::pin(async {}), ());
+    ///
+    /// async_std::task::block_on(async {
+    ///     queue.try_emit(1u32).unwrap();
+    ///     queue.drain().await;
+    /// });
+    ///
+    /// let metrics = queue.metrics();
+    /// assert_eq!((metrics.depth, metrics.handled), (0, 1));
+    /// ```
+    pub fn metrics(&self) -> QueueMetrics {
+        self.stats.metrics()
+    }
+
+    /// The name of the queue in the metrics: the type name of its events, unless it was
+    /// set with `SenderBuilder::with_name`
+    pub fn name(&self) -> &'static str {
+        self.stats.name()
+    }
+
+    /// Stop accepting events, emitting fails from now on. The events already queued are
+    /// still handled, see `drain`. With a bounded queue, this waits for the `emit`s
+    /// already waiting for room.
+    ///
+    /// Dropping the `Sender` also closes the queue, the task stops once it is empty.
+    pub async fn close(&self) {
+        self.sender.close().await;
+    }
+
+    /// Cancel the token passed to the handlers of a `with_cancellation` queue, close the
+    /// queue and wait for the task to stop. Events still queued are handled with the
+    /// cancelled token.
+    pub async fn shutdown(&self) {
+        self.token.cancel();
+        self.close().await;
+        self.drain().await;
+    }
+
+    /// Wait until every event queued so far has been handled. If the queue is closed,
+    /// this waits for the task to stop.
+    ///
+    /// ```
+    /// use hermod::Sender;
+    /// use std::sync::{
+    ///     atomic::{AtomicUsize, Ordering},
+    ///     Arc,
+    /// };
+    ///
+    /// static HANDLED: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// let queue = Sender::<(), ()>::new(|_, _| Box::pin(async {
+    ///     HANDLED.fetch_add(1, Ordering::SeqCst);
+    /// }), ());
+    ///
+    /// async_std::task::block_on(async {
+    ///     for _ in 0..10 {
+    ///         queue.try_emit(()).unwrap();
+    ///     }
+    ///
+    ///     queue.close().await;
+    ///     assert!(queue.try_emit(()).is_err());
+    ///
+    ///     queue.drain().await;
+    ///     assert_eq!(HANDLED.load(Ordering::SeqCst), 10);
+    /// });
+    /// ```
+    pub async fn drain(&self) {
+        let (ack, acked) = oneshot::channel();
+
+        match self.sender.send(Message::Drain(ack)).await {
+            Ok(()) => {
+                let _ = acked.await;
+            }
+            Err(_) => {
+                let _ = self.finished.clone().await;
+            }
+        }
+    }
+
+    /// Resolves once the task has stopped
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub(crate) async fn stopped(&self) {
+        let _ = self.finished.clone().await;
+    }
+
+    /// Wait until nothing is queued or being handled, including the events queued while
+    /// waiting. Unlike `drain`, this can wait forever if events keep coming.
+    ///
+    /// ```
+    /// use hermod::Sender;
+    /// use std::sync::Arc;
+    ///
+    /// let queue = Arc::new(Sender::<u32, ()>::new(|_, _| Box::pin(async {}), ()));
+    ///
+    /// async_std::task::block_on(async {
+    ///     for n in 0..10u32 {
+    ///         queue.try_emit(n).unwrap();
+    ///     }
+    ///
+    ///     queue.idle().await;
+    /// });
+    ///
+    /// assert_eq!(queue.metrics().depth, 0);
+    /// ```
+    pub async fn idle(&self) {
+        loop {
+            self.drain().await;
+
+            if self.stats.metrics().depth == 0 {
+                return;
             }
+        }
+    }
+
+    pub async fn emit(self: Arc<Self>, event: impl Into<T>) -> Result<MRecv<R>, Error<T>> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        self.sender
+            .send(self.message(event.into(), sender))
+            .await
+            .map_err(|msg| Error::Closed(msg.into_event()))?;
+
+        self.stats.queued();
+        Ok(receiver)
+    }
+
+    /// Emit an event and wait for the response, for at most `timeout` (including the time
+    /// spent waiting for room in a bounded queue). On timeout, the response is discarded.
+    ///
+    /// ```
+    /// use hermod::{Error, Sender};
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// let queue = Arc::new(Sender::<u32, u32>::new(|n, _| Box::pin(async move {
+    ///     if n == 0 {
+    ///         std::future::pending::<()>().await;
+    ///     }
+    ///
+    ///     n * 2
+    /// }), ()));
+    ///
+    /// async_std::task::block_on(async {
+    ///     let timeout = Duration::from_secs(1);
+    ///
+    ///     assert_eq!(Arc::clone(&queue).emit_timeout(2u32, timeout).await.unwrap(), 4);
+    ///
+    ///     let hung = Arc::clone(&queue).emit_timeout(0u32, Duration::from_millis(10)).await;
+    ///     assert!(matches!(hung, Err(Error::Timeout)));
+    /// });
+    /// ```
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub async fn emit_timeout(
+        self: Arc<Self>,
+        event: impl Into<T>,
+        timeout: Duration,
+    ) -> Result<R, Error<T>> {
+        let event = event.into();
+
+        let response = crate::runtime::timeout(timeout, async move {
+            let mut receiver = self.emit(event).await?;
+            receiver.next().await.ok_or(Error::HandlerFailed)
         });
 
-        Sender { sender }
+        response.await.unwrap_or(Err(Error::Timeout))
     }
 
-    pub async fn emit(self: Arc<Self>, event: impl Into<T>) -> Result<MRecv<R>, SendError> {
+    /// Like `emit`, but fails instead of waiting when a bounded queue is full. A full
+    /// queue also includes other `emit`s still waiting for room. As it does not wait,
+    /// this can be called from synchronous code.
+    pub fn try_emit(&self, event: impl Into<T>) -> Result<MRecv<R>, Error<T>> {
         let (sender, receiver) = mpsc::unbounded();
-        self.sender.clone().send((event.into(), sender)).await?;
 
+        self.sender
+            .try_send(self.message(event.into(), sender))
+            .map_err(|e| e.map(Message::into_event))?;
+
+        self.stats.queued();
         Ok(receiver)
     }
 
-    pub async fn emit_responseless(self: Arc<Self>, event: impl Into<T>) -> Result<(), SendError> {
+    pub async fn emit_responseless(self: Arc<Self>, event: impl Into<T>) -> Result<(), Error<T>> {
         self.sender
-            .clone()
-            .send((event.into(), mpsc::unbounded().0))
+            .send(self.message(event.into(), mpsc::unbounded().0))
             .await
+            .map_err(|msg| Error::Closed(msg.into_event()))?;
+
+        self.stats.queued();
+        Ok(())
+    }
+
+    /// Queue an event without a response once `delay` has passed. The delivery can be
+    /// cancelled with the returned handle.
+    ///
+    /// ```
+    /// use futures::{channel::mpsc, StreamExt};
+    /// use hermod::Sender;
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// let (handled, mut received) = mpsc::unbounded();
+    ///
+    /// let queue = Arc::new(Sender::<u32, ()>::new(|n, handled| Box::pin(async move {
+    ///     let _ = handled.unbounded_send(n);
+    /// }), handled));
+    ///
+    /// Arc::clone(&queue).emit_after(1u32, Duration::from_millis(10));
+    /// let later = Arc::clone(&queue).emit_after(2u32, Duration::from_secs(3600));
+    ///
+    /// // cancelled before its deadline, so never queued
+    /// assert!(later.cancel());
+    ///
+    /// async_std::task::block_on(async {
+    ///     assert_eq!(received.next().await, Some(1));
+    ///     queue.drain().await;
+    /// });
+    ///
+    /// assert_eq!(queue.metrics().handled, 1);
+    /// ```
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn emit_after(self: Arc<Self>, event: impl Into<T>, delay: Duration) -> Scheduled {
+        self.emit_at(event, Instant::now() + delay)
+    }
+
+    /// Like `emit_after`, but the event is queued at `at`
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn emit_at(self: Arc<Self>, event: impl Into<T>, at: Instant) -> Scheduled {
+        let event = event.into();
+        let queue = Arc::clone(&self);
+
+        self.timer.schedule(
+            at,
+            Box::pin(async move {
+                // a closed queue drops the event, like any other emit
+                let _ = queue.emit_responseless(event).await;
+            }),
+        )
+    }
+
+    /// Queue a clone of `event` without a response on every `recurrence`, until the
+    /// returned handle is cancelled or the queue is dropped. The schedule does not
+    /// keep the queue alive.
+    ///
+    /// ```
+    /// use futures::{channel::mpsc, StreamExt};
+    /// use hermod::{every, Sender};
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// let (ran, mut runs) = mpsc::unbounded();
+    ///
+    /// let queue = Arc::new(Sender::<&str, ()>::new(|job, ran| Box::pin(async move {
+    ///     let _ = ran.unbounded_send(job);
+    /// }), ran));
+    ///
+    /// let cleanup = Arc::clone(&queue).schedule(every(Duration::from_millis(10)), "cleanup");
+    ///
+    /// async_std::task::block_on(async {
+    ///     for _ in 0..3 {
+    ///         assert_eq!(runs.next().await, Some("cleanup"));
+    ///     }
+    /// });
+    ///
+    /// assert!(cleanup.cancel());
+    /// ```
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn schedule(
+        self: Arc<Self>,
+        recurrence: impl Into<Recurrence>,
+        event: impl Into<T>,
+    ) -> Scheduled
+    where
+        T: Clone,
+    {
+        let event = event.into();
+        let queue = Arc::downgrade(&self);
+
+        self.timer.repeat(
+            recurrence.into(),
+            Box::new(move || {
+                let queue = queue.upgrade()?;
+                let event = event.clone();
+
+                Some(Box::pin(async move {
+                    let _ = queue.emit_responseless(event).await;
+                }))
+            }),
+        )
+    }
+}
+
+#[cfg(all(test, feature = "async-std", not(feature = "tokio")))]
+mod tests {
+    use super::*;
+    use crate::every;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn panics_are_passed_to_the_handler() {
+        static PANICS: AtomicUsize = AtomicUsize::new(0);
+
+        let queue = Sender::<u32, ()>::builder()
+            .with_name("panicking")
+            .build(|n| async move { assert_ne!(n, 0) });
+
+        queue.set_panic_handler(|queue, message| {
+            assert_eq!(queue, "panicking");
+            assert!(message.contains("assertion"));
+            PANICS.fetch_add(1, Ordering::SeqCst);
+        });
+
+        async_std::task::block_on(async {
+            queue.try_emit(0u32).unwrap();
+            queue.try_emit(1u32).unwrap();
+            queue.drain().await;
+        });
+
+        assert_eq!(PANICS.load(Ordering::SeqCst), 1);
+        assert_eq!(queue.metrics().handled, 2);
+    }
+
+    #[test]
+    fn a_panicking_batch_is_one_panic() {
+        let queue = Sender::<u32, ()>::batched(
+            2,
+            Duration::from_secs(3600),
+            |_, _| Box::pin(async { panic!("batch") }),
+            (),
+        );
+
+        queue.set_panic_handler(|_, _| {});
+
+        async_std::task::block_on(async {
+            queue.try_emit(1u32).unwrap();
+            queue.try_emit(2u32).unwrap();
+            queue.drain().await;
+        });
+
+        let metrics = queue.metrics();
+        assert_eq!((metrics.handled, metrics.panics), (2, 1));
+    }
+
+    /// A queue of `capacity` whose handler reports each event, then waits for a `()`
+    fn gated(
+        capacity: usize,
+    ) -> (
+        Arc<Sender<u32, ()>>,
+        std::sync::mpsc::Receiver<u32>,
+        MSend<()>,
+    ) {
+        let (started, events) = std::sync::mpsc::sync_channel(8);
+        let (gate, opened) = mpsc::unbounded::<()>();
+
+        let queue = Sender::bounded(
+            capacity,
+            |n, (started, opened): &mut (std::sync::mpsc::SyncSender<u32>, MRecv<()>)| {
+                started.send(n).unwrap();
+                Box::pin(async move {
+                    opened.next().await;
+                })
+            },
+            (started, opened),
+        );
+
+        (Arc::new(queue), events, gate)
+    }
+
+    #[test]
+    fn bounded_queues_hold_their_capacity() {
+        let (queue, events, gate) = gated(2);
+
+        // once the handler has the first event, the queue is empty again
+        queue.try_emit(0u32).unwrap();
+        assert_eq!(events.recv().unwrap(), 0);
+
+        queue.try_emit(1u32).unwrap();
+        queue.try_emit(2u32).unwrap();
+        assert!(matches!(queue.try_emit(3u32), Err(Error::Full(3))));
+
+        for n in 1..=2 {
+            gate.unbounded_send(()).unwrap();
+            assert_eq!(events.recv().unwrap(), n);
+        }
+
+        gate.unbounded_send(()).unwrap();
+        async_std::task::block_on(queue.drain());
+    }
+
+    #[test]
+    fn waiting_emits_do_not_block_other_callers() {
+        let (queue, events, gate) = gated(1);
+
+        queue.try_emit(0u32).unwrap();
+        assert_eq!(events.recv().unwrap(), 0);
+        queue.try_emit(1u32).unwrap();
+
+        async_std::task::block_on(async {
+            let mut waiting = Box::pin(Arc::clone(&queue).emit(2u32));
+            assert!(futures::poll!(&mut waiting).is_pending());
+            assert!(matches!(queue.try_emit(3u32), Err(Error::Full(3))));
+
+            for n in 1..=2 {
+                gate.unbounded_send(()).unwrap();
+                assert_eq!(events.recv().unwrap(), n);
+            }
+
+            // the queue has room again, even though the waiting emit was not polled since
+            queue.try_emit(4u32).unwrap();
+            assert!(waiting.await.is_ok());
+
+            for _ in 0..2 {
+                gate.unbounded_send(()).unwrap();
+            }
+
+            queue.drain().await;
+        });
+    }
+
+    #[test]
+    fn panicking_events_are_dead_lettered() {
+        let queue = Sender::<u32, ()>::new(
+            |n, _| Box::pin(async move { assert_ne!(n, 0, "offline") }),
+            (),
+        );
+
+        let (sender, failed) = std::sync::mpsc::channel();
+
+        queue.set_panic_handler(|_, _| {});
+        queue.set_dead_letter_sink(move |event, message| {
+            sender.send((event, message.to_string())).unwrap();
+        });
+
+        async_std::task::block_on(async {
+            queue.try_emit(0u32).unwrap();
+            queue.try_emit(1u32).unwrap();
+            queue.drain().await;
+        });
+
+        let failed = failed.try_iter().collect::<Vec<_>>();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, 0);
+        assert!(failed[0].1.contains("offline"));
+    }
+
+    #[test]
+    fn failing_events_are_dead_lettered_after_the_last_attempt() {
+        static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+        let queue = Sender::<u32, ()>::new(
+            |_, _| {
+                Box::pin(async {
+                    ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+                    panic!("offline");
+                })
+            },
+            (),
+        );
+
+        let (sender, failed) = std::sync::mpsc::channel();
+
+        queue.set_panic_handler(|_, _| {});
+        queue.set_retry_policy(RetryPolicy::new(3));
+        queue.set_dead_letter_sink(move |event, message| {
+            sender.send((event, message.to_string())).unwrap();
+        });
+
+        async_std::task::block_on(async {
+            queue.try_emit(7u32).unwrap();
+            queue.drain().await;
+        });
+
+        assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            failed.try_iter().collect::<Vec<_>>(),
+            [(7, "offline".to_string())]
+        );
+
+        let metrics = queue.metrics();
+        assert_eq!((metrics.handled, metrics.panics), (1, 1));
+    }
+
+    #[test]
+    fn retried_events_are_not_dead_lettered() {
+        static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+        let queue = Sender::<u32, ()>::new(
+            |_, _| {
+                Box::pin(async {
+                    assert_ne!(ATTEMPTS.fetch_add(1, Ordering::SeqCst), 0);
+                })
+            },
+            (),
+        );
+
+        let (sender, failed) = std::sync::mpsc::channel();
+
+        queue.set_panic_handler(|_, _| {});
+        queue.set_retry_policy(RetryPolicy::new(2));
+        queue.set_dead_letter_sink(move |event, _| sender.send(event).unwrap());
+
+        async_std::task::block_on(async {
+            queue.try_emit(7u32).unwrap();
+            queue.drain().await;
+        });
+
+        assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 2);
+        assert!(failed.try_recv().is_err());
+        assert_eq!(queue.metrics().panics, 0);
+    }
+
+    #[test]
+    fn errors_are_retried() {
+        static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+        let queue = Arc::new(Sender::<u32, u32>::fallible(
+            |n, _| {
+                Box::pin(async move {
+                    match ATTEMPTS.fetch_add(1, Ordering::SeqCst) {
+                        0 => Err("server busy".into()),
+                        _ => Ok(n * 2),
+                    }
+                })
+            },
+            (),
+        ));
+
+        queue.set_retry_policy(RetryPolicy::new(2));
+
+        async_std::task::block_on(async {
+            let mut response = Arc::clone(&queue).emit(21u32).await.unwrap();
+            assert_eq!(response.next().await, Some(42));
+        });
+
+        assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 2);
+        assert_eq!(queue.metrics().panics, 0);
+    }
+
+    #[test]
+    fn failing_events_get_no_response() {
+        static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+        let queue = Arc::new(Sender::<u32, u32>::fallible(
+            |_, _| {
+                Box::pin(async {
+                    ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+                    Err("offline".into())
+                })
+            },
+            (),
+        ));
+
+        let (sender, failed) = std::sync::mpsc::channel();
+
+        queue.set_retry_policy(RetryPolicy::new(3));
+        queue.set_dead_letter_sink(move |event, message| {
+            sender.send((event, message.to_string())).unwrap();
+        });
+
+        async_std::task::block_on(async {
+            let mut response = Arc::clone(&queue).emit(7u32).await.unwrap();
+            assert_eq!(response.next().await, None);
+            queue.drain().await;
+        });
+
+        assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            failed.try_iter().collect::<Vec<_>>(),
+            [(7, "offline".to_string())]
+        );
+
+        let metrics = queue.metrics();
+        assert_eq!((metrics.handled, metrics.panics), (1, 0));
+    }
+
+    #[test]
+    fn dead_letters_keep_the_error() {
+        let queue = Sender::<u32, ()>::fallible(
+            |_, _| {
+                Box::pin(async { Err(std::io::Error::from(std::io::ErrorKind::TimedOut).into()) })
+            },
+            (),
+        );
+
+        let (sender, failed) = std::sync::mpsc::channel();
+
+        queue.set_dead_letter_sink(move |event, failure| {
+            let kind = match failure {
+                Failure::Error(e) => e.downcast_ref::<std::io::Error>().map(|e| e.kind()),
+                Failure::Panicked(_) => None,
+            };
+
+            sender.send((event, kind)).unwrap();
+        });
+
+        async_std::task::block_on(async {
+            queue.try_emit(7u32).unwrap();
+            queue.drain().await;
+        });
+
+        assert_eq!(
+            failed.try_iter().collect::<Vec<_>>(),
+            [(7, Some(std::io::ErrorKind::TimedOut))]
+        );
+    }
+
+    #[test]
+    fn schedules_do_not_keep_the_queue_alive() {
+        let queue = Arc::new(Sender::<u32, ()>::new(|_, _| Box::pin(async {}), ()));
+        let weak = Arc::downgrade(&queue);
+
+        let _schedule = Arc::clone(&queue).schedule(every(Duration::from_secs(3600)), 1u32);
+        drop(queue);
+
+        assert!(weak.upgrade().is_none());
     }
 }
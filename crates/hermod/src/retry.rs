@@ -0,0 +1,98 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+/// # Backoff
+///
+/// How long to wait before retrying a listener, or restarting a supervised worker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backoff {
+    /// The same delay before every retry
+    Fixed(Duration),
+    /// `initial`, doubled after every retry, up to `max`
+    Exponential { initial: Duration, max: Duration },
+}
+
+impl Backoff {
+    /// The delay after the `attempt`th (from 1) attempt
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 2u32
+                    .checked_pow(attempt.saturating_sub(1))
+                    .unwrap_or(u32::MAX);
+                initial.checked_mul(factor).map_or(max, |n| n.min(max))
+            }
+        }
+    }
+}
+
+/// # RetryPolicy
+///
+/// How often failing listeners are retried, see `EventEmitter::set_retry_policy` and
+/// `Sender::set_retry_policy`.
+///
+/// ```
+/// use hermod::{Backoff, RetryPolicy};
+/// use std::time::Duration;
+///
+/// // 1 attempt and up to 4 retries, waiting ~100ms, ~200ms, ~400ms and ~800ms
+/// let policy = RetryPolicy::new(5)
+///     .with_backoff(Backoff::Exponential {
+///         initial: Duration::from_millis(100),
+///         max: Duration::from_secs(1),
+///     })
+///     .with_jitter();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    attempts: u32,
+    backoff: Backoff,
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Call a listener up to `attempts` times (at least once), without delay between them
+    pub fn new(attempts: u32) -> Self {
+        Self {
+            attempts: attempts.max(1),
+            backoff: Backoff::Fixed(Duration::ZERO),
+            jitter: false,
+        }
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Randomize every delay to between half and all of it, so listeners that failed
+    /// together do not all retry at the same time
+    pub fn with_jitter(mut self) -> Self {
+        self.jitter = true;
+        self
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// How long to wait after the `attempt`th (from 1) attempt failed
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.backoff.delay(attempt);
+
+        if !self.jitter {
+            return delay;
+        }
+
+        // std has no RNG, but every `RandomState` is seeded randomly
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(attempt);
+
+        let half = delay / 2;
+        half + half.mul_f64(hasher.finish() as f64 / u64::MAX as f64)
+    }
+}
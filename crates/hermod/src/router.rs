@@ -0,0 +1,173 @@
+use crate::Sender;
+use futures::{channel::mpsc::UnboundedReceiver as MRecv, future};
+use std::{error::Error, fmt, sync::Arc};
+
+type Predicate<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+type Route<T, R> = (Predicate<T>, Arc<Sender<T, R>>);
+
+/// # RouteError
+///
+/// Returned by `Router::emit`.
+pub enum RouteError<T> {
+    /// No route matched the event, and there is no fallback. Gives the event back.
+    Unmatched(T),
+    /// The task of the matching route has stopped
+    Disconnected,
+}
+
+impl<T> fmt::Debug for RouteError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::Unmatched(_) => f.write_str("Unmatched(..)"),
+            RouteError::Disconnected => f.write_str("Disconnected"),
+        }
+    }
+}
+
+impl<T> fmt::Display for RouteError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::Unmatched(_) => f.write_str("no route matches the event"),
+            RouteError::Disconnected => f.write_str("route is disconnected"),
+        }
+    }
+}
+
+impl<T> Error for RouteError<T> {}
+
+/// # Router
+///
+/// Sends each event to exactly one of several queues: the first route whose
+/// predicate matches, or the fallback. Each route is a `Sender`, so its handler
+/// runs on its own task, with its own data.
+///
+/// ```
+/// use async_std::stream::StreamExt;
+/// use hermod::{Router, RouteError, Sender};
+///
+/// pub enum Command {
+///     Save(String),
+///     Load(String),
+///     Quit,
+/// }
+///
+/// let router = Router::new()
+///     .route(
+///         |cmd| matches!(cmd, Command::Save(_)),
+///         Sender::new(|_, saved| Box::pin(async move {
+///             *saved += 1;
+///             "saved"
+///         }), 0u32),
+///     )
+///     .route(
+///         |cmd| matches!(cmd, Command::Load(_)),
+///         Sender::new(|_, _| Box::pin(async { "loaded" }), ()),
+///     );
+///
+/// async_std::task::block_on(async {
+///     let mut response = router.emit(Command::Save(String::from("a.txt"))).await.unwrap();
+///     assert_eq!(response.next().await, Some("saved"));
+///
+///     let mut response = router.emit(Command::Load(String::from("a.txt"))).await.unwrap();
+///     assert_eq!(response.next().await, Some("loaded"));
+///
+///     assert!(matches!(router.emit(Command::Quit).await, Err(RouteError::Unmatched(_))));
+/// });
+/// ```
+pub struct Router<T, R>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+{
+    routes: Vec<Route<T, R>>,
+    fallback: Option<Arc<Sender<T, R>>>,
+}
+
+impl<T, R> Router<T, R>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            routes: vec![],
+            fallback: None,
+        }
+    }
+
+    /// Send the events matching `predicate` to `sender`. Routes are tried in the
+    /// order they were added.
+    pub fn route(
+        mut self,
+        predicate: impl Fn(&T) -> bool + Send + Sync + 'static,
+        sender: Sender<T, R>,
+    ) -> Self {
+        self.routes.push((Box::new(predicate), Arc::new(sender)));
+        self
+    }
+
+    /// Send the events no route matches to `sender`
+    pub fn fallback(mut self, sender: Sender<T, R>) -> Self {
+        self.fallback = Some(Arc::new(sender));
+        self
+    }
+
+    /// Send `event` to the queue of its route, see `Sender::emit`.
+    pub async fn emit(&self, event: impl Into<T>) -> Result<MRecv<R>, RouteError<T>> {
+        let event = event.into();
+
+        let route = self
+            .routes
+            .iter()
+            .find(|(predicate, _)| predicate(&event))
+            .map(|(_, sender)| sender)
+            .or(self.fallback.as_ref());
+
+        match route {
+            Some(sender) => Arc::clone(sender)
+                .emit(event)
+                .await
+                .map_err(|_| RouteError::Disconnected),
+            None => Err(RouteError::Unmatched(event)),
+        }
+    }
+
+    /// Wait until every event sent so far has been handled, see `Sender::drain`.
+    pub async fn drain(&self) {
+        let senders = self.routes.iter().map(|(_, n)| n).chain(&self.fallback);
+        future::join_all(senders.map(|n| n.drain())).await;
+    }
+}
+
+impl<T, R> Default for Router<T, R>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "async-std", not(feature = "tokio")))]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn default_routers_send_everything_to_the_fallback() {
+        let router = Router::<u32, u32>::default();
+
+        async_std::task::block_on(async {
+            assert!(matches!(
+                router.emit(1u32).await,
+                Err(RouteError::Unmatched(1))
+            ));
+
+            let router = router.fallback(Sender::new(|n, _| Box::pin(async move { n * 2 }), ()));
+            let mut response = router.emit(2u32).await.unwrap();
+
+            assert_eq!(response.next().await, Some(4));
+        });
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{MaybeSendFuture, Sender, Spawn};
+use futures::StreamExt;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+/// # The `Request` Trait
+///
+/// Specify that a type can be sent with `Rpc::request`, and the type of its
+/// response.
+pub trait Request: Send + Sync + 'static {
+    type Response: Send + Sync + 'static;
+}
+
+/// # RequestError
+///
+/// Returned by `Rpc::request`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestError {
+    /// Nothing responds to the request
+    NoResponder,
+    /// The responder's queue stopped before it responded
+    Disconnected,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::NoResponder => f.write_str("no responder for the request"),
+            RequestError::Disconnected => f.write_str("responder is disconnected"),
+        }
+    }
+}
+
+impl Error for RequestError {}
+
+/// # Rpc
+///
+/// Requests answered by exactly one responder, each running on its own `Sender`
+/// queue. The response type is given by the `Request`, so requests and responses
+/// cannot be mismatched.
+///
+/// ```
+/// use hermod::{Request, RequestError, Rpc};
+/// use std::collections::HashMap;
+///
+/// pub struct GetUser(u64);
+///
+/// impl Request for GetUser {
+///     type Response = Option<String>;
+/// }
+///
+/// pub struct Ping;
+///
+/// impl Request for Ping {
+///     type Response = ();
+/// }
+///
+/// let users = HashMap::from([(1, String::from("alice"))]);
+/// let rpc = Rpc::new();
+///
+/// rpc.respond::<GetUser, _>(|GetUser(id), users| Box::pin(async move {
+///     users.get(&id).cloned()
+/// }), users);
+///
+/// async_std::task::block_on(async {
+///     assert_eq!(rpc.request(GetUser(1)).await, Ok(Some(String::from("alice"))));
+///     assert_eq!(rpc.request(Ping).await, Err(RequestError::NoResponder));
+/// });
+/// ```
+pub struct Rpc {
+    // a `Sender<Req, Req::Response>` per request type
+    responders: RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl Rpc {
+    pub fn new() -> Self {
+        Self {
+            responders: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Answer every `Req` with `listener`, on a queue spawned on the bundled runtime
+    /// (see `Sender::new`). Returns `false`, without spawning anything, if `Req`
+    /// already has a responder.
+    #[cfg(any(feature = "async-std", feature = "tokio"))]
+    pub fn respond<Req: Request, D: Send + Sync + 'static>(
+        &self,
+        listener: impl for<'a> Fn(Req, &'a mut D) -> MaybeSendFuture<'a, Req::Response>
+            + Send
+            + Sync
+            + 'static,
+        data: D,
+    ) -> bool {
+        self.respond_on(crate::runtime::spawn, listener, data)
+    }
+
+    /// Like `respond`, but the queue is spawned with `spawner`.
+    pub fn respond_on<Req: Request, D: Send + Sync + 'static>(
+        &self,
+        spawner: impl Spawn,
+        listener: impl for<'a> Fn(Req, &'a mut D) -> MaybeSendFuture<'a, Req::Response>
+            + Send
+            + Sync
+            + 'static,
+        data: D,
+    ) -> bool {
+        let mut responders = self.responders.write().unwrap();
+
+        if responders.contains_key(&TypeId::of::<Req>()) {
+            return false;
+        }
+
+        let sender = Sender::<Req, Req::Response>::new_on(spawner, listener, data);
+        responders.insert(TypeId::of::<Req>(), Arc::new(Arc::new(sender)));
+
+        true
+    }
+
+    /// Remove the responder of `Req`. Returns whether there was one. Requests already
+    /// queued are still answered.
+    pub fn stop_responding<Req: Request>(&self) -> bool {
+        self.responders
+            .write()
+            .unwrap()
+            .remove(&TypeId::of::<Req>())
+            .is_some()
+    }
+
+    /// Send `request` to its responder, and wait for the response.
+    pub async fn request<Req: Request>(&self, request: Req) -> Result<Req::Response, RequestError> {
+        let responder = self.responder::<Req>().ok_or(RequestError::NoResponder)?;
+
+        let mut response = responder
+            .emit(request)
+            .await
+            .map_err(|_| RequestError::Disconnected)?;
+
+        response.next().await.ok_or(RequestError::Disconnected)
+    }
+
+    fn responder<Req: Request>(&self) -> Option<Arc<Sender<Req, Req::Response>>> {
+        let responders = self.responders.read().unwrap();
+        let responder = responders.get(&TypeId::of::<Req>())?;
+
+        responder
+            .downcast_ref::<Arc<Sender<Req, Req::Response>>>()
+            .cloned()
+    }
+}
+
+impl Default for Rpc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "async-std", not(feature = "tokio")))]
+mod tests {
+    use super::*;
+
+    struct Ping;
+
+    impl Request for Ping {
+        type Response = u32;
+    }
+
+    #[test]
+    fn default_rpcs_start_without_responders() {
+        let rpc = Rpc::default();
+
+        async_std::task::block_on(async {
+            assert_eq!(rpc.request(Ping).await, Err(RequestError::NoResponder));
+
+            assert!(rpc.respond::<Ping, _>(|_, _| Box::pin(async { 1 }), ()));
+            assert!(!rpc.respond::<Ping, _>(|_, _| Box::pin(async { 2 }), ()));
+
+            assert_eq!(rpc.request(Ping).await, Ok(1));
+        });
+    }
+}
@@ -0,0 +1,212 @@
+#[cfg(all(
+    any(
+        feature = "queue",
+        all(feature = "events", any(feature = "async-std", feature = "tokio"))
+    ),
+    not(all(feature = "wasm", target_arch = "wasm32"))
+))]
+use futures::future::BoxFuture;
+
+#[cfg(all(feature = "queue", feature = "wasm", target_arch = "wasm32"))]
+use futures::future::LocalBoxFuture;
+
+#[cfg(any(feature = "queue", feature = "events"))]
+use std::any::Any;
+
+#[cfg(all(
+    any(feature = "queue", feature = "events"),
+    any(feature = "async-std", feature = "tokio")
+))]
+use std::time::Duration;
+
+#[cfg(all(feature = "queue", any(feature = "async-std", feature = "tokio")))]
+use std::future::Future;
+
+/// # Spawn
+///
+/// Runs futures in the background, e.g. the task of a `Sender`. Implemented for
+/// closures, so any executor can be used:
+///
+/// ```
+/// use futures::{executor, future::BoxFuture};
+/// use hermod::Sender;
+/// use std::thread;
+///
+/// // run every task on its own thread
+/// let spawner = |future: BoxFuture<'static, ()>| {
+///     thread::spawn(|| executor::block_on(future));
+/// };
+///
+/// let queue = Sender::<String, ()>::new_on(spawner, |msg, _| Box::pin(async move {
+///     println!("{msg}");
+/// }), ());
+/// ```
+#[cfg(feature = "queue")]
+pub trait Spawn {
+    fn spawn(&self, future: MaybeSendFuture<'static, ()>);
+}
+
+#[cfg(feature = "queue")]
+impl<F: Fn(MaybeSendFuture<'static, ()>)> Spawn for F {
+    fn spawn(&self, future: MaybeSendFuture<'static, ()>) {
+        self(future)
+    }
+}
+
+/// # MaybeSend
+///
+/// `Send`, except on wasm32 with the `wasm` feature, where the tasks of queues run on
+/// the browser's single thread, so their events, responses and data don't have to be
+/// `Send`. Implemented for every type that is.
+#[cfg(all(feature = "queue", not(all(feature = "wasm", target_arch = "wasm32"))))]
+pub trait MaybeSend: Send {}
+
+#[cfg(all(feature = "queue", not(all(feature = "wasm", target_arch = "wasm32"))))]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+#[cfg(all(feature = "queue", feature = "wasm", target_arch = "wasm32"))]
+pub trait MaybeSend {}
+
+#[cfg(all(feature = "queue", feature = "wasm", target_arch = "wasm32"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// # MaybeSync
+///
+/// `Sync`, except on wasm32 with the `wasm` feature, see `MaybeSend`.
+#[cfg(all(feature = "queue", not(all(feature = "wasm", target_arch = "wasm32"))))]
+pub trait MaybeSync: Sync {}
+
+#[cfg(all(feature = "queue", not(all(feature = "wasm", target_arch = "wasm32"))))]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+#[cfg(all(feature = "queue", feature = "wasm", target_arch = "wasm32"))]
+pub trait MaybeSync {}
+
+#[cfg(all(feature = "queue", feature = "wasm", target_arch = "wasm32"))]
+impl<T: ?Sized> MaybeSync for T {}
+
+/// The future returned by the handlers of a queue, and spawned by a `Spawn`: a
+/// `BoxFuture`, or a `LocalBoxFuture` on wasm32 with the `wasm` feature, see `MaybeSend`
+#[cfg(all(
+    any(
+        feature = "queue",
+        all(feature = "events", any(feature = "async-std", feature = "tokio"))
+    ),
+    not(all(feature = "wasm", target_arch = "wasm32"))
+))]
+pub type MaybeSendFuture<'a, T> = BoxFuture<'a, T>;
+
+#[cfg(all(feature = "queue", feature = "wasm", target_arch = "wasm32"))]
+pub type MaybeSendFuture<'a, T> = LocalBoxFuture<'a, T>;
+
+/// When an instant is needed: `std::time::Instant` is not available in browsers
+#[cfg(all(
+    any(feature = "queue", feature = "events"),
+    not(all(feature = "wasm", target_arch = "wasm32"))
+))]
+pub(crate) use std::time::Instant;
+
+#[cfg(all(
+    any(feature = "queue", feature = "events"),
+    feature = "wasm",
+    target_arch = "wasm32"
+))]
+pub(crate) use web_time::Instant;
+
+#[cfg(all(
+    feature = "wasm",
+    target_arch = "wasm32",
+    any(feature = "async-std", feature = "tokio")
+))]
+compile_error!(
+    "on wasm32, the `wasm` feature replaces the bundled runtimes, disable `async-std` and `tokio`"
+);
+
+/// Run `future` on the bundled runtime, or on wasm32 with the `wasm` feature, on the
+/// browser's event loop. With both `async-std` and `tokio`, e.g. when another
+/// dependency enables the default features, Tokio is used, here and in `timeout` and
+/// `sleep`.
+#[cfg(all(
+    any(feature = "queue", feature = "events"),
+    any(
+        feature = "async-std",
+        feature = "tokio",
+        all(feature = "wasm", target_arch = "wasm32")
+    )
+))]
+pub(crate) fn spawn(future: MaybeSendFuture<'static, ()>) {
+    #[cfg(feature = "tokio")]
+    tokio::spawn(future);
+
+    #[cfg(all(feature = "async-std", not(feature = "tokio")))]
+    async_std::task::spawn(future);
+
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+/// Wait for `future` for at most `duration`, with the bundled runtime's timer
+#[cfg(all(feature = "queue", any(feature = "async-std", feature = "tokio")))]
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    #[cfg(feature = "tokio")]
+    let result = tokio::time::timeout(duration, future).await;
+
+    #[cfg(all(feature = "async-std", not(feature = "tokio")))]
+    let result = async_std::future::timeout(duration, future).await;
+
+    result.ok()
+}
+
+/// Wait for `duration`, with the bundled runtime's timer
+#[cfg(all(
+    any(feature = "queue", feature = "events"),
+    any(feature = "async-std", feature = "tokio")
+))]
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(feature = "tokio")]
+    tokio::time::sleep(duration).await;
+
+    #[cfg(all(feature = "async-std", not(feature = "tokio")))]
+    async_std::task::sleep(duration).await;
+}
+
+/// The message of a caught panic
+#[cfg(any(feature = "queue", feature = "events"))]
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => String::from("unknown panic payload"),
+        },
+    }
+}
+
+#[cfg(all(test, feature = "queue", feature = "tokio"))]
+mod tests {
+    use crate::Sender;
+    use futures::StreamExt;
+    use std::{sync::Arc, time::Duration};
+
+    // also with `async-std`, which the default features enable. The other tests run on
+    // async-std, so they are only compiled without `tokio`.
+    #[test]
+    fn tasks_run_on_tokio() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let queue = Arc::new(Sender::<u32, u32>::new(
+                |n, _| Box::pin(async move { n + 1 }),
+                (),
+            ));
+
+            let mut response = queue.emit(1u32).await.unwrap();
+            assert_eq!(response.next().await, Some(2));
+
+            super::sleep(Duration::from_millis(1)).await;
+        });
+    }
+}
@@ -0,0 +1,332 @@
+use crate::{runtime, Cron};
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver as MRecv, UnboundedSender as MSend},
+    future::{self, BoxFuture, Either},
+    StreamExt,
+};
+use std::{
+    cmp::{Ordering as CmpOrdering, Reverse},
+    collections::BinaryHeap,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, OnceLock, Weak,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+const PENDING: u8 = 0;
+const DELIVERED: u8 = 1;
+const CANCELLED: u8 = 2;
+
+const CRON_SLACK: Duration = Duration::from_secs(1);
+
+/// The shortest period of `every`, so a recurring event cannot keep the timer busy
+const MIN_PERIOD: Duration = Duration::from_millis(1);
+
+/// # Recurrence
+///
+/// When a scheduled event is emitted again, see `EventEmitter::schedule` and
+/// `Sender::schedule`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recurrence {
+    /// Every period, starting one period from now
+    Every(Duration),
+    /// Every minute matching the expression
+    Cron(Cron),
+}
+
+impl Recurrence {
+    /// When the event is next emitted, if ever
+    fn next(&self, after: Instant) -> Option<Instant> {
+        match self {
+            Recurrence::Every(period) => Some(after + (*period).max(MIN_PERIOD)),
+            Recurrence::Cron(cron) => {
+                // the timer runs on `Instant`s, so wall-clock times are converted
+                let wall = SystemTime::now() + after.saturating_duration_since(Instant::now());
+
+                // with some slack, so a tick firing just before the minute by the wall
+                // clock is not repeated
+                let next = cron.next_after(wall + CRON_SLACK)?;
+                Some(after + next.duration_since(wall).unwrap_or_default())
+            }
+        }
+    }
+}
+
+impl From<Cron> for Recurrence {
+    fn from(cron: Cron) -> Self {
+        Recurrence::Cron(cron)
+    }
+}
+
+/// Emit an event every `period`, periods shorter than a millisecond are rounded up
+/// to one
+pub fn every(period: Duration) -> Recurrence {
+    Recurrence::Every(period)
+}
+
+/// # Scheduled
+///
+/// A handle to an event scheduled with `emit_after`, `emit_at` or `schedule`.
+/// Dropping the handle does not cancel the delivery.
+#[derive(Debug, Clone)]
+pub struct Scheduled {
+    state: Arc<AtomicU8>,
+    timer: Weak<MSend<Message>>,
+}
+
+impl Scheduled {
+    /// Cancel the delivery, returns `false` if the event was already delivered
+    /// (or cancelled).
+    pub fn cancel(&self) -> bool {
+        let cancelled = self
+            .state
+            .compare_exchange(PENDING, CANCELLED, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
+
+        // so the delivery, and what it holds on to, is dropped now rather than at its
+        // deadline
+        if let (true, Some(timer)) = (cancelled, self.timer.upgrade()) {
+            let _ = timer.unbounded_send(Message::Cancelled);
+        }
+
+        cancelled
+    }
+
+    /// Whether the event is still waiting for its deadline. Recurring events are
+    /// pending until cancelled, or until what they emit to is gone.
+    pub fn is_pending(&self) -> bool {
+        self.state.load(Ordering::Acquire) == PENDING
+    }
+
+    fn deliver(&self) -> bool {
+        self.state
+            .compare_exchange(PENDING, DELIVERED, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+/// The next delivery of a recurring event, `None` once there is nothing to emit to
+type Job = Box<dyn FnMut() -> Option<BoxFuture<'static, ()>> + Send>;
+
+/// What happens when an entry is due
+enum Delivery {
+    Once(BoxFuture<'static, ()>),
+    Recurring(Recurrence, Job),
+}
+
+/// A delivery waiting for its deadline
+struct Entry {
+    at: Instant,
+    seq: u64,
+    handle: Scheduled,
+    delivery: Delivery,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        (self.at, self.seq) == (other.at, other.seq)
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+/// What the timer task is sent
+enum Message {
+    Schedule(Entry),
+    /// A handle was cancelled
+    Cancelled,
+}
+
+/// A single background task delivering every scheduled event of its owner, started
+/// on the first `schedule`
+#[derive(Default)]
+pub(crate) struct Timer {
+    sender: OnceLock<Arc<MSend<Message>>>,
+}
+
+impl Timer {
+    /// Run `delivery` at `at`, unless the returned handle is cancelled first
+    pub(crate) fn schedule(&self, at: Instant, delivery: BoxFuture<'static, ()>) -> Scheduled {
+        self.push(at, Delivery::Once(delivery))
+    }
+
+    /// Run `job` on every `recurrence`, until the returned handle is cancelled
+    pub(crate) fn repeat(&self, recurrence: Recurrence, job: Job) -> Scheduled {
+        match recurrence.next(Instant::now()) {
+            Some(at) => self.push(at, Delivery::Recurring(recurrence, job)),
+            // never due, e.g. February 30th
+            None => Scheduled {
+                state: Arc::new(AtomicU8::new(DELIVERED)),
+                timer: Weak::new(),
+            },
+        }
+    }
+
+    fn push(&self, at: Instant, delivery: Delivery) -> Scheduled {
+        let sender = self.sender.get_or_init(|| {
+            let (sender, receiver) = mpsc::unbounded();
+            runtime::spawn(Box::pin(run(receiver)));
+            Arc::new(sender)
+        });
+
+        // the handles only hold a weak reference, the task stops once the timer is
+        // dropped
+        let handle = Scheduled {
+            state: Arc::new(AtomicU8::new(PENDING)),
+            timer: Arc::downgrade(sender),
+        };
+
+        let _ = sender.unbounded_send(Message::Schedule(Entry {
+            at,
+            seq: 0,
+            handle: handle.clone(),
+            delivery,
+        }));
+
+        handle
+    }
+}
+
+/// Wait for the earliest deadline, or for a new entry that could come before it
+async fn run(mut receiver: MRecv<Message>) {
+    let mut pending = BinaryHeap::<Reverse<Entry>>::new();
+    let mut seq = 0;
+    let mut open = true;
+
+    loop {
+        let now = Instant::now();
+
+        while pending.peek().is_some_and(|n| n.0.at <= now) {
+            let Reverse(mut entry) = pending.pop().unwrap();
+
+            // each delivery runs on its own, so a slow one cannot delay the others
+            match entry.delivery {
+                Delivery::Once(delivery) => {
+                    if entry.handle.deliver() {
+                        runtime::spawn(delivery);
+                    }
+                }
+                Delivery::Recurring(recurrence, mut job) => {
+                    if !entry.handle.is_pending() {
+                        continue;
+                    }
+
+                    let Some(delivery) = job() else {
+                        entry.handle.deliver();
+                        continue;
+                    };
+
+                    runtime::spawn(delivery);
+
+                    // a late tick is not caught up, the next one is a period after it
+                    if let Some(at) = recurrence.next(entry.at.max(now)) {
+                        entry.at = at;
+                        entry.delivery = Delivery::Recurring(recurrence, job);
+                        pending.push(Reverse(entry));
+                    }
+                }
+            }
+        }
+
+        let next = pending.peek().map(|n| n.0.at - now);
+
+        let received = match (open, next) {
+            (false, None) => break,
+            (false, Some(delay)) => {
+                runtime::sleep(delay).await;
+                continue;
+            }
+            (true, None) => receiver.next().await,
+            (true, Some(delay)) => {
+                let sleep = Box::pin(runtime::sleep(delay));
+
+                match future::select(receiver.next(), sleep).await {
+                    Either::Left((received, _)) => received,
+                    Either::Right(_) => continue,
+                }
+            }
+        };
+
+        match received {
+            Some(Message::Schedule(mut entry)) => {
+                seq += 1;
+                entry.seq = seq;
+                pending.push(Reverse(entry));
+            }
+            // every entry left in the heap is pending, unless it was cancelled
+            Some(Message::Cancelled) => pending.retain(|n| n.0.handle.is_pending()),
+            // the owner is gone, deliver what is left
+            None => open = false,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "async-std", not(feature = "tokio")))]
+mod tests {
+    use super::*;
+    use futures::channel::oneshot;
+
+    /// Tells when it is dropped
+    struct Dropped(#[allow(dead_code)] oneshot::Sender<()>);
+
+    #[test]
+    fn zero_periods_are_rounded_up() {
+        let now = Instant::now();
+        assert_eq!(every(Duration::ZERO).next(now), Some(now + MIN_PERIOD));
+    }
+
+    #[test]
+    fn cancelled_entries_are_dropped() {
+        let timer = Timer::default();
+        let (sender, dropped) = oneshot::channel();
+        let guard = Dropped(sender);
+
+        let handle = timer.schedule(
+            Instant::now() + Duration::from_secs(3600),
+            Box::pin(async move { drop(guard) }),
+        );
+
+        assert!(handle.cancel());
+        assert!(!handle.cancel());
+
+        // long before the deadline
+        async_std::task::block_on(async {
+            assert!(dropped.await.is_err());
+        });
+    }
+
+    #[test]
+    fn recurrences_stop_without_a_delivery() {
+        let timer = Timer::default();
+        let (sender, stopped) = oneshot::channel();
+        let guard = Dropped(sender);
+
+        let handle = timer.repeat(
+            every(Duration::ZERO),
+            Box::new(move || {
+                let _ = &guard;
+                None
+            }),
+        );
+
+        // the job, and so the guard, is dropped with the entry
+        async_std::task::block_on(async {
+            assert!(stopped.await.is_err());
+        });
+
+        assert!(!handle.is_pending());
+    }
+}
@@ -0,0 +1,71 @@
+use futures::{channel::mpsc::UnboundedReceiver as MRecv, Stream};
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+/// # SubscriptionId
+///
+/// Identifies a listener registered with `EventEmitter::on`, so it can be
+/// removed again with `EventEmitter::off`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(pub(crate) u64);
+
+/// # Subscription
+///
+/// A guard returned by `EventEmitter::subscribe`. The listener is removed when
+/// the guard is dropped, unless it is `detach`ed first.
+#[must_use = "dropping a Subscription immediately removes its listener"]
+#[derive(Debug)]
+pub struct Subscription {
+    pub(crate) id: SubscriptionId,
+    pub(crate) alive: Arc<AtomicBool>,
+}
+
+impl Subscription {
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+
+    /// Keep the listener registered after the guard is dropped. It can still be
+    /// removed with `EventEmitter::off`.
+    pub fn detach(self) -> SubscriptionId {
+        let id = self.id;
+        std::mem::forget(self);
+        id
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.alive.store(false, Ordering::Release);
+    }
+}
+
+/// # EventStream
+///
+/// The messages of an event, returned by `EventEmitter::stream`. The listener
+/// behind the stream is removed when it is dropped.
+#[must_use = "streams do nothing unless polled"]
+pub struct EventStream<M> {
+    pub(crate) receiver: MRecv<Arc<M>>,
+    pub(crate) subscription: Subscription,
+}
+
+impl<M> EventStream<M> {
+    pub fn id(&self) -> SubscriptionId {
+        self.subscription.id
+    }
+}
+
+impl<M> Stream for EventStream<M> {
+    type Item = Arc<M>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Arc<M>>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
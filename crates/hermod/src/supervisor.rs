@@ -0,0 +1,425 @@
+use crate::{runtime, Backoff, CancellationToken, Error, Sender};
+use futures::{
+    channel::mpsc::UnboundedReceiver as MRecv,
+    future::{self, Either},
+    pin_mut,
+};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "events")]
+use crate::{Event, EventEmitter};
+
+type Factory<T, R> = Box<dyn Fn() -> Sender<T, R> + Send + Sync>;
+type LifecycleHandler = Box<dyn Fn(&WorkerEvent) + Send + Sync>;
+
+/// # RestartPolicy
+///
+/// When a `Supervisor` restarts a worker whose task stopped.
+///
+/// ```
+/// use hermod::{Backoff, RestartPolicy};
+/// use std::time::Duration;
+///
+/// // up to 5 restarts per minute, waiting ~100ms, ~200ms, ... before each
+/// let policy = RestartPolicy::new()
+///     .with_max_restarts(5, Duration::from_secs(60))
+///     .with_backoff(Backoff::Exponential {
+///         initial: Duration::from_millis(100),
+///         max: Duration::from_secs(5),
+///     });
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RestartPolicy {
+    max_restarts: Option<(u32, Duration)>,
+    backoff: Backoff,
+}
+
+impl RestartPolicy {
+    /// Always restart, right away
+    pub fn new() -> Self {
+        Self {
+            max_restarts: None,
+            backoff: Backoff::Fixed(Duration::ZERO),
+        }
+    }
+
+    /// Give up on a worker restarted `count` times within `period`
+    pub fn with_max_restarts(mut self, count: u32, period: Duration) -> Self {
+        self.max_restarts = Some((count, period));
+        self
+    }
+
+    /// Wait before restarting. The delay grows with the restarts within the period of
+    /// `with_max_restarts`, or with every restart without one.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// # WorkerEvent
+///
+/// What happened to a worker of a `Supervisor`, see `Supervisor::on_lifecycle`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WorkerEvent {
+    /// The task of the worker stopped
+    Stopped { worker: String },
+    /// The worker was restarted, for the `restarts`th time
+    Restarted { worker: String, restarts: u32 },
+    /// The worker stopped too often, it is not restarted anymore
+    GaveUp { worker: String },
+}
+
+/// The lifecycle event of the workers of a `Supervisor`, see `Supervisor::with_emitter`
+#[cfg(feature = "events")]
+pub struct WorkerLifecycle;
+
+#[cfg(feature = "events")]
+impl Event for WorkerLifecycle {
+    type Message = WorkerEvent;
+}
+
+struct Worker<T, R>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+{
+    name: String,
+    factory: Factory<T, R>,
+    sender: RwLock<Arc<Sender<T, R>>>,
+}
+
+impl<T, R> Worker<T, R>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+{
+    fn current(&self) -> Arc<Sender<T, R>> {
+        Arc::clone(&self.sender.read().unwrap())
+    }
+}
+
+/// What the workers' monitors share with the supervisor
+struct Shared {
+    policy: RestartPolicy,
+    token: CancellationToken,
+    handlers: RwLock<Vec<LifecycleHandler>>,
+    #[cfg(feature = "events")]
+    emitter: RwLock<Option<Arc<EventEmitter>>>,
+}
+
+impl Shared {
+    async fn notify(&self, event: WorkerEvent) {
+        for handler in self.handlers.read().unwrap().iter() {
+            handler(&event);
+        }
+
+        #[cfg(feature = "events")]
+        {
+            let emitter = self.emitter.read().unwrap().clone();
+
+            if let Some(emitter) = emitter {
+                emitter.emit::<WorkerLifecycle>(event).await;
+            }
+        }
+    }
+}
+
+/// # Supervisor
+///
+/// Owns the tasks of one or more `Sender`s, its workers, and restarts them with a
+/// `RestartPolicy` when they stop, e.g. because their executor dropped them or they
+/// panicked outside of a handler. A worker is created by a factory, which is called
+/// again to restart it, so its data starts over.
+///
+/// Events emitted to a worker while it is stopped fail like on a closed `Sender`.
+///
+/// A handler panicking does not stop its worker, so it is not restarted: the queue
+/// catches the panic and keeps going, with its data, see `Sender::set_panic_handler`.
+///
+/// ```
+/// use futures::future::BoxFuture;
+/// use hermod::{RestartPolicy, Sender, Supervisor, WorkerEvent};
+/// use std::sync::{
+///     atomic::{AtomicUsize, Ordering},
+///     Arc, Mutex,
+/// };
+/// use async_std::stream::StreamExt;
+///
+/// static STARTS: AtomicUsize = AtomicUsize::new(0);
+///
+/// // the first task of the worker is lost
+/// let spawner = |task: BoxFuture<'static, ()>| {
+///     if STARTS.fetch_add(1, Ordering::SeqCst) > 0 {
+///         async_std::task::spawn(task);
+///     }
+/// };
+///
+/// let events = Arc::new(Mutex::new(vec![]));
+/// let seen = Arc::clone(&events);
+///
+/// let supervisor = Supervisor::new(RestartPolicy::new())
+///     .on_lifecycle(move |event| seen.lock().unwrap().push(event.clone()))
+///     .supervise("doubler", move || {
+///         Sender::<u32, u32>::new_on(spawner, |n, _| Box::pin(async move { n * 2 }), ())
+///     });
+///
+/// async_std::task::block_on(async {
+///     while events.lock().unwrap().len() < 2 {
+///         async_std::task::yield_now().await;
+///     }
+///
+///     let mut response = supervisor.emit(21u32).await.unwrap();
+///     assert_eq!(response.next().await, Some(42));
+/// });
+///
+/// assert_eq!(*events.lock().unwrap(), [
+///     WorkerEvent::Stopped { worker: String::from("doubler") },
+///     WorkerEvent::Restarted { worker: String::from("doubler"), restarts: 1 },
+/// ]);
+/// ```
+pub struct Supervisor<T, R>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+{
+    workers: Vec<Arc<Worker<T, R>>>,
+    shared: Arc<Shared>,
+    // the worker `emit` sends the next event to
+    next: AtomicUsize,
+}
+
+impl<T, R> Supervisor<T, R>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+{
+    pub fn new(policy: RestartPolicy) -> Self {
+        Self {
+            workers: vec![],
+            shared: Arc::new(Shared {
+                policy,
+                token: CancellationToken::new(),
+                handlers: RwLock::new(vec![]),
+                #[cfg(feature = "events")]
+                emitter: RwLock::new(None),
+            }),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Call `handler` when a worker stops, is restarted, or is given up on
+    pub fn on_lifecycle(self, handler: impl Fn(&WorkerEvent) + Send + Sync + 'static) -> Self {
+        self.shared
+            .handlers
+            .write()
+            .unwrap()
+            .push(Box::new(handler));
+        self
+    }
+
+    /// Emit `WorkerLifecycle` on `emitter` when a worker stops, is restarted, or is
+    /// given up on
+    #[cfg(feature = "events")]
+    pub fn with_emitter(self, emitter: Arc<EventEmitter>) -> Self {
+        *self.shared.emitter.write().unwrap() = Some(emitter);
+        self
+    }
+
+    /// Start a worker created by `factory`, and restart it when it stops
+    pub fn supervise(
+        mut self,
+        name: impl Into<String>,
+        factory: impl Fn() -> Sender<T, R> + Send + Sync + 'static,
+    ) -> Self {
+        let worker = Arc::new(Worker {
+            name: name.into(),
+            sender: RwLock::new(Arc::new(factory())),
+            factory: Box::new(factory),
+        });
+
+        runtime::spawn(Box::pin(monitor(
+            Arc::clone(&self.shared),
+            Arc::clone(&worker),
+        )));
+
+        self.workers.push(worker);
+        self
+    }
+
+    /// The current `Sender` of the worker `name`
+    pub fn worker(&self, name: &str) -> Option<Arc<Sender<T, R>>> {
+        self.workers
+            .iter()
+            .find(|n| n.name == name)
+            .map(|n| n.current())
+    }
+
+    /// Send `event` to the next worker, round-robin, see `Sender::emit`. Fails with
+    /// `Error::Closed` if there are no workers.
+    pub async fn emit(&self, event: impl Into<T>) -> Result<MRecv<R>, Error<T>> {
+        if self.workers.is_empty() {
+            return Err(Error::Closed(event.into()));
+        }
+
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.workers.len();
+        self.workers[index].current().emit(event).await
+    }
+
+    /// Stop restarting the workers, and shut them down, see `Sender::shutdown`
+    pub async fn shutdown(&self) {
+        self.shared.token.cancel();
+        future::join_all(
+            self.workers
+                .iter()
+                .map(|n| async move { n.current().shutdown().await }),
+        )
+        .await;
+    }
+}
+
+impl<T, R> Drop for Supervisor<T, R>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        // the monitors stop, dropping the workers
+        self.shared.token.cancel();
+    }
+}
+
+/// Restart `worker` whenever its task stops, until the supervisor is shut down
+async fn monitor<T, R>(shared: Arc<Shared>, worker: Arc<Worker<T, R>>)
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+{
+    let mut recent = VecDeque::<Instant>::new();
+    let mut restarts = 0;
+
+    loop {
+        {
+            let sender = worker.current();
+            let stopped = sender.stopped();
+            let cancelled = shared.token.cancelled();
+            pin_mut!(stopped, cancelled);
+
+            if let Either::Right(_) = future::select(stopped, cancelled).await {
+                return;
+            }
+        }
+
+        if shared.token.is_cancelled() {
+            return;
+        }
+
+        let name = worker.name.clone();
+        shared
+            .notify(WorkerEvent::Stopped {
+                worker: name.clone(),
+            })
+            .await;
+
+        // the restarts the backoff grows with
+        let attempt = match shared.policy.max_restarts {
+            Some((count, period)) => {
+                let now = Instant::now();
+                recent.retain(|n| now.duration_since(*n) < period);
+
+                if recent.len() >= count as usize {
+                    shared.notify(WorkerEvent::GaveUp { worker: name }).await;
+                    return;
+                }
+
+                recent.push_back(now);
+                recent.len() as u32
+            }
+            None => restarts + 1,
+        };
+
+        let delay = runtime::sleep(shared.policy.backoff.delay(attempt));
+        let cancelled = shared.token.cancelled();
+        pin_mut!(delay, cancelled);
+
+        if let Either::Right(_) = future::select(delay, cancelled).await {
+            return;
+        }
+
+        *worker.sender.write().unwrap() = Arc::new((worker.factory)());
+        restarts += 1;
+
+        shared
+            .notify(WorkerEvent::Restarted {
+                worker: name,
+                restarts,
+            })
+            .await;
+    }
+}
+
+#[cfg(all(test, feature = "async-std", not(feature = "tokio")))]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::sync::Mutex;
+
+    #[test]
+    fn emitting_without_workers_fails() {
+        let supervisor = Supervisor::<u32, u32>::new(RestartPolicy::new());
+        let failed = async_std::task::block_on(supervisor.emit(1u32));
+
+        assert!(matches!(failed, Err(Error::Closed(1))));
+    }
+
+    #[test]
+    fn panicking_handlers_do_not_restart_the_worker() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let seen = Arc::clone(&events);
+
+        let supervisor = Supervisor::new(RestartPolicy::new())
+            .on_lifecycle(move |event| seen.lock().unwrap().push(event.clone()))
+            .supervise("doubler", || {
+                let sender = Sender::<u32, u32>::new(
+                    |n, _| {
+                        Box::pin(async move {
+                            assert_ne!(n, 0);
+                            n * 2
+                        })
+                    },
+                    (),
+                );
+
+                sender.set_panic_handler(|_, _| {});
+                sender
+            });
+
+        let worker = supervisor.worker("doubler").unwrap();
+
+        async_std::task::block_on(async {
+            let mut failed = supervisor.emit(0u32).await.unwrap();
+            assert_eq!(failed.next().await, None);
+
+            let mut response = supervisor.emit(21u32).await.unwrap();
+            assert_eq!(response.next().await, Some(42));
+        });
+
+        // the same queue handled both
+        assert!(Arc::ptr_eq(&worker, &supervisor.worker("doubler").unwrap()));
+        assert_eq!(worker.metrics().panics, 1);
+        assert!(events.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use crate::Event;
+use std::{any::Any, sync::Arc};
+
+/// # Published
+///
+/// A message published on a topic, as passed to the listeners registered with
+/// `EventEmitter::on_topic`.
+pub struct Published {
+    pub topic: String,
+    /// The payload, of whatever type the publisher chose
+    pub payload: Arc<dyn Any + Send + Sync>,
+}
+
+impl Published {
+    /// The payload, if it is an `M`
+    pub fn downcast<M: Any + Send + Sync>(&self) -> Option<Arc<M>> {
+        Arc::clone(&self.payload).downcast().ok()
+    }
+}
+
+/// Topic listeners are registered as listeners of this event
+pub(crate) struct Topics;
+
+impl Event for Topics {
+    type Message = Published;
+}
+
+/// Whether `topic` matches `pattern`. Both are split into dot-separated segments, `*`
+/// matches a single segment and `**` any number of them (including none).
+pub(crate) fn matches(pattern: &str, topic: &str) -> bool {
+    let pattern = pattern.split('.').collect::<Vec<_>>();
+    let topic = topic.split('.').collect::<Vec<_>>();
+
+    matches_segments(&pattern, &topic)
+}
+
+fn matches_segments(pattern: &[&str], topic: &[&str]) -> bool {
+    match (pattern.first(), topic.first()) {
+        (None, None) => true,
+        (Some(&"**"), _) => {
+            matches_segments(&pattern[1..], topic)
+                || (!topic.is_empty() && matches_segments(pattern, &topic[1..]))
+        }
+        (Some(&"*"), Some(_)) => matches_segments(&pattern[1..], &topic[1..]),
+        (Some(p), Some(t)) if p == t => matches_segments(&pattern[1..], &topic[1..]),
+        _ => false,
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{
+    metrics::QueueStats,
+    queue::{Message, OnHandled},
+};
+use futures::channel::mpsc::{self, UnboundedSender as MSend};
+use log::{error, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// The events that were never acknowledged, by sequence number
+pub(crate) type Pending = BTreeMap<u64, Value>;
+
+/// How often an event is handled before it is moved to the dead letters, if its
+/// handler keeps panicking
+const ATTEMPTS: u32 = 3;
+
+/// A line of the log
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Record {
+    Event { seq: u64, event: Value },
+    Ack { seq: u64 },
+}
+
+/// Where a durable queue writes its events, see `Sender::durable`
+pub(crate) trait Journal<T>: Send + Sync {
+    /// Persist `event`, returns what to call once it was handled
+    fn append(self: Arc<Self>, event: &T) -> io::Result<OnHandled>;
+}
+
+struct Log {
+    file: File,
+    next_seq: u64,
+    // events appended but not acknowledged yet
+    pending: HashSet<u64>,
+}
+
+/// # Wal
+///
+/// A write-ahead log of JSON lines. Events are appended (and synced) before they are
+/// queued, and acknowledged once handled. Opening the log returns the events that
+/// were never acknowledged, and compacts it.
+pub(crate) struct Wal {
+    log: Mutex<Log>,
+    // where the events that cannot be handled are moved
+    dead: PathBuf,
+}
+
+impl Wal {
+    /// Open the log at `path`, returning it with the unacknowledged events, in order
+    pub(crate) fn open(path: &Path) -> io::Result<(Arc<Self>, Pending)> {
+        let mut events = BTreeMap::new();
+
+        match File::open(path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    // a crash while appending leaves a truncated last line
+                    match serde_json::from_str(&line?) {
+                        Ok(Record::Event { seq, event }) => {
+                            events.insert(seq, event);
+                        }
+                        Ok(Record::Ack { seq }) => {
+                            events.remove(&seq);
+                        }
+                        Err(e) => warn!("Skipping corrupt record in {}: {e}", path.display()),
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        // rewrite the log with only the pending events, then swap it in
+        let compacted = PathBuf::from(format!("{}.tmp", path.display()));
+        let mut file = File::create(&compacted)?;
+
+        for (&seq, event) in &events {
+            let event = event.clone();
+            writeln!(
+                file,
+                "{}",
+                serde_json::to_string(&Record::Event { seq, event })?
+            )?;
+        }
+
+        file.sync_all()?;
+        fs::rename(&compacted, path)?;
+
+        let file = OpenOptions::new().append(true).open(path)?;
+        let next_seq = events.keys().next_back().map_or(0, |n| n + 1);
+
+        let wal = Arc::new(Self {
+            log: Mutex::new(Log {
+                file,
+                next_seq,
+                pending: events.keys().copied().collect(),
+            }),
+            dead: PathBuf::from(format!("{}.dead", path.display())),
+        });
+
+        Ok((wal, events))
+    }
+
+    /// Append `event`, returning its sequence number
+    fn append(&self, event: Value) -> io::Result<u64> {
+        let mut log = self.log.lock().unwrap();
+        let seq = log.next_seq;
+
+        let line = serde_json::to_string(&Record::Event { seq, event })?;
+        writeln!(log.file, "{line}")?;
+        log.file.sync_data()?;
+
+        log.next_seq += 1;
+        log.pending.insert(seq);
+        Ok(seq)
+    }
+
+    /// Acknowledge the event `seq`, logging a failure
+    fn acknowledge(&self, seq: u64) {
+        if let Err(e) = self.ack(seq) {
+            // the event is handled again after a restart
+            error!("Failed to acknowledge queued event: {e}");
+        }
+    }
+
+    /// Move the event `seq` to the dead letters, as it cannot be handled because of
+    /// `reason`
+    fn bury(&self, seq: u64, event: Value, reason: &str) {
+        error!("Moving queued event to {}, {reason}", self.dead.display());
+
+        let line = json!({ "seq": seq, "event": event, "reason": reason });
+        let buried = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.dead)
+            .and_then(|mut file| writeln!(file, "{line}"));
+
+        match buried {
+            Ok(()) => self.acknowledge(seq),
+            // kept in the log, and tried again after a restart
+            Err(e) => error!("Failed to write {}: {e}", self.dead.display()),
+        }
+    }
+
+    fn ack(&self, seq: u64) -> io::Result<()> {
+        let mut log = self.log.lock().unwrap();
+        log.pending.remove(&seq);
+
+        // nothing is pending, so the whole log can go
+        if log.pending.is_empty() {
+            return log.file.set_len(0);
+        }
+
+        let line = serde_json::to_string(&Record::Ack { seq })?;
+        writeln!(log.file, "{line}")
+    }
+}
+
+/// # Durable
+///
+/// The journal of a durable queue: its log, and the queue itself, to queue the events
+/// of the log again
+pub(crate) struct Durable<T, R> {
+    wal: Arc<Wal>,
+    queue: MSend<Message<T, R>>,
+    stats: Arc<QueueStats>,
+}
+
+impl<T, R> Durable<T, R>
+where
+    T: DeserializeOwned + Send + 'static,
+    R: Send + 'static,
+{
+    pub(crate) fn new(
+        wal: Arc<Wal>,
+        queue: MSend<Message<T, R>>,
+        stats: Arc<QueueStats>,
+    ) -> Arc<Self> {
+        Arc::new(Self { wal, queue, stats })
+    }
+
+    /// Queue the logged `event` for its `attempt`, unless it cannot be read back
+    pub(crate) fn requeue(self: &Arc<Self>, seq: u64, event: Value, attempt: u32) {
+        match serde_json::from_value::<T>(event.clone()) {
+            Ok(decoded) => {
+                let on_handled = self.on_handled(seq, event, attempt);
+                let message = Message::Event(decoded, mpsc::unbounded().0, Some(on_handled));
+
+                self.stats.queued();
+                let _ = self.queue.unbounded_send(message);
+            }
+            Err(e) => self
+                .wal
+                .bury(seq, event, &format!("it cannot be read: {e}")),
+        }
+    }
+
+    /// Acknowledge the event `seq` once handled, or retry it if its handler panicked
+    fn on_handled(self: &Arc<Self>, seq: u64, event: Value, attempt: u32) -> OnHandled {
+        let durable = Arc::clone(self);
+
+        Box::new(move |panicked| match panicked {
+            false => durable.wal.acknowledge(seq),
+            true if attempt < ATTEMPTS => durable.requeue(seq, event, attempt + 1),
+            true => durable.wal.bury(
+                seq,
+                event,
+                &format!("its handler panicked {ATTEMPTS} times"),
+            ),
+        })
+    }
+}
+
+impl<T, R> Journal<T> for Durable<T, R>
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+    R: Send + 'static,
+{
+    fn append(self: Arc<Self>, event: &T) -> io::Result<OnHandled> {
+        let event = serde_json::to_value(event)?;
+        let seq = self.wal.append(event.clone())?;
+
+        Ok(self.on_handled(seq, event, 1))
+    }
+}
+
+#[cfg(all(test, feature = "async-std", not(feature = "tokio")))]
+mod tests {
+    use crate::Sender;
+    use std::{
+        fs,
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// A log path of its own for each test
+    fn log_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("hermod-{name}-{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(path.with_extension("log.dead"));
+        path
+    }
+
+    #[test]
+    fn unreadable_events_are_moved_to_the_dead_letters() {
+        let path = log_path("unreadable");
+
+        fs::write(
+            &path,
+            concat!(
+                r#"{"op":"event","seq":0,"event":"not a number"}"#,
+                "\n",
+                r#"{"op":"event","seq":1,"event":7}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let queue =
+            Sender::<u32, ()>::durable(&path, |n, _| Box::pin(async move { assert_eq!(n, 7) }), ())
+                .unwrap();
+
+        async_std::task::block_on(queue.drain());
+        assert_eq!(queue.metrics().handled, 1);
+
+        let dead = fs::read_to_string(path.with_extension("log.dead")).unwrap();
+        assert!(dead.contains("not a number"));
+
+        // both events are gone from the log
+        drop(queue);
+        let queue = Sender::<u32, ()>::durable(&path, |_, _| Box::pin(async {}), ()).unwrap();
+        async_std::task::block_on(queue.drain());
+        assert_eq!(queue.metrics().handled, 0);
+    }
+
+    #[test]
+    fn panicking_events_are_retried() {
+        static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+        let path = log_path("panicking");
+
+        let queue = Sender::<u32, ()>::durable(
+            &path,
+            |_, _| {
+                Box::pin(async {
+                    ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+                    panic!("always");
+                })
+            },
+            (),
+        )
+        .unwrap();
+
+        queue.set_panic_handler(|_, _| {});
+
+        async_std::task::block_on(async {
+            queue.try_emit(1u32).unwrap();
+
+            // each retry is queued before the attempt before it counts as handled, and
+            // the last attempt moved to the dead letters
+            while queue.metrics().handled < super::ATTEMPTS as u64 {
+                queue.drain().await;
+            }
+        });
+
+        assert_eq!(ATTEMPTS.load(Ordering::SeqCst), super::ATTEMPTS as usize);
+
+        let dead = fs::read_to_string(path.with_extension("log.dead")).unwrap();
+        assert!(dead.contains("panicked"));
+    }
+}
@@ -0,0 +1,78 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use mimir::{Cache, Item};
+use serde::{Deserialize, Serialize};
+use std::hash::BuildHasher;
+
+const ENTRIES: u64 = 1_000_000;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct Entry {
+    id: u64,
+}
+
+impl Item for Entry {
+    type Key = u64;
+    const TYPE_KEY: &'static str = "struct Entry";
+
+    fn key(&self) -> Self::Key {
+        self.id
+    }
+}
+
+fn filled<H: BuildHasher + Clone + Send + Sync + 'static>(hasher: H) -> Cache<H> {
+    let mut cache = Cache::with_hasher(hasher);
+
+    for id in 0..ENTRIES {
+        cache.insert(Entry { id });
+    }
+
+    cache
+}
+
+fn bench_hasher<H: BuildHasher + Clone + Send + Sync + 'static>(
+    c: &mut Criterion,
+    name: &str,
+    hasher: H,
+) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+
+    group.bench_function("insert 1M", |b| {
+        b.iter_batched(
+            || Cache::with_hasher(hasher.clone()),
+            |mut cache| {
+                for id in 0..ENTRIES {
+                    cache.insert(Entry { id });
+                }
+
+                cache
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let cache = filled(hasher);
+
+    group.bench_function("get 1M", |b| {
+        b.iter(|| {
+            for id in 0..ENTRIES {
+                black_box(cache.get::<Entry>(id));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    bench_hasher(c, "siphash", std::collections::hash_map::RandomState::new());
+
+    #[cfg(feature = "ahash")]
+    bench_hasher(c, "ahash", ahash::RandomState::new());
+
+    #[cfg(feature = "fxhash")]
+    bench_hasher(c, "fxhash", fxhash::FxBuildHasher::default());
+}
+
+criterion_group!(cache, benches);
+criterion_main!(cache);
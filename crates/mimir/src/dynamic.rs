@@ -0,0 +1,65 @@
+use crate::{expired, Cache};
+use serde_json::Value;
+use std::hash::BuildHasher;
+
+impl<H: BuildHasher + Clone + Send + Sync + 'static> Cache<H> {
+    /// Look up an entry by type key, with the key given as JSON, without knowing
+    /// its type at compile time. Meant for admin and debugging tools.
+    ///
+    /// Entries that are still serialized are looked up without materializing them,
+    /// and lookups do not count as accesses (see `with_access_tracking`).
+    ///
+    /// ```
+    /// # use serde::{Deserialize, Serialize};
+    /// # use mimir::{Cache, Item};
+    /// #
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct User {
+    /// # 	id: u32,
+    /// # 	name: String,
+    /// # }
+    /// #
+    /// # impl Item for User {
+    /// # 	type Key = u32;
+    /// # 	const TYPE_KEY: &'static str = "struct User";
+    /// #
+    /// # 	fn key(&self) -> Self::Key {
+    /// # 		self.id
+    /// # 	}
+    /// # }
+    /// #
+    /// let mut cache = Cache::new();
+    /// cache.insert(User { id: 7, name: "Ada".to_string() });
+    ///
+    /// let user = cache.get_dynamic("struct User", "7").unwrap();
+    /// assert_eq!(user["name"], "Ada");
+    /// ```
+    pub fn get_dynamic(&self, type_key: &str, key_json: &str) -> Option<Value> {
+        let key = serde_json::from_str(key_json).ok()?;
+
+        self.items
+            .get(type_key)?
+            .get_dynamic(key)
+            .filter(|(_, inserted)| !expired(self.ttl, *inserted))
+            .map(|(item, _)| item)
+    }
+
+    /// Iterate over the (unexpired) entries of a type key as `(key, item)` JSON pairs.
+    /// See `get_dynamic`.
+    ///
+    /// For entries that are still serialized, keys that look like numbers or booleans
+    /// are reported as such, even if they were strings.
+    pub fn iter_dynamic(&self, type_key: &str) -> impl Iterator<Item = (Value, Value)> + '_ {
+        self.items
+            .get(type_key)
+            .into_iter()
+            .flat_map(|n| n.iter_dynamic())
+            .filter(|(_, _, inserted)| !expired(self.ttl, *inserted))
+            .map(|(key, item, _)| (key, item))
+    }
+
+    /// Every type key in the cache, including ones that are still serialized
+    pub fn type_keys(&self) -> impl Iterator<Item = &str> + '_ {
+        self.items.keys().map(String::as_str)
+    }
+}
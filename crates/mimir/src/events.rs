@@ -0,0 +1,159 @@
+#[cfg(feature = "events")]
+use crate::Cache;
+#[cfg(feature = "events")]
+use hermod::Sender;
+#[cfg(feature = "events")]
+use std::sync::Arc;
+
+/// # ChangeKind
+///
+/// What happened to the entry in a `CacheEvent`.
+#[cfg(feature = "events")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Inserted, or replaced an entry with the same key
+    Insert,
+    /// Removed explicitly, through `take`
+    Remove,
+    /// Evicted to fit the capacity, or removed because it expired
+    Evict,
+}
+
+/// # CacheEvent
+///
+/// Sent to the `Sender` registered with `Cache::with_events` whenever an entry
+/// changes. The key is serialized, as listeners cannot know its type.
+#[cfg(feature = "events")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEvent {
+    pub kind: ChangeKind,
+    pub type_key: String,
+    pub key: serde_json::Value,
+}
+
+/// Sends `CacheEvent`s, if enabled. Without the `events` feature, this does nothing.
+#[derive(Default)]
+pub(crate) struct Notifier {
+    #[cfg(feature = "events")]
+    sender: Option<Arc<Sender<CacheEvent, ()>>>,
+}
+
+#[cfg_attr(not(feature = "events"), allow(unused_variables))]
+impl Notifier {
+    pub(crate) fn inserted(&self, type_key: &str, key: &dyn erased_serde::Serialize) {
+        #[cfg(feature = "events")]
+        self.notify(ChangeKind::Insert, type_key, key);
+    }
+
+    pub(crate) fn removed(&self, type_key: &str, key: &dyn erased_serde::Serialize) {
+        #[cfg(feature = "events")]
+        self.notify(ChangeKind::Remove, type_key, key);
+    }
+
+    pub(crate) fn evicted(&self, type_key: &str, key: &dyn erased_serde::Serialize) {
+        #[cfg(feature = "events")]
+        self.notify(ChangeKind::Evict, type_key, key);
+    }
+
+    #[cfg(feature = "events")]
+    fn notify(&self, kind: ChangeKind, type_key: &str, key: &dyn erased_serde::Serialize) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        let Ok(key) = serde_json::to_value(key) else {
+            return;
+        };
+
+        // nobody is listening anymore, which is not the cache's problem
+        let _ = sender.try_emit(CacheEvent {
+            kind,
+            type_key: type_key.to_string(),
+            key,
+        });
+    }
+}
+
+#[cfg(feature = "events")]
+impl<H> Cache<H> {
+    /// Send a `CacheEvent` to `sender` for every insertion, removal and eviction.
+    /// Dropping whole type keys (e.g. `remove_type_key`) does not send events.
+    ///
+    /// ```no_run
+    /// use hermod::Sender;
+    /// use mimir::{Cache, CacheEvent};
+    /// use std::sync::Arc;
+    ///
+    /// let sender = Arc::new(Sender::new(
+    /// 	|event: CacheEvent, _| {
+    /// 		Box::pin(async move {
+    /// 			println!("{:?} {} {}", event.kind, event.type_key, event.key);
+    /// 		})
+    /// 	},
+    /// 	(),
+    /// ));
+    ///
+    /// let cache = Cache::new().with_events(sender);
+    /// ```
+    pub fn with_events(mut self, sender: Arc<Sender<CacheEvent, ()>>) -> Self {
+        self.events.sender = Some(sender);
+        self
+    }
+}
+
+#[cfg(all(test, feature = "events"))]
+mod tests {
+    use super::{CacheEvent, ChangeKind};
+    use crate::{Cache, Item};
+    use hermod::Sender;
+    use serde::{Deserialize, Serialize};
+    use std::{
+        sync::{mpsc, Arc},
+        time::Duration,
+    };
+
+    #[derive(Serialize, Deserialize)]
+    struct Entry(u32);
+
+    impl Item for Entry {
+        type Key = u32;
+        const TYPE_KEY: &'static str = "struct Entry";
+
+        fn key(&self) -> Self::Key {
+            self.0
+        }
+    }
+
+    #[test]
+    fn changes_are_sent_in_order() {
+        let (events, received) = mpsc::sync_channel(4);
+        let sender = Sender::new(
+            |event: CacheEvent, events: &mut mpsc::SyncSender<CacheEvent>| {
+                events.send(event).unwrap();
+                Box::pin(async {})
+            },
+            events,
+        );
+
+        let mut cache = Cache::new().with_capacity(1).with_events(Arc::new(sender));
+        cache.insert(Entry(1));
+        cache.insert(Entry(2));
+        cache.take::<Entry>(2);
+
+        let expected = [
+            (ChangeKind::Insert, 1),
+            (ChangeKind::Insert, 2),
+            (ChangeKind::Evict, 1),
+            (ChangeKind::Remove, 2),
+        ];
+
+        for (kind, key) in expected {
+            let event = received.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(
+                (event.kind, event.type_key.as_str()),
+                (kind, "struct Entry")
+            );
+            assert_eq!(event.key, key);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{store::Slot, Cache, FormatMismatch, PersistError, VERSION_KEY};
+use serde_json::{Map, Value};
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::BuildHasher,
+    io::{Read, Write},
+};
+
+/// # ImportIssue
+///
+/// A type key that could not be loaded by `Cache::import`, along with the reason.
+#[derive(Debug)]
+pub struct ImportIssue {
+    pub type_key: String,
+    pub error: serde_json::Error,
+}
+
+impl fmt::Display for ImportIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to load \"{}\": {}", self.type_key, self.error)
+    }
+}
+
+/// # Import
+///
+/// The result of a lenient import: everything that could be loaded, plus the
+/// issues for everything that could not.
+pub struct Import<H = RandomState> {
+    pub cache: Cache<H>,
+    pub issues: Vec<ImportIssue>,
+}
+
+impl<H> Import<H> {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl<H> Cache<H> {
+    /// Write the cache as indented JSON, grouped by type key. Meant for
+    /// inspecting cache contents by hand; `import` can read it back.
+    pub fn export_pretty(&self, writer: impl Write) -> Result<(), PersistError> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
+
+impl<H: BuildHasher + Clone + Default + Send + Sync + 'static> Cache<H> {
+    /// Leniently load a JSON cache (as written by `export_pretty` or `serde_json`).
+    ///
+    /// Unlike deserializing a `Cache` directly, a type key that fails to load
+    /// (e.g. not a map of entries) does not fail the whole import. It is skipped
+    /// and reported in `Import::issues` instead.
+    ///
+    /// The format version is still checked up front, a mismatch fails the import.
+    ///
+    /// ```
+    /// use mimir::{Cache, Import};
+    ///
+    /// let json = r#"{ "$mimir": 3, "struct Missing": 42 }"#;
+    /// let import: Import = Cache::import(json.as_bytes()).unwrap();
+    ///
+    /// assert_eq!(import.issues.len(), 1);
+    /// assert_eq!(import.issues[0].type_key, "struct Missing");
+    /// ```
+    pub fn import(reader: impl Read) -> Result<Import<H>, PersistError> {
+        let mut entries: Map<String, Value> = serde_json::from_reader(reader)?;
+
+        let version = entries
+            .remove(VERSION_KEY)
+            .and_then(|v| v.as_u64())
+            .and_then(|v| u32::try_from(v).ok())
+            .unwrap_or(0);
+
+        FormatMismatch::check(version)?;
+
+        let mut cache = Cache::with_hasher(H::default());
+        let mut issues = vec![];
+
+        for (type_key, value) in entries {
+            match serde_json::from_value::<Map<String, Value>>(value) {
+                Ok(items) => {
+                    cache
+                        .items
+                        .insert(type_key, Slot::serialized(Value::Object(items)));
+                }
+                Err(error) => issues.push(ImportIssue { type_key, error }),
+            }
+        }
+
+        Ok(Import { cache, issues })
+    }
+}
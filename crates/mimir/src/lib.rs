@@ -2,6 +2,8 @@
 //!
 //! A serializable, multi-type cache.
 //!
+//! Builds on stable Rust (MSRV 1.70).
+//!
 //! ```
 //! use serde::{Deserialize, Serialize};
 //! use mimir::{Cache, Item};
@@ -53,12 +55,183 @@
 //! assert_eq!(Some(b), dser.copied::<SomeStruct>(b.id));
 //! assert_eq!(Some(c), dser.cloned::<SomeOtherStruct>(c.id));
 //! ```
+//!
+//! ## Persistence
+//!
+//! `Cache::save` and `Cache::load` write and read the cache through any `Write`/`Read`.
+//! The payload can optionally be compressed (`compression` feature, zstd) and/or encrypted
+//! (`encryption` feature, XChaCha20-Poly1305) using `PersistOptions`. Loading detects
+//! both automatically, only the key must be supplied for encrypted caches.
+//!
+//! For very large caches, `Cache::load_streaming` decodes and deserializes while reading
+//! instead of buffering the whole file first.
+//!
+//! `Cache::save_dir` and `Cache::load_dir` instead write one file per type key. Each type
+//! is read on first access, and saving only rewrites the types that changed.
+//!
+//! Applications can keep their cache in the platform cache directory with
+//! `Cache::persistent`, which loads it if it exists and saves it when dropped. A file
+//! that cannot be loaded is replaced by an empty cache, see `PersistentCache::load_error`.
+//!
+//! `TieredCache` puts a `Cache` in front of a slower `Backend`, e.g. a directory
+//! (`DirBackend`) or a Redis server. Missing entries are read through to the backend,
+//! and changes are written through right away or written back on `flush`.
+//!
+//! For debugging, `Cache::export_pretty` writes indented JSON grouped by type key, and
+//! `Cache::import` loads such a file leniently: type keys that fail to load are reported
+//! as `ImportIssue`s while everything else is still loaded.
+//!
+//! Tools that do not know every `Item` type can inspect a cache with `Cache::type_keys`,
+//! `Cache::get_dynamic` and `Cache::iter_dynamic`, which work with JSON values.
+//!
+//! Every serialized cache carries its `FORMAT_VERSION`. Reading a cache written with a
+//! different version fails with a `FormatMismatch` instead of silently mangling it.
+//!
+//! ## Maintenance
+//!
+//! Entries can expire (`Cache::with_ttl`) and the cache can be bounded (`Cache::with_capacity`).
+//! Individual types can be bounded too (`Cache::with_limit`), so one noisy type cannot
+//! evict everything else.
+//! Applications that want to control when maintenance happens can call `purge_expired`,
+//! `evict_to_capacity` and `compact` themselves. The TTL and limits are not saved with the
+//! cache, so they are set again after loading it, e.g. with `PersistentCache::configure`.
+//!
+//! `Cache::metadata` reports when an entry was created and, with `Cache::with_access_tracking`,
+//! when it was last accessed. Both are persisted with the cache.
+//!
+//! A loaded cache keeps each type's entries serialized until they are first accessed.
+//! `Cache::serialized_type_keys` lists those, `Cache::materialize`/`Cache::materialize_all`
+//! deserialize them up front, and `Cache::drop_serialized` discards type keys nobody claimed.
+//!
+//! ## Hashing
+//!
+//! The per-type maps use std's SipHash by default. `Cache::with_hasher` accepts any
+//! `BuildHasher`, and the `ahash`/`fxhash` features add the `AHashCache` and
+//! `FxHashCache` shorthands for faster hashers. Caches with any hasher can be loaded,
+//! e.g. with `AHashCache::load`.
+//!
+//! Types whose keys are `Ord` can be kept in a `BTreeMap` instead (`Cache::with_ordered`),
+//! which allows querying a range of keys with `Cache::range`.
+//!
+//! ## Sharing
+//!
+//! `Cache` is `Send + Sync`. `SharedCache` wraps one in an `Arc<RwLock<_>>`, giving a
+//! cheaply cloneable handle that can be moved into threads and async tasks.
+//!
+//! With the `events` feature, `Cache::with_events` sends a `CacheEvent` through a hermod
+//! `Sender` on every insertion, removal and eviction, so other parts of an application
+//! can react to changes without polling.
+//!
+//! With the `memoize` feature, `Memoized` answers the events sent to a hermod `Sender`
+//! from a `SharedCache` when an event with the same key was handled before, within an
+//! optional time-to-live.
+//!
+//! With the `tracing` feature, `Cache::get` and `Cache::get_mut` record each lookup as a
+//! `trace` event, with the type key of the item and whether it was a hit.
+//!
+//! ## Testing
+//!
+//! The `test-utils` feature adds `test_utils`, which generates arbitrary caches of
+//! registered types and checks that they survive a round trip through every format.
 
+extern crate erased_serde;
 extern crate serde;
-extern crate serde_traitobject as t;
+extern crate serde_json;
+extern crate thiserror;
+
+#[cfg(feature = "ahash")]
+extern crate ahash;
+#[cfg(feature = "test-utils")]
+extern crate arbitrary;
+#[cfg(feature = "encryption")]
+extern crate chacha20poly1305;
+#[cfg(feature = "memoize")]
+extern crate futures;
+#[cfg(feature = "fxhash")]
+extern crate fxhash;
+#[cfg(any(feature = "events", feature = "memoize"))]
+extern crate hermod;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "compression")]
+extern crate zstd;
+
+mod dynamic;
+mod events;
+mod export;
+mod maintenance;
+mod materialize;
+#[cfg(feature = "memoize")]
+mod memo;
+mod metadata;
+mod persist;
+mod persistent;
+mod shard;
+mod shared;
+mod store;
+mod tiered;
+
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+
+#[cfg(feature = "events")]
+pub use events::*;
+pub use export::*;
+pub use materialize::*;
+#[cfg(feature = "memoize")]
+pub use memo::*;
+pub use metadata::*;
+pub use persist::*;
+pub use persistent::*;
+pub use shared::*;
+pub use tiered::*;
+
+use events::Notifier;
+use serde::{
+    de::{self, Visitor},
+    ser::SerializeMap,
+    Deserialize, Serialize,
+};
+use std::{
+    collections::{hash_map::RandomState, BTreeMap, HashMap},
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+    ops::RangeBounds,
+    time::{Duration, SystemTime},
+};
+use store::{Entries, Entry, Slot};
+use thiserror::Error;
+
+/// Version of the serialized cache layout. Bumped whenever the layout changes
+/// in a way that older versions of mimir cannot read.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Reserved map key holding the format version in a serialized `Cache`.
+pub(crate) const VERSION_KEY: &str = "$mimir";
+
+/// # FormatMismatch
+///
+/// Returned when a serialized cache was written with a different format version.
+/// Caches written before versioning was introduced report `found: 0`.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Cache format mismatch: found version {found}, expected {expected}")]
+pub struct FormatMismatch {
+    pub found: u32,
+    pub expected: u32,
+}
 
-use serde::{de::Visitor, ser::SerializeMap, Deserialize, Serialize};
-use std::{collections::HashMap, hash::Hash};
+impl FormatMismatch {
+    pub(crate) fn check(found: u32) -> Result<(), Self> {
+        if found == FORMAT_VERSION {
+            Ok(())
+        } else {
+            Err(Self {
+                found,
+                expected: FORMAT_VERSION,
+            })
+        }
+    }
+}
 
 /// # The `Item` Trait
 ///
@@ -66,9 +239,12 @@ use std::{collections::HashMap, hash::Hash};
 ///  - The type of the key which will be used for the object
 ///  - The unique key of the type, which will be used for serialization
 ///  - A function to get the key for the object
-pub trait Item: t::Serialize + t::Deserialize + Serialize + for<'de> Deserialize<'de> {
+///
+/// Items (and their keys) must be `Send + Sync`, so that a `Cache` can be shared
+/// between threads, see `SharedCache`.
+pub trait Item: Serialize + for<'de> Deserialize<'de> + Send + Sync {
     /// Type of the key that you want to use with your object.
-    type Key: Hash + Eq + t::Serialize + t::Deserialize + Serialize + for<'de> Deserialize<'de>;
+    type Key: Hash + Eq + Serialize + for<'de> Deserialize<'de> + Send + Sync;
 
     /// The key used for serializing this type
     const TYPE_KEY: &'static str;
@@ -77,44 +253,412 @@ pub trait Item: t::Serialize + t::Deserialize + Serialize + for<'de> Deserialize
     fn key(&self) -> Self::Key;
 }
 
+/// # Occupied
+///
+/// Returned by `Cache::insert_unique` (see `UniqueError`) when an entry with the same
+/// key exists: the existing item, and the item that was not inserted.
+#[derive(Debug)]
+pub struct Occupied<'a, T> {
+    pub existing: &'a T,
+    pub item: T,
+}
+
+/// # Mismatched
+///
+/// Returned by `Cache::try_insert` and `Cache::insert_unique` when the entries stored
+/// under `T::TYPE_KEY` are not a `T`: they were inserted as another type with the same
+/// type key, or were loaded and fail to deserialize as `T`, e.g. after its fields
+/// changed. They are kept as they are, and the item is given back.
+#[derive(Debug)]
+pub struct Mismatched<T> {
+    pub type_key: &'static str,
+    pub item: T,
+}
+
+/// # UniqueError
+///
+/// Returned by `Cache::insert_unique` when the item was not inserted. Both give it back.
+#[derive(Debug)]
+pub enum UniqueError<'a, T> {
+    Occupied(Occupied<'a, T>),
+    Mismatched(Mismatched<T>),
+}
+
+impl<T> UniqueError<'_, T> {
+    /// The item that was not inserted
+    pub fn into_item(self) -> T {
+        match self {
+            UniqueError::Occupied(n) => n.item,
+            UniqueError::Mismatched(n) => n.item,
+        }
+    }
+}
+
 /// # Cache
 ///
 /// A multi-type serializable cache, using the `Item` trait.
-pub struct Cache {
-    // HashMap<TypeKey of T, HashMap<T::Key, T>>
-    items: HashMap<String, t::Box<dyn t::Any>>,
+///
+/// Optionally, entries can expire after a time-to-live (`with_ttl`) and the total
+/// number of entries can be bounded (`with_capacity`). Expired entries are hidden
+/// immediately, but only removed by `purge_expired`. When over capacity, the oldest
+/// entries are evicted first. These limits are settings, not data: they are not saved
+/// with the cache, and have to be set again on a loaded one.
+///
+/// A deserialized cache cannot know which Rust type belongs to a type key, so each
+/// type's entries stay serialized until they are first accessed as that type.
+///
+/// The per-type maps use `H` as their hasher, see `Cache::with_hasher`. Types
+/// registered with `with_ordered` use a `BTreeMap` instead, which enables `range`.
+pub struct Cache<H = RandomState> {
+    // HashMap<TypeKey of T, HashMap<T::Key, Entry<T>, H>>
+    items: HashMap<String, Slot>,
+    hasher: H,
+    ttl: Option<Duration>,
+    capacity: Option<usize>,
+    // TypeKey of T -> max number of entries of T
+    limits: HashMap<&'static str, usize>,
+    // TypeKey of T -> constructor for an empty ordered slot of T
+    ordered: HashMap<&'static str, fn() -> Slot>,
+    track_access: bool,
+    events: Notifier,
 }
 
-type InnerHashMap<T> = HashMap<<T as Item>::Key, Box<T>>;
-
 impl Cache {
     pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<H: BuildHasher + Clone + Default + Send + Sync + 'static> Default for Cache<H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+
+/// A `Cache` hashing with `ahash`
+#[cfg(feature = "ahash")]
+pub type AHashCache = Cache<ahash::RandomState>;
+
+/// A `Cache` hashing with `fxhash`. Fast, but not resistant to HashDoS.
+#[cfg(feature = "fxhash")]
+pub type FxHashCache = Cache<fxhash::FxBuildHasher>;
+
+impl<H: BuildHasher + Clone + Send + Sync + 'static> Cache<H> {
+    /// Create a cache whose per-type maps use `hasher`. The `ahash` and `fxhash`
+    /// features provide the `AHashCache` and `FxHashCache` shorthands.
+    ///
+    /// ```
+    /// use mimir::Cache;
+    /// use std::{collections::hash_map::DefaultHasher, hash::BuildHasherDefault};
+    ///
+    /// let cache = Cache::with_hasher(BuildHasherDefault::<DefaultHasher>::default());
+    /// ```
+    pub fn with_hasher(hasher: H) -> Self {
         Self {
             items: HashMap::new(),
+            hasher,
+            ttl: None,
+            capacity: None,
+            limits: HashMap::new(),
+            ordered: HashMap::new(),
+            track_access: false,
+            events: Notifier::default(),
         }
     }
 
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Bound the number of entries of `T`, independently of other types. When `T`
+    /// exceeds its limit, its oldest entries are evicted first, just like with
+    /// `with_capacity` (which still bounds the cache as a whole).
+    pub fn with_limit<T: Item + 'static>(mut self, limit: usize) -> Self {
+        self.limits.insert(T::TYPE_KEY, limit);
+        self
+    }
+
+    /// Record when each entry was last accessed (through `get`, `get_mut` or `range`
+    /// and the helpers built on them), see `Cache::metadata`.
+    pub fn with_access_tracking(mut self) -> Self {
+        self.track_access = true;
+        self
+    }
+
+    /// Keep the entries of `T` ordered by key, enabling `range` queries for `T`.
+    /// Entries of `T` already in the cache are moved over.
+    ///
+    /// ```
+    /// # use serde::{Deserialize, Serialize};
+    /// # use mimir::{Cache, Item};
+    /// #
+    /// # #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+    /// # struct Reading {
+    /// # 	at: u64,
+    /// # }
+    /// #
+    /// # impl Item for Reading {
+    /// # 	type Key = u64;
+    /// # 	const TYPE_KEY: &'static str = "struct Reading";
+    /// #
+    /// # 	fn key(&self) -> Self::Key {
+    /// # 		self.at
+    /// # 	}
+    /// # }
+    /// #
+    /// let mut cache = Cache::new().with_ordered::<Reading>();
+    ///
+    /// for at in [30, 10, 20, 40] {
+    /// 	cache.insert(Reading { at });
+    /// }
+    ///
+    /// let keys = cache
+    /// 	.range::<Reading, _>(15..=30)
+    /// 	.unwrap()
+    /// 	.map(|n| n.at)
+    /// 	.collect::<Vec<_>>();
+    ///
+    /// assert_eq!(keys, [20, 30]);
+    /// ```
+    pub fn with_ordered<T: Item + 'static>(mut self) -> Self
+    where
+        T::Key: Ord,
+    {
+        self.ordered.insert(T::TYPE_KEY, Slot::new_ordered::<T>);
+
+        if let Some(slot) = self.items.remove(T::TYPE_KEY) {
+            let slot = slot.into_ordered::<T>().unwrap_or_else(|slot| slot);
+            self.items.insert(T::TYPE_KEY.to_string(), slot);
+        }
+
+        self
+    }
+
+    /// Total number of entries, across all types. Includes expired entries which
+    /// have not been purged yet.
+    pub fn len(&self) -> usize {
+        self.items.values().map(|n| n.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Insert an item, overwriting any entry with the same key. See `insert_unique`
+    /// and `replace` for alternatives that do not overwrite silently.
+    ///
+    /// If the entries stored for its type key are not a `T` (see `Mismatched`), they
+    /// are kept and the item is dropped. Use `try_insert` to get it back.
     pub fn insert<T: Item + 'static>(&mut self, item: T) {
-        let typekey = T::TYPE_KEY.to_string();
+        self.replace(item);
+    }
+
+    /// Insert an item, returning the (unexpired) entry it replaced. See `insert` for
+    /// entries that are not a `T`.
+    pub fn replace<T: Item + 'static>(&mut self, item: T) -> Option<T> {
+        self.try_replace(item).unwrap_or_else(|_mismatched| {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                item = T::TYPE_KEY,
+                "not inserted: the stored entries are not of this type"
+            );
+
+            None
+        })
+    }
+
+    /// Insert an item, overwriting any entry with the same key, unless the entries
+    /// stored for its type key are not a `T`.
+    ///
+    /// ```
+    /// # use serde::{Deserialize, Serialize};
+    /// # use mimir::{Cache, Item, Mismatched};
+    /// #
+    /// #[derive(Serialize, Deserialize)]
+    /// struct User {
+    /// 	id: u32,
+    /// 	name: String,
+    /// }
+    ///
+    /// impl Item for User {
+    /// 	type Key = u32;
+    /// 	const TYPE_KEY: &'static str = "struct User";
+    ///
+    /// 	fn key(&self) -> Self::Key {
+    /// 		self.id
+    /// 	}
+    /// }
+    ///
+    /// // written by an older version, before users had a name
+    /// let json = r#"{"$mimir": 3, "struct User": {"0": {"item": {"id": 0}, "inserted": {"secs_since_epoch": 0, "nanos_since_epoch": 0}}}}"#;
+    /// let mut cache = serde_json::from_str::<Cache>(json).unwrap();
+    ///
+    /// let ada = User { id: 1, name: "Ada".to_string() };
+    /// let Err(Mismatched { item, .. }) = cache.try_insert(ada) else {
+    /// 	panic!("the old users are not a `User`");
+    /// };
+    ///
+    /// assert_eq!(item.name, "Ada");
+    /// assert_eq!(cache.len(), 1);
+    /// ```
+    pub fn try_insert<T: Item + 'static>(&mut self, item: T) -> Result<(), Mismatched<T>> {
+        self.try_replace(item).map(|_| ())
+    }
+
+    fn try_replace<T: Item + 'static>(&mut self, item: T) -> Result<Option<T>, Mismatched<T>> {
         let key = item.key();
 
-        let items = self
-            .items
-            .entry(typekey)
-            .or_insert_with(|| t::Box::new(InnerHashMap::<T>::new()))
-            .as_any_mut()
-            .downcast_mut::<InnerHashMap<T>>()
-            .unwrap();
+        let Some(entries) = entries_mut::<T, H>(&mut self.items, &self.ordered, &self.hasher)
+        else {
+            return Err(Mismatched {
+                type_key: T::TYPE_KEY,
+                item,
+            });
+        };
+
+        self.events.inserted(T::TYPE_KEY, &key);
+
+        let previous = entries
+            .insert(key, Entry::new(item))
+            .filter(|n| !expired(self.ttl, n.inserted));
 
-        items.insert(key, Box::new(item));
+        self.evict_to_limit(T::TYPE_KEY);
+        self.evict_to_capacity();
+
+        Ok(previous.map(|n| *n.item))
+    }
+
+    /// Insert an item, unless an (unexpired) entry with the same key already exists, or
+    /// the entries stored for its type key are not a `T` (see `Mismatched`).
+    ///
+    /// ```
+    /// # use serde::{Deserialize, Serialize};
+    /// # use mimir::{Cache, Item, Occupied, UniqueError};
+    /// #
+    /// # #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    /// # struct User {
+    /// # 	id: u32,
+    /// # 	name: String,
+    /// # }
+    /// #
+    /// # impl Item for User {
+    /// # 	type Key = u32;
+    /// # 	const TYPE_KEY: &'static str = "struct User";
+    /// #
+    /// # 	fn key(&self) -> Self::Key {
+    /// # 		self.id
+    /// # 	}
+    /// # }
+    /// #
+    /// let mut cache = Cache::new();
+    /// cache.insert(User { id: 0, name: "Ada".to_string() });
+    ///
+    /// let bob = User { id: 0, name: "Bob".to_string() };
+    ///
+    /// let Err(UniqueError::Occupied(Occupied { existing, item })) = cache.insert_unique(bob)
+    /// else {
+    /// 	panic!("User 0 already exists");
+    /// };
+    ///
+    /// assert_eq!(existing.name, "Ada");
+    /// assert_eq!(item.name, "Bob");
+    /// ```
+    pub fn insert_unique<T: Item + 'static>(&mut self, item: T) -> Result<(), UniqueError<'_, T>> {
+        let key = item.key();
+        let ttl = self.ttl;
+
+        let Some(entries) = entries_mut::<T, H>(&mut self.items, &self.ordered, &self.hasher)
+        else {
+            return Err(UniqueError::Mismatched(Mismatched {
+                type_key: T::TYPE_KEY,
+                item,
+            }));
+        };
+
+        let unexpired = |n: &Entry<T>| !expired(ttl, n.inserted);
+
+        let entry = match entries.insert_unless(item.key(), Entry::new(item), &unexpired) {
+            Err(entry) => entry,
+            Ok(_) => {
+                self.events.inserted(T::TYPE_KEY, &key);
+                self.evict_to_limit(T::TYPE_KEY);
+                self.evict_to_capacity();
+
+                return Ok(());
+            }
+        };
+
+        // returning a borrow taken before the insertion would keep `self` borrowed for
+        // the evictions too, so the existing entry is looked up again. Not by `entry`, as
+        // it may have expired since.
+        match self.stored::<T>(&key) {
+            Some(existing) => Err(UniqueError::Occupied(Occupied {
+                existing: &*existing.item,
+                item: *entry.item,
+            })),
+            None => unreachable!("the entry of an occupied key is gone"),
+        }
     }
 
     pub fn get<T: Item + 'static>(&self, key: T::Key) -> Option<&T> {
+        let entry = self.entry::<T>(&key);
+
+        #[cfg(feature = "tracing")]
+        lookup::<T>(entry.is_some());
+
+        entry.map(|n| {
+            self.touch(n);
+            &*n.item
+        })
+    }
+
+    /// The unexpired entry for `key`, without counting as an access
+    fn entry<T: Item + 'static>(&self, key: &T::Key) -> Option<&Entry<T>> {
+        self.stored::<T>(key).filter(|n| !self.is_expired(n))
+    }
+
+    /// The entry for `key`, expired or not
+    fn stored<T: Item + 'static>(&self, key: &T::Key) -> Option<&Entry<T>> {
         self.items
             .get(T::TYPE_KEY)
-            .and_then(|v| v.as_any().downcast_ref::<InnerHashMap<T>>())
-            .and_then(|n| n.get(&key))
-            .map(|n| &**n)
+            .and_then(|v| v.get::<T, H>(&self.hasher))
+            .and_then(|n| n.get(key))
+    }
+
+    /// Iterate over the (unexpired) entries of `T` whose keys fall in `range`, in key order.
+    /// Returns `None` unless `T` was registered with `with_ordered`.
+    pub fn range<T: Item + 'static, R: RangeBounds<T::Key>>(
+        &self,
+        range: R,
+    ) -> Option<impl Iterator<Item = &T> + '_>
+    where
+        T::Key: Ord,
+    {
+        if !self.ordered.contains_key(T::TYPE_KEY) {
+            return None;
+        }
+
+        let items = self
+            .items
+            .get(T::TYPE_KEY)
+            .and_then(|v| v.get::<T, H>(&self.hasher))
+            .and_then(|n| n.as_any().downcast_ref::<BTreeMap<T::Key, Entry<T>>>());
+
+        Some(
+            items
+                .map(|n| n.range(range))
+                .into_iter()
+                .flatten()
+                .filter(|(_, n)| !self.is_expired(n))
+                .inspect(|(_, n)| self.touch(n))
+                .map(|(_, n)| &*n.item),
+        )
     }
 
     pub fn copied<T: Item + 'static>(&self, key: T::Key) -> Option<T>
@@ -132,28 +676,98 @@ impl Cache {
     }
 
     pub fn get_mut<T: Item + 'static>(&mut self, key: T::Key) -> Option<&mut T> {
-        self.items
+        let ttl = self.ttl;
+        let track_access = self.track_access;
+
+        let item = self
+            .items
             .get_mut(T::TYPE_KEY)
-            .and_then(|v| v.as_any_mut().downcast_mut::<InnerHashMap<T>>())
+            .and_then(|v| v.try_get_mut::<T, H>(&self.hasher))
             .and_then(|n| n.get_mut(&key))
-            .map(|n| &mut **n)
+            .filter(|n| !expired(ttl, n.inserted))
+            .map(|n| {
+                if track_access {
+                    n.touch();
+                }
+
+                &mut *n.item
+            });
+
+        #[cfg(feature = "tracing")]
+        lookup::<T>(item.is_some());
+
+        item
     }
 
     pub fn take<T: Item + 'static>(&mut self, key: T::Key) -> Option<T> {
-        self.items
+        let entry = self
+            .items
             .get_mut(T::TYPE_KEY)
-            .and_then(|v| v.as_any_mut().downcast_mut::<InnerHashMap<T>>())
-            .and_then(|n| n.remove(&key))
-            .map(|n| *n)
+            .and_then(|v| v.try_get_mut::<T, H>(&self.hasher))
+            .and_then(|n| n.remove(&key))?;
+
+        if self.is_expired(&entry) {
+            self.events.evicted(T::TYPE_KEY, &key);
+            return None;
+        }
+
+        self.events.removed(T::TYPE_KEY, &key);
+        Some(*entry.item)
+    }
+
+    fn is_expired<T>(&self, entry: &Entry<T>) -> bool {
+        expired(self.ttl, entry.inserted)
+    }
+
+    fn touch<T>(&self, entry: &Entry<T>) {
+        if self.track_access {
+            entry.touch();
+        }
     }
 }
 
-impl Serialize for Cache {
+/// The entries of `T`, created if there are none yet. `None` if the entries stored for
+/// its type key are not a `T`.
+fn entries_mut<'a, T, H>(
+    items: &'a mut HashMap<String, Slot>,
+    ordered: &HashMap<&'static str, fn() -> Slot>,
+    hasher: &H,
+) -> Option<&'a mut dyn Entries<T>>
+where
+    T: Item + 'static,
+    H: BuildHasher + Clone + Send + Sync + 'static,
+{
+    let new = || match ordered.get(T::TYPE_KEY) {
+        Some(new) => new(),
+        None => Slot::new::<T, H>(hasher.clone()),
+    };
+
+    items
+        .entry(T::TYPE_KEY.to_string())
+        .or_insert_with(new)
+        .try_get_mut::<T, H>(hasher)
+}
+
+fn expired(ttl: Option<Duration>, inserted: SystemTime) -> bool {
+    ttl.is_some_and(|ttl| inserted + ttl <= SystemTime::now())
+}
+
+/// Record a lookup of a `T` as a tracing event
+#[cfg(feature = "tracing")]
+fn lookup<T: Item>(hit: bool) {
+    match hit {
+        true => tracing::trace!(item = T::TYPE_KEY, hit, "cache hit"),
+        false => tracing::trace!(item = T::TYPE_KEY, hit, "cache miss"),
+    }
+}
+
+impl<H> Serialize for Cache<H> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let mut map = serializer.serialize_map(Some(self.items.len()))?;
+        let mut map = serializer.serialize_map(Some(self.items.len() + 1))?;
+        map.serialize_entry(VERSION_KEY, &FORMAT_VERSION)?;
 
         for (key, value) in &self.items {
             map.serialize_entry(key, value)?;
@@ -163,34 +777,163 @@ impl Serialize for Cache {
     }
 }
 
-struct CacheVisitor;
+struct CacheVisitor<H> {
+    // whether the entries are read as JSON text (only from a `serde_json::Deserializer`)
+    json: bool,
+    hasher: PhantomData<H>,
+}
 
-impl<'de> Visitor<'de> for CacheVisitor {
-    type Value = Cache;
+impl<'de, H: BuildHasher + Clone + Default + Send + Sync + 'static> Visitor<'de>
+    for CacheVisitor<H>
+{
+    type Value = Cache<H>;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(formatter, "HashMap<String, t::Box<dyn t::Any>>")
+        write!(formatter, "a map of type keys to cached entries")
     }
 
     fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
     where
         A: serde::de::MapAccess<'de>,
     {
-        let mut this = Cache::new();
+        let mut this = Cache::with_hasher(H::default());
+        let mut version = None;
 
-        while let Some((k, v)) = map.next_entry()? {
-            this.items.insert(k, v);
+        // the version is always written first, so it is checked before any items are read
+        while let Some(k) = map.next_key::<String>()? {
+            if k == VERSION_KEY {
+                version = Some(map.next_value()?);
+                continue;
+            }
+
+            FormatMismatch::check(version.unwrap_or(0)).map_err(de::Error::custom)?;
+            let slot = match self.json {
+                true => Slot::json(map.next_value()?),
+                false => Slot::serialized(map.next_value()?),
+            };
+
+            this.items.insert(k, slot);
         }
 
+        FormatMismatch::check(version.unwrap_or(0)).map_err(de::Error::custom)?;
+
         Ok(this)
     }
 }
 
-impl<'de> Deserialize<'de> for Cache {
+impl<'de, H: BuildHasher + Clone + Default + Send + Sync + 'static> Deserialize<'de> for Cache<H> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        deserializer.deserialize_map(CacheVisitor)
+        deserializer.deserialize_map(CacheVisitor {
+            json: false,
+            hasher: PhantomData,
+        })
+    }
+}
+
+impl<H: BuildHasher + Clone + Default + Send + Sync + 'static> Cache<H> {
+    /// Like `deserialize`, keeping the entries of each type as the JSON text they were
+    /// read as, instead of parsing them into a `Value`
+    pub(crate) fn from_json<'de, R: serde_json::de::Read<'de>>(
+        deserializer: &mut serde_json::Deserializer<R>,
+    ) -> serde_json::Result<Self> {
+        let cache = serde::Deserializer::deserialize_map(
+            &mut *deserializer,
+            CacheVisitor {
+                json: true,
+                hasher: PhantomData,
+            },
+        )?;
+
+        deserializer.end()?;
+        Ok(cache)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    impl Item for User {
+        type Key = u32;
+        const TYPE_KEY: &'static str = "struct User";
+
+        fn key(&self) -> Self::Key {
+            self.id
+        }
+    }
+
+    fn user(id: u32, name: &str) -> User {
+        let name = String::from(name);
+        User { id, name }
+    }
+
+    #[test]
+    fn unique_inserts_keep_the_existing_entry() {
+        let mut cache = Cache::new();
+        assert!(cache.insert_unique(user(0, "ada")).is_ok());
+
+        let Err(UniqueError::Occupied(Occupied { existing, item })) =
+            cache.insert_unique(user(0, "bob"))
+        else {
+            panic!("user 0 was inserted already");
+        };
+
+        assert_eq!((existing.name.as_str(), item.name.as_str()), ("ada", "bob"));
+    }
+
+    #[test]
+    fn unique_inserts_give_back_mismatched_items() {
+        #[derive(Serialize, Deserialize)]
+        struct Admin {
+            id: u32,
+        }
+
+        impl Item for Admin {
+            type Key = u32;
+            const TYPE_KEY: &'static str = "struct User";
+
+            fn key(&self) -> Self::Key {
+                self.id
+            }
+        }
+
+        let mut cache = Cache::new();
+        cache.insert(Admin { id: 0 });
+
+        let Err(UniqueError::Mismatched(Mismatched { type_key, item })) =
+            cache.insert_unique(user(1, "ada"))
+        else {
+            panic!("the users are stored as `Admin`");
+        };
+
+        assert_eq!((type_key, item.name.as_str()), ("struct User", "ada"));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn unique_inserts_replace_expired_entries() {
+        let mut cache = Cache::new().with_ttl(Duration::ZERO);
+
+        assert!(cache.insert_unique(user(0, "ada")).is_ok());
+        assert!(cache.insert_unique(user(0, "bob")).is_ok());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn unique_inserts_are_evicted_to_capacity() {
+        let mut cache = Cache::new().with_capacity(1);
+
+        assert!(cache.insert_unique(user(0, "ada")).is_ok());
+        assert!(cache.insert_unique(user(1, "bob")).is_ok());
+        assert_eq!(cache.len(), 1);
     }
 }
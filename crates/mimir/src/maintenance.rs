@@ -0,0 +1,172 @@
+use crate::{events::Notifier, store::Slot, Cache};
+use std::{hash::BuildHasher, time::SystemTime};
+
+impl<H: BuildHasher + Clone + Send + Sync + 'static> Cache<H> {
+    /// Remove every entry older than the cache's TTL. Does nothing if no TTL is set.
+    /// Returns how many entries were removed.
+    ///
+    /// Like all maintenance, this only applies to types that have been accessed
+    /// since the cache was deserialized.
+    pub fn purge_expired(&mut self) -> usize {
+        let Some(ttl) = self.ttl else {
+            return 0;
+        };
+
+        let Some(deadline) = SystemTime::now().checked_sub(ttl) else {
+            return 0;
+        };
+
+        let events = &self.events;
+
+        // entries inserted exactly at the deadline are already expired
+        self.items
+            .iter_mut()
+            .map(|(type_key, slot)| {
+                slot.remove_with(|n| {
+                    n.purge_until(deadline, &mut |key| events.evicted(type_key, key))
+                })
+            })
+            .sum()
+    }
+
+    /// Evict the oldest entries (across all types) until the cache fits its capacity.
+    /// Does nothing if no capacity is set. Returns how many entries were evicted.
+    ///
+    /// This also runs after every `insert`, but can be called explicitly, e.g. after
+    /// loading a cache. The entries of each type are indexed by age on their first
+    /// eviction, so evicting after that does not go through every entry.
+    pub fn evict_to_capacity(&mut self) -> usize {
+        let Some(capacity) = self.capacity else {
+            return 0;
+        };
+
+        let len = self.len();
+
+        if len <= capacity {
+            return 0;
+        }
+
+        let events = &self.events;
+
+        // entries that are still serialized count towards the capacity, but cannot be evicted
+        let slots = self.items.iter_mut().map(|(k, n)| (k.as_str(), n));
+        evict_oldest(slots, len - capacity, events)
+    }
+
+    /// Evict the oldest entries of every type that exceeds its limit (see `with_limit`).
+    /// Returns how many entries were evicted.
+    ///
+    /// Like `evict_to_capacity`, this also runs after every `insert` (for the inserted type).
+    pub fn evict_to_limits(&mut self) -> usize {
+        let type_keys = self.limits.keys().copied().collect::<Vec<_>>();
+
+        type_keys
+            .into_iter()
+            .map(|type_key| self.evict_to_limit(type_key))
+            .sum()
+    }
+
+    pub(crate) fn evict_to_limit(&mut self, type_key: &str) -> usize {
+        let Some(&limit) = self.limits.get(type_key) else {
+            return 0;
+        };
+
+        let Some(slot) = self.items.get_mut(type_key) else {
+            return 0;
+        };
+
+        let len = slot.len();
+
+        if len <= limit {
+            return 0;
+        }
+
+        evict_oldest([(type_key, slot)].into_iter(), len - limit, &self.events)
+    }
+
+    /// Drop type keys with no entries left and release the memory the others do not
+    /// need: unused map capacity, the serialized copy of entries that have been
+    /// accessed, and the parsed JSON of entries that have not, which is re-encoded as
+    /// compact JSON until they are.
+    pub fn compact(&mut self) {
+        self.items.retain(|_, n| n.len() > 0);
+
+        for n in self.items.values_mut() {
+            n.compact();
+        }
+
+        self.items.shrink_to_fit();
+    }
+}
+
+/// Evict the `excess` oldest entries across `slots`, returns how many were evicted
+fn evict_oldest<'a>(
+    slots: impl Iterator<Item = (&'a str, &'a mut Slot)>,
+    excess: usize,
+    events: &Notifier,
+) -> usize {
+    let mut slots = slots.collect::<Vec<_>>();
+    let mut evicted = 0;
+
+    while evicted < excess {
+        // ties are broken arbitrarily
+        let oldest = slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(n, (_, slot))| Some((slot.oldest()?, n)))
+            .min();
+
+        let Some((_, n)) = oldest else {
+            break;
+        };
+
+        let (type_key, slot) = &mut slots[n];
+        evicted += slot.remove_with(|n| n.pop_oldest(&mut |key| events.evicted(type_key, key)));
+    }
+
+    evicted
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cache, Item};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    impl Item for User {
+        type Key = u32;
+        const TYPE_KEY: &'static str = "struct User";
+
+        fn key(&self) -> Self::Key {
+            self.id
+        }
+    }
+
+    #[test]
+    fn compacting_keeps_every_entry() {
+        let mut cache = Cache::new();
+
+        for (id, name) in [(0, "ada"), (1, "grace")] {
+            let name = String::from(name);
+            cache.insert(User { id, name });
+        }
+
+        let saved = serde_json::to_value(&cache).unwrap();
+        let mut loaded = serde_json::from_value::<Cache>(saved.clone()).unwrap();
+
+        // still serialized
+        loaded.compact();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), saved);
+
+        // materialized
+        assert_eq!(loaded.get::<User>(1).unwrap().name, "grace");
+        loaded.compact();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), saved);
+    }
+}
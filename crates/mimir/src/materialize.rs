@@ -0,0 +1,110 @@
+use crate::{Cache, Item};
+use std::{collections::HashMap, hash::BuildHasher};
+
+/// # Registry
+///
+/// A set of `Item` types, used by `Cache::materialize_all` to deserialize
+/// every known type key at once.
+pub struct Registry<H = std::collections::hash_map::RandomState> {
+    types: HashMap<&'static str, fn(&Cache<H>) -> bool>,
+}
+
+impl<H: BuildHasher + Clone + Send + Sync + 'static> Registry<H> {
+    pub fn new() -> Self {
+        Self {
+            types: HashMap::new(),
+        }
+    }
+
+    pub fn with<T: Item + 'static>(mut self) -> Self {
+        self.types.insert(T::TYPE_KEY, Cache::<H>::materialize::<T>);
+        self
+    }
+}
+
+impl<H: BuildHasher + Clone + Send + Sync + 'static> Default for Registry<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: BuildHasher + Clone + Send + Sync + 'static> Cache<H> {
+    /// Type keys whose entries are still in serialized form, i.e. have not been
+    /// accessed since the cache was deserialized.
+    pub fn serialized_type_keys(&self) -> impl Iterator<Item = &str> + '_ {
+        self.items
+            .iter()
+            .filter(|(_, v)| v.is_serialized())
+            .map(|(k, _)| k.as_str())
+    }
+
+    /// Deserialize the entries of `T` now, rather than on first access. Returns
+    /// whether they are materialized afterwards, `false` if there are no entries
+    /// of `T` or they fail to deserialize as `T`.
+    pub fn materialize<T: Item + 'static>(&self) -> bool {
+        self.items
+            .get(T::TYPE_KEY)
+            .and_then(|v| v.get::<T, H>(&self.hasher))
+            .is_some()
+    }
+
+    /// Materialize every type in `registry`. Returns how many type keys failed to
+    /// materialize, not counting type keys that are absent from the cache.
+    ///
+    /// ```
+    /// # use serde::{Deserialize, Serialize};
+    /// # use mimir::{Cache, Item, Registry};
+    /// #
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct User {
+    /// # 	id: u32,
+    /// # }
+    /// #
+    /// # impl Item for User {
+    /// # 	type Key = u32;
+    /// # 	const TYPE_KEY: &'static str = "struct User";
+    /// #
+    /// # 	fn key(&self) -> Self::Key {
+    /// # 		self.id
+    /// # 	}
+    /// # }
+    /// #
+    /// let json = r#"{ "$mimir": 3, "struct User": {}, "struct Unknown": {} }"#;
+    /// let mut cache = serde_json::from_str::<Cache>(json).unwrap();
+    ///
+    /// assert_eq!(cache.materialize_all(&Registry::new().with::<User>()), 0);
+    /// assert_eq!(cache.serialized_type_keys().collect::<Vec<_>>(), ["struct Unknown"]);
+    ///
+    /// cache.drop_serialized();
+    /// assert_eq!(cache.serialized_type_keys().count(), 0);
+    /// ```
+    pub fn materialize_all(&self, registry: &Registry<H>) -> usize {
+        registry
+            .types
+            .iter()
+            .filter(|(k, _)| self.items.contains_key(**k))
+            .filter(|(_, materialize)| !materialize(self))
+            .count()
+    }
+
+    /// Remove every entry stored under `type_key`, whether materialized or not.
+    /// Returns whether there was anything to remove.
+    pub fn remove_type_key(&mut self, type_key: &str) -> bool {
+        self.items.remove(type_key).is_some()
+    }
+
+    /// Remove every type key that is still serialized, e.g. those left over by
+    /// types that no longer exist after `materialize_all`. Returns the removed type keys.
+    pub fn drop_serialized(&mut self) -> Vec<String> {
+        let dropped = self
+            .serialized_type_keys()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        for type_key in &dropped {
+            self.items.remove(type_key);
+        }
+
+        dropped
+    }
+}
@@ -0,0 +1,244 @@
+use crate::{Item, SharedCache};
+use futures::StreamExt;
+use hermod::{Error, Sender};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::hash_map::RandomState, hash::BuildHasher, marker::PhantomData, sync::Arc,
+    time::Duration,
+};
+
+/// The responses to an event, stored in the cache of a `Memoized`
+#[derive(Serialize, Deserialize)]
+struct Memo {
+    // the namespace, the type key and the serialized key of the event
+    key: String,
+    responses: serde_json::Value,
+}
+
+impl Item for Memo {
+    type Key = String;
+    const TYPE_KEY: &'static str = "mimir::Memo";
+
+    fn key(&self) -> Self::Key {
+        self.key.clone()
+    }
+}
+
+/// # Memoized
+///
+/// Sends events to a hermod `Sender`, and stores the responses in a `SharedCache`,
+/// keyed by the key of the event. An event with the same key as an earlier one is
+/// answered from the cache, without running the handler again. Empty responses, e.g.
+/// of a handler that panicked, are not stored.
+///
+/// The responses are stored under a namespace, so `Memoized`s of different handlers
+/// of the same events can share a cache without answering each other's events.
+///
+/// Responses expire with the cache's `Cache::with_ttl`, or with `Memoized::with_ttl`
+/// when only memoized responses should expire. They are saved with the cache, as JSON.
+///
+/// ```
+/// use futures::{executor, future::BoxFuture};
+/// use hermod::Sender;
+/// use mimir::{Cache, Item, Memoized, SharedCache};
+/// use serde::{Deserialize, Serialize};
+/// use std::{
+/// 	sync::{atomic::{AtomicUsize, Ordering}, Arc},
+/// 	thread,
+/// };
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Square(u64);
+///
+/// impl Item for Square {
+/// 	type Key = u64;
+/// 	const TYPE_KEY: &'static str = "struct Square";
+///
+/// 	fn key(&self) -> Self::Key {
+/// 		self.0
+/// 	}
+/// }
+///
+/// static CALLS: AtomicUsize = AtomicUsize::new(0);
+///
+/// let spawner = |future: BoxFuture<'static, ()>| {
+/// 	thread::spawn(|| executor::block_on(future));
+/// };
+///
+/// let sender = Arc::new(Sender::<Square, u64>::new_on(spawner, |square, _| Box::pin(async move {
+/// 	CALLS.fetch_add(1, Ordering::SeqCst);
+/// 	square.0 * square.0
+/// }), ()));
+///
+/// let memoized = Memoized::new("squares", sender, SharedCache::new(Cache::new()));
+///
+/// executor::block_on(async {
+/// 	assert_eq!(memoized.emit(Square(12)).await.unwrap(), [144]);
+/// 	assert_eq!(memoized.emit(Square(12)).await.unwrap(), [144]);
+/// });
+///
+/// assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+/// ```
+pub struct Memoized<T, R, H = RandomState>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+{
+    namespace: String,
+    sender: Arc<Sender<T, R>>,
+    cache: SharedCache<H>,
+    ttl: Option<Duration>,
+    _responses: PhantomData<fn() -> R>,
+}
+
+impl<T, R, H> Memoized<T, R, H>
+where
+    T: Item + 'static,
+    R: Serialize + DeserializeOwned + Send + Sync + 'static,
+    H: BuildHasher + Clone + Send + Sync + 'static,
+{
+    /// Memoize the responses of `sender` in `cache`, under `namespace`
+    pub fn new(
+        namespace: impl Into<String>,
+        sender: Arc<Sender<T, R>>,
+        cache: SharedCache<H>,
+    ) -> Self {
+        Self {
+            namespace: namespace.into(),
+            sender,
+            cache,
+            ttl: None,
+            _responses: PhantomData,
+        }
+    }
+
+    /// Run the handler again for events whose responses were stored more than `ttl` ago
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// The responses to `event`, from the cache if an event with the same key was
+    /// sent before, otherwise from the handler, see `Sender::emit`
+    pub async fn emit(&self, event: impl Into<T>) -> Result<Vec<R>, Error<T>> {
+        let event = event.into();
+        let key = self.memo_key(&event.key());
+
+        if let Some(responses) = key.as_ref().and_then(|key| self.cached(key)) {
+            return Ok(responses);
+        }
+
+        let responses = Arc::clone(&self.sender)
+            .emit(event)
+            .await?
+            .collect::<Vec<_>>()
+            .await;
+
+        // a panicking handler ends the responses without any
+        if responses.is_empty() {
+            return Ok(responses);
+        }
+
+        // responses that cannot be serialized are not memoized
+        if let (Some(key), Ok(value)) = (key, serde_json::to_value(&responses)) {
+            self.cache.insert(Memo {
+                key,
+                responses: value,
+            });
+        }
+
+        Ok(responses)
+    }
+
+    /// Forget the responses to the event with `key`, so the handler runs again
+    pub fn invalidate(&self, key: &T::Key) {
+        if let Some(key) = self.memo_key(key) {
+            self.cache.take::<Memo>(key);
+        }
+    }
+
+    fn cached(&self, key: &str) -> Option<Vec<R>> {
+        let cache = self.cache.read();
+
+        if let Some(ttl) = self.ttl {
+            let created = cache.metadata::<Memo>(key.to_string())?.created;
+
+            if created.elapsed().unwrap_or_default() >= ttl {
+                return None;
+            }
+        }
+
+        let memo = cache.get::<Memo>(key.to_string())?;
+        serde_json::from_value(memo.responses.clone()).ok()
+    }
+
+    /// The key of the `Memo` of the event with `key`
+    fn memo_key(&self, key: &T::Key) -> Option<String> {
+        serde_json::to_string(key)
+            .ok()
+            .map(|key| format!("{}:{}:{key}", self.namespace, T::TYPE_KEY))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cache, Item, Memoized, SharedCache};
+    use futures::{executor, future::BoxFuture};
+    use hermod::Sender;
+    use serde::{Deserialize, Serialize};
+    use std::{sync::Arc, thread};
+
+    #[derive(Serialize, Deserialize)]
+    struct Number(u64);
+
+    impl Item for Number {
+        type Key = u64;
+        const TYPE_KEY: &'static str = "struct Number";
+
+        fn key(&self) -> Self::Key {
+            self.0
+        }
+    }
+
+    fn spawner(future: BoxFuture<'static, ()>) {
+        thread::spawn(|| executor::block_on(future));
+    }
+
+    fn sender(handler: fn(u64) -> u64) -> Arc<Sender<Number, u64>> {
+        Arc::new(Sender::new_on(
+            spawner,
+            move |n: Number, _| Box::pin(async move { handler(n.0) }),
+            (),
+        ))
+    }
+
+    #[test]
+    fn namespaces_are_kept_apart() {
+        let cache = SharedCache::new(Cache::new());
+        let double = Memoized::new("double", sender(|n| n * 2), cache.clone());
+        let square = Memoized::new("square", sender(|n| n * n), cache);
+
+        executor::block_on(async {
+            assert_eq!(double.emit(Number(3)).await.unwrap(), [6]);
+            assert_eq!(square.emit(Number(3)).await.unwrap(), [9]);
+        });
+    }
+
+    #[test]
+    fn failures_are_not_memoized() {
+        let memoized = Memoized::new(
+            "checked",
+            sender(|n| {
+                assert_ne!(n, 0, "zero");
+                n
+            }),
+            SharedCache::new(Cache::new()),
+        );
+
+        executor::block_on(async {
+            assert!(memoized.emit(Number(0)).await.unwrap().is_empty());
+        });
+
+        assert!(memoized.cached(&memoized.memo_key(&0).unwrap()).is_none());
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{Cache, Item};
+use std::{hash::BuildHasher, time::SystemTime};
+
+/// # Metadata
+///
+/// Bookkeeping for a single entry, as returned by `Cache::metadata`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    /// When the entry was inserted (or last replaced)
+    pub created: SystemTime,
+    /// When the entry was last accessed. Always `None` unless the cache was
+    /// built with `with_access_tracking`.
+    pub last_accessed: Option<SystemTime>,
+}
+
+impl<H: BuildHasher + Clone + Send + Sync + 'static> Cache<H> {
+    /// Get the metadata of an entry, including expired ones which have not been
+    /// purged yet. Looking up metadata does not count as an access.
+    ///
+    /// ```
+    /// # use serde::{Deserialize, Serialize};
+    /// # use mimir::{Cache, Item};
+    /// #
+    /// # #[derive(Serialize, Deserialize)]
+    /// # struct User {
+    /// # 	id: u32,
+    /// # }
+    /// #
+    /// # impl Item for User {
+    /// # 	type Key = u32;
+    /// # 	const TYPE_KEY: &'static str = "struct User";
+    /// #
+    /// # 	fn key(&self) -> Self::Key {
+    /// # 		self.id
+    /// # 	}
+    /// # }
+    /// #
+    /// let mut cache = Cache::new().with_access_tracking();
+    /// cache.insert(User { id: 0 });
+    ///
+    /// assert_eq!(cache.metadata::<User>(0).unwrap().last_accessed, None);
+    ///
+    /// cache.get::<User>(0);
+    /// let metadata = cache.metadata::<User>(0).unwrap();
+    ///
+    /// assert!(metadata.last_accessed.unwrap() >= metadata.created);
+    /// ```
+    pub fn metadata<T: Item + 'static>(&self, key: T::Key) -> Option<Metadata> {
+        self.items
+            .get(T::TYPE_KEY)
+            .and_then(|v| v.get::<T, H>(&self.hasher))
+            .and_then(|n| n.get(&key))
+            .map(|n| Metadata {
+                created: n.inserted,
+                last_accessed: n.accessed(),
+            })
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{Cache, FormatMismatch, FORMAT_VERSION};
+use std::{
+    hash::BuildHasher,
+    io::{self, BufReader, Read, Write},
+};
+use thiserror::Error;
+
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Key, XChaCha20Poly1305, XNonce,
+};
+
+pub(crate) const MAGIC: &[u8; 4] = b"MIMR";
+pub(crate) const HEADER_LEN: usize = MAGIC.len() + 4 + 1;
+const FLAG_COMPRESSED: u8 = 1 << 0;
+const FLAG_ENCRYPTED: u8 = 1 << 1;
+
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 24;
+
+#[derive(Error, Debug)]
+pub enum PersistError {
+    #[error("IO error: {0}")]
+    IO(#[from] io::Error),
+
+    #[error("Failed to (de)serialize cache: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("Not a mimir cache (bad header)")]
+    BadHeader,
+
+    #[error(transparent)]
+    FormatMismatch(#[from] FormatMismatch),
+
+    #[error("Cache is encrypted, but no key was provided")]
+    MissingKey,
+
+    #[error("Cache is compressed, but the `compression` feature is disabled")]
+    CompressionUnsupported,
+
+    #[error("Cache is encrypted, but the `encryption` feature is disabled")]
+    EncryptionUnsupported,
+
+    #[error("Failed to encrypt cache")]
+    Encrypt,
+
+    #[error("Failed to decrypt cache (wrong key or corrupted data)")]
+    Decrypt,
+
+    #[error("No cache directory found for this platform")]
+    NoCacheDir,
+}
+
+/// # PersistOptions
+///
+/// Controls how `Cache::save` encodes the payload. By default the cache is
+/// written as plain JSON. Compression (`compression` feature, zstd) and
+/// encryption (`encryption` feature, XChaCha20-Poly1305) are opt-in.
+///
+/// Loading detects both from the header, so the same options (or just the key)
+/// can be passed to `Cache::load`.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct PersistOptions {
+    #[cfg(feature = "compression")]
+    level: Option<i32>,
+    #[cfg(feature = "encryption")]
+    key: Option<[u8; 32]>,
+}
+
+impl PersistOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compress the payload with zstd at the given level (`0` uses zstd's default)
+    #[cfg(feature = "compression")]
+    pub fn compressed(mut self, level: i32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Encrypt the payload with the given 256-bit key
+    #[cfg(feature = "encryption")]
+    pub fn encrypted(mut self, key: [u8; 32]) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    pub(crate) fn flags(&self) -> u8 {
+        #[allow(unused_mut)]
+        let mut flags = 0;
+
+        #[cfg(feature = "compression")]
+        if self.level.is_some() {
+            flags |= FLAG_COMPRESSED;
+        }
+
+        #[cfg(feature = "encryption")]
+        if self.key.is_some() {
+            flags |= FLAG_ENCRYPTED;
+        }
+
+        flags
+    }
+
+    pub(crate) fn encode(&self, payload: Vec<u8>) -> Result<Vec<u8>, PersistError> {
+        #[allow(unused_mut)]
+        let mut payload = payload;
+
+        #[cfg(feature = "compression")]
+        if let Some(level) = self.level {
+            payload = zstd::encode_all(&payload[..], level)?;
+        }
+
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.key {
+            let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+            let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+            let encrypted = cipher
+                .encrypt(&nonce, &payload[..])
+                .map_err(|_| PersistError::Encrypt)?;
+
+            payload = nonce.into_iter().chain(encrypted).collect();
+        }
+
+        Ok(payload)
+    }
+
+    pub(crate) fn decode(&self, flags: u8, payload: Vec<u8>) -> Result<Vec<u8>, PersistError> {
+        if flags & (FLAG_ENCRYPTED | FLAG_COMPRESSED) == 0 {
+            return Ok(payload);
+        }
+
+        let mut decoded = vec![];
+        self.decoder(flags, io::Cursor::new(payload))?
+            .read_to_end(&mut decoded)?;
+
+        Ok(decoded)
+    }
+
+    /// Check that a payload with `flags` can be decoded with these options
+    pub(crate) fn check(&self, flags: u8) -> Result<(), PersistError> {
+        if flags & FLAG_ENCRYPTED != 0 && !cfg!(feature = "encryption") {
+            return Err(PersistError::EncryptionUnsupported);
+        }
+
+        if flags & FLAG_COMPRESSED != 0 && !cfg!(feature = "compression") {
+            return Err(PersistError::CompressionUnsupported);
+        }
+
+        #[cfg(feature = "encryption")]
+        if flags & FLAG_ENCRYPTED != 0 && self.key.is_none() {
+            return Err(PersistError::MissingKey);
+        }
+
+        Ok(())
+    }
+
+    /// Wrap `reader` so that reading from it yields the decoded payload.
+    fn decoder<'a>(
+        &self,
+        flags: u8,
+        reader: impl Read + 'a,
+    ) -> Result<Box<dyn Read + 'a>, PersistError> {
+        self.check(flags)?;
+
+        #[allow(unused_mut)]
+        let mut reader: Box<dyn Read + 'a> = Box::new(reader);
+
+        #[cfg(feature = "encryption")]
+        if flags & FLAG_ENCRYPTED != 0 {
+            let Some(key) = &self.key else {
+                return Err(PersistError::MissingKey);
+            };
+
+            // the ciphertext can only be authenticated as a whole, so it has to be buffered
+            let mut payload = vec![];
+            reader.read_to_end(&mut payload)?;
+
+            if payload.len() < NONCE_LEN {
+                return Err(PersistError::Decrypt);
+            }
+
+            let (nonce, encrypted) = payload.split_at(NONCE_LEN);
+            let cipher = XChaCha20Poly1305::new(Key::from_slice(key));
+
+            let decrypted = cipher
+                .decrypt(XNonce::from_slice(nonce), encrypted)
+                .map_err(|_| PersistError::Decrypt)?;
+
+            reader = Box::new(io::Cursor::new(decrypted));
+        }
+
+        #[cfg(feature = "compression")]
+        if flags & FLAG_COMPRESSED != 0 {
+            reader = Box::new(zstd::stream::read::Decoder::new(reader)?);
+        }
+
+        Ok(reader)
+    }
+}
+
+pub(crate) fn write_header(bytes: &mut Vec<u8>, flags: u8) {
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.push(flags);
+}
+
+/// Check the header, returning its flags
+pub(crate) fn read_header(header: &[u8; HEADER_LEN]) -> Result<u8, PersistError> {
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(PersistError::BadHeader);
+    }
+
+    let version = u32::from_le_bytes(header[MAGIC.len()..HEADER_LEN - 1].try_into().unwrap());
+    FormatMismatch::check(version)?;
+
+    Ok(header[HEADER_LEN - 1])
+}
+
+impl<H> Cache<H> {
+    /// Serialize the cache into bytes, applying the compression/encryption in `opts`.
+    pub fn to_bytes(&self, opts: &PersistOptions) -> Result<Vec<u8>, PersistError> {
+        let payload = opts.encode(serde_json::to_vec(self)?)?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        write_header(&mut bytes, opts.flags());
+        bytes.extend(payload);
+
+        Ok(bytes)
+    }
+
+    /// Write the cache to `writer`. See `PersistOptions`.
+    ///
+    /// ```no_run
+    /// use mimir::{Cache, PersistOptions};
+    /// use std::fs::File;
+    ///
+    /// let cache = Cache::new();
+    /// let opts = PersistOptions::new();
+    ///
+    /// cache.save(File::create("cache.bin").unwrap(), &opts).unwrap();
+    /// let cache: Cache = Cache::load(File::open("cache.bin").unwrap(), &opts).unwrap();
+    /// ```
+    pub fn save(&self, mut writer: impl Write, opts: &PersistOptions) -> Result<(), PersistError> {
+        writer.write_all(&self.to_bytes(opts)?)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+}
+
+impl<H: BuildHasher + Clone + Default + Send + Sync + 'static> Cache<H> {
+    /// Deserialize a cache produced by `to_bytes`. Compression is detected automatically,
+    /// encrypted caches need the key set in `opts`.
+    pub fn from_bytes(bytes: &[u8], opts: &PersistOptions) -> Result<Self, PersistError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(PersistError::BadHeader);
+        }
+
+        let (header, payload) = bytes.split_at(HEADER_LEN);
+        let flags = read_header(header.try_into().unwrap())?;
+
+        let payload = opts.decode(flags, payload.to_vec())?;
+        Ok(Self::from_json(&mut serde_json::Deserializer::from_slice(
+            &payload,
+        ))?)
+    }
+
+    /// Read a cache written by `save` from `reader`.
+    pub fn load(mut reader: impl Read, opts: &PersistOptions) -> Result<Self, PersistError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+
+        Self::from_bytes(&bytes, opts)
+    }
+
+    /// Read a cache written by `save` from `reader`, decompressing and deserializing it
+    /// while reading, one type key at a time. Unlike `load`, neither the file nor the
+    /// decompressed payload are ever held in memory as a whole, which keeps the peak
+    /// memory of loading large caches close to the size of the loaded cache itself: the
+    /// entries of each type are copied from the reader as JSON text, and only parsed
+    /// when they are first accessed.
+    ///
+    /// Encrypted caches are the exception: the ciphertext can only be authenticated
+    /// as a whole, so it is buffered before decrypting.
+    ///
+    /// ```no_run
+    /// use mimir::{Cache, PersistOptions};
+    /// use std::fs::File;
+    ///
+    /// let file = File::open("cache.bin").unwrap();
+    /// let cache: Cache = Cache::load_streaming(file, &PersistOptions::new()).unwrap();
+    /// ```
+    pub fn load_streaming(reader: impl Read, opts: &PersistOptions) -> Result<Self, PersistError> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0; HEADER_LEN];
+
+        reader.read_exact(&mut header).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => PersistError::BadHeader,
+            _ => PersistError::IO(e),
+        })?;
+
+        let payload = opts.decoder(read_header(&header)?, reader)?;
+        let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(payload));
+
+        Ok(Self::from_json(&mut deserializer)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cache, Item, PersistOptions};
+    use serde::{Deserialize, Serialize};
+    use std::{collections::hash_map::DefaultHasher, hash::BuildHasherDefault};
+
+    #[derive(Serialize, Deserialize)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    impl Item for User {
+        type Key = u32;
+        const TYPE_KEY: &'static str = "struct User";
+
+        fn key(&self) -> Self::Key {
+            self.id
+        }
+    }
+
+    #[test]
+    fn streamed_caches_are_read_lazily() {
+        let mut cache = Cache::new();
+
+        for (id, name) in [(0, "ada"), (1, "grace")] {
+            let name = String::from(name);
+            cache.insert(User { id, name });
+        }
+
+        let opts = PersistOptions::new();
+        let bytes = cache.to_bytes(&opts).unwrap();
+        let loaded: Cache = Cache::load_streaming(&bytes[..], &opts).unwrap();
+
+        assert_eq!(loaded.serialized_type_keys().count(), 1);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&cache).unwrap()
+        );
+
+        assert_eq!(loaded.get::<User>(1).unwrap().name, "grace");
+        assert_eq!(loaded.serialized_type_keys().count(), 0);
+    }
+
+    #[test]
+    fn caches_load_with_any_hasher() {
+        let mut cache = Cache::new();
+        cache.insert(User {
+            id: 0,
+            name: String::from("ada"),
+        });
+
+        let opts = PersistOptions::new();
+        let bytes = cache.to_bytes(&opts).unwrap();
+        let loaded = Cache::<BuildHasherDefault<DefaultHasher>>::load(&bytes[..], &opts).unwrap();
+
+        assert_eq!(loaded.get::<User>(0).unwrap().name, "ada");
+    }
+}
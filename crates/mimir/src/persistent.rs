@@ -0,0 +1,201 @@
+use crate::{Cache, PersistError, PersistOptions};
+use std::{
+    env,
+    fs::{self, File},
+    io,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+};
+
+/// The file of a persistent cache, in the directory of the application
+const FILE_NAME: &str = "cache.bin";
+
+/// # PersistentCache
+///
+/// A `Cache` stored in the platform cache directory of an application, see
+/// `Cache::persistent`. It dereferences to the cache, and is saved with `save` or when
+/// dropped. Errors while saving on drop are ignored, call `save` to handle them.
+pub struct PersistentCache {
+    cache: Cache,
+    path: PathBuf,
+    opts: PersistOptions,
+    load_error: Option<PersistError>,
+}
+
+impl Cache {
+    /// Load the cache of the application `app` from the platform cache directory, or
+    /// create an empty one if there is none yet, or it cannot be loaded (e.g. it is
+    /// corrupt, or was saved by an incompatible version), see
+    /// `PersistentCache::load_error`. The directory is
+    ///  - `$XDG_CACHE_HOME/app` or `~/.cache/app` on Linux and other Unixes
+    ///  - `~/Library/Caches/app` on macOS
+    ///  - `%LOCALAPPDATA%\app` on Windows
+    ///
+    /// ```no_run
+    /// use mimir::Cache;
+    /// use std::time::Duration;
+    ///
+    /// let mut cache = Cache::persistent("my-app")
+    /// 	.unwrap()
+    /// 	.configure(|cache| cache.with_ttl(Duration::from_secs(3600)));
+    ///
+    /// cache.purge_expired();
+    /// cache.save().unwrap();
+    /// ```
+    pub fn persistent(app: &str) -> Result<PersistentCache, PersistError> {
+        Self::persistent_with(app, PersistOptions::new())
+    }
+
+    /// Like `persistent`, but the cache is loaded and saved with `opts`
+    pub fn persistent_with(
+        app: &str,
+        opts: PersistOptions,
+    ) -> Result<PersistentCache, PersistError> {
+        let path = cache_dir()
+            .ok_or(PersistError::NoCacheDir)?
+            .join(app)
+            .join(FILE_NAME);
+
+        Ok(PersistentCache::open(path, opts))
+    }
+}
+
+impl PersistentCache {
+    /// The cache saved at `path`, or an empty one
+    fn open(path: PathBuf, opts: PersistOptions) -> Self {
+        let loaded = match File::open(&path) {
+            Ok(file) => Cache::load(file, &opts),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Cache::new()),
+            Err(e) => Err(e.into()),
+        };
+
+        // it is only a cache, starting over beats failing on every start
+        let (cache, load_error) = match loaded {
+            Ok(cache) => (cache, None),
+            Err(e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(path = %path.display(), "starting with an empty cache: {e}");
+
+                (Cache::new(), Some(e))
+            }
+        };
+
+        Self {
+            cache,
+            path,
+            opts,
+            load_error,
+        }
+    }
+
+    /// Why the saved cache could not be loaded, if it could not. The cache then
+    /// started empty, and replaces the file once saved.
+    pub fn load_error(&self) -> Option<&PersistError> {
+        self.load_error.as_ref()
+    }
+
+    /// The file the cache is saved to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Rebuild the cache with `f`, e.g. to set a time-to-live or a capacity, which are
+    /// not saved with the cache
+    pub fn configure(mut self, f: impl FnOnce(Cache) -> Cache) -> Self {
+        self.cache = f(std::mem::take(&mut self.cache));
+        self
+    }
+
+    /// Write the cache to its file, creating the directory if needed. The file is
+    /// replaced at once, so a crash while saving does not lose the previous one.
+    pub fn save(&self) -> Result<(), PersistError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, self.cache.to_bytes(&self.opts)?)?;
+        fs::rename(temp, &self.path)?;
+
+        Ok(())
+    }
+}
+
+impl Deref for PersistentCache {
+    type Target = Cache;
+
+    fn deref(&self) -> &Cache {
+        &self.cache
+    }
+}
+
+impl DerefMut for PersistentCache {
+    fn deref_mut(&mut self) -> &mut Cache {
+        &mut self.cache
+    }
+}
+
+impl Drop for PersistentCache {
+    fn drop(&mut self) {
+        let _ = self.save();
+    }
+}
+
+/// The platform cache directory, if it can be found
+fn cache_dir() -> Option<PathBuf> {
+    let var = |name| {
+        env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|n| n.is_absolute())
+    };
+
+    if cfg!(windows) {
+        var("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|n| n.join("Library").join("Caches"))
+    } else {
+        var("XDG_CACHE_HOME").or_else(|| var("HOME").map(|n| n.join(".cache")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PersistentCache;
+    use crate::{Item, PersistOptions};
+    use serde::{Deserialize, Serialize};
+    use std::{env, fs, process};
+
+    #[derive(Serialize, Deserialize)]
+    struct Entry(u32);
+
+    impl Item for Entry {
+        type Key = u32;
+        const TYPE_KEY: &'static str = "struct Entry";
+
+        fn key(&self) -> Self::Key {
+            self.0
+        }
+    }
+
+    #[test]
+    fn corrupt_files_start_empty() {
+        let dir = env::temp_dir().join(format!("mimir-persistent-{}", process::id()));
+        let path = dir.join("cache.bin");
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, b"not a cache").unwrap();
+
+        let mut cache = PersistentCache::open(path.clone(), PersistOptions::new());
+        assert!(cache.load_error().is_some());
+        assert!(cache.is_empty());
+
+        cache.insert(Entry(1));
+        drop(cache);
+
+        let cache = PersistentCache::open(path, PersistOptions::new());
+        assert!(cache.load_error().is_none());
+        assert!(cache.get::<Entry>(1).is_some());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
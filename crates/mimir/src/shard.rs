@@ -0,0 +1,268 @@
+use crate::{
+    persist::{read_header, write_header, HEADER_LEN, MAGIC},
+    store::Slot,
+    Cache, PersistError, PersistOptions,
+};
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    hash::BuildHasher,
+    io::{self, Read},
+    path::Path,
+};
+
+/// A shard is a regular header, the number of entries (u64, LE) and the payload
+const SHARD_HEADER_LEN: usize = HEADER_LEN + 8;
+const EXTENSION: &str = "bin";
+
+/// `name`, with anything but ASCII alphanumerics, spaces and `-_.` percent-encoded,
+/// so any name maps to a valid file name
+pub(crate) fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+
+    for b in name.bytes() {
+        if b.is_ascii_alphanumeric() || b" -_.".contains(&b) {
+            escaped.push(b as char);
+        } else {
+            write!(escaped, "%{b:02X}").unwrap();
+        }
+    }
+
+    escaped
+}
+
+/// File name for the shard of `type_key`
+fn file_name(type_key: &str) -> String {
+    escape(type_key) + "." + EXTENSION
+}
+
+/// Inverse of `file_name`, `None` for files that are not shards
+fn type_key(file_name: &str) -> Option<String> {
+    let stem = file_name.strip_suffix(EXTENSION)?.strip_suffix('.')?;
+    let mut bytes = stem.bytes();
+    let mut decoded = Vec::with_capacity(stem.len());
+
+    while let Some(b) = bytes.next() {
+        if b != b'%' {
+            decoded.push(b);
+            continue;
+        }
+
+        let hex = [bytes.next()?, bytes.next()?];
+        decoded.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// Read and decode the payload of the shard at `path`
+pub(crate) fn read(path: &Path, opts: &PersistOptions) -> Result<Vec<u8>, PersistError> {
+    let bytes = fs::read(path)?;
+
+    if bytes.len() < SHARD_HEADER_LEN {
+        return Err(PersistError::BadHeader);
+    }
+
+    let flags = read_header(bytes[..HEADER_LEN].try_into().unwrap())?;
+    opts.decode(flags, bytes[SHARD_HEADER_LEN..].to_vec())
+}
+
+/// Whether the file at `path` starts like a file written by mimir, so that `save_dir`
+/// never removes files it did not write
+fn is_mimir_file(path: &Path) -> io::Result<bool> {
+    let mut magic = [0; MAGIC.len()];
+
+    match File::open(path)?.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+impl<H> Cache<H> {
+    /// Write the cache to `dir`, one file per type key (`dir/{type_key}.bin`).
+    ///
+    /// Only type keys that changed since they were last loaded from or saved to
+    /// `dir` (with the same `opts`) are rewritten, and shards of type keys that are
+    /// no longer in the cache are removed (other files are left alone). Type keys that
+    /// were never accessed are dropped from memory once written, and read back from
+    /// `dir` when needed. A shard that fails to be read back is never rewritten.
+    ///
+    /// ```no_run
+    /// use mimir::{Cache, PersistOptions};
+    ///
+    /// let opts = PersistOptions::new();
+    /// let mut cache: Cache = Cache::load_dir("cache", &opts).unwrap();
+    ///
+    /// // ... only the types touched here are written back
+    /// cache.save_dir("cache", &opts).unwrap();
+    /// ```
+    pub fn save_dir(
+        &mut self,
+        dir: impl AsRef<Path>,
+        opts: &PersistOptions,
+    ) -> Result<(), PersistError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        for file in fs::read_dir(dir)? {
+            let file = file?;
+
+            let Some(type_key) = file.file_name().to_str().and_then(type_key) else {
+                continue;
+            };
+
+            if !self.items.contains_key(&type_key) && is_mimir_file(&file.path())? {
+                fs::remove_file(file.path())?;
+            }
+        }
+
+        for (type_key, slot) in &mut self.items {
+            let path = dir.join(file_name(type_key));
+
+            if slot.is_shard(&path, opts) {
+                continue;
+            }
+
+            let payload = opts.encode(serde_json::to_vec(&*slot)?)?;
+
+            let mut bytes = Vec::with_capacity(SHARD_HEADER_LEN + payload.len());
+            write_header(&mut bytes, opts.flags());
+            bytes.extend_from_slice(&(slot.len() as u64).to_le_bytes());
+            bytes.extend(payload);
+
+            // write to a temporary file first, so a crash never leaves a torn shard behind
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, bytes)?;
+            fs::rename(&tmp, &path)?;
+
+            slot.set_shard(path, opts.clone());
+        }
+
+        Ok(())
+    }
+}
+
+impl<H: BuildHasher + Clone + Default + Send + Sync + 'static> Cache<H> {
+    /// Open a cache written by `save_dir`. Only the shard headers are read here,
+    /// each type key is read from its shard on first access.
+    pub fn load_dir(dir: impl AsRef<Path>, opts: &PersistOptions) -> Result<Self, PersistError> {
+        let mut cache = Cache::with_hasher(H::default());
+
+        for file in fs::read_dir(dir)? {
+            let file = file?;
+
+            let Some(type_key) = file.file_name().to_str().and_then(type_key) else {
+                continue;
+            };
+
+            let mut header = [0; SHARD_HEADER_LEN];
+
+            File::open(file.path())?
+                .read_exact(&mut header)
+                .map_err(|e| match e.kind() {
+                    io::ErrorKind::UnexpectedEof => PersistError::BadHeader,
+                    _ => PersistError::IO(e),
+                })?;
+
+            opts.check(read_header(header[..HEADER_LEN].try_into().unwrap())?)?;
+
+            let len = u64::from_le_bytes(header[HEADER_LEN..].try_into().unwrap());
+            let len = usize::try_from(len).unwrap_or(usize::MAX);
+
+            cache
+                .items
+                .insert(type_key, Slot::shard(file.path(), len, opts.clone()));
+        }
+
+        Ok(cache)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cache, Item, PersistOptions};
+    use serde::{Deserialize, Serialize};
+    use std::{fs, path::PathBuf, time::Duration};
+
+    #[derive(Serialize, Deserialize)]
+    struct User {
+        id: u32,
+    }
+
+    impl Item for User {
+        type Key = u32;
+        const TYPE_KEY: &'static str = "struct User";
+
+        fn key(&self) -> Self::Key {
+            self.id
+        }
+    }
+
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mimir-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn saved(dir: &PathBuf) -> PathBuf {
+        let mut cache = Cache::new();
+        cache.insert(User { id: 7 });
+        cache.save_dir(dir, &PersistOptions::new()).unwrap();
+
+        dir.join("struct User.bin")
+    }
+
+    #[test]
+    fn foreign_files_are_kept() {
+        let dir = dir("foreign-files");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("notes.bin"), "not a shard").unwrap();
+
+        Cache::new().save_dir(&dir, &PersistOptions::new()).unwrap();
+
+        assert!(dir.join("notes.bin").exists());
+    }
+
+    #[test]
+    fn unreadable_shards_are_not_rewritten() {
+        let dir = dir("unreadable-shard");
+        let path = saved(&dir);
+
+        // keep the header, corrupt the payload
+        let mut bytes = fs::read(&path).unwrap();
+        let len = bytes.len();
+        bytes[len - 4..].copy_from_slice(b"!!!!");
+        fs::write(&path, &bytes).unwrap();
+
+        let opts = PersistOptions::new();
+        let mut cache: Cache = Cache::load_dir(&dir, &opts).unwrap();
+
+        assert!(cache.get::<User>(7).is_none());
+        assert!(cache.try_insert(User { id: 8 }).is_err());
+
+        cache.save_dir(&dir, &opts).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+    }
+
+    #[test]
+    fn maintenance_only_rewrites_changed_types() {
+        let dir = dir("maintenance");
+        let path = saved(&dir);
+
+        let opts = PersistOptions::new();
+        let mut cache: Cache = Cache::load_dir(&dir, &opts)
+            .unwrap()
+            .with_ttl(Duration::from_secs(3600))
+            .with_capacity(10);
+
+        assert!(cache.get::<User>(7).is_some());
+        assert_eq!(cache.purge_expired(), 0);
+        assert_eq!(cache.evict_to_capacity(), 0);
+
+        // an unchanged type is not written again
+        fs::remove_file(&path).unwrap();
+        cache.save_dir(&dir, &opts).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{Cache, Item};
+use std::{
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+/// # SharedCache
+///
+/// A cheaply cloneable handle to a `Cache` behind an `Arc<RwLock<_>>`. Every clone
+/// refers to the same cache, so handles can be moved into threads and async tasks freely.
+///
+/// Reads (including the lazy deserialization of a type's entries) only take the
+/// read lock, writes take the write lock. A panic while holding the lock does not
+/// poison the handle, the cache is used as the panicking thread left it.
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// # use mimir::{Cache, Item, SharedCache};
+/// # use std::thread;
+/// #
+/// # #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+/// # struct User {
+/// # 	id: u32,
+/// # }
+/// #
+/// # impl Item for User {
+/// # 	type Key = u32;
+/// # 	const TYPE_KEY: &'static str = "struct User";
+/// #
+/// # 	fn key(&self) -> Self::Key {
+/// # 		self.id
+/// # 	}
+/// # }
+/// #
+/// let cache = SharedCache::new(Cache::new());
+///
+/// let handles = (0..4)
+/// 	.map(|id| {
+/// 		let cache = cache.clone();
+/// 		thread::spawn(move || cache.insert(User { id }))
+/// 	})
+/// 	.collect::<Vec<_>>();
+///
+/// for handle in handles {
+/// 	handle.join().unwrap();
+/// }
+///
+/// assert_eq!(cache.read().len(), 4);
+/// assert_eq!(cache.copied::<User>(3), Some(User { id: 3 }));
+/// ```
+///
+/// With an async runtime, the handle is moved into tasks the same way
+/// (e.g. `tokio::spawn(async move { cache.insert(item) })`). The lock is not held
+/// across `.await` points by any of the methods below.
+pub struct SharedCache<H = RandomState> {
+    inner: Arc<RwLock<Cache<H>>>,
+}
+
+impl<H> Clone for SharedCache<H> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<H> From<Cache<H>> for SharedCache<H> {
+    fn from(cache: Cache<H>) -> Self {
+        Self::new(cache)
+    }
+}
+
+impl<H> SharedCache<H> {
+    pub fn new(cache: Cache<H>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(cache)),
+        }
+    }
+
+    /// Lock the cache for reading
+    pub fn read(&self) -> RwLockReadGuard<'_, Cache<H>> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lock the cache for writing
+    pub fn write(&self) -> RwLockWriteGuard<'_, Cache<H>> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<H: BuildHasher + Clone + Send + Sync + 'static> SharedCache<H> {
+    pub fn insert<T: Item + 'static>(&self, item: T) {
+        self.write().insert(item)
+    }
+
+    pub fn copied<T: Item + 'static>(&self, key: T::Key) -> Option<T>
+    where
+        T: Copy,
+    {
+        self.read().copied(key)
+    }
+
+    pub fn cloned<T: Item + 'static>(&self, key: T::Key) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.read().cloned(key)
+    }
+
+    pub fn take<T: Item + 'static>(&self, key: T::Key) -> Option<T> {
+        self.write().take(key)
+    }
+
+    /// Run `f` on an entry while holding the read lock, for items that are not `Clone`
+    pub fn with<T: Item + 'static, R>(&self, key: T::Key, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.read().get(key).map(f)
+    }
+
+    /// Run `f` on an entry while holding the write lock
+    pub fn with_mut<T: Item + 'static, R>(
+        &self,
+        key: T::Key,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        self.write().get_mut(key).map(f)
+    }
+}
@@ -0,0 +1,930 @@
+use crate::{shard, Item, PersistOptions};
+use serde::{
+    de::{DeserializeSeed, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::{value::RawValue, Value};
+use std::{
+    any::Any,
+    collections::{btree_map, hash_map, BTreeMap, BTreeSet, HashMap},
+    fmt,
+    hash::BuildHasher,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// A single cached item, along with the bookkeeping needed for expiry and eviction.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "T: Item")]
+pub(crate) struct Entry<T> {
+    pub(crate) item: Box<T>,
+    pub(crate) inserted: SystemTime,
+    // nanoseconds since the unix epoch, 0 if never accessed (or not tracked)
+    #[serde(default, skip_serializing_if = "never")]
+    accessed: AtomicU64,
+}
+
+fn never(accessed: &AtomicU64) -> bool {
+    accessed.load(Ordering::Relaxed) == 0
+}
+
+impl<T> Entry<T> {
+    pub(crate) fn new(item: T) -> Self {
+        Self {
+            item: Box::new(item),
+            inserted: SystemTime::now(),
+            accessed: AtomicU64::new(0),
+        }
+    }
+
+    /// Record an access at the current time
+    pub(crate) fn touch(&self) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |n| u64::try_from(n.as_nanos()).unwrap_or(u64::MAX));
+
+        self.accessed.store(now, Ordering::Relaxed);
+    }
+
+    pub(crate) fn accessed(&self) -> Option<SystemTime> {
+        match self.accessed.load(Ordering::Relaxed) {
+            0 => None,
+            n => Some(UNIX_EPOCH + Duration::from_nanos(n)),
+        }
+    }
+}
+
+pub(crate) type InnerHashMap<T, H> = HashMap<<T as Item>::Key, Entry<T>, H>;
+pub(crate) type InnerBTreeMap<T> = BTreeMap<<T as Item>::Key, Entry<T>>;
+
+/// Deserializes an `InnerHashMap` using a given hasher instance, rather than `H::default()`
+struct InnerSeed<T, H>(H, PhantomData<T>);
+
+impl<'de, T: Item, H: BuildHasher> DeserializeSeed<'de> for InnerSeed<T, H> {
+    type Value = InnerHashMap<T, H>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, T: Item, H: BuildHasher> Visitor<'de> for InnerSeed<T, H> {
+    type Value = InnerHashMap<T, H>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a map of entries for \"{}\"", T::TYPE_KEY)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut items = HashMap::with_capacity_and_hasher(map.size_hint().unwrap_or(0), self.0);
+
+        while let Some((k, v)) = map.next_entry()? {
+            items.insert(k, v);
+        }
+
+        Ok(items)
+    }
+}
+
+/// # The `Entries` Trait
+///
+/// The map holding every entry of one type: an `InnerHashMap` by default, or an
+/// `InnerBTreeMap` for types in ordered mode (see `Cache::with_ordered`).
+pub(crate) trait Entries<T: Item>: erased_serde::Serialize + Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn len(&self) -> usize;
+
+    fn get(&self, key: &T::Key) -> Option<&Entry<T>>;
+
+    fn get_mut(&mut self, key: &T::Key) -> Option<&mut Entry<T>>;
+
+    fn insert(&mut self, key: T::Key, entry: Entry<T>) -> Option<Entry<T>>;
+
+    /// Insert `entry`, unless the entry for `key` is `occupied`, in which case `entry`
+    /// is given back. Returns the entry it replaced.
+    fn insert_unless(
+        &mut self,
+        key: T::Key,
+        entry: Entry<T>,
+        occupied: &dyn Fn(&Entry<T>) -> bool,
+    ) -> Result<Option<Entry<T>>, Entry<T>>;
+
+    fn remove(&mut self, key: &T::Key) -> Option<Entry<T>>;
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&T::Key, &Entry<T>)> + '_>;
+
+    fn into_entries(self: Box<Self>) -> Box<dyn Iterator<Item = (T::Key, Entry<T>)>>;
+
+    fn shrink(&mut self);
+}
+
+impl<T: Item + 'static, H: BuildHasher + Send + Sync + 'static> Entries<T> for InnerHashMap<T, H> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn get(&self, key: &T::Key) -> Option<&Entry<T>> {
+        HashMap::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &T::Key) -> Option<&mut Entry<T>> {
+        HashMap::get_mut(self, key)
+    }
+
+    fn insert(&mut self, key: T::Key, entry: Entry<T>) -> Option<Entry<T>> {
+        HashMap::insert(self, key, entry)
+    }
+
+    fn insert_unless(
+        &mut self,
+        key: T::Key,
+        entry: Entry<T>,
+        occupied: &dyn Fn(&Entry<T>) -> bool,
+    ) -> Result<Option<Entry<T>>, Entry<T>> {
+        match HashMap::entry(self, key) {
+            hash_map::Entry::Occupied(n) if occupied(n.get()) => Err(entry),
+            hash_map::Entry::Occupied(mut n) => Ok(Some(n.insert(entry))),
+            hash_map::Entry::Vacant(n) => {
+                n.insert(entry);
+                Ok(None)
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &T::Key) -> Option<Entry<T>> {
+        HashMap::remove(self, key)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&T::Key, &Entry<T>)> + '_> {
+        Box::new(HashMap::iter(self))
+    }
+
+    fn into_entries(self: Box<Self>) -> Box<dyn Iterator<Item = (T::Key, Entry<T>)>> {
+        Box::new(HashMap::into_iter(*self))
+    }
+
+    fn shrink(&mut self) {
+        self.shrink_to_fit();
+    }
+}
+
+impl<T: Item + 'static> Entries<T> for InnerBTreeMap<T>
+where
+    T::Key: Ord,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    fn get(&self, key: &T::Key) -> Option<&Entry<T>> {
+        BTreeMap::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &T::Key) -> Option<&mut Entry<T>> {
+        BTreeMap::get_mut(self, key)
+    }
+
+    fn insert(&mut self, key: T::Key, entry: Entry<T>) -> Option<Entry<T>> {
+        BTreeMap::insert(self, key, entry)
+    }
+
+    fn insert_unless(
+        &mut self,
+        key: T::Key,
+        entry: Entry<T>,
+        occupied: &dyn Fn(&Entry<T>) -> bool,
+    ) -> Result<Option<Entry<T>>, Entry<T>> {
+        match BTreeMap::entry(self, key) {
+            btree_map::Entry::Occupied(n) if occupied(n.get()) => Err(entry),
+            btree_map::Entry::Occupied(mut n) => Ok(Some(n.insert(entry))),
+            btree_map::Entry::Vacant(n) => {
+                n.insert(entry);
+                Ok(None)
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &T::Key) -> Option<Entry<T>> {
+        BTreeMap::remove(self, key)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&T::Key, &Entry<T>)> + '_> {
+        Box::new(BTreeMap::iter(self))
+    }
+
+    fn into_entries(self: Box<Self>) -> Box<dyn Iterator<Item = (T::Key, Entry<T>)>> {
+        Box::new(BTreeMap::into_iter(*self))
+    }
+
+    // a BTreeMap has no spare capacity to release
+    fn shrink(&mut self) {}
+}
+
+/// Entries by insertion time, oldest first, with their keys as JSON (an `Item::Key`
+/// is neither `Ord` nor `Clone`)
+type ByAge = BTreeSet<(SystemTime, String)>;
+
+/// The entries of one type, behind the type-erased `Store`
+struct Typed<T: Item> {
+    entries: Box<dyn Entries<T>>,
+    // built on the first eviction and kept up to date after, so that caches which
+    // never evict do not pay for it
+    by_age: Option<ByAge>,
+}
+
+impl<T: Item + 'static> Typed<T> {
+    fn new(entries: Box<dyn Entries<T>>) -> Self {
+        Self {
+            entries,
+            by_age: None,
+        }
+    }
+
+    fn by_age(&mut self) -> &mut ByAge {
+        let entries = &self.entries;
+
+        self.by_age.get_or_insert_with(|| {
+            entries
+                .iter()
+                .filter_map(|(k, e)| Some((e.inserted, serde_json::to_string(k).ok()?)))
+                .collect()
+        })
+    }
+}
+
+impl<T: Item> Serialize for Typed<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        erased_serde::serialize(&*self.entries, serializer)
+    }
+}
+
+/// Keeps the index up to date with every change. Looks like the inner map otherwise,
+/// e.g. to `as_any`.
+impl<T: Item + 'static> Entries<T> for Typed<T> {
+    fn as_any(&self) -> &dyn Any {
+        self.entries.as_any()
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn get(&self, key: &T::Key) -> Option<&Entry<T>> {
+        self.entries.get(key)
+    }
+
+    // the insertion time of an entry never changes
+    fn get_mut(&mut self, key: &T::Key) -> Option<&mut Entry<T>> {
+        self.entries.get_mut(key)
+    }
+
+    fn insert(&mut self, key: T::Key, entry: Entry<T>) -> Option<Entry<T>> {
+        self.insert_unless(key, entry, &|_| false).ok().flatten()
+    }
+
+    fn insert_unless(
+        &mut self,
+        key: T::Key,
+        entry: Entry<T>,
+        occupied: &dyn Fn(&Entry<T>) -> bool,
+    ) -> Result<Option<Entry<T>>, Entry<T>> {
+        let Some(by_age) = &mut self.by_age else {
+            return self.entries.insert_unless(key, entry, occupied);
+        };
+
+        let json = serde_json::to_string(&key).ok();
+        let inserted = entry.inserted;
+        let previous = self.entries.insert_unless(key, entry, occupied)?;
+
+        if let Some(json) = json {
+            if let Some(previous) = &previous {
+                by_age.remove(&(previous.inserted, json.clone()));
+            }
+
+            by_age.insert((inserted, json));
+        }
+
+        Ok(previous)
+    }
+
+    fn remove(&mut self, key: &T::Key) -> Option<Entry<T>> {
+        let removed = self.entries.remove(key)?;
+
+        if let (Some(by_age), Ok(json)) = (&mut self.by_age, serde_json::to_string(key)) {
+            by_age.remove(&(removed.inserted, json));
+        }
+
+        Some(removed)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&T::Key, &Entry<T>)> + '_> {
+        self.entries.iter()
+    }
+
+    fn into_entries(self: Box<Self>) -> Box<dyn Iterator<Item = (T::Key, Entry<T>)>> {
+        self.entries.into_entries()
+    }
+
+    fn shrink(&mut self) {
+        self.entries.shrink();
+    }
+}
+
+/// # The `Store` Trait
+///
+/// Type-erased operations on the inner map of one type key, so that maintenance
+/// and serialization can run over every type in the cache without knowing them.
+pub(crate) trait Store: erased_serde::Serialize + Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+
+    fn len(&self) -> usize;
+
+    /// When the oldest entry was inserted
+    fn oldest(&mut self) -> Option<SystemTime>;
+
+    /// Remove the oldest entry, calling `removed` with its key. Returns how many were
+    /// removed.
+    fn pop_oldest(&mut self, removed: &mut Removed) -> usize;
+
+    /// Look up an entry by its key as JSON, returning the item as JSON
+    fn get_dynamic(&self, key: Value) -> Option<(Value, SystemTime)>;
+
+    /// Every entry, with its key and item as JSON
+    fn iter_dynamic(&self) -> Box<dyn Iterator<Item = DynamicEntry> + '_>;
+
+    /// Remove every entry inserted at or before `deadline`, calling `removed` with the
+    /// key of each. Returns how many were removed.
+    fn purge_until(&mut self, deadline: SystemTime, removed: &mut Removed) -> usize;
+
+    fn shrink(&mut self);
+}
+
+erased_serde::serialize_trait_object!(Store);
+
+/// Key, item and insertion time of an entry of unknown type
+pub(crate) type DynamicEntry = (Value, Value, SystemTime);
+
+/// An entry that is still serialized, deserialized without knowing its type
+#[derive(Deserialize)]
+struct RawEntry {
+    item: Value,
+    inserted: SystemTime,
+}
+
+type RawEntries = HashMap<String, RawEntry>;
+
+/// The number of entries in a serialized map, counted without reading them
+struct EntryCount(usize);
+
+impl<'de> Deserialize<'de> for EntryCount {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CountVisitor;
+
+        impl<'de> Visitor<'de> for CountVisitor {
+            type Value = EntryCount;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a map of entries")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut count = 0;
+
+                while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {
+                    count += 1;
+                }
+
+                Ok(EntryCount(count))
+            }
+        }
+
+        deserializer.deserialize_map(CountVisitor)
+    }
+}
+
+/// The key of a serialized map for a key given as JSON, the inverse of `from_map_key`
+fn map_key(key: Value) -> Option<String> {
+    match key {
+        Value::String(key) => Some(key),
+        Value::Number(key) => Some(key.to_string()),
+        Value::Bool(key) => Some(key.to_string()),
+        _ => None,
+    }
+}
+
+/// Best guess of the JSON of a key from a serialized map key: the map key does not
+/// tell whether e.g. `"1"` was a number or a string, so anything that parses as a
+/// number or boolean is assumed to be one.
+fn from_map_key(key: String) -> Value {
+    serde_json::from_str(&key)
+        .ok()
+        .filter(|n: &Value| n.is_number() || n.is_boolean())
+        .unwrap_or(Value::String(key))
+}
+
+/// Called with the key of every entry removed by `Store`
+pub(crate) type Removed<'a> = dyn FnMut(&dyn erased_serde::Serialize) + 'a;
+
+impl<T: Item + 'static> Store for Typed<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn oldest(&mut self) -> Option<SystemTime> {
+        self.by_age().first().map(|(inserted, _)| *inserted)
+    }
+
+    fn pop_oldest(&mut self, removed: &mut Removed) -> usize {
+        let Some((_, json)) = self.by_age().pop_first() else {
+            return 0;
+        };
+
+        let Ok(key) = serde_json::from_str::<T::Key>(&json) else {
+            return 0;
+        };
+
+        match self.entries.remove(&key) {
+            Some(_) => {
+                removed(&key);
+                1
+            }
+            None => 0,
+        }
+    }
+
+    fn get_dynamic(&self, key: Value) -> Option<(Value, SystemTime)> {
+        let entry = self.entries.get(&T::Key::deserialize(key).ok()?)?;
+        Some((serde_json::to_value(&*entry.item).ok()?, entry.inserted))
+    }
+
+    fn iter_dynamic(&self) -> Box<dyn Iterator<Item = DynamicEntry> + '_> {
+        Box::new(self.entries.iter().filter_map(|(k, e)| {
+            Some((
+                serde_json::to_value(k).ok()?,
+                serde_json::to_value(&*e.item).ok()?,
+                e.inserted,
+            ))
+        }))
+    }
+
+    fn purge_until(&mut self, deadline: SystemTime, removed: &mut Removed) -> usize {
+        let mut count = 0;
+
+        while self.oldest().is_some_and(|n| n <= deadline) {
+            count += self.pop_oldest(removed);
+        }
+
+        count
+    }
+
+    fn shrink(&mut self) {
+        self.entries.shrink();
+    }
+}
+
+/// Serialized entries, not yet deserialized as any type
+enum Raw {
+    Value(Value),
+    /// The same as a `Value`, as JSON text, which takes far less memory
+    Json {
+        json: Box<RawValue>,
+        len: usize,
+    },
+    /// A shard written by `Cache::save_dir`, only read on first access
+    Shard {
+        path: PathBuf,
+        len: usize,
+        opts: PersistOptions,
+    },
+}
+
+impl Raw {
+    fn len(&self) -> usize {
+        match self {
+            Raw::Value(Value::Object(raw)) => raw.len(),
+            Raw::Value(_) => 0,
+            Raw::Json { len, .. } | Raw::Shard { len, .. } => *len,
+        }
+    }
+
+    fn deserialize<S, V>(&self, seed: S) -> Option<V>
+    where
+        S: for<'de> DeserializeSeed<'de, Value = V>,
+    {
+        match self {
+            Raw::Value(value) => seed.deserialize(value).ok(),
+            Raw::Json { json, .. } => seed
+                .deserialize(&mut serde_json::Deserializer::from_str(json.get()))
+                .ok(),
+            Raw::Shard { path, opts, .. } => {
+                let payload = shard::read(path, opts).ok()?;
+                seed.deserialize(&mut serde_json::Deserializer::from_slice(&payload))
+                    .ok()
+            }
+        }
+    }
+}
+
+impl Serialize for Raw {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Raw::Value(value) => value.serialize(serializer),
+            Raw::Json { json, .. } => serde_json::from_str::<Value>(json.get())
+                .map_err(serde::ser::Error::custom)?
+                .serialize(serializer),
+            Raw::Shard { path, opts, .. } => shard::read(path, opts)
+                .and_then(|payload| Ok(serde_json::from_slice::<Value>(&payload)?))
+                .map_err(serde::ser::Error::custom)?
+                .serialize(serializer),
+        }
+    }
+}
+
+/// # Slot
+///
+/// Everything stored under one type key. A deserialized cache has no way of
+/// knowing which Rust type belongs to a type key, so the entries are kept in
+/// serialized form (`raw`) until they are first accessed as some `T: Item`.
+pub(crate) struct Slot {
+    store: OnceLock<Box<dyn Store>>,
+    raw: Option<Raw>,
+}
+
+impl Slot {
+    fn typed<T: Item + 'static>(entries: Box<dyn Entries<T>>) -> Self {
+        Self {
+            store: OnceLock::from(Box::new(Typed::new(entries)) as Box<dyn Store>),
+            raw: None,
+        }
+    }
+
+    pub(crate) fn new<T: Item + 'static, H: BuildHasher + Send + Sync + 'static>(
+        hasher: H,
+    ) -> Self {
+        Self::typed(Box::new(InnerHashMap::<T, H>::with_hasher(hasher)))
+    }
+
+    pub(crate) fn new_ordered<T: Item + 'static>() -> Self
+    where
+        T::Key: Ord,
+    {
+        Self::typed(Box::new(InnerBTreeMap::<T>::new()))
+    }
+
+    pub(crate) fn serialized(raw: Value) -> Self {
+        Self {
+            store: OnceLock::new(),
+            raw: Some(Raw::Value(raw)),
+        }
+    }
+
+    /// Like `serialized`, for entries read as JSON text, e.g. by `Cache::load_streaming`
+    pub(crate) fn json(json: Box<RawValue>) -> Self {
+        let len = EntryCount::deserialize(&mut serde_json::Deserializer::from_str(json.get()))
+            .map_or(0, |n| n.0);
+
+        Self {
+            store: OnceLock::new(),
+            raw: Some(Raw::Json { json, len }),
+        }
+    }
+
+    pub(crate) fn shard(path: PathBuf, len: usize, opts: PersistOptions) -> Self {
+        Self {
+            store: OnceLock::new(),
+            raw: Some(Raw::Shard { path, len, opts }),
+        }
+    }
+
+    /// Whether the entries are unchanged since they were read from (or written to)
+    /// the shard at `path`, using `opts`
+    pub(crate) fn is_shard(&self, path: &Path, opts: &PersistOptions) -> bool {
+        matches!(&self.raw, Some(Raw::Shard { path: p, opts: o, .. }) if p == path && o == opts)
+    }
+
+    /// Mark the entries as written to the shard at `path`. Entries that have not been
+    /// accessed yet are dropped from memory, and read back from the shard when needed.
+    pub(crate) fn set_shard(&mut self, path: PathBuf, opts: PersistOptions) {
+        let len = self.len();
+        self.raw = Some(Raw::Shard { path, len, opts });
+    }
+
+    /// Number of entries, including ones that are still serialized
+    pub(crate) fn len(&self) -> usize {
+        match (self.store.get(), &self.raw) {
+            (Some(store), _) => store.len(),
+            (None, Some(raw)) => raw.len(),
+            (None, None) => 0,
+        }
+    }
+
+    /// Whether the entries have not been accessed (and deserialized) yet
+    pub(crate) fn is_serialized(&self) -> bool {
+        self.store.get().is_none()
+    }
+
+    /// The materialized entries, if they have been accessed already
+    pub(crate) fn store(&self) -> Option<&dyn Store> {
+        self.store.get().map(|n| &**n)
+    }
+
+    /// The materialized entries, if they have been accessed already. They are about
+    /// to be modified, so any serialized copy is dropped.
+    pub(crate) fn store_mut(&mut self) -> Option<&mut dyn Store> {
+        let store = self.store.get_mut()?;
+        self.raw = None;
+
+        Some(&mut **store)
+    }
+
+    /// Remove entries from the materialized entries with `remove`, which returns how
+    /// many it removed. The serialized copy is only dropped if there were any, so that
+    /// unchanged entries are not written again.
+    pub(crate) fn remove_with(&mut self, remove: impl FnOnce(&mut dyn Store) -> usize) -> usize {
+        let Some(store) = self.store.get_mut() else {
+            return 0;
+        };
+
+        let removed = remove(&mut **store);
+
+        if removed > 0 {
+            self.raw = None;
+        }
+
+        removed
+    }
+
+    /// Release the memory the entries do not need, without changing them: the unused
+    /// capacity of the materialized entries, and their serialized copy, which is never
+    /// read again. Entries that are still serialized are encoded as JSON, instead of
+    /// being kept as a `Value`.
+    pub(crate) fn compact(&mut self) {
+        if let Some(store) = self.store.get_mut() {
+            store.shrink();
+
+            // a shard is kept to tell whether it has to be written again
+            if let Some(Raw::Value(_) | Raw::Json { .. }) = self.raw {
+                self.raw = None;
+            }
+
+            return;
+        }
+
+        let len = self.len();
+
+        if let Some(Raw::Value(value)) = &self.raw {
+            if let Ok(json) = serde_json::value::to_raw_value(value) {
+                self.raw = Some(Raw::Json { json, len });
+            }
+        }
+    }
+
+    /// When the oldest of the materialized entries was inserted
+    pub(crate) fn oldest(&mut self) -> Option<SystemTime> {
+        self.store.get_mut()?.oldest()
+    }
+
+    /// Get the entries as `T`, deserializing them on first access. Returns `None`
+    /// if they were stored as a different type or fail to deserialize as `T`.
+    pub(crate) fn get<T, H>(&self, hasher: &H) -> Option<&dyn Entries<T>>
+    where
+        T: Item + 'static,
+        H: BuildHasher + Clone + Send + Sync + 'static,
+    {
+        if self.store.get().is_none() {
+            let seed = InnerSeed::<T, H>(hasher.clone(), PhantomData);
+            let items = self.raw.as_ref()?.deserialize(seed)?;
+
+            // if another thread won the race, its value is used instead
+            let _ = self.store.set(Box::new(Typed::<T>::new(Box::new(items))));
+        }
+
+        let typed = self.store.get()?.as_any().downcast_ref::<Typed<T>>()?;
+        Some(&*typed.entries)
+    }
+
+    /// Look up an entry without knowing its type. Entries that are still serialized
+    /// are looked up in their serialized form, without materializing them.
+    pub(crate) fn get_dynamic(&self, key: Value) -> Option<(Value, SystemTime)> {
+        if let Some(store) = self.store() {
+            return store.get_dynamic(key);
+        }
+
+        let mut entries = self.raw.as_ref()?.deserialize(PhantomData::<RawEntries>)?;
+
+        let entry = entries.remove(&map_key(key)?)?;
+        Some((entry.item, entry.inserted))
+    }
+
+    /// Every entry, without knowing their type. See `get_dynamic`.
+    pub(crate) fn iter_dynamic(&self) -> Box<dyn Iterator<Item = DynamicEntry> + '_> {
+        if let Some(store) = self.store() {
+            return store.iter_dynamic();
+        }
+
+        let entries = self
+            .raw
+            .as_ref()
+            .and_then(|n| n.deserialize(PhantomData::<RawEntries>))
+            .unwrap_or_default();
+
+        Box::new(
+            entries
+                .into_iter()
+                .map(|(k, e)| (from_map_key(k), e.item, e.inserted)),
+        )
+    }
+
+    /// Like `get`, for methods that are about to change the entries. Entries that are
+    /// not a `T` are never replaced, so that they are not lost.
+    pub(crate) fn try_get_mut<T, H>(&mut self, hasher: &H) -> Option<&mut dyn Entries<T>>
+    where
+        T: Item + 'static,
+        H: BuildHasher + Clone + Send + Sync + 'static,
+    {
+        self.get::<T, H>(hasher)?;
+
+        let typed = self.store_mut()?.as_any_mut().downcast_mut::<Typed<T>>()?;
+        Some(typed)
+    }
+
+    /// Move the entries of `T` into an ordered map. Gives the slot back unchanged
+    /// if they were stored as a different type or fail to deserialize as `T`.
+    pub(crate) fn into_ordered<T: Item + 'static>(mut self) -> Result<Self, Self>
+    where
+        T::Key: Ord,
+    {
+        if self.store.get().is_none() {
+            let seed = PhantomData::<InnerBTreeMap<T>>;
+
+            return match self.raw.as_ref().and_then(|n| n.deserialize(seed)) {
+                Some(items) => Ok(Self::typed(Box::new(items))),
+                None => Err(self),
+            };
+        }
+
+        let Some(store) = self.store.take() else {
+            return Err(self);
+        };
+
+        match store.as_any().downcast_ref::<Typed<T>>() {
+            Some(typed) if typed.entries.as_any().is::<InnerBTreeMap<T>>() => {
+                self.store = OnceLock::from(store);
+                Ok(self)
+            }
+            Some(_) => {
+                let typed = store.into_any().downcast::<Typed<T>>().unwrap();
+                let items = typed.entries.into_entries().collect::<InnerBTreeMap<T>>();
+
+                Ok(Self::typed(Box::new(items)))
+            }
+            None => {
+                self.store = OnceLock::from(store);
+                Err(self)
+            }
+        }
+    }
+}
+
+impl Serialize for Slot {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match (self.store(), &self.raw) {
+            (Some(store), _) => store.serialize(serializer),
+            (None, Some(raw)) => raw.serialize(serializer),
+            (None, None) => serializer.serialize_none(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::RandomState;
+
+    #[derive(Serialize, Deserialize)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    impl Item for User {
+        type Key = u32;
+        const TYPE_KEY: &'static str = "struct User";
+
+        fn key(&self) -> Self::Key {
+            self.id
+        }
+    }
+
+    fn entry(id: u32, secs: u64) -> Entry<User> {
+        let mut entry = Entry::new(User {
+            id,
+            name: String::new(),
+        });
+
+        entry.inserted = UNIX_EPOCH + Duration::from_secs(secs);
+        entry
+    }
+
+    fn typed(entries: impl IntoIterator<Item = (u32, u64)>) -> Typed<User> {
+        let mut typed = Typed::new(Box::new(InnerHashMap::<User, RandomState>::default()));
+
+        for (id, secs) in entries {
+            typed.insert(id, entry(id, secs));
+        }
+
+        typed
+    }
+
+    #[test]
+    fn the_oldest_entries_are_evicted_first() {
+        let mut typed = typed([(0, 3), (1, 1), (2, 2)]);
+        let mut removed = vec![];
+        let mut record =
+            |key: &dyn erased_serde::Serialize| removed.push(serde_json::to_string(key).unwrap());
+
+        assert_eq!(typed.oldest(), Some(UNIX_EPOCH + Duration::from_secs(1)));
+
+        // changes after the index is built are kept track of
+        typed.insert(2, entry(2, 5));
+        typed.remove(&0);
+        typed.insert(3, entry(3, 4));
+
+        while typed.pop_oldest(&mut record) > 0 {}
+        assert_eq!(removed, ["1", "3", "2"]);
+    }
+
+    #[test]
+    fn purging_includes_the_deadline() {
+        let mut typed = typed([(0, 1), (1, 2), (2, 3)]);
+        let deadline = UNIX_EPOCH + Duration::from_secs(2);
+
+        assert_eq!(typed.purge_until(deadline, &mut |_| {}), 2);
+        assert!(Entries::get(&typed, &2).is_some());
+    }
+
+    #[test]
+    fn mismatched_entries_are_kept() {
+        let raw = serde_json::json!({
+            "0": { "item": { "id": 0 }, "inserted": UNIX_EPOCH },
+        });
+
+        let mut slot = Slot::serialized(raw.clone());
+        let hasher = RandomState::new();
+
+        assert!(slot.try_get_mut::<User, _>(&hasher).is_none());
+        assert_eq!(slot.len(), 1);
+        assert_eq!(serde_json::to_value(&slot).unwrap(), raw);
+    }
+}
@@ -0,0 +1,152 @@
+//! Helpers for testing `Item` implementations (`test-utils` feature): `Generator`
+//! builds arbitrary caches of some types, and `assert_roundtrip` checks that a cache
+//! survives being saved and loaded in every format.
+//!
+//! ```
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use mimir::{test_utils::Generator, Item};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Arbitrary)]
+//! struct User {
+//! 	id: u32,
+//! 	name: String,
+//! }
+//!
+//! impl Item for User {
+//! 	type Key = u32;
+//! 	const TYPE_KEY: &'static str = "struct User";
+//!
+//! 	fn key(&self) -> Self::Key {
+//! 		self.id
+//! 	}
+//! }
+//!
+//! let generator = Generator::new().with::<User>();
+//!
+//! // usually the bytes come from a fuzzer or a property testing crate
+//! for seed in 0..32u8 {
+//! 	let bytes = (0..256).map(|n| (n as u8).wrapping_mul(seed)).collect::<Vec<_>>();
+//! 	let cache = generator.cache(&mut Unstructured::new(&bytes)).unwrap();
+//!
+//! 	generator.assert_roundtrip(&cache);
+//! }
+//! ```
+
+use crate::{Cache, Item, PersistOptions, Registry};
+use arbitrary::{Arbitrary, Unstructured};
+
+type Generate = fn(&mut Cache, &mut Unstructured<'_>) -> arbitrary::Result<()>;
+
+/// # Generator
+///
+/// Builds caches with arbitrary entries of the `Item` types added with `with`, from
+/// the bytes of an `Unstructured`.
+pub struct Generator {
+    types: Vec<Generate>,
+    registry: Registry,
+}
+
+impl Generator {
+    pub fn new() -> Self {
+        Self {
+            types: vec![],
+            registry: Registry::new(),
+        }
+    }
+
+    /// Generate entries of `T` too
+    pub fn with<T>(mut self) -> Self
+    where
+        T: Item + for<'a> Arbitrary<'a> + 'static,
+    {
+        self.types.push(|cache, u| {
+            for item in u.arbitrary_iter::<T>()? {
+                cache.insert(item?);
+            }
+
+            Ok(())
+        });
+
+        self.registry = self.registry.with::<T>();
+        self
+    }
+
+    /// The types of this generator, e.g. for `Cache::materialize_all`
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// A cache with any number of arbitrary entries of each type
+    pub fn cache(&self, u: &mut Unstructured<'_>) -> arbitrary::Result<Cache> {
+        let mut cache = Cache::new();
+
+        for generate in &self.types {
+            generate(&mut cache, u)?;
+        }
+
+        Ok(cache)
+    }
+
+    /// `assert_roundtrip` with the types of this generator
+    pub fn assert_roundtrip(&self, cache: &Cache) {
+        assert_roundtrip(cache, &self.registry)
+    }
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Save and load `cache` as JSON and in the binary format (compressed and encrypted
+/// too, with the `compression` and `encryption` features), and panic unless every
+/// type of `registry` deserializes and serializes to the same JSON as before
+pub fn assert_roundtrip(cache: &Cache, registry: &Registry) {
+    let expected = serde_json::to_value(cache).expect("the cache fails to serialize");
+
+    let check = |format: &str, loaded: Result<Cache, String>| {
+        let loaded = loaded.unwrap_or_else(|e| panic!("{format}: the cache fails to load: {e}"));
+
+        assert_eq!(
+            loaded.materialize_all(registry),
+            0,
+            "{format}: some types fail to deserialize"
+        );
+
+        let actual = serde_json::to_value(&loaded).expect("the cache fails to serialize");
+        assert_eq!(actual, expected, "{format}: the cache changed");
+    };
+
+    let json = serde_json::to_string(cache).expect("the cache fails to serialize");
+    check(
+        "JSON",
+        serde_json::from_str(&json).map_err(|e| e.to_string()),
+    );
+
+    #[allow(unused_mut)]
+    let mut formats = vec![("binary", PersistOptions::new())];
+
+    #[cfg(feature = "compression")]
+    formats.push(("compressed", PersistOptions::new().compressed(0)));
+
+    #[cfg(feature = "encryption")]
+    formats.push(("encrypted", PersistOptions::new().encrypted([7; 32])));
+
+    for (format, opts) in formats {
+        let bytes = cache
+            .to_bytes(&opts)
+            .unwrap_or_else(|e| panic!("{format}: the cache fails to save: {e}"));
+
+        check(
+            format,
+            Cache::from_bytes(&bytes, &opts).map_err(|e| e.to_string()),
+        );
+
+        check(
+            &format!("{format} (streaming)"),
+            Cache::load_streaming(bytes.as_slice(), &opts).map_err(|e| e.to_string()),
+        );
+    }
+}
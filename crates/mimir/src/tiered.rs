@@ -0,0 +1,287 @@
+use crate::{shard::escape, Cache, Item};
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    fs,
+    hash::BuildHasher,
+    io,
+    path::PathBuf,
+};
+use thiserror::Error;
+
+/// # The `Backend` Trait
+///
+/// The slower second level (L2) of a `TieredCache`, e.g. a directory on disk
+/// (`DirBackend`) or a Redis server. Entries are stored by type key and key, both as
+/// strings, the key and the item being serialized as JSON.
+pub trait Backend {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// The item stored for `key`, if any
+    fn get(&self, type_key: &str, key: &str) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    /// Store `item` for `key`, replacing any previous one
+    fn set(&mut self, type_key: &str, key: &str, item: &[u8]) -> Result<(), Self::Error>;
+
+    /// Remove the item stored for `key`, if any
+    fn remove(&mut self, type_key: &str, key: &str) -> Result<(), Self::Error>;
+}
+
+/// # DirBackend
+///
+/// A `Backend` storing each entry in its own file, `dir/{type_key}/{key}.json`.
+pub struct DirBackend {
+    dir: PathBuf,
+}
+
+impl DirBackend {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, type_key: &str, key: &str) -> PathBuf {
+        self.dir.join(escape(type_key)).join(escape(key) + ".json")
+    }
+}
+
+impl Backend for DirBackend {
+    type Error = io::Error;
+
+    fn get(&self, type_key: &str, key: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.path(type_key, key)) {
+            Ok(item) => Ok(Some(item)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn set(&mut self, type_key: &str, key: &str, item: &[u8]) -> io::Result<()> {
+        let path = self.path(type_key, key);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // write to a temporary file first, so a crash never leaves a torn entry behind
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, item)?;
+        fs::rename(tmp, path)
+    }
+
+    fn remove(&mut self, type_key: &str, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(type_key, key)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// # WriteMode
+///
+/// When a `TieredCache` writes its changes to the second level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriteMode {
+    /// On every `insert` and `take`, before changing the first level
+    #[default]
+    Through,
+    /// On `flush`, or when the cache is dropped, ignoring errors
+    Back,
+}
+
+#[derive(Error, Debug)]
+pub enum TieredError<E> {
+    #[error("Failed to (de)serialize entry: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("Second level cache error: {0}")]
+    Backend(E),
+}
+
+/// # TieredCache
+///
+/// A `Cache` (L1) in front of a slower `Backend` (L2). Entries missing from the first
+/// level are read from the second one and kept in the first one. Changes are written
+/// to the second level right away or on `flush`, see `WriteMode`.
+///
+/// The time-to-live and capacity of the first level only apply to it, entries it
+/// evicts are read from the second level again when needed.
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// # use mimir::{Cache, Item};
+/// #
+/// # #[derive(Serialize, Deserialize)]
+/// # struct User {
+/// # 	id: u32,
+/// # 	name: String,
+/// # }
+/// #
+/// # impl Item for User {
+/// # 	type Key = u32;
+/// # 	const TYPE_KEY: &'static str = "struct User";
+/// #
+/// # 	fn key(&self) -> Self::Key {
+/// # 		self.id
+/// # 	}
+/// # }
+/// #
+/// use mimir::{DirBackend, TieredCache, WriteMode};
+///
+/// let dir = std::env::temp_dir().join("mimir-tiered-doctest");
+/// # let _ = std::fs::remove_dir_all(&dir);
+///
+/// let mut cache = TieredCache::new(Cache::new(), DirBackend::new(&dir))
+/// 	.with_write_mode(WriteMode::Back);
+///
+/// cache.insert(User { id: 7, name: "Ada".to_string() }).unwrap();
+/// cache.flush().unwrap();
+///
+/// // another process, with an empty first level
+/// let mut other = TieredCache::new(Cache::new(), DirBackend::new(&dir));
+///
+/// assert_eq!(other.get::<User>(7).unwrap().unwrap().name, "Ada");
+/// assert!(other.l1().get::<User>(7).is_some());
+/// ```
+pub struct TieredCache<B: Backend, H = RandomState> {
+    l1: Cache<H>,
+    l2: B,
+    mode: WriteMode,
+    // (type key, key) -> the item to write back, or `None` to remove it
+    dirty: HashMap<(&'static str, String), Option<Vec<u8>>>,
+}
+
+impl<B: Backend, H: BuildHasher + Clone + Send + Sync + 'static> TieredCache<B, H> {
+    pub fn new(l1: Cache<H>, l2: B) -> Self {
+        Self {
+            l1,
+            l2,
+            mode: WriteMode::default(),
+            dirty: HashMap::new(),
+        }
+    }
+
+    pub fn with_write_mode(mut self, mode: WriteMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The first level. Changes made to it directly are not written to the second one.
+    pub fn l1(&self) -> &Cache<H> {
+        &self.l1
+    }
+
+    pub fn l1_mut(&mut self) -> &mut Cache<H> {
+        &mut self.l1
+    }
+
+    pub fn l2(&self) -> &B {
+        &self.l2
+    }
+
+    /// The entry for `key` from the first level, or from the second one, in which case
+    /// it is added to the first level
+    pub fn get<T: Item + 'static>(
+        &mut self,
+        key: T::Key,
+    ) -> Result<Option<&T>, TieredError<B::Error>> {
+        if self.l1.entry::<T>(&key).is_some() {
+            return Ok(self.l1.get(key));
+        }
+
+        let Some(item) = self.read::<T>(&serde_json::to_string(&key)?)? else {
+            return Ok(None);
+        };
+
+        self.l1.insert(serde_json::from_slice::<T>(&item)?);
+        Ok(self.l1.get(key))
+    }
+
+    /// Insert an item in both levels, overwriting any entry with the same key
+    pub fn insert<T: Item + 'static>(&mut self, item: T) -> Result<(), TieredError<B::Error>> {
+        let key = serde_json::to_string(&item.key())?;
+        self.write(T::TYPE_KEY, key, Some(serde_json::to_vec(&item)?))?;
+
+        self.l1.insert(item);
+        Ok(())
+    }
+
+    /// Remove an entry from both levels, returning it
+    pub fn take<T: Item + 'static>(
+        &mut self,
+        key: T::Key,
+    ) -> Result<Option<T>, TieredError<B::Error>> {
+        let json = serde_json::to_string(&key)?;
+
+        let stored = match self.l1.entry::<T>(&key) {
+            Some(_) => None,
+            None => self.read::<T>(&json)?,
+        };
+
+        self.write(T::TYPE_KEY, json, None)?;
+
+        match self.l1.take::<T>(key) {
+            Some(item) => Ok(Some(item)),
+            None => Ok(stored.map(|n| serde_json::from_slice(&n)).transpose()?),
+        }
+    }
+
+    /// Write the changes not written yet to the second level. Changes that fail to be
+    /// written are kept for the next `flush`.
+    pub fn flush(&mut self) -> Result<(), TieredError<B::Error>> {
+        let dirty = std::mem::take(&mut self.dirty);
+        let mut dirty = dirty.into_iter();
+
+        while let Some(((type_key, key), item)) = dirty.next() {
+            if let Err(e) = store(&mut self.l2, type_key, &key, item.as_deref()) {
+                self.dirty.insert((type_key, key), item);
+                self.dirty.extend(dirty);
+
+                return Err(TieredError::Backend(e));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The item stored for `key` in the second level, including changes not written yet
+    fn read<T: Item>(&self, key: &str) -> Result<Option<Vec<u8>>, TieredError<B::Error>> {
+        match self.dirty.get(&(T::TYPE_KEY, key.to_string())) {
+            Some(item) => Ok(item.clone()),
+            None => self.l2.get(T::TYPE_KEY, key).map_err(TieredError::Backend),
+        }
+    }
+
+    fn write(
+        &mut self,
+        type_key: &'static str,
+        key: String,
+        item: Option<Vec<u8>>,
+    ) -> Result<(), TieredError<B::Error>> {
+        if self.mode == WriteMode::Back {
+            self.dirty.insert((type_key, key), item);
+            return Ok(());
+        }
+
+        store(&mut self.l2, type_key, &key, item.as_deref()).map_err(TieredError::Backend)
+    }
+}
+
+/// Write `item` for `key` to `l2`, or remove the stored item if there is none
+fn store<B: Backend>(
+    l2: &mut B,
+    type_key: &str,
+    key: &str,
+    item: Option<&[u8]>,
+) -> Result<(), B::Error> {
+    match item {
+        Some(item) => l2.set(type_key, key, item),
+        None => l2.remove(type_key, key),
+    }
+}
+
+impl<B: Backend, H> Drop for TieredCache<B, H> {
+    fn drop(&mut self) {
+        for ((type_key, key), item) in self.dirty.drain() {
+            let _ = store(&mut self.l2, type_key, &key, item.as_deref());
+        }
+    }
+}
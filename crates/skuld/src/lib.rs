@@ -5,6 +5,15 @@
 //!  - `bail!`: A macro to return an error from a function
 //!  - `location!`: Get the full location information of the call (using file/line/column macros)
 //!  - `SkuldLogger`: A `log` crate facade that writes to the disk.
+//!    With the `tracing` feature, it can also be the `tracing` subscriber
+//!    (`SkuldLogger::init_tracing`), or a layer of one.
+//!    Its levels can be set from a config file, which `watch_config` applies
+//!    again whenever it changes, or right away with `reload`.
+//!    `scoped` returns a `Scope`, which logs with its own target and fields.
+//!    `verbose` and `VerboseGuard` raise its level for one task or thread, e.g. to
+//!    trace a single request.
+//!    `with_crash_reports` writes a report file when the application panics, with a
+//!    backtrace and the last lines of the log, and only shows the user its path.
 
 #[cfg(feature = "location")]
 use std::fmt;
@@ -87,7 +96,7 @@ impl fmt::Debug for ProvideLocation {
 #[macro_export]
 macro_rules! location {
     () => {
-        ::skuld::ProvideLocation::new(::core::file!(), ::core::line!(), ::core::column!())
+        $crate::ProvideLocation::new(::core::file!(), ::core::line!(), ::core::column!())
     };
 }
 
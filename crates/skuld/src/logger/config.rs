@@ -0,0 +1,161 @@
+use super::{error::*, verbose};
+use chrono::format::{Item, StrftimeItems};
+use log::LevelFilter;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, RwLock, Weak},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// How often a watched config file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The part of a `SkuldLogger` that can be changed by a config file
+#[derive(Clone)]
+pub(crate) struct Settings {
+    pub(crate) level: LevelFilter,
+    pub(crate) modules: HashMap<String, LevelFilter>,
+    pub(crate) fmt: String,
+}
+
+impl Settings {
+    pub(crate) fn max_level(&self) -> LevelFilter {
+        self.modules
+            .values()
+            .copied()
+            .max()
+            .unwrap_or(self.level)
+            .max(self.level)
+    }
+
+    /// These settings, with the ones set in the config file at `path` on top, see
+    /// `SkuldLogger::watch_config` for its format
+    pub(crate) fn with_config(&self, path: &Path) -> Result<Self, ConfigError> {
+        let mut settings = self.clone();
+
+        for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let parse_error = |message: String| ConfigError::Parse {
+                line: index + 1,
+                message,
+            };
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(parse_error(format!("expected `key = value`, got `{line}`")));
+            };
+
+            let key = key.trim();
+            let value = value.trim().trim_matches('"');
+
+            let level = || {
+                LevelFilter::from_str(value)
+                    .map_err(|_| parse_error(format!("`{value}` is not a level")))
+            };
+
+            match key {
+                "level" => settings.level = level()?,
+                "date_fmt" => {
+                    check_date_fmt(value).map_err(|e| parse_error(e.to_string()))?;
+                    settings.fmt = value.to_string();
+                }
+                module => {
+                    settings.modules.insert(module.to_string(), level()?);
+                }
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+/// Check that `fmt` is a valid chrono format, so formatting the time of a record
+/// cannot fail
+pub(crate) fn check_date_fmt(fmt: &str) -> Result<(), InvalidDateFormat> {
+    let valid = |spec: &str| !StrftimeItems::new(spec).any(|n| matches!(n, Item::Error));
+
+    let mut specifiers = vec![];
+    let mut rest = fmt;
+
+    while let Some(start) = rest.find('%') {
+        let spec = &rest[start..];
+
+        // the shortest valid specifier, e.g. `%Y`, `%-d` or `%.3f`
+        let end = (2..=spec.len().min(5))
+            .filter(|end| spec.is_char_boundary(*end))
+            .find(|end| valid(&spec[..*end]));
+
+        let end = end.unwrap_or_else(|| {
+            let end = spec.char_indices().nth(2).map_or(spec.len(), |(n, _)| n);
+            specifiers.push(spec[..end].to_string());
+            end
+        });
+
+        rest = &spec[end..];
+    }
+
+    match specifiers.is_empty() {
+        true => Ok(()),
+        false => Err(InvalidDateFormat {
+            format: fmt.to_string(),
+            specifiers,
+        }),
+    }
+}
+
+/// A config file applied on top of `base`, see `SkuldLogger::watch_config`
+pub(crate) struct Watched {
+    path: PathBuf,
+    base: Settings,
+}
+
+impl Watched {
+    pub(crate) fn new(path: PathBuf, base: Settings) -> Self {
+        Self { path, base }
+    }
+
+    /// Apply the file on top of the base settings. An invalid file leaves `settings`
+    /// as they are.
+    pub(crate) fn reload(&self, settings: &RwLock<Settings>) -> Result<(), ConfigError> {
+        let new = self.base.with_config(&self.path)?;
+
+        verbose::set_max_level(new.max_level());
+        *settings.write().unwrap() = new;
+        Ok(())
+    }
+}
+
+/// Reload `watched` whenever its file changes, until the logger is dropped. An
+/// invalid file is reported on stderr and ignored.
+pub(crate) fn watch(watched: Arc<Watched>, settings: Weak<RwLock<Settings>>) {
+    let modified = |path: &Path| fs::metadata(path).and_then(|n| n.modified()).ok();
+    let mut last: Option<SystemTime> = modified(&watched.path);
+
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let Some(settings) = settings.upgrade() else {
+            return;
+        };
+
+        let current = modified(&watched.path);
+
+        if current == last {
+            continue;
+        }
+
+        last = current;
+
+        if let Err(error) = watched.reload(&settings) {
+            eprintln!("skuld: {}: {error}", watched.path.display());
+        }
+    });
+}
@@ -0,0 +1,180 @@
+use chrono::Local;
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    env::consts,
+    fmt::Write as _,
+    fs, io,
+    panic::{self, PanicHookInfo},
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+};
+
+/// # CrashReports
+///
+/// Where and how `SkuldLogger` writes a report when the application panics, see
+/// `SkuldLogger::with_crash_reports`. The report has the name and version of the
+/// application, the OS, the panic message and location, a backtrace and the last
+/// lines that were logged. The user is only shown a short message with its path.
+///
+/// Only the panics that crash the application should be reported, but a panic hook
+/// cannot tell whether a panic will be caught, e.g. by a library isolating its tasks.
+/// By default, only the panics of the main thread are reported, see `with_filter`.
+/// Every other panic goes to the panic hook that was set before.
+///
+/// ```no_run
+/// use skuld::log::{CrashReports, SkuldLogger};
+///
+/// SkuldLogger::new("log.txt".into())
+/// 	.unwrap()
+/// 	.with_crash_reports(
+/// 		CrashReports::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+/// 			.with_dir("crashes")
+/// 			.with_lines(20),
+/// 	)
+/// 	.init()
+/// 	.unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct CrashReports {
+    name: String,
+    version: String,
+    dir: PathBuf,
+    lines: usize,
+    filter: fn(&PanicHookInfo) -> bool,
+}
+
+/// Whether the panic is on the main thread, whose panics end the application
+fn on_main_thread(_: &PanicHookInfo) -> bool {
+    thread::current().name() == Some("main")
+}
+
+impl CrashReports {
+    /// Reports for the application `name` at `version`, written to the temporary
+    /// directory with the last 50 lines of the log
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            dir: std::env::temp_dir(),
+            lines: 50,
+            filter: on_main_thread,
+        }
+    }
+
+    /// Write the reports to `dir`, which is created if needed
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = dir.into();
+        self
+    }
+
+    /// Include the last `lines` lines of the log in the reports
+    pub fn with_lines(mut self, lines: usize) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    /// Only report the panics for which `filter` returns `true`, e.g. to also report
+    /// the panics of the threads that are never restarted
+    pub fn with_filter(mut self, filter: fn(&PanicHookInfo) -> bool) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub(crate) fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Set a panic hook writing a report, which passes the panics that are not
+    /// reported to the previous hook
+    pub(crate) fn install(self, history: History) {
+        let previous = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            if !(self.filter)(info) {
+                return previous(info);
+            }
+
+            let report = self.report(info, &history);
+
+            match self.write(&report) {
+                Ok(path) => eprintln!(
+                    "{} crashed, sorry about that.\n\nA report was saved to `{}`.\nPlease attach it when reporting the problem.",
+                    self.name,
+                    path.display()
+                ),
+                Err(e) => eprintln!(
+                    "{} crashed, and the report could not be saved ({e}):\n\n{report}",
+                    self.name
+                ),
+            }
+        }));
+    }
+
+    fn report(&self, info: &PanicHookInfo, history: &History) -> String {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+
+        let location = info
+            .location()
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| String::from("unknown"));
+
+        let mut report = String::new();
+        let _ = writeln!(report, "name = {}", self.name);
+        let _ = writeln!(report, "version = {}", self.version);
+        let _ = writeln!(report, "os = {} ({})", consts::OS, consts::ARCH);
+        let _ = writeln!(report, "time = {}", Local::now().to_rfc3339());
+        let _ = writeln!(
+            report,
+            "thread = {}",
+            thread::current().name().unwrap_or("<unnamed>")
+        );
+        let _ = writeln!(report, "location = {location}");
+        let _ = writeln!(report, "message = {message}");
+        let _ = writeln!(report, "\n[backtrace]\n{}", Backtrace::force_capture());
+        let _ = writeln!(report, "[log]");
+
+        let history = history.lock().unwrap_or_else(PoisonError::into_inner);
+        report.extend(history.iter().map(String::as_str));
+
+        report
+    }
+
+    fn write(&self, report: &str) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+
+        let time = Local::now().format("%Y%m%d-%H%M%S");
+        let path = self.dir.join(format!(
+            "{}-crash-{time}-{}.txt",
+            self.name,
+            std::process::id()
+        ));
+
+        fs::write(&path, report)?;
+        Ok(path)
+    }
+}
+
+/// The last lines written by a `SkuldLogger`, for crash reports
+pub(crate) type History = Arc<Mutex<VecDeque<String>>>;
+
+/// Add `line` to `history`, dropping the oldest lines past `capacity`
+pub(crate) fn remember(history: &History, capacity: usize, line: &str) {
+    if capacity == 0 {
+        return;
+    }
+
+    let mut history = history.lock().unwrap_or_else(PoisonError::into_inner);
+
+    while history.len() >= capacity {
+        history.pop_front();
+    }
+
+    history.push_back(line.to_string());
+}
@@ -5,6 +5,7 @@ use std::{
     sync::{MutexGuard, PoisonError},
 };
 
+use itertools::Itertools;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -22,6 +23,51 @@ pub enum CreateLoggerError {
         error: log::SetLoggerError,
         location: &'static Location<'static>,
     },
+
+    #[error("At {location}: {error}")]
+    DateFormat {
+        #[from]
+        error: InvalidDateFormat,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: Invalid config: {error}")]
+    Config {
+        #[from]
+        error: ConfigError,
+        location: &'static Location<'static>,
+    },
+
+    #[cfg(feature = "tracing")]
+    #[error("At {location}: Failed to set subscriber: {error}")]
+    SetSubscriber {
+        #[from]
+        error: tracing::subscriber::SetGlobalDefaultError,
+        location: &'static Location<'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("At {location}: IO error: {error}")]
+    IO {
+        #[from]
+        error: io::Error,
+        location: &'static Location<'static>,
+    },
+
+    #[error("Line {line}: {message}")]
+    Parse { line: usize, message: String },
+}
+
+#[derive(Error, Debug)]
+#[error(
+    "Invalid date format `{format}`: unknown specifiers {}",
+    .specifiers.iter().map(|n| format!("`{n}`")).join(", ")
+)]
+pub struct InvalidDateFormat {
+    pub format: String,
+    pub specifiers: Vec<String>,
 }
 
 #[derive(Error, Debug)]
@@ -3,10 +3,25 @@ extern crate itertools;
 extern crate log;
 extern crate thiserror;
 
+#[cfg(feature = "tracing")]
+extern crate tracing;
+
+#[cfg(feature = "tracing")]
+extern crate tracing_subscriber;
+
+mod config;
+mod crash;
 mod error;
 mod pretty;
+mod scope;
+mod verbose;
+
+#[cfg(feature = "tracing")]
+mod subscriber;
 
 use chrono::Local;
+use config::{Settings, Watched};
+use crash::{CrashReports, History};
 use error::*;
 use itertools::Itertools;
 use log::LevelFilter;
@@ -16,14 +31,16 @@ use std::{
     fs::{File, OpenOptions},
     io::Write,
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, RwLock},
 };
 
+#[derive(Clone)]
 pub struct SkuldLogger {
-    level: LevelFilter,
-    modules: HashMap<String, LevelFilter>,
-    fmt: &'static str,
+    settings: Arc<RwLock<Settings>>,
     file: Arc<Mutex<File>>,
+    crash: Option<CrashReports>,
+    history: History,
+    watched: Option<Arc<Watched>>,
 }
 
 impl SkuldLogger {
@@ -35,43 +52,113 @@ impl SkuldLogger {
             .open(path)?;
 
         Ok(SkuldLogger {
-            level: LevelFilter::Info,
-            modules: HashMap::new(),
+            settings: Arc::new(RwLock::new(Settings {
+                level: LevelFilter::Info,
+                modules: HashMap::new(),
+                fmt: String::from("%Y-%m-%d %l:%M:%S%.3f %p"),
+            })),
             file: Arc::new(Mutex::new(file)),
-            fmt: "%Y-%m-%d %l:%M:%S%.3f %p",
+            crash: None,
+            history: History::default(),
+            watched: None,
         })
     }
 
-    pub fn with_level(mut self, level: LevelFilter) -> Self {
-        self.level = level;
+    pub fn with_level(self, level: LevelFilter) -> Self {
+        self.settings.write().unwrap().level = level;
         self
     }
 
-    pub fn with_module(mut self, module: impl Into<String>, level: LevelFilter) -> Self {
-        self.modules.insert(module.into(), level);
+    pub fn with_module(self, module: impl Into<String>, level: LevelFilter) -> Self {
+        self.settings
+            .write()
+            .unwrap()
+            .modules
+            .insert(module.into(), level);
         self
     }
 
     pub fn max_level(&self) -> LevelFilter {
-        self.modules
-            .values()
-            .copied()
-            .max()
-            .unwrap_or(self.level)
-            .max(self.level)
+        self.settings.read().unwrap().max_level()
+    }
+
+    /// The chrono format of the time of the records, e.g. `%H:%M:%S`. Fails if it has
+    /// a specifier chrono does not know.
+    pub fn date_fmt(self, date_fmt: impl Into<String>) -> Result<Self, InvalidDateFormat> {
+        self.set_date_fmt(date_fmt)?;
+        Ok(self)
+    }
+
+    /// Like `date_fmt`, but changes the format of a running logger
+    pub fn set_date_fmt(&self, date_fmt: impl Into<String>) -> Result<(), InvalidDateFormat> {
+        let date_fmt = date_fmt.into();
+        config::check_date_fmt(&date_fmt)?;
+
+        self.settings.write().unwrap().fmt = date_fmt;
+        Ok(())
+    }
+
+    /// Apply the level, module and date format settings of the config file at `path`
+    /// on top of the current ones, see `watch_config` for its format
+    pub fn with_config(self, path: impl Into<PathBuf>) -> Result<Self, CreateLoggerError> {
+        let settings = self.settings.read().unwrap().with_config(&path.into())?;
+        *self.settings.write().unwrap() = settings;
+        Ok(self)
     }
 
-    pub fn date_fmt(mut self, date_fmt: &'static str) -> Self {
-        self.fmt = date_fmt;
+    /// Like `with_config`, and apply the config file again whenever it changes while
+    /// the logger is running, on top of the settings set before this call. An invalid
+    /// file is reported on stderr and the current settings are kept.
+    ///
+    /// The file has one `key = value` per line, and `#` comments:
+    ///  - `level`: the level of every module, e.g. `debug`
+    ///  - `date_fmt`: the chrono format of the time, e.g. `%H:%M:%S`
+    ///  - any other key is a module, e.g. `hermod::queue = trace`
+    pub fn watch_config(self, path: impl Into<PathBuf>) -> Result<Self, CreateLoggerError> {
+        let path = path.into();
+        let base = self.settings.read().unwrap().clone();
+
+        let mut logger = self.with_config(&path)?;
+        let watched = Arc::new(Watched::new(path, base));
+
+        config::watch(Arc::clone(&watched), Arc::downgrade(&logger.settings));
+        logger.watched = Some(watched);
+
+        Ok(logger)
+    }
+
+    /// Apply the config file of `watch_config` again right away, instead of when the
+    /// change is noticed, e.g. on a signal. An invalid file keeps the current settings.
+    /// Does nothing if no file is watched.
+    pub fn reload(&self) -> Result<(), ConfigError> {
+        match &self.watched {
+            Some(watched) => watched.reload(&self.settings),
+            None => Ok(()),
+        }
+    }
+
+    /// Write a crash report when the application panics, and only show the user where
+    /// it is, see `CrashReports`. The panic hook is set by `init` (or `init_tracing`),
+    /// and passes the panics that are not reported to the previous one.
+    pub fn with_crash_reports(mut self, reports: CrashReports) -> Self {
+        self.crash = Some(reports);
         self
     }
 
     pub fn init(self) -> Result<(), CreateLoggerError> {
-        log::set_max_level(self.max_level());
+        let max_level = self.max_level();
+        self.install_crash_reports();
         log::set_boxed_logger(Box::new(self))?;
+        verbose::set_max_level(max_level);
         Ok(())
     }
 
+    pub(crate) fn install_crash_reports(&self) {
+        if let Some(crash) = self.crash.clone() {
+            crash.install(Arc::clone(&self.history));
+        }
+    }
+
     fn write(&self, message: String) -> Result<(), WriteFileError<'_>> {
         let mut file = self.file.lock()?;
         file.write_all(message.as_bytes())?;
@@ -86,6 +173,42 @@ impl SkuldLogger {
         Ok(())
     }
 
+    fn enabled_for(&self, level: log::Level, target: &str) -> bool {
+        let settings = self.settings.read().unwrap();
+
+        let configured = *settings
+            .modules
+            .iter()
+            .find(|(name, _level)| target.starts_with(*name))
+            .map(|(_name, level)| level)
+            .unwrap_or(&settings.level);
+
+        level <= configured.max(verbose::current())
+    }
+
+    fn record(&self, level: log::Level, module: &str, args: &Arguments) {
+        let fmt = self.settings.read().unwrap().fmt.clone();
+        let time = Local::now().format(&fmt).to_string().trim().to_string();
+        let message = SkuldLogger::multiline_message(args);
+
+        let formatted = {
+            let message = pretty::light(&message);
+            let level = pretty::level(level);
+            let module = pretty::bold(module);
+
+            format!("{time} {level} [{module}] {message}\n")
+        };
+
+        let unformatted = format!("{time} {level} [{module}] {message}\n");
+
+        if let Some(crash) = &self.crash {
+            crash::remember(&self.history, crash.lines(), &unformatted);
+        }
+
+        print!("{}", formatted);
+        self.write(unformatted).unwrap();
+    }
+
     fn multiline_message(args: &Arguments) -> String {
         let msg = args.to_string().trim().to_string();
 
@@ -99,13 +222,7 @@ impl SkuldLogger {
 
 impl log::Log for SkuldLogger {
     fn enabled(&self, meta: &log::Metadata) -> bool {
-        meta.level()
-            <= *self
-                .modules
-                .iter()
-                .find(|(name, _level)| meta.target().starts_with(*name))
-                .map(|(_name, level)| level)
-                .unwrap_or(&self.level)
+        self.enabled_for(meta.level(), meta.target())
     }
 
     fn log(&self, record: &log::Record) {
@@ -113,23 +230,7 @@ impl log::Log for SkuldLogger {
             return;
         }
 
-        let time = Local::now().format(self.fmt).to_string().trim().to_string();
-        let level = record.level();
-        let module = record.target();
-        let message = SkuldLogger::multiline_message(record.args());
-
-        let formatted = {
-            let message = pretty::light(&message);
-            let level = pretty::level(level);
-            let module = pretty::bold(module);
-
-            format!("{time} {level} [{module}] {message}\n")
-        };
-
-        let unformatted = format!("{time} {level} [{module}] {message}\n");
-
-        print!("{}", formatted);
-        self.write(unformatted).unwrap();
+        self.record(record.level(), record.target(), record.args());
     }
 
     fn flush(&self) {
@@ -138,6 +239,9 @@ impl log::Log for SkuldLogger {
 }
 
 pub mod prelude {
+    pub use super::crash::CrashReports;
     pub use super::error::*;
+    pub use super::scope::Scope;
+    pub use super::verbose::{verbose, Verbose, VerboseGuard};
     pub use super::SkuldLogger;
 }
@@ -0,0 +1,78 @@
+use super::SkuldLogger;
+use log::Level;
+use std::{fmt::Display, sync::Arc};
+
+/// # Scope
+///
+/// A handle to a `SkuldLogger` for one component, see `SkuldLogger::scoped`. Its
+/// records use the name of the scope as their target, so `with_module` filters apply
+/// to it, and end with the fields of the scope.
+///
+/// Scopes are cheap to clone, and keep writing to the logger after it is initialized.
+#[derive(Clone)]
+pub struct Scope {
+    logger: SkuldLogger,
+    target: Arc<str>,
+    fields: Arc<str>,
+}
+
+impl SkuldLogger {
+    /// A handle whose records have the target `name`
+    pub fn scoped(&self, name: impl Display) -> Scope {
+        Scope {
+            logger: self.clone(),
+            target: Arc::from(name.to_string()),
+            fields: Arc::from(""),
+        }
+    }
+}
+
+impl Scope {
+    /// A scope nested in this one, with the target `parent::name` and the same fields
+    pub fn scoped(&self, name: impl Display) -> Scope {
+        Scope {
+            target: Arc::from(format!("{}::{name}", self.target)),
+            ..self.clone()
+        }
+    }
+
+    /// Add `key=value` to every record of this scope
+    pub fn with_field(mut self, key: impl Display, value: impl Display) -> Self {
+        self.fields = Arc::from(format!("{} {key}={value}", self.fields));
+        self
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn log(&self, level: Level, message: impl Display) {
+        if self.logger.enabled_for(level, &self.target) {
+            self.logger.record(
+                level,
+                &self.target,
+                &format_args!("{message}{}", self.fields),
+            );
+        }
+    }
+
+    pub fn error(&self, message: impl Display) {
+        self.log(Level::Error, message)
+    }
+
+    pub fn warn(&self, message: impl Display) {
+        self.log(Level::Warn, message)
+    }
+
+    pub fn info(&self, message: impl Display) {
+        self.log(Level::Info, message)
+    }
+
+    pub fn debug(&self, message: impl Display) {
+        self.log(Level::Debug, message)
+    }
+
+    pub fn trace(&self, message: impl Display) {
+        self.log(Level::Trace, message)
+    }
+}
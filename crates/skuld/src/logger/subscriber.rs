@@ -0,0 +1,85 @@
+use super::{error::*, SkuldLogger};
+use itertools::Itertools;
+use std::fmt;
+use tracing::{
+    field::{Field, Visit},
+    subscriber::Interest,
+    Event, Level, Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+impl SkuldLogger {
+    /// Install the logger as the global `tracing` subscriber, instead of the `log`
+    /// logger. Events are written like records, prefixed by the names of their spans.
+    pub fn init_tracing(self) -> Result<(), CreateLoggerError> {
+        self.install_crash_reports();
+        tracing::subscriber::set_global_default(tracing_subscriber::registry().with(self))?;
+        Ok(())
+    }
+}
+
+impl<S> Layer<S> for SkuldLogger
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    // `verbose` changes the answer of `enabled` per task, so it must not be cached
+    fn register_callsite(&self, _meta: &'static Metadata<'static>) -> Interest {
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, meta: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        self.enabled_for(level(meta.level()), meta.target())
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut visitor = Message::default();
+        event.record(&mut visitor);
+
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| scope.from_root().map(|span| span.name()).join(":"));
+
+        let text = [visitor.message, visitor.fields.join(" ")]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .join(" ");
+
+        let message = match spans {
+            Some(spans) => format!("{spans}: {text}"),
+            None => text,
+        };
+
+        self.record(
+            level(meta.level()),
+            meta.target(),
+            &format_args!("{message}"),
+        );
+    }
+}
+
+/// The message and the other fields of an event
+#[derive(Default)]
+struct Message {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{value:?}"),
+            name => self.fields.push(format!("{name}={value:?}")),
+        }
+    }
+}
+
+fn level(level: &Level) -> log::Level {
+    match *level {
+        Level::ERROR => log::Level::Error,
+        Level::WARN => log::Level::Warn,
+        Level::INFO => log::Level::Info,
+        Level::DEBUG => log::Level::Debug,
+        _ => log::Level::Trace,
+    }
+}
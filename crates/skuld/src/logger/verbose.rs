@@ -0,0 +1,152 @@
+use log::LevelFilter;
+use std::{
+    cell::Cell,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+thread_local! {
+    /// The level the current thread or task is raised to
+    static OVERRIDE: Cell<LevelFilter> = const { Cell::new(LevelFilter::Off) };
+}
+
+/// The max level of the installed `SkuldLogger`, and how many overrides of each level
+/// are active. `log` drops records above its max level before asking the logger, so
+/// it is raised while an override is active.
+struct Levels {
+    base: Option<LevelFilter>,
+    active: [usize; 6],
+}
+
+static LEVELS: Mutex<Levels> = Mutex::new(Levels {
+    base: None,
+    active: [0; 6],
+});
+
+impl Levels {
+    fn apply(&self) {
+        let Some(base) = self.base else {
+            return;
+        };
+
+        let raised = LevelFilter::iter()
+            .filter(|level| self.active[*level as usize] > 0)
+            .max()
+            .unwrap_or(LevelFilter::Off);
+
+        log::set_max_level(base.max(raised));
+    }
+}
+
+/// Set the max level of `log` to `base`, or higher while an override is active
+pub(crate) fn set_max_level(base: LevelFilter) {
+    let mut levels = LEVELS.lock().unwrap();
+    levels.base = Some(base);
+    levels.apply();
+}
+
+/// The level the current thread or task is raised to, `Off` if it is not
+pub(crate) fn current() -> LevelFilter {
+    OVERRIDE.with(Cell::get)
+}
+
+fn activate(level: LevelFilter) {
+    let mut levels = LEVELS.lock().unwrap();
+    levels.active[level as usize] += 1;
+    levels.apply();
+}
+
+fn deactivate(level: LevelFilter) {
+    let mut levels = LEVELS.lock().unwrap();
+    levels.active[level as usize] -= 1;
+    levels.apply();
+}
+
+/// Raise the level of the current thread to `level`, returning the previous one
+fn raise(level: LevelFilter) -> LevelFilter {
+    OVERRIDE.with(|n| n.replace(n.get().max(level)))
+}
+
+fn restore(previous: LevelFilter) {
+    OVERRIDE.with(|n| n.set(previous))
+}
+
+/// Log everything up to `level` while `future` runs, whatever the level of its module,
+/// e.g. to trace a single request without tracing every other one.
+///
+/// ```
+/// use skuld::log::verbose;
+/// use log::LevelFilter;
+///
+/// async fn handle(request: u32) {
+/// 	log::trace!("handling {request}");
+/// }
+///
+/// async_std::task::block_on(verbose(LevelFilter::Trace, handle(7)));
+/// ```
+pub fn verbose<F: Future>(level: LevelFilter, future: F) -> Verbose<F> {
+    activate(level);
+
+    Verbose {
+        level,
+        future: Box::pin(future),
+    }
+}
+
+/// # Verbose
+///
+/// A future that raises the level of the logger while it is polled, see `verbose`.
+pub struct Verbose<F> {
+    level: LevelFilter,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Verbose<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let previous = raise(self.level);
+        let poll = self.future.as_mut().poll(cx);
+        restore(previous);
+
+        poll
+    }
+}
+
+impl<F> Drop for Verbose<F> {
+    fn drop(&mut self) {
+        deactivate(self.level);
+    }
+}
+
+/// # VerboseGuard
+///
+/// Log everything up to a level on the current thread until the guard is dropped,
+/// like `verbose` for synchronous code.
+pub struct VerboseGuard {
+    level: LevelFilter,
+    previous: LevelFilter,
+    _thread: PhantomData<*const ()>,
+}
+
+impl VerboseGuard {
+    pub fn new(level: LevelFilter) -> Self {
+        activate(level);
+
+        Self {
+            level,
+            previous: raise(level),
+            _thread: PhantomData,
+        }
+    }
+}
+
+impl Drop for VerboseGuard {
+    fn drop(&mut self) {
+        restore(self.previous);
+        deactivate(self.level);
+    }
+}
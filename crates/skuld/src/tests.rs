@@ -25,3 +25,161 @@ fn logger() {
 
     panic!("This is a test panic!")
 }
+
+#[test]
+fn watch_config() {
+    use crate::logger::SkuldLogger;
+    use log::LevelFilter;
+    use std::{env, fs, process};
+
+    let dir = env::temp_dir();
+    let path = dir.join(format!("skuld-watch-config-{}.txt", process::id()));
+    fs::write(&path, "level = warn\n").unwrap();
+
+    let logger = SkuldLogger::new(dir.join(format!("skuld-watch-config-{}.log", process::id())))
+        .unwrap()
+        .watch_config(&path)
+        .unwrap();
+
+    assert_eq!(logger.max_level(), LevelFilter::Warn);
+
+    fs::write(
+        &path,
+        "# more details\nlevel = warn\nhermod::queue = trace\n",
+    )
+    .unwrap();
+    logger.reload().unwrap();
+
+    assert_eq!(logger.max_level(), LevelFilter::Trace);
+
+    // invalid changes are ignored
+    fs::write(&path, "level = loud\n").unwrap();
+    assert!(logger.reload().is_err());
+
+    assert_eq!(logger.max_level(), LevelFilter::Trace);
+}
+
+#[test]
+fn scoped() {
+    use crate::logger::SkuldLogger;
+    use log::LevelFilter;
+    use std::fs;
+
+    let path = std::env::temp_dir().join("skuld-scoped.txt");
+    let _ = fs::remove_file(&path);
+
+    let logger = SkuldLogger::new(path.clone())
+        .unwrap()
+        .with_level(LevelFilter::Warn)
+        .with_module("worker-3", LevelFilter::Debug);
+
+    let worker = logger.scoped("worker-3").with_field("id", 3);
+    worker.debug("started");
+    worker.scoped("db").trace("connected");
+    logger.scoped("worker-4").info("started");
+
+    let log = fs::read_to_string(&path).unwrap();
+
+    assert!(log.contains("DEBUG [worker-3] started id=3"));
+    assert!(!log.contains("connected"));
+    assert!(!log.contains("worker-4"));
+}
+
+#[test]
+fn verbose() {
+    use crate::logger::{
+        prelude::{verbose, VerboseGuard},
+        SkuldLogger,
+    };
+    use log::LevelFilter;
+    use std::fs;
+
+    let path = std::env::temp_dir().join("skuld-verbose.txt");
+    let _ = fs::remove_file(&path);
+
+    let logger = SkuldLogger::new(path.clone())
+        .unwrap()
+        .with_level(LevelFilter::Info);
+
+    let scope = logger.scoped("requests");
+    scope.debug("before");
+
+    {
+        let _guard = VerboseGuard::new(LevelFilter::Debug);
+        scope.debug("guarded");
+        scope.trace("too detailed");
+    }
+
+    async_std::task::block_on(verbose(LevelFilter::Trace, async {
+        scope.trace("traced");
+    }));
+
+    scope.debug("after");
+
+    let log = fs::read_to_string(&path).unwrap();
+
+    assert!(log.contains("[requests] guarded"));
+    assert!(log.contains("[requests] traced"));
+    assert!(!log.contains("before"));
+    assert!(!log.contains("too detailed"));
+    assert!(!log.contains("after"));
+}
+
+#[test]
+fn date_fmt() {
+    use crate::logger::SkuldLogger;
+
+    let logger = SkuldLogger::new("log.txt".into()).unwrap();
+
+    assert!(logger.set_date_fmt("%Y-%m-%d %-l:%M:%S%.3f %p").is_ok());
+
+    let error = logger.set_date_fmt(String::from("%Y-%Q %H %")).unwrap_err();
+    assert_eq!(error.specifiers, ["%Q", "%"]);
+}
+
+#[test]
+fn crash_reports() {
+    use crate::logger::{prelude::CrashReports, SkuldLogger};
+    use std::{fs, thread};
+
+    let dir = std::env::temp_dir().join(format!("skuld-crash-reports-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let logger = SkuldLogger::new(dir.with_extension("txt"))
+        .unwrap()
+        .with_crash_reports(
+            CrashReports::new("skuld-test", "1.2.3")
+                .with_dir(&dir)
+                .with_lines(2)
+                // the other tests of this binary panic too, on their own threads
+                .with_filter(|_| thread::current().name() == Some("crasher")),
+        );
+
+    let scope = logger.scoped("crash");
+    scope.info("first");
+    scope.info("second");
+    scope.info("third");
+
+    logger.install_crash_reports();
+
+    let crasher = thread::Builder::new().name(String::from("crasher"));
+    assert!(crasher.spawn(|| panic!("boom")).unwrap().join().is_err());
+
+    let reports = fs::read_dir(&dir)
+        .unwrap()
+        .map(|n| n.unwrap().path())
+        .filter(|n| {
+            let name = n.file_name().unwrap().to_string_lossy();
+            name.starts_with("skuld-test-crash-")
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(reports.len(), 1);
+    let report = fs::read_to_string(&reports[0]).unwrap();
+
+    assert!(report.contains("version = 1.2.3"));
+    assert!(report.contains("thread = crasher"));
+    assert!(report.contains("message = boom"));
+    assert!(report.contains("[crash] third"));
+    assert!(!report.contains("[crash] first"));
+}